@@ -1,4 +1,5 @@
 use std::fmt;
+use std::collections::BTreeMap;
 
 use serde::{Serialize, Deserialize};
 
@@ -9,6 +10,7 @@ use crate::messaging::*;
 use crate::utils::*;
 use crate::config::Config;
 use crate::map::Wall;
+use crate::pathing::astar_path_with_reach;
 
 
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
@@ -21,6 +23,8 @@ pub enum Behavior {
     Idle,
     Investigating(Pos),
     Attacking(EntityId),
+    Fleeing(Pos),
+    Patrolling(usize, PatrolDir), // index of the next waypoint, and the direction along the route
 }
 
 impl fmt::Display for Behavior {
@@ -29,6 +33,8 @@ impl fmt::Display for Behavior {
             Behavior::Idle => write!(f, "idle"),
             Behavior::Investigating(pos) => write!(f, "investigating {} {}", pos.x, pos.y),
             Behavior::Attacking(entity_id) => write!(f, "attacking {}", entity_id),
+            Behavior::Fleeing(pos) => write!(f, "fleeing {} {}", pos.x, pos.y),
+            Behavior::Patrolling(index, patrol_dir) => write!(f, "patrolling {} {}", index, patrol_dir),
         }
     }
 }
@@ -45,6 +51,8 @@ impl Behavior {
             Behavior::Idle => "idle".to_string(),
             Behavior::Investigating(_position) => "investigating".to_string(),
             Behavior::Attacking(_obj_id) => "attacking".to_string(),
+            Behavior::Fleeing(_position) => "fleeing".to_string(),
+            Behavior::Patrolling(_index, _patrol_dir) => "patrolling".to_string(),
         }
     }
 
@@ -77,7 +85,26 @@ pub fn basic_ai_take_turn(monster_id: EntityId,
     let monster_pos = data.entities.pos[&monster_id];
 
     if data.map.is_within_bounds(monster_pos) {
-        if data.entities.status[&monster_id].frozen == 0 {
+        if !data.entities.has_status(monster_id, StatusKind::Frozen) {
+            if data.entities.status[&monster_id].asleep && !ai_try_wake(monster_id, data, msg_log, config) {
+                return;
+            }
+
+            if let Some(target_id) = ai_infighting_target(monster_id, data, config) {
+                let target_pos = data.entities.pos[&target_id];
+                msg_log.log(Msg::FaceTowards(monster_id, target_pos));
+                msg_log.log(Msg::TryAttack(monster_id, Attack::Attack(target_id), target_pos));
+                return;
+            }
+
+            let is_fleeing = matches!(data.entities.behavior[&monster_id], Behavior::Fleeing(_));
+            if !is_fleeing && ai_should_flee(monster_id, data, config) {
+                let player_id = data.find_by_name(EntityName::Player).unwrap();
+                let player_pos = data.entities.pos[&player_id];
+                msg_log.log(Msg::StateChange(monster_id, Behavior::Fleeing(player_pos)));
+                return;
+            }
+
             match data.entities.behavior[&monster_id] {
                 Behavior::Idle => {
                     ai_idle(monster_id, data, msg_log, config);
@@ -90,11 +117,49 @@ pub fn basic_ai_take_turn(monster_id: EntityId,
                 Behavior::Attacking(object_id) => {
                     ai_attack(monster_id, object_id, data, msg_log, config);
                 }
+
+                Behavior::Fleeing(threat_pos) => {
+                    ai_flee(threat_pos, monster_id, data, msg_log, config);
+                }
+
+                Behavior::Patrolling(index, patrol_dir) => {
+                    ai_patrol(index, patrol_dir, monster_id, data, msg_log, config);
+                }
             }
         }
     }
 }
 
+/// A sleeping monster wakes when attacked, or when the player comes within the wake radius.
+/// Returns whether the monster can go on to act normally this turn.
+pub fn ai_try_wake(monster_id: EntityId,
+                   data: &mut GameData,
+                   msg_log: &mut MsgLog,
+                   config: &Config) -> bool {
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+    let monster_pos = data.entities.pos[&monster_id];
+    let player_pos = data.entities.pos[&player_id];
+
+    let was_attacked = data.entities.was_attacked(monster_id).is_some();
+    let player_near = distance_maximum(monster_pos, player_pos) <= config.wake_radius;
+
+    if !was_attacked && !player_near {
+        return false;
+    }
+
+    data.entities.status[&monster_id].asleep = false;
+    msg_log.log(Msg::WokeUp(monster_id));
+
+    // a monster woken by the player nearby turns to see what disturbed it
+    if !was_attacked {
+        msg_log.log(Msg::FaceTowards(monster_id, player_pos));
+        msg_log.log(Msg::StateChange(monster_id, Behavior::Investigating(player_pos)));
+        return false;
+    }
+
+    return true;
+}
+
 pub fn ai_attack(monster_id: EntityId,
                  target_id: EntityId,
                  data: &mut GameData,
@@ -120,8 +185,8 @@ pub fn ai_idle(monster_id: EntityId,
         msg_log.log(Msg::Sound(monster_id, monster_pos, config.sound_golem_idle_radius, should_animate));
     }
 
-    if ai_is_in_fov(monster_id, player_id, data, config) {
-        let player_pos = data.entities.pos[&player_id];
+    let player_pos = data.entities.pos[&player_id];
+    if ai_is_in_fov(monster_id, player_id, data, config) && !ai_beyond_leash(monster_id, player_pos, data, config) {
         msg_log.log(Msg::FaceTowards(monster_id, player_pos));
 
         if data.entities.attack.get(&monster_id).is_some() {
@@ -138,10 +203,13 @@ pub fn ai_idle(monster_id: EntityId,
         } else {
             msg_log.log(Msg::StateChange(monster_id, Behavior::Investigating(entity_pos)));
         }
-    } else if let Some(Message::Sound(entity_id, sound_pos)) = data.entities.heard_sound(monster_id) {
+    } else if let Some(Message::Sound(entity_id, sound_pos, strength)) = data.entities.heard_sound(monster_id) {
         let is_player = entity_id == player_id;
 
-        let needs_investigation = is_player;
+        // faint sounds, such as those muffled by walls, are ignored
+        let needs_investigation = is_player &&
+                                  strength >= config.sound_investigate_strength &&
+                                  !ai_beyond_leash(monster_id, sound_pos, data, config);
 
         if needs_investigation {
             msg_log.log(Msg::FaceTowards(monster_id, sound_pos));
@@ -159,11 +227,19 @@ pub fn ai_investigate(target_pos: Pos,
 
     let monster_pos = data.entities.pos[&monster_id];
 
-    let player_in_fov = ai_is_in_fov(monster_id, player_id, data, config);
+    // a leashed monster gives up on targets too far from home and returns there instead
+    if ai_beyond_leash(monster_id, target_pos, data, config) {
+        let home = data.entities.home[&monster_id];
+        msg_log.log(Msg::StateChange(monster_id, Behavior::Investigating(home)));
+        return;
+    }
+
+    let player_pos = data.entities.pos[&player_id];
+    let player_in_fov = ai_is_in_fov(monster_id, player_id, data, config) &&
+                        !ai_beyond_leash(monster_id, player_pos, data, config);
 
     if player_in_fov {
         //let fov_path_clear = data.map.path_blocked_fov(monster_pos, player_pos).is_none();
-        let player_pos = data.entities.pos[&player_id];
         msg_log.log(Msg::FaceTowards(monster_id, player_pos));
 
         if data.entities.attack.get(&monster_id).is_some() {
@@ -176,7 +252,7 @@ pub fn ai_investigate(target_pos: Pos,
             msg_log.log(Msg::StateChange(monster_id, Behavior::Investigating(player_pos)));
         }
     } else { // the monster can't see the player
-        if let Some(Message::Sound(_entity_id, pos)) = data.entities.heard_sound(monster_id) {
+        if let Some(Message::Sound(_entity_id, pos, _strength)) = data.entities.heard_sound(monster_id) {
             msg_log.log(Msg::StateChange(monster_id, Behavior::Investigating(pos)));
         } else {
             if target_pos == monster_pos { 
@@ -190,7 +266,188 @@ pub fn ai_investigate(target_pos: Pos,
     }
 }
 
-fn ai_move_towards_target(target_pos: Pos, monster_id: EntityId, data: &mut GameData, msg_log: &mut MsgLog) {
+/// A fleeing monster moves away from the player while it can see them, giving up and
+/// resting once out of sight. A cornered monster turns to fight instead.
+pub fn ai_flee(threat_pos: Pos,
+               monster_id: EntityId,
+               data: &mut GameData,
+               msg_log: &mut MsgLog,
+               config: &Config) {
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = data.entities.pos[&player_id];
+
+    if !ai_is_in_fov(monster_id, player_id, data, config) {
+        data.entities.took_turn[&monster_id] = true;
+        msg_log.log(Msg::StateChange(monster_id, Behavior::Idle));
+    } else if let Some(flee_pos) = ai_flee_step(monster_id, player_pos, data, config) {
+        let monster_pos = data.entities.pos[&monster_id];
+        if let Some(direction) = Direction::from_positions(monster_pos, flee_pos) {
+            msg_log.log(Msg::TryMove(monster_id, direction, 1, MoveMode::Walk));
+        }
+
+        // the player moved, so remember where they are now. the monster already ran
+        // this turn, so the state change does not continue its turn
+        if player_pos != threat_pos {
+            data.entities.took_turn[&monster_id] = true;
+            msg_log.log(Msg::StateChange(monster_id, Behavior::Fleeing(player_pos)));
+        }
+    } else {
+        msg_log.log(Msg::StateChange(monster_id, Behavior::Attacking(player_id)));
+    }
+}
+
+/// A patrolling monster walks to each waypoint of its route in turn, pausing for a turn at
+/// each one and turning back at either end of the route, until it sees the player.
+pub fn ai_patrol(index: usize,
+                 patrol_dir: PatrolDir,
+                 monster_id: EntityId,
+                 data: &mut GameData,
+                 msg_log: &mut MsgLog,
+                 config: &Config) {
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = data.entities.pos[&player_id];
+
+    if ai_is_in_fov(monster_id, player_id, data, config) && !ai_beyond_leash(monster_id, player_pos, data, config) {
+        msg_log.log(Msg::FaceTowards(monster_id, player_pos));
+
+        if data.entities.attack.get(&monster_id).is_some() {
+            msg_log.log(Msg::StateChange(monster_id, Behavior::Attacking(player_id)));
+        } else {
+            msg_log.log(Msg::StateChange(monster_id, Behavior::Investigating(player_pos)));
+        }
+        return;
+    }
+
+    let route = data.entities.patrol.get(&monster_id).cloned().unwrap_or(Vec::new());
+    if route.len() == 0 {
+        data.entities.took_turn[&monster_id] = true;
+        msg_log.log(Msg::StateChange(monster_id, Behavior::Idle));
+        return;
+    }
+
+    let index = std::cmp::min(index, route.len() - 1);
+    let monster_pos = data.entities.pos[&monster_id];
+    if monster_pos == route[index] {
+        data.entities.took_turn[&monster_id] = true;
+
+        let (next_index, next_dir) = patrol_next(index, patrol_dir, route.len());
+        msg_log.log(Msg::StateChange(monster_id, Behavior::Patrolling(next_index, next_dir)));
+    } else {
+        ai_move_towards_target(route[index], monster_id, data, msg_log);
+    }
+}
+
+/// The waypoint after the given one on a route of the given length, turning back at the ends.
+pub fn patrol_next(index: usize, patrol_dir: PatrolDir, route_len: usize) -> (usize, PatrolDir) {
+    match patrol_dir {
+        PatrolDir::Forward => {
+            if index + 1 < route_len {
+                return (index + 1, PatrolDir::Forward);
+            } else {
+                return (index.saturating_sub(1), PatrolDir::Reverse);
+            }
+        }
+
+        PatrolDir::Reverse => {
+            if index > 0 {
+                return (index - 1, PatrolDir::Reverse);
+            } else {
+                return (std::cmp::min(1, route_len - 1), PatrolDir::Forward);
+            }
+        }
+    }
+}
+
+/// Whether a monster is hurt badly enough to run from a player it can see, and has somewhere to run.
+pub fn ai_should_flee(monster_id: EntityId, data: &mut GameData, config: &Config) -> bool {
+    if config.flee_hp_fraction <= 0.0 {
+        return false;
+    }
+
+    let fighter = match data.entities.fighter.get(&monster_id) {
+        Some(fighter) => *fighter,
+        None => return false,
+    };
+
+    if fighter.hp as f32 >= fighter.max_hp as f32 * config.flee_hp_fraction {
+        return false;
+    }
+
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = data.entities.pos[&player_id];
+    return ai_is_in_fov(monster_id, player_id, data, config) &&
+           ai_flee_step(monster_id, player_pos, data, config).is_some();
+}
+
+/// The position a fleeing monster should move to. This is the step opposite the A* path
+/// towards the threat if that is open, and otherwise whichever reachable position gets
+/// furthest from the threat. There is no step if every move brings the monster closer.
+pub fn ai_flee_step(monster_id: EntityId, threat_pos: Pos, data: &GameData, config: &Config) -> Option<Pos> {
+    let monster_pos = data.entities.pos[&monster_id];
+    let reach = match data.entities.movement.get(&monster_id) {
+        Some(reach) => *reach,
+        None => return None,
+    };
+
+    let mut towards = ai_take_astar_step(monster_id, threat_pos, false, data);
+    if pos_mag(towards) == 0 {
+        towards = step_towards(monster_pos, threat_pos);
+    }
+    let away_pos = sub_pos(monster_pos, towards);
+
+    let current_dist = distance(monster_pos, threat_pos);
+    let escapes = reachable_positions(monster_id, reach, data, config).into_iter()
+                                     .filter(|pos| distance(*pos, threat_pos) > current_dist)
+                                     .collect::<Vec<Pos>>();
+
+    if escapes.contains(&away_pos) {
+        return Some(away_pos);
+    }
+
+    return escapes.into_iter().max_by_key(|pos| distance(*pos, threat_pos));
+}
+
+/// With infighting enabled, find a monster of a different kind that this monster can hit.
+pub fn ai_infighting_target(monster_id: EntityId, data: &mut GameData, config: &Config) -> Option<EntityId> {
+    if !config.monster_infighting {
+        return None;
+    }
+
+    // monsters that only push, like the armil, have no attack to turn on others
+    let reach = match data.entities.attack.get(&monster_id) {
+        Some(reach) => *reach,
+        None => return None,
+    };
+    let monster_name = data.entities.name[&monster_id];
+
+    let hostile_ids = data.entities.active_ais().iter()
+                                    .filter(|id| **id != monster_id && data.entities.name[*id] != monster_name)
+                                    .map(|id| *id)
+                                    .collect::<Vec<EntityId>>();
+    for target_id in hostile_ids {
+        let target_pos = data.entities.pos[&target_id];
+        if ai_can_hit_target(data, monster_id, target_pos, &reach, config).is_some() {
+            return Some(target_id);
+        }
+    }
+
+    return None;
+}
+
+/// Whether a position is further from a monster's home than the configured leash allows.
+pub fn ai_beyond_leash(monster_id: EntityId, pos: Pos, data: &GameData, config: &Config) -> bool {
+    if config.leash_distance == 0 {
+        return false;
+    }
+
+    if let Some(home) = data.entities.home.get(&monster_id) {
+        return distance(*home, pos) > config.leash_distance as i32;
+    }
+
+    return false;
+}
+
+pub fn ai_move_towards_target(target_pos: Pos, monster_id: EntityId, data: &mut GameData, msg_log: &mut MsgLog) {
     let monster_pos = data.entities.pos[&monster_id];
 
     // if the monster has not reached its target, move towards the target.
@@ -409,6 +666,64 @@ pub fn ai_move_to_attack_pos(monster_id: EntityId,
     return maybe_pos;
 }
 
+/// Spread out pack members hunting the same target. Each member is assigned its own tile
+/// to attack from, with members closer to the target choosing first. Monsters without a
+/// pack, or hunting alone, are given no tile and behave as usual.
+pub fn ai_coordinate_packs(data: &mut GameData, config: &Config) {
+    data.entities.pack_target.clear();
+
+    // gather the members of each pack by the target they are attacking
+    let mut hunts: BTreeMap<(EntityId, EntityId), Vec<EntityId>> = BTreeMap::new();
+    for monster_id in data.entities.active_ais() {
+        if let Some(pack_id) = data.entities.pack.get(&monster_id).map(|id| *id) {
+            if let Behavior::Attacking(target_id) = data.entities.behavior[&monster_id] {
+                if data.entities.ids.contains(&target_id) && !data.entities.is_dead(target_id) {
+                    hunts.entry((pack_id, target_id)).or_insert(Vec::new()).push(monster_id);
+                }
+            }
+        }
+    }
+
+    for ((_pack_id, target_id), mut members) in hunts {
+        if members.len() < 2 {
+            continue;
+        }
+
+        let target_pos = data.entities.pos[&target_id];
+        members.sort_by_key(|id| distance(data.entities.pos[id], target_pos));
+
+        let mut claimed: Vec<Pos> = Vec::new();
+        for member_id in members {
+            let member_pos = data.entities.pos[&member_id];
+
+            // members without an attack, like the armil, are given no tile to attack from
+            let attack = match data.entities.attack.get(&member_id) {
+                Some(attack) => *attack,
+                None => continue,
+            };
+
+            // a member that can already attack holds its tile
+            if ai_can_hit_target(data, member_id, target_pos, &attack, config).is_some() &&
+               !claimed.contains(&member_pos) {
+                claimed.push(member_pos);
+                data.entities.pack_target.insert(member_id, member_pos);
+                continue;
+            }
+
+            let mut options = ai_pos_that_hit_target(member_id, target_id, data, config);
+            options.retain(|pos| !claimed.contains(pos) &&
+                                 !data.map[*pos].block_move &&
+                                 data.has_blocking_entity(*pos).is_none());
+            sort_by_distance_to(member_pos, &mut options);
+
+            if let Some(pos) = options.first() {
+                claimed.push(*pos);
+                data.entities.pack_target.insert(member_id, *pos);
+            }
+        }
+    }
+}
+
 // NOTE perhaps this should be merged into is_in_fov?
 pub fn ai_is_in_fov(monster_id: EntityId, target_id: EntityId, data: &mut GameData, config: &Config) -> bool {
     let monster_pos = data.entities.pos[&monster_id];
@@ -428,21 +743,6 @@ pub fn ai_is_in_fov(monster_id: EntityId, target_id: EntityId, data: &mut GameDa
     }
 }
 
-fn ai_astar_cost(_start: Pos, _prev: Pos, next: Pos, data: &GameData) -> Option<i32> {
-    let mut cost = Some(1);
-
-    // check for an armed trap in the list of entities on this tile
-    for entity_id in data.has_entities(next) {
-        if data.entities.trap.get(&entity_id).is_some() &&
-           data.entities.armed.get(&entity_id) == Some(&true) {
-               // NOTE determined randomly. could be infinite, or smaller?
-               cost = None;
-        }
-    }
-
-    return cost;
-}
-
 fn ai_astar_step(monster_id: EntityId,
                  target_pos: Pos,
                  must_reach: bool,
@@ -450,9 +750,12 @@ fn ai_astar_step(monster_id: EntityId,
     let reach = data.entities.movement[&monster_id];
     let monster_pos = data.entities.pos[&monster_id];
 
-    let traps_block = true;
+    let path = astar_path_with_reach(monster_pos, target_pos, reach, data);
 
-    let path = data.path_between(monster_pos, target_pos, reach, must_reach, traps_block, Some(ai_astar_cost));
+    // a blocked target is only approached, which is not enough if it must be reached
+    if must_reach && path.last() != Some(&target_pos) {
+        return Vec::new();
+    }
 
     return path;
 }
@@ -1,14 +1,49 @@
 use std::fs::File;
 use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
 
 use crate::types::*;
 use crate::map::*;
+use crate::movement::Direction;
 
 
 use serde_derive::*;
 use serde_yaml;
 
 
+/// The name of the game's directory within the user's config directory.
+pub const CONFIG_DIR_NAME: &str = "roguelike";
+
+/// Keys for the player's actions. Actions are named as they are parsed as an InputAction.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
+pub struct Keybindings {
+    pub actions: BTreeMap<char, String>,
+    pub directions: BTreeMap<char, Direction>,
+    pub pass: char,
+}
+
+/// What pressing a mouse button on the map does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MouseAction {
+    MapClick, // travel to a tile, or select it in cursor mode
+    Examine,  // describe what is on a tile
+    Button,   // nothing in particular, only report the button
+}
+
+impl Default for MouseAction {
+    fn default() -> MouseAction {
+        return MouseAction::Button;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
+pub struct MouseBindings {
+    pub left: MouseAction,
+    pub middle: MouseAction,
+    pub right: MouseAction,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct Config {
     pub color_dark_brown: Color,
@@ -43,14 +78,31 @@ pub struct Config {
     pub grid_alpha_overlay: u8,
     pub map_load: MapLoadConfig,
     pub map_file: String,
+    pub resource_dir: String,
     pub idle_speed: f32,
     pub draw_mouse_line: bool,
     pub draw_star_path: bool,
     pub frame_rate: usize,
+    pub frame_rate_adaptive: bool,
     pub item_throw_speed: f32,
+    pub move_anim_time: f32,
     pub key_speed: f32,
     pub player_attack_speed: f32,
     pub player_attack_hammer_speed: f32,
+    pub animate_attacks: bool,
+    pub attack_delay_turns: usize,
+    pub retaliate: bool,
+    pub grace_turns: usize,
+    pub poison_damage: i32,
+    pub poison_turns: usize,
+    pub monster_damage_types: BTreeMap<EntityName, DamageType>,
+    pub burning_damage: i32,
+    pub burning_turns: usize,
+    pub xp_per_kill: usize,
+    pub xp_per_tile: usize,
+    pub xp_levels: Vec<usize>,
+    pub level_up_hp: i32,
+    pub level_up_power: i32,
     pub player_vault_sprite_speed: f32,
     pub player_vault_move_speed: f32,
     pub sound_timeout: f32,
@@ -59,6 +111,7 @@ pub struct Config {
     pub fog_of_war: bool,
     pub player_health: i32,
     pub explored_alpha: u8,
+    pub lit_alpha: u8,
     pub fov_edge_alpha: u8,
     pub sound_rubble_radius: usize,
     pub sound_golem_idle_radius: usize,
@@ -71,31 +124,103 @@ pub struct Config {
     pub sound_radius_player: usize,
     pub sound_radius_hammer: usize,
     pub freeze_trap_radius: usize,
+    pub reusable_traps: bool,
     pub push_stun_turns: usize,
+    pub hammer_durability: usize,
+    pub sword_durability: usize,
+    pub time_stop_turns: usize,
+    pub time_stop_cooldown: usize,
     pub overlay_directions: bool,
     pub overlay_player_fov: bool,
     pub overlay_fov_alg: bool,
     pub overlay_floodfill: bool,
+    pub overlay_reachable: bool,
+    pub overlay_path_preview: bool,
+    pub health_bars: bool,
+    pub health_bar_hide_full: bool,
+    pub health_bar_height: u32,
+    pub health_bar_color: Color,
     pub fov_radius_monster: i32,
     pub fov_radius_player: i32,
+    pub carried_light_fov: bool,
+    pub fov_algorithm: FovAlgorithm,
+    pub fov_cone_monster: bool,
+    pub fov_arc_monster: f32,
+    pub sight_persistence_turns: usize,
+    pub leash_distance: usize,
+    pub monster_infighting: bool,
+    pub flee_hp_fraction: f32,
+    pub monster_sleep: bool,
+    pub wake_radius: i32,
     pub sound_radius_sneak: usize,
     pub sound_radius_walk: usize,
     pub sound_radius_run: usize,
     pub dampen_blocked_tile: i32,
     pub dampen_short_wall: i32,
     pub dampen_tall_wall: i32,
+    pub sound_investigate_strength: usize,
     pub take_screenshot: bool,
     pub show_info: bool,
     pub use_cursor: bool,
     pub cursor_long: i32,
+    pub cursor_clamp_fov: bool,
+    pub target_order: TargetOrder,
     pub repeat_delay: f32,
+    pub repeat_delays: BTreeMap<char, f32>,
+    pub charge_key: char,
+    pub charge_max: usize,
+    pub skill_keys: Vec<char>,
+    pub item_keys: Vec<char>,
+    pub inventory_page_size: usize,
+    pub keybindings: Keybindings,
+    pub mouse_bindings: MouseBindings,
     pub write_map_distribution: bool,
+    pub write_wfc_map: bool,
     pub print_key_log: bool,
     pub idle_animations: bool,
     pub recording: bool,
     pub fire_speed: f32,
+    pub water_frames: Vec<u32>,
+    pub water_fps: f32,
     pub beam_duration: usize,
     pub draw_directional_arrow: bool,
+    pub auto_face_player: bool,
+    pub momentum_through_attacks: bool,
+    pub momentum_max: usize,
+    pub knockback_attacks: bool,
+    pub logic_budget_ms: f32,
+    pub msg_history_limit: usize,
+    pub msg_panel_lines: usize,
+    pub minimap_size: u32,
+    pub camera_view_width: i32,
+    pub camera_view_height: i32,
+    pub camera_lerp: f32,
+    pub level_banner_duration: f32,
+    pub reveal_map_on_win: bool,
+    pub reveal_map_duration: f32,
+    pub level_transition: bool,
+    pub level_transition_duration: f32,
+    pub intro_messages: Vec<String>,
+    pub intro_message_duration: f32,
+    pub intro_camera: Vec<Pos>,
+    pub intro_camera_lerp: f32,
+    pub feeling_uneasy_threat: i32,
+    pub feeling_dangerous_threat: i32,
+    pub feeling_rewarding_items: usize,
+    pub travel_delay: f32,
+    pub autosave_on_level: bool,
+    pub persistent_levels: bool,
+    pub level_rest_fraction: f32,
+    pub stairs_safe_radius: i32,
+    pub spawn_table: BTreeMap<EntityName, u32>,
+    pub spawn_budget: Vec<usize>,
+    pub pack_monsters: Vec<EntityName>,
+    pub save_file: String,
+    pub thick_line_collision: bool,
+    pub strict_diagonals: bool,
+    pub mute_audio: bool,
+    pub sounds: BTreeMap<String, String>,
+    pub wall_slide: bool,
 }
 
 impl Config {
@@ -106,9 +231,179 @@ impl Config {
         file.read_to_string(&mut config_string)
             .expect(&format!("Could not read contents of {}", file_name));
 
-        let config = serde_yaml::from_str(&config_string).expect(&format!("Could not parse {} file!", file_name));
+        let config: Config = serde_yaml::from_str(&config_string).expect(&format!("Could not parse {} file!", file_name));
+        config.check_spawn_table(file_name);
+        config.check_water_frames(file_name);
 
         return config
     }
+
+    /// Load a user's config file over the bundled default config. Keys missing from the
+    /// user's file, such as ones added since it was copied, keep their default values.
+    /// If the user's file can't be used at all, the default config is loaded instead.
+    pub fn from_user_file(user_file: &Path, default_file: &str) -> Config {
+        let default_string = std::fs::read_to_string(default_file)
+            .expect(&format!("Could not read contents of {}", default_file));
+        let mut config_value: serde_yaml::Value = serde_yaml::from_str(&default_string)
+            .expect(&format!("Could not parse {} file!", default_file));
+
+        let user_value = std::fs::read_to_string(user_file)
+            .map_err(|err| err.to_string())
+            .and_then(|user_string| serde_yaml::from_str::<serde_yaml::Value>(&user_string).map_err(|err| err.to_string()));
+
+        match (&mut config_value, user_value) {
+            (serde_yaml::Value::Mapping(config_map), Ok(serde_yaml::Value::Mapping(user_map))) => {
+                let missing_keys: Vec<&str> =
+                    config_map.iter()
+                              .map(|(key, _value)| key)
+                              .filter(|key| !user_map.contains_key(key))
+                              .filter_map(|key| key.as_str())
+                              .collect();
+                if !missing_keys.is_empty() {
+                    eprintln!("{} is missing {}, using the values from {}",
+                              user_file.display(), missing_keys.join(", "), default_file);
+                }
+
+                for (key, value) in user_map {
+                    config_map.insert(key, value);
+                }
+            }
+
+            (_, Err(err)) => {
+                eprintln!("Could not read {} ({}), using {}", user_file.display(), err, default_file);
+                return Config::from_file(default_file);
+            }
+
+            (_, Ok(_)) => {
+                eprintln!("{} is not a map of config values, using {}", user_file.display(), default_file);
+                return Config::from_file(default_file);
+            }
+        }
+
+        match serde_yaml::from_value::<Config>(config_value) {
+            Ok(config) => {
+                config.check_spawn_table(&user_file.display().to_string());
+                config.check_water_frames(&user_file.display().to_string());
+                return config;
+            }
+
+            Err(err) => {
+                eprintln!("Could not parse {} ({}), using {}", user_file.display(), err, default_file);
+                return Config::from_file(default_file);
+            }
+        }
+    }
+
+    fn check_spawn_table(&self, file_name: &str) {
+        for name in self.spawn_table.keys() {
+            if !name.is_monster() {
+                panic!("{:?} in the spawn table of {} is not a monster!", name, file_name);
+            }
+        }
+    }
+
+    fn check_water_frames(&self, file_name: &str) {
+        for frame in self.water_frames.iter() {
+            if std::char::from_u32(*frame).is_none() {
+                panic!("Water frame {} in {} is not a valid character!", frame, file_name);
+            }
+        }
+    }
+}
+
+/// The game's directory within the platform's user config directory, if the platform provides one.
+pub fn user_config_dir() -> Option<PathBuf> {
+    let base_dir =
+        if cfg!(target_os = "windows") {
+            std::env::var_os("APPDATA").map(PathBuf::from)
+        } else if cfg!(target_os = "macos") {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library").join("Application Support"))
+        } else {
+            std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+                .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        };
+
+    return base_dir.map(|dir| dir.join(CONFIG_DIR_NAME));
+}
+
+/// The path to load a config file from. A copy in the user config directory is preferred,
+/// falling back to the bundled file relative to the current directory.
+pub fn find_config(file_name: &str, user_dir: Option<PathBuf>) -> PathBuf {
+    if let Some(user_dir) = user_dir {
+        let user_path = user_dir.join(file_name);
+        if user_path.exists() {
+            return user_path;
+        }
+    }
+
+    return PathBuf::from(file_name);
+}
+
+/// Load the config, preferring the user's copy merged over the bundled file.
+pub fn load_config(file_name: &str, user_dir: Option<PathBuf>) -> Config {
+    let config_path = find_config(file_name, user_dir);
+    if config_path == Path::new(file_name) {
+        return Config::from_file(file_name);
+    }
+
+    return Config::from_user_file(&config_path, file_name);
+}
+
+#[test]
+pub fn test_find_config() {
+    let user_dir = std::env::temp_dir().join(format!("roguelike_config_test_{}", std::process::id()));
+    std::fs::create_dir_all(&user_dir).unwrap();
+
+    // without a user config, the bundled default is used
+    assert_eq!(PathBuf::from("../config.yaml"), find_config("../config.yaml", None));
+    assert_eq!(PathBuf::from("config.yaml"), find_config("config.yaml", Some(user_dir.clone())));
+
+    // a user config is preferred when it exists
+    let mut config_string = std::fs::read_to_string("../config.yaml").unwrap();
+    config_string = config_string.replace("charge_key: 'b'", "charge_key: 'n'");
+    std::fs::write(user_dir.join("config.yaml"), config_string).unwrap();
+
+    let path = find_config("config.yaml", Some(user_dir.clone()));
+    assert_eq!(user_dir.join("config.yaml"), path);
+    assert_eq!('n', Config::from_file(path.to_str().unwrap()).charge_key);
+
+    std::fs::remove_dir_all(&user_dir).unwrap();
+}
+
+#[test]
+pub fn test_stale_user_config() {
+    let user_dir = std::env::temp_dir().join(format!("roguelike_stale_config_test_{}", std::process::id()));
+    std::fs::create_dir_all(&user_dir).unwrap();
+    let user_file = user_dir.join("config.yaml");
+
+    // a user config from before a key was added keeps its own values and defaults the rest
+    let default_config = Config::from_file("../config.yaml");
+    let config_string = std::fs::read_to_string("../config.yaml").unwrap();
+    let stale_string: String =
+        config_string.lines()
+                     .filter(|line| !line.starts_with("wall_slide:"))
+                     .map(|line| if line.starts_with("charge_key:") { "charge_key: 'n'" } else { line })
+                     .collect::<Vec<&str>>()
+                     .join("\n");
+    std::fs::write(&user_file, stale_string).unwrap();
+
+    let config = Config::from_user_file(&user_file, "../config.yaml");
+    assert_eq!('n', config.charge_key);
+    assert_eq!(default_config.wall_slide, config.wall_slide);
+
+    // a user config that can't be parsed falls back to the default
+    std::fs::write(&user_file, "charge_key: [").unwrap();
+    assert_eq!(default_config.charge_key, Config::from_user_file(&user_file, "../config.yaml").charge_key);
+
+    std::fs::remove_dir_all(&user_dir).unwrap();
 }
 
+#[test]
+#[should_panic(expected = "is not a valid character")]
+pub fn test_invalid_water_frame() {
+    let mut config = Config::from_file("../config.yaml");
+
+    // surrogate code points can't be drawn as characters
+    config.water_frames = vec!(0xD800);
+    config.check_water_frames("../config.yaml");
+}
@@ -14,6 +14,7 @@ pub const INVENTORY_HEIGHT: usize = 500;
 pub const INITIAL_OBJECT_CAPACITY: usize = 1000;
 pub const BLINK_RADIUS: usize = 4;
 pub const TILE_FILL_METRIC_DIST: usize = 3;
+pub const DRUNKARD_WALK_CENTER_BIAS: f32 = 0.2;
 pub const ILLUMINATE_FOV_RADIUS: i32 = 1000;
 pub const STAB_STUN_TURNS: usize = 3;
 
@@ -21,6 +22,8 @@ pub const GOLEM_ATTACK_DIAG: u8 = 180;
 pub const GOLEM_ATTACK_HORIZ: u8 = 181;
 
 /* Monster Settings */
+// energy needed for a monster to act, gained at the monster's speed each player turn
+pub const NORMAL_SPEED: u32 = 2;
 pub const MAX_MONSTERS: i32 = 15;
 
 pub const GOL_ATTACK_DISTANCE: usize = 5;
@@ -37,11 +40,12 @@ pub const ROOK_ATTACK_DISTANCE: usize = 1;
 
 pub const ARMIL_MOVE_DISTANCE: usize = 1;
 
+pub const SLIME_MOVE_DISTANCE: usize = 1;
+pub const SLIME_ATTACK_DISTANCE: usize = 1;
+
 pub const KEY_ATTACK_DISTANCE: usize = 3;
 pub const KEY_MOVE_DISTANCE: usize = 2;
 
-pub const MAX_MOMENTUM: i32 = 2;
-
 pub const SPIKE_DAMAGE: i32 = 20;
 pub const HAMMER_DAMAGE: i32 = 40;
 pub const SWORD_DAMAGE: i32 = 20;
@@ -71,6 +75,9 @@ pub const PLAYER_THROW_DIST: usize = 5;
 
 pub const PLAYER_MAX_ITEMS: usize = 3;
 
+pub const MAX_SKILL_SLOTS: usize = 8;
+pub const MAX_ITEM_SLOTS: usize = 8;
+
 /* UI */
 pub const BAR_WIDTH: u32 = 20;
 pub const PANEL_HEIGHT: u32 = 7;
@@ -84,6 +91,9 @@ pub const MSG_HEIGHT: u32 = PANEL_HEIGHT - 1;
 pub const MAP_WIDTH: i32 = 30;
 pub const MAP_HEIGHT: i32 = 30;
 
+pub const ARENA_WIDTH: i32 = 11;
+pub const ARENA_HEIGHT: i32 = 11;
+
 pub const ISLAND_DISTANCE: i32 = 7;
 
 pub const ISLAND_RADIUS: i32 = 15;
@@ -4,6 +4,7 @@ pub mod movement;
 pub mod constants;
 pub mod config;
 pub mod ai;
+pub mod pathing;
 pub mod utils;
 pub mod messaging;
 pub mod line;
@@ -156,6 +156,10 @@ pub enum MapLoadConfig {
     FromFile(String),
     VaultFile(String),
     ProcGen(String),
+    Arena(Vec<(EntityName, Pos)>),
+    Cellular { fill_percent: f32, iterations: usize },
+    DrunkardWalk { steps: usize, start: Option<Pos> },
+    Bsp { min_room: usize, max_depth: usize },
 }
 
 impl Default for MapLoadConfig {
@@ -178,6 +182,17 @@ impl fmt::Display for MapLoadConfig {
             MapLoadConfig::FromFile(file) => write!(f, "from_file {}", file),
             MapLoadConfig::VaultFile(file) => write!(f, "vault_file {}", file),
             MapLoadConfig::ProcGen(file) => write!(f, "procgen {}", file),
+            MapLoadConfig::Arena(spawns) => {
+                write!(f, "arena")?;
+                for (name, pos) in spawns.iter() {
+                    write!(f, " {} {} {}", name, pos.x, pos.y)?;
+                }
+                return Ok(());
+            }
+            MapLoadConfig::Cellular { fill_percent, iterations } => write!(f, "cellular {} {}", fill_percent, iterations),
+            MapLoadConfig::DrunkardWalk { steps, start: None } => write!(f, "drunkard_walk {}", steps),
+            MapLoadConfig::DrunkardWalk { steps, start: Some(start) } => write!(f, "drunkard_walk {} {} {}", steps, start.x, start.y),
+            MapLoadConfig::Bsp { min_room, max_depth } => write!(f, "bsp {} {}", min_room, max_depth),
         }
     }
 }
@@ -213,6 +228,53 @@ impl FromStr for MapLoadConfig {
         } else if s.starts_with("procgen") {
             let args = s.split(" ").collect::<Vec<&str>>();
             return Ok(MapLoadConfig::ProcGen(args[1].to_string()));
+        } else if s.starts_with("arena") {
+            // arena is given as a list of 'name x y' triples
+            let args = s.split_whitespace().skip(1).collect::<Vec<&str>>();
+            if args.len() % 3 != 0 {
+                return Err(format!("Could not parse '{}' as MapLoadConfig", s));
+            }
+
+            let mut spawns = Vec::new();
+            for spawn in args.chunks(3) {
+                let name = spawn[0].parse::<EntityName>()?;
+                let x = spawn[1].parse::<i32>().map_err(|_| format!("Could not parse '{}' as MapLoadConfig", s))?;
+                let y = spawn[2].parse::<i32>().map_err(|_| format!("Could not parse '{}' as MapLoadConfig", s))?;
+                spawns.push((name, Pos::new(x, y)));
+            }
+            return Ok(MapLoadConfig::Arena(spawns));
+        } else if s.starts_with("cellular") {
+            let args = s.split_whitespace().collect::<Vec<&str>>();
+            if args.len() != 3 {
+                return Err(format!("Could not parse '{}' as MapLoadConfig", s));
+            }
+
+            let fill_percent = args[1].parse::<f32>().map_err(|_| format!("Could not parse '{}' as MapLoadConfig", s))?;
+            let iterations = args[2].parse::<usize>().map_err(|_| format!("Could not parse '{}' as MapLoadConfig", s))?;
+            return Ok(MapLoadConfig::Cellular { fill_percent, iterations });
+        } else if s.starts_with("drunkard_walk") {
+            let args = s.split_whitespace().collect::<Vec<&str>>();
+            if args.len() != 2 && args.len() != 4 {
+                return Err(format!("Could not parse '{}' as MapLoadConfig", s));
+            }
+
+            let steps = args[1].parse::<usize>().map_err(|_| format!("Could not parse '{}' as MapLoadConfig", s))?;
+            let mut start = None;
+            if args.len() == 4 {
+                let x = args[2].parse::<i32>().map_err(|_| format!("Could not parse '{}' as MapLoadConfig", s))?;
+                let y = args[3].parse::<i32>().map_err(|_| format!("Could not parse '{}' as MapLoadConfig", s))?;
+                start = Some(Pos::new(x, y));
+            }
+            return Ok(MapLoadConfig::DrunkardWalk { steps, start });
+        } else if s.starts_with("bsp") {
+            let args = s.split_whitespace().collect::<Vec<&str>>();
+            if args.len() != 3 {
+                return Err(format!("Could not parse '{}' as MapLoadConfig", s));
+            }
+
+            let min_room = args[1].parse::<usize>().map_err(|_| format!("Could not parse '{}' as MapLoadConfig", s))?;
+            let max_depth = args[2].parse::<usize>().map_err(|_| format!("Could not parse '{}' as MapLoadConfig", s))?;
+            return Ok(MapLoadConfig::Bsp { min_room, max_depth });
         }
 
         return Err(format!("Could not parse '{}' as MapLoadConfig", s));
@@ -604,10 +666,72 @@ impl Wall {
     }
 }
 
+/// How visibility between two tiles is decided.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FovAlgorithm {
+    Standard,  // shadowcasting plus a clear line from the viewer
+    Symmetric, // as Standard, but the tile must also be able to see the viewer
+}
+
+impl Default for FovAlgorithm {
+    fn default() -> FovAlgorithm {
+        return FovAlgorithm::Standard;
+    }
+}
+
+/// The tiles visible from a position, stored as one bit per map tile so it can be
+/// cached cheaply and saved along with the rest of the game.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FovMap {
+    pub width: i32,
+    pub height: i32,
+    bits: Vec<u64>,
+}
+
+impl FovMap {
+    pub fn new(width: i32, height: i32) -> FovMap {
+        let num_words = (width * height + 63) / 64;
+        return FovMap { width, height, bits: vec![0; num_words as usize] };
+    }
+
+    fn index(&self, pos: Pos) -> Option<usize> {
+        if pos.x < 0 || pos.y < 0 || pos.x >= self.width || pos.y >= self.height {
+            return None;
+        }
+
+        return Some((pos.x + pos.y * self.width) as usize);
+    }
+
+    /// Positions outside of the map are never visible.
+    pub fn set_visible(&mut self, pos: Pos) {
+        if let Some(index) = self.index(pos) {
+            self.bits[index / 64] |= 1 << (index % 64);
+        }
+    }
+
+    pub fn is_visible(&self, pos: Pos) -> bool {
+        return self.index(pos).map_or(false, |index| (self.bits[index / 64] & (1 << (index % 64))) != 0);
+    }
+
+    pub fn visible_positions(&self) -> Vec<Pos> {
+        let mut positions = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let pos = Pos::new(x, y);
+                if self.is_visible(pos) {
+                    positions.push(pos);
+                }
+            }
+        }
+        return positions;
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Map {
     pub tiles: Vec<Vec<Tile>>,
-    pub fov_cache: RefCell<HashMap<Pos, Vec<Pos>>>,
+    #[serde(skip)]
+    pub fov_cache: RefCell<HashMap<Pos, FovMap>>,
 }
 
 impl Map {
@@ -698,6 +822,45 @@ impl Map {
         return self.path_blocked(start_pos, end_pos, BlockedType::Move);
     }
 
+    /// Whether a single diagonal step passes between two blocking tiles into an open tile.
+    pub fn corner_squeeze(&self, start_pos: Pos, end_pos: Pos) -> bool {
+        let dxy = sub_pos(end_pos, start_pos);
+        if dxy.x.abs() != 1 || dxy.y.abs() != 1 || !self.is_within_bounds(end_pos) {
+            return false;
+        }
+
+        let x_pos = Pos::new(end_pos.x, start_pos.y);
+        let y_pos = Pos::new(start_pos.x, end_pos.y);
+        return self[x_pos].block_move && self[y_pos].block_move && !self[end_pos].block_move;
+    }
+
+    /// Walk a line like path_blocked, but at each diagonal step also check both orthogonal
+    /// neighbors. This is a thick (supercover) line, so a diagonal step past a corner is
+    /// reported as blocked even if only one side of it is blocked.
+    pub fn path_blocked_thick(&self, start_pos: Pos, end_pos: Pos, blocked_type: BlockedType) -> Option<Blocked> {
+        let line = line(start_pos, end_pos);
+        let positions = iter::once(start_pos).chain(line.into_iter());
+        for (pos, target_pos) in positions.tuple_windows() {
+            if pos.x != target_pos.x && pos.y != target_pos.y {
+                let x_pos = Pos::new(target_pos.x, pos.y);
+                let y_pos = Pos::new(pos.x, target_pos.y);
+                if let Some(mut blocked) = self.move_blocked(pos, x_pos, blocked_type)
+                                               .or_else(|| self.move_blocked(pos, y_pos, blocked_type)) {
+                    blocked.end_pos = target_pos;
+                    blocked.direction = Direction::from_positions(pos, target_pos).unwrap();
+                    return Some(blocked);
+                }
+            }
+
+            let blocked = self.move_blocked(pos, target_pos, blocked_type);
+            if blocked.is_some() {
+                return blocked;
+            }
+        }
+
+        return None;
+    }
+
     pub fn path_blocked_all(&self, start_pos: Pos, end_pos: Pos, blocked_type: BlockedType) -> Vec<Blocked> {
         let mut blocked_vec = Vec::new();
         let mut cur_pos = start_pos;
@@ -920,6 +1083,27 @@ impl Map {
         return x_bounds && y_bounds;
     }
 
+    /// FNV-1a hash of the tile layout, used to check that map generation is stable.
+    pub fn checksum(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for column in self.tiles.iter() {
+            for tile in column.iter() {
+                let tile = *tile;
+                let bytes = [tile.tile_type as u8,
+                             tile.bottom_wall as u8,
+                             tile.left_wall as u8,
+                             tile.block_move as u8,
+                             tile.block_sight as u8];
+                for byte in bytes.iter() {
+                    hash ^= *byte as u64;
+                    hash = hash.wrapping_mul(0x100000001b3);
+                }
+            }
+        }
+
+        return hash;
+    }
+
     pub fn size(&self) -> (i32, i32) {
         return (self.tiles.len() as i32, self.tiles[0].len() as i32);
     }
@@ -954,17 +1138,42 @@ impl Map {
         return alg_fov && within_radius && clear_fov_path;
     }
 
+    /// The line used to check for a clear path depends on which end it is drawn from, so standard
+    /// FOV can see a tile that cannot see back. Symmetric FOV requires visibility both ways,
+    /// except for tiles that block sight, which are only ever seen from outside.
+    pub fn is_in_fov_with(&self, start_pos: Pos, end_pos: Pos, radius: i32, low: bool, fov_algorithm: FovAlgorithm) -> bool {
+        let visible = self.is_in_fov(start_pos, end_pos, radius, low);
+
+        if fov_algorithm == FovAlgorithm::Symmetric &&
+           visible &&
+           self.is_within_bounds(end_pos) &&
+           !self[end_pos].block_sight {
+            return self.is_in_fov(end_pos, start_pos, radius, low);
+        }
+
+        return visible;
+    }
+
     pub fn is_in_fov_shadowcast(&self, start_pos: Pos, end_pos: Pos) -> bool {
-        if let Some(visible) = self.fov_cache.borrow_mut().get(&start_pos) {
-            return visible.contains(&end_pos);
+        if let Some(visible) = self.fov_cache.borrow().get(&start_pos) {
+            return visible.is_visible(end_pos);
         }
 
-        // NOTE(perf) this pre-allocation speeds up FOV significantly
-        let mut visible_positions = Vec::with_capacity(120);
+        let fov_map = self.compute_fov(start_pos);
+        let in_fov = fov_map.is_visible(end_pos);
+        self.fov_cache.borrow_mut().insert(start_pos, fov_map);
+
+        return in_fov;
+    }
+
+    /// The tiles visible from a position by shadowcasting, ignoring radius and walls
+    /// between tiles.
+    pub fn compute_fov(&self, start_pos: Pos) -> FovMap {
+        let mut fov_map = FovMap::new(self.width(), self.height());
 
         let mut mark_fov = |sym_pos: SymPos| {
             let pos = Pos::new(sym_pos.0 as i32, sym_pos.1 as i32);
-            visible_positions.push(pos);
+            fov_map.set_visible(pos);
         };
 
         let mut is_blocking = |sym_pos: SymPos| {
@@ -981,16 +1190,13 @@ impl Map {
 
         compute_fov((start_pos.x as isize, start_pos.y as isize), &mut is_blocking, &mut mark_fov);
 
-        let in_fov = visible_positions.contains(&end_pos);
-        self.fov_cache.borrow_mut().insert(start_pos, visible_positions);
-
-        return in_fov;
+        return fov_map;
     }
 
-    pub fn is_in_fov_direction(&self, start_pos: Pos, end_pos: Pos, radius: i32, dir: Direction, low: bool) -> bool {
+    pub fn is_in_fov_direction(&self, start_pos: Pos, end_pos: Pos, radius: i32, dir: Direction, low: bool, fov_algorithm: FovAlgorithm) -> bool {
         if start_pos == end_pos {
             return true;
-        } else if self.is_in_fov(start_pos, end_pos, radius, low) {
+        } else if self.is_in_fov_with(start_pos, end_pos, radius, low, fov_algorithm) {
             return visible_in_direction(start_pos, end_pos, dir);
         } else {
             return false;
@@ -1100,6 +1306,13 @@ impl Map {
                          .collect::<Vec<Pos>>();
     }
 
+    /// Mark every tile as explored.
+    pub fn explore_all(&mut self) {
+        for pos in self.get_all_pos() {
+            self[pos].explored = true;
+        }
+    }
+
     pub fn clamp(&self, pos: Pos) -> Pos {
         let (width, height) = self.size();
         let new_x = std::cmp::min(width - 1, std::cmp::max(0, pos.x));
@@ -1485,3 +1698,65 @@ fn test_path_blocked_all() {
     assert_eq!(Wall::TallWall, blocked_positions[3].wall_type);
 }
 
+
+#[test]
+fn test_symmetric_fov() {
+    let mut map = Map::from_dims(10, 10);
+    map[(4, 4)] = Tile::wall();
+    map[(5, 6)] = Tile::wall();
+
+    let start = Pos::new(1, 2);
+    let end = Pos::new(5, 4);
+    let radius = 6;
+
+    // the line from start to end is clear, but the line back is blocked by the wall
+    assert!(map.is_in_fov(start, end, radius, false));
+    assert!(!map.is_in_fov(end, start, radius, false));
+
+    assert!(!map.is_in_fov_with(start, end, radius, false, FovAlgorithm::Symmetric));
+    assert!(!map.is_in_fov_with(end, start, radius, false, FovAlgorithm::Symmetric));
+
+    // walls are still seen from outside
+    assert!(map.is_in_fov_with(Pos::new(4, 2), Pos::new(4, 4), radius, false, FovAlgorithm::Symmetric));
+
+    // every pair of open tiles agrees on whether they can see each other
+    for pos in map.get_all_pos() {
+        for other in map.get_all_pos() {
+            if !map[pos].block_sight && !map[other].block_sight {
+                assert_eq!(map.is_in_fov_with(pos, other, radius, false, FovAlgorithm::Symmetric),
+                           map.is_in_fov_with(other, pos, radius, false, FovAlgorithm::Symmetric));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_fov_map() {
+    let mut map = Map::from_dims(15, 15);
+    for pos in vec!((4, 4), (4, 5), (10, 3), (9, 9), (10, 9), (11, 9), (3, 11), (7, 12)) {
+        map[pos] = Tile::wall();
+    }
+
+    // shadowcasting broadly agrees with checking for a clear line to each tile
+    let start = Pos::new(7, 7);
+    let fov_map = map.compute_fov(start);
+    let mut agree = 0;
+    for pos in map.get_all_pos() {
+        let line_visible = map.path_blocked_fov(start, pos).map_or(true, |blocked| blocked.end_pos == pos && blocked.blocked_tile);
+        if line_visible == fov_map.is_visible(pos) {
+            agree += 1;
+        }
+    }
+    assert!(agree as f32 / map.get_all_pos().len() as f32 > 0.9);
+
+    assert!(fov_map.is_visible(start));
+    assert!(fov_map.is_visible(Pos::new(9, 9)));
+    assert!(!fov_map.is_visible(Pos::new(11, 11)));
+    assert!(!fov_map.is_visible(Pos::new(-1, 0)));
+
+    // the visible tiles survive a round trip through serialization
+    let fov_string = serde_yaml::to_string(&fov_map).unwrap();
+    let loaded: FovMap = serde_yaml::from_str(&fov_string).unwrap();
+    assert_eq!(fov_map, loaded);
+    assert_eq!(fov_map.visible_positions(), loaded.visible_positions());
+}
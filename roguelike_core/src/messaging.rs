@@ -29,9 +29,11 @@ pub enum Msg {
     ItemThrow(EntityId, EntityId, Pos, Pos), // thrower, stone id, start, end
     TryAttack(EntityId, Attack, Pos), // attacker, attack description, attack pos
     Attack(EntityId, EntityId, Hp), // attacker, attacked, hp lost
+    AttackStarted(EntityId, EntityId), // attacker, attacked
     Killed(EntityId, EntityId, Hp), // attacker, attacked, hp lost
     Push(EntityId, Direction, usize), // attacker, direction, amount
     Pushed(EntityId, EntityId, Direction, usize, bool), // attacker, attacked, direction, amount, move into pushed square
+    Knockback(EntityId, EntityId, Pos), // attacker, attacked, position knocked into
     TryMove(EntityId, Direction, usize, MoveMode),
     Moved(EntityId, MoveType, Pos),
     Interact(EntityId, Pos),
@@ -40,6 +42,7 @@ pub enum Msg {
     StateChange(EntityId, Behavior),
     Collided(EntityId, Pos),
     Yell(EntityId),
+    ChargeAttack(EntityId, usize), // entity, charge
     ChangeMoveMode(EntityId, bool), // true = increase, false = decrease
     MoveMode(EntityId, MoveMode),
     TriedRunWithHeavyEquipment,
@@ -51,13 +54,25 @@ pub enum Msg {
     FailedBlink(EntityId),
     NotEnoughEnergy(EntityId),
     DropFailed(EntityId),
-    DroppedItem(EntityId, EntityId),
+    EmptySlot(EntityId, usize), // entity, inventory slot
+    DroppedItem(EntityId, EntityId), // entity, item
+    ItemStacked(EntityId, EntityId), // entity, item
+    ItemBroke(EntityId, EntityId), // entity, item
+    LevelFeeling(LevelFeeling),
+    StatusApplied(EntityId, StatusKind, usize), // entity, status, turns
+    StatusExpired(EntityId, StatusKind),
+    PoisonDamage(EntityId, Hp), // entity, hp lost
+    BurnDamage(EntityId, Hp), // entity, hp lost
+    LevelUp(EntityId, usize), // entity, new level
+    WokeUp(EntityId),
+    Examined(EntityId, Pos), // entity, examined position
     PlayerTurn,
     Triggered(EntityId, EntityId), // trap, entity
     Untriggered(EntityId, EntityId), // trap, entity
     AddClass(EntityClass),
     SwapPrimaryItem,
     DropItem(EntityId, EntityId), // entity, item
+    DropAll(EntityId),
     GrassThrow(EntityId, Direction),
     GrassShoes(EntityId, ActionMode),
     GrassBlade(EntityId, ActionMode, Direction),
@@ -65,6 +80,7 @@ pub enum Msg {
     Heal(EntityId, usize), // entity, amount
     FarSight(EntityId, usize), // entity, amount
     Sprint(EntityId, Direction, usize), // entity, direction, amount
+    TimeStop(EntityId, usize), // entity, turns
     Rubble(EntityId, Pos),
     Reform(EntityId, Pos),
     Swap(EntityId, EntityId), // casting entity, entity to swap with
@@ -78,6 +94,8 @@ pub enum Msg {
     Facing(EntityId, Direction),
     AiAttack(EntityId),
     RemovedEntity(EntityId),
+    SaveFailed,
+    LoadFailed,
 }
 
 impl fmt::Display for Msg {
@@ -106,9 +124,11 @@ impl fmt::Display for Msg {
                 }
             }
             Msg::Attack(entity_id, target_id, hp) => write!(f, "attack {} {} {}", entity_id, target_id, hp),
+            Msg::AttackStarted(entity_id, target_id) => write!(f, "attack_started {} {}", entity_id, target_id),
             Msg::Killed(entity_id, target_id, hp) => write!(f, "killed {} {} {}", entity_id, target_id, hp),
             Msg::Push(entity_id, direction, amount) => write!(f, "pushed {} {} {}", entity_id, direction, amount),
             Msg::Pushed(entity_id, target_id, direction, amount, follow) => write!(f, "pushed {} {} {} {} {}", entity_id, target_id, direction, amount, follow),
+            Msg::Knockback(entity_id, target_id, pos) => write!(f, "knockback {} {} {} {}", entity_id, target_id, pos.x, pos.y),
             Msg::TryMove(entity_id, direction, amount, move_mode) => write!(f, "try_move {} {} {} {}", entity_id, direction, amount, move_mode),
             Msg::Moved(entity_id, move_type, pos) => write!(f, "moved {} {} {} {}", entity_id, move_type, pos.x, pos.y),
             Msg::Interact(entity_id, pos) => write!(f, "interact {} {} {}", entity_id, pos.x, pos.y),
@@ -119,10 +139,14 @@ impl fmt::Display for Msg {
                     Behavior::Idle => write!(f, "state_change_idle {}", entity_id),
                     Behavior::Investigating(pos) => write!(f, "state_change_investigating {} {} {}", entity_id, pos.x, pos.y),
                     Behavior::Attacking(target_id) => write!(f, "state_change_attacking {} {}", entity_id, target_id),
+                    Behavior::Fleeing(pos) => write!(f, "state_change_fleeing {} {} {}", entity_id, pos.x, pos.y),
+                    Behavior::Patrolling(index, patrol_dir) => write!(f, "state_change_patrolling {} {} {}", entity_id, index, patrol_dir),
                 }
             }
             Msg::Collided(entity_id, pos) => write!(f, "collided {} {} {}", entity_id, pos.x, pos.y),
             Msg::Yell(entity_id) => write!(f, "yell {}", entity_id),
+            Msg::ChargeAttack(entity_id, charge) => write!(f, "charge_attack {} {}", entity_id, charge),
+            Msg::EmptySlot(entity_id, slot) => write!(f, "empty_slot {} {}", entity_id, slot),
             Msg::ChangeMoveMode(entity_id, upwards) => write!(f, "chage_move_mode {} {}", entity_id, upwards),
             Msg::MoveMode(entity_id, move_mode) => write!(f, "move_mode {} {}", entity_id, move_mode),
             Msg::TriedRunWithHeavyEquipment => write!(f, "tried_run_with_heavy_equipment"),
@@ -134,13 +158,24 @@ impl fmt::Display for Msg {
             Msg::FailedBlink(entity_id) => write!(f, "failed_blink {}", entity_id),
             Msg::NotEnoughEnergy(entity_id) => write!(f, "not_enough_energy {}", entity_id),
             Msg::DropFailed(entity_id) => write!(f, "drop_failed {}", entity_id),
-            Msg::DroppedItem(entity_id, trap_id) => write!(f, "dropped_item {} {}", entity_id, trap_id),
+            Msg::DroppedItem(entity_id, item_id) => write!(f, "dropped_item {} {}", entity_id, item_id),
+            Msg::ItemStacked(entity_id, item_id) => write!(f, "item_stacked {} {}", entity_id, item_id),
+            Msg::ItemBroke(entity_id, item_id) => write!(f, "item_broke {} {}", entity_id, item_id),
+            Msg::LevelFeeling(feeling) => write!(f, "level_feeling {}", feeling),
+            Msg::StatusApplied(entity_id, kind, turns) => write!(f, "status_applied {} {} {}", entity_id, kind, turns),
+            Msg::StatusExpired(entity_id, kind) => write!(f, "status_expired {} {}", entity_id, kind),
+            Msg::PoisonDamage(entity_id, hp) => write!(f, "poison_damage {} {}", entity_id, hp),
+            Msg::BurnDamage(entity_id, hp) => write!(f, "burn_damage {} {}", entity_id, hp),
+            Msg::LevelUp(entity_id, level) => write!(f, "level_up {} {}", entity_id, level),
+            Msg::WokeUp(entity_id) => write!(f, "woke_up {}", entity_id),
+            Msg::Examined(entity_id, pos) => write!(f, "examined {} {} {}", entity_id, pos.x, pos.y),
             Msg::PlayerTurn => write!(f, "player_turn"),
             Msg::Triggered(trap_id, entity_id) => write!(f, "triggered {} {}", trap_id, entity_id),
             Msg::Untriggered(trap_id, entity_id) => write!(f, "untriggered {} {}", trap_id, entity_id),
             Msg::AddClass(entity_class) => write!(f, "add_class {}", entity_class),
             Msg::SwapPrimaryItem => write!(f, "swap_primary_item"),
             Msg::DropItem(entity_id, item_id) => write!(f, "drop_item {} {}", entity_id, item_id),
+            Msg::DropAll(entity_id) => write!(f, "drop_all {}", entity_id),
             Msg::GrassThrow(entity_id, direction) => write!(f, "grass_throw {} {}", entity_id, direction),
             Msg::GrassShoes(entity_id, action_mode) => write!(f, "grass_shoes {} {}", entity_id, action_mode),
             Msg::GrassBlade(entity_id, action_mode, direction) => write!(f, "grass_blade {} {} {}", entity_id, action_mode, direction),
@@ -148,6 +183,7 @@ impl fmt::Display for Msg {
             Msg::Heal(entity_id, amount) => write!(f, "heal {} {}", entity_id, amount),
             Msg::FarSight(entity_id, amount) => write!(f, "farsight {} {}", entity_id, amount),
             Msg::Sprint(entity_id, direction, amount) => write!(f, "sprint {} {} {}", entity_id, direction, amount),
+            Msg::TimeStop(entity_id, turns) => write!(f, "timestop {} {}", entity_id, turns),
             Msg::Rubble(entity_id, pos) => write!(f, "rubble {} {} {}", entity_id, pos.x, pos.y),
             Msg::Reform(entity_id, pos) => write!(f, "reform {} {} {}", entity_id, pos.x, pos.y),
             Msg::Swap(entity_id, target_id) => write!(f, "swap {} {}", entity_id, target_id),
@@ -161,6 +197,8 @@ impl fmt::Display for Msg {
             Msg::Facing(entity_id, direction) => write!(f, "facing {} {}", entity_id, direction),
             Msg::AiAttack(entity_id) => write!(f, "ai_attack {}", entity_id),
             Msg::RemovedEntity(entity_id) => write!(f, "removed {}", entity_id),
+            Msg::SaveFailed => write!(f, "save_failed"),
+            Msg::LoadFailed => write!(f, "load_failed"),
         }
     }
 }
@@ -243,6 +281,10 @@ impl Msg {
                 return format!("{:?} pushed {:?}", data.entities.name[attacker], data.entities.name[attacked]);
             }
 
+            Msg::Knockback(attacker, attacked, _pos) => {
+                return format!("{:?} knocked back {:?}", data.entities.name[attacker], data.entities.name[attacked]);
+            }
+
             Msg::Interact(_entity_id, _pos) => {
                 return "".to_string();
             }
@@ -275,6 +317,14 @@ impl Msg {
                 return format!("{:?} yelled", data.entities.name[entity_id]);
             }
 
+            Msg::ChargeAttack(entity_id, charge) => {
+                return format!("{:?} releases a charged attack ({})", data.entities.name[entity_id], charge);
+            }
+
+            Msg::EmptySlot(_entity_id, slot) => {
+                return format!("Nothing in slot {}", slot + 1);
+            }
+
             Msg::Collided(_entity_id, _pos) => {
                 return "Collided".to_string();
             }
@@ -335,6 +385,82 @@ impl Msg {
                 return format!("{:?} does not have enough energy for that", data.entities.name[entity_id]);
             }
 
+            Msg::ItemBroke(entity_id, item_id) => {
+                if data.entities.typ[entity_id] == EntityType::Player {
+                    return format!("Your {} breaks!", data.entities.name[item_id]);
+                } else {
+                    return format!("{:?}'s {} breaks!", data.entities.name[entity_id], data.entities.name[item_id]);
+                }
+            }
+
+            Msg::StatusApplied(entity_id, kind, _turns) => {
+                match kind {
+                    StatusKind::Frozen => return format!("{:?} is frozen", data.entities.name[entity_id]),
+                    StatusKind::SoftSteps => return format!("{:?} steps softly", data.entities.name[entity_id]),
+                    StatusKind::Grace => return format!("{:?} braces against further blows", data.entities.name[entity_id]),
+                    StatusKind::Poison => return format!("{:?} is poisoned", data.entities.name[entity_id]),
+                    StatusKind::Burning => return format!("{:?} catches fire", data.entities.name[entity_id]),
+                }
+            }
+
+            Msg::PoisonDamage(entity_id, hp) => {
+                return format!("{:?} takes {} poison damage", data.entities.name[entity_id], hp);
+            }
+
+            Msg::BurnDamage(entity_id, hp) => {
+                return format!("{:?} takes {} burn damage", data.entities.name[entity_id], hp);
+            }
+
+            Msg::LevelUp(entity_id, level) => {
+                return format!("{:?} reached level {}!", data.entities.name[entity_id], level);
+            }
+
+            Msg::WokeUp(entity_id) => {
+                return format!("{:?} woke up!", data.entities.name[entity_id]);
+            }
+
+            Msg::Examined(_entity_id, pos) => {
+                if !data.map.is_within_bounds(*pos) || !data.map[*pos].explored {
+                    return "You can't see there".to_string();
+                }
+
+                let names = data.has_entities(*pos).iter()
+                                .map(|id| format!("{:?}", data.entities.name[id]))
+                                .collect::<Vec<String>>();
+                if names.len() > 0 {
+                    return format!("You see {}", names.join(", "));
+                } else {
+                    return format!("You see {:?}", data.map[*pos].tile_type);
+                }
+            }
+
+            Msg::StatusExpired(entity_id, kind) => {
+                match kind {
+                    StatusKind::Frozen => return format!("{:?} is no longer frozen", data.entities.name[entity_id]),
+                    StatusKind::SoftSteps => return format!("{:?} no longer steps softly", data.entities.name[entity_id]),
+                    StatusKind::Grace => return format!("{:?} is vulnerable again", data.entities.name[entity_id]),
+                    StatusKind::Poison => return format!("{:?} is no longer poisoned", data.entities.name[entity_id]),
+                    StatusKind::Burning => return format!("{:?} is no longer burning", data.entities.name[entity_id]),
+                }
+            }
+
+            Msg::SaveFailed => {
+                return "Could not save the game!".to_string();
+            }
+
+            Msg::LoadFailed => {
+                return "Could not load the saved game!".to_string();
+            }
+
+            Msg::LevelFeeling(feeling) => {
+                match feeling {
+                    LevelFeeling::Quiet => return "This level seems quiet.".to_string(),
+                    LevelFeeling::Rewarding => return "You sense treasure nearby.".to_string(),
+                    LevelFeeling::Uneasy => return "You feel uneasy.".to_string(),
+                    LevelFeeling::Dangerous => return "You feel a sense of dread!".to_string(),
+                }
+            }
+
             Msg::DropFailed(entity_id) => {
                 return format!("{:?} tried to drop an item, but its too crowded!", data.entities.name[entity_id]);
             }
@@ -359,12 +485,16 @@ impl Msg {
                 return format!("Player swapped primary and secondary item");
             }
 
-            Msg::DropItem(entity_id, item_index) => {
-                if let Some(item_id) = data.entities.inventory[entity_id].get(*item_index as usize) {
-                    return format!("{:?} dropped a {:?}!", data.entities.name[entity_id], item_id);
-                } else {
-                    return "".to_string();
-                }
+            Msg::DropItem(entity_id, item_id) => {
+                return format!("{:?} dropped a {:?}!", data.entities.name[entity_id], data.entities.name[item_id]);
+            }
+
+            Msg::DroppedItem(entity_id, item_id) => {
+                return format!("{:?} dropped a {:?}!", data.entities.name[entity_id], data.entities.name[item_id]);
+            }
+
+            Msg::ItemStacked(entity_id, item_id) => {
+                return format!("{:?} had no room, so the {:?} was stacked", data.entities.name[entity_id], data.entities.name[item_id]);
             }
 
             Msg::GrassThrow(entity_id, direction) => {
@@ -395,6 +525,10 @@ impl Msg {
                 return format!("{:?} has sprinted!", data.entities.name[entity_id]);
             }
 
+            Msg::TimeStop(entity_id, turns) => {
+                return format!("{:?} stopped time for {} turns!", data.entities.name[entity_id], turns);
+            }
+
             Msg::Rubble(entity_id, pos) => {
                 return format!("{:?} turned a wall to rubble at {}", data.entities.name[entity_id], pos);
             }
@@ -438,13 +572,23 @@ impl Msg {
 pub struct MsgLog {
     pub messages: VecDeque<Msg>,
     pub turn_messages: VecDeque<Msg>,
+    pub history: VecDeque<Msg>,
+    pub history_limit: usize,
 }
 
 impl MsgLog {
     pub fn new() -> MsgLog {
+        return MsgLog::with_history(0);
+    }
+
+    /// A message log that keeps up to the given number of processed messages,
+    /// which are not cleared between turns.
+    pub fn with_history(history_limit: usize) -> MsgLog {
         return MsgLog {
             messages: VecDeque::new(),
             turn_messages: VecDeque::new(),
+            history: VecDeque::new(),
+            history_limit,
         };
     }
 
@@ -452,6 +596,11 @@ impl MsgLog {
         let msg = self.messages.pop_front();
         if let Some(msg) = msg {
             self.turn_messages.push_back(msg);
+
+            self.history.push_back(msg);
+            while self.history.len() > self.history_limit {
+                self.history.pop_front();
+            }
         }
         return msg;
     }
@@ -470,3 +619,18 @@ impl MsgLog {
     }
 }
 
+
+#[test]
+fn test_msg_history_limit() {
+    let mut msg_log = MsgLog::with_history(3);
+
+    for turn in 0..5 {
+        msg_log.log(Msg::NotEnoughEnergy(turn));
+        msg_log.pop();
+        msg_log.clear();
+    }
+
+    // only the newest messages are kept, and clearing a turn does not remove them
+    let history = msg_log.history.iter().map(|msg| *msg).collect::<Vec<Msg>>();
+    assert_eq!(vec!(Msg::NotEnoughEnergy(2), Msg::NotEnoughEnergy(3), Msg::NotEnoughEnergy(4)), history);
+}
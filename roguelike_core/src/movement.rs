@@ -7,8 +7,9 @@ use euclid::*;
 use serde::{Serialize, Deserialize};
 
 use crate::types::*;
+use crate::config::Config;
 use crate::utils::*;
-use crate::map::{Wall, Blocked, TileType};
+use crate::map::{Wall, Blocked, BlockedType, TileType};
 use crate::line::*;
 
 
@@ -477,6 +478,7 @@ pub enum Reach {
     Single(usize),
     Diag(usize),
     Horiz(usize),
+    Knight,
 }
 
 impl Reach {
@@ -496,11 +498,16 @@ impl Reach {
         return Reach::Horiz(dist);
     }
 
+    pub fn knight() -> Reach {
+        return Reach::Knight;
+    }
+
     pub fn dist(&self) -> usize {
         match self {
             Reach::Single(dist) => *dist,
             Reach::Diag(dist) => *dist,
             Reach::Horiz(dist) => *dist,
+            Reach::Knight => 2,
         }
     }
 
@@ -509,15 +516,27 @@ impl Reach {
             Reach::Single(_) => Reach::Single(dist),
             Reach::Diag(_) => Reach::Diag(dist),
             Reach::Horiz(_) => Reach::Horiz(dist),
+            Reach::Knight => Reach::Knight,
         }
     }
 
+    /// Whether moves with this reach jump directly to their end position, rather than
+    /// passing over the tiles in between.
+    pub fn jumps(&self) -> bool {
+        return matches!(self, Reach::Knight);
+    }
+
     pub fn furthest_in_direction(&self, pos: Pos, dir: Direction) -> Option<Pos> {
+        if self.jumps() {
+            return self.move_with_reach(&dir).map(|offset| add_pos(pos, offset));
+        }
+
         let valid = 
             match self {
                 Reach::Diag(_) => dir.diag(),
                 Reach::Horiz(_) => dir.horiz(),
                 Reach::Single(_) => true,
+                Reach::Knight => true,
             };
 
         if valid {
@@ -546,8 +565,12 @@ impl Reach {
         let mut positions = Vec::new();
 
         if let Some(pos) = self.move_with_reach(move_action) {
-            for pos in line_inclusive(Pos::new(0, 0), pos) {
-                positions.push(Pos::from(pos));
+            if self.jumps() {
+                positions.push(pos);
+            } else {
+                for pos in line_inclusive(Pos::new(0, 0), pos) {
+                    positions.push(Pos::from(pos));
+                }
             }
         }
 
@@ -600,6 +623,20 @@ impl Reach {
                     Direction::UpRight => None,
                 }
             }
+
+            // each direction jumps to the knight's move just clockwise of it
+            Reach::Knight => {
+                match move_action {
+                    Direction::Up => Some(Pos::new(1, -2)),
+                    Direction::UpRight => Some(Pos::new(2, -1)),
+                    Direction::Right => Some(Pos::new(2, 1)),
+                    Direction::DownRight => Some(Pos::new(1, 2)),
+                    Direction::Down => Some(Pos::new(-1, 2)),
+                    Direction::DownLeft => Some(Pos::new(-2, 1)),
+                    Direction::Left => Some(Pos::new(-2, -1)),
+                    Direction::UpLeft => Some(Pos::new(-1, -2)),
+                }
+            }
         }
     }
 
@@ -647,6 +684,13 @@ impl Reach {
                 }
                 end_points = offsets.iter().map(|pair| Pos::from(*pair)).collect();
             },
+
+            Reach::Knight => {
+                // jumps pass over the tiles between the start and end
+                return Direction::move_actions().iter()
+                                                .filter_map(|dir| self.move_with_reach(dir))
+                                                .collect::<Vec<Pos>>();
+            },
         }
 
         let mut offsets = Vec::new();
@@ -672,6 +716,24 @@ pub fn test_reach_offsets_horiz() {
     assert!(offsets.iter().all(|p| expected_pos.iter().any(|other| other == p)));
 }
 
+#[test]
+pub fn test_reach_offsets_knight() {
+    let knight = Reach::Knight;
+    let offsets = knight.offsets();
+
+    let expected_pos =
+        vec!((1, 2), (2, 1), (-1, 2), (-2, 1), (1, -2), (2, -1), (-1, -2), (-2, -1)).iter()
+                                                                                   .map(|p| Pos::from(*p))
+                                                                                   .collect::<Vec<Pos>>();
+    assert_eq!(8, offsets.len());
+    assert!(offsets.iter().all(|p| expected_pos.iter().any(|other| other == p)));
+
+    // every direction is a different jump
+    let moves = Direction::move_actions().iter().filter_map(|dir| knight.move_with_reach(dir)).collect::<Vec<Pos>>();
+    assert!(expected_pos.iter().all(|p| moves.contains(p)));
+    assert_eq!(vec!(Pos::new(2, 1)), knight.attacks_with_reach(&Direction::Right));
+}
+
 #[test]
 pub fn test_reach_offsets_diag() {
     let horiz = Reach::Diag(1);
@@ -718,6 +780,10 @@ impl MoveResult {
     pub fn no_collision(&self) -> bool {
         return self.blocked.is_none() && self.entity.is_none();
     }
+
+    pub fn hit_wall(&self) -> bool {
+        return self.blocked.is_some();
+    }
 }
 
 /// Moves the given object with a given offset, returning the square that it collides with, or None
@@ -728,19 +794,34 @@ impl MoveResult {
 pub fn check_collision(pos: Pos,
                        dx: i32,
                        dy: i32,
-                       data: &GameData) -> MoveResult {
+                       data: &GameData,
+                       config: &Config) -> MoveResult {
     let mut last_pos = pos;
     let mut result: MoveResult =
         MoveResult::with_pos(pos + Vector2D::new(dx, dy));
 
     // if no movement occurs, no need to check walls and entities.
     if !(dx == 0 && dy == 0) {
-        if let Some(blocked) = data.map.path_blocked_move(pos, Pos::new(pos.x + dx, pos.y + dy)) {
-            result.blocked = Some(blocked);
-            result.move_pos = blocked.start_pos;
+        let target_pos = Pos::new(pos.x + dx, pos.y + dy);
+        let maybe_blocked =
+            if config.thick_line_collision {
+                data.map.path_blocked_thick(pos, target_pos, BlockedType::Move)
+            } else {
+                data.map.path_blocked_move(pos, target_pos)
+            };
+
+        // without strict diagonals, a single diagonal step can squeeze between two walls
+        let squeeze = !config.strict_diagonals && data.map.corner_squeeze(pos, target_pos);
+
+        if let Some(blocked) = maybe_blocked {
+            if !squeeze {
+                result.blocked = Some(blocked);
+                result.move_pos = blocked.start_pos;
+            }
         } 
 
-        // check for collision with an enitity
+        // check for collision with an enitity. The line is walked from the mover outwards,
+        // so the nearest entity is found first, with ties on a tile broken by id.
         let move_line = line_inclusive(pos, Pos::new(pos.x + dx, pos.y + dy));
 
         for line_tuple in move_line {
@@ -767,6 +848,19 @@ pub fn check_collision(pos: Pos,
     return result;
 }
 
+/// A jump only collides with its landing tile. Jumping onto a blocked tile or
+/// another blocking entity is not a valid move.
+pub fn entity_jump(entity_id: EntityId, delta_pos: Pos, data: &GameData) -> Option<Movement> {
+    let pos = data.entities.pos[&entity_id];
+    let jump_pos = add_pos(pos, delta_pos);
+
+    if data.clear_jump(jump_pos, false) {
+        return Some(Movement::move_to(jump_pos, MoveType::Move));
+    } else {
+        return None;
+    }
+}
+
 pub fn entity_move_not_blocked(entity_id: EntityId, move_pos: Pos, delta_pos: Pos, data: &GameData) -> Option<Movement> {
     let movement: Option<Movement>;
 
@@ -931,17 +1025,20 @@ pub fn entity_move_blocked_by_entity_and_wall(entity_id: EntityId, other_id: Ent
 pub fn calculate_move(dir: Direction,
                       reach: Reach,
                       entity_id: EntityId,
-                      data: &GameData) -> Option<Movement> {
+                      data: &GameData,
+                      config: &Config) -> Option<Movement> {
     let mut movement: Option<Movement>;
 
     let pos = data.entities.pos[&entity_id];
 
     // get the location we would move to given the input direction
-    if let Some(delta_pos) = reach.move_with_reach(&dir) {
+    if reach.jumps() {
+        movement = reach.move_with_reach(&dir).and_then(|delta_pos| entity_jump(entity_id, delta_pos, data));
+    } else if let Some(delta_pos) = reach.move_with_reach(&dir) {
         let (dx, dy) = delta_pos.to_tuple();
 
         // check if movement collides with a blocked location or an entity
-        let move_result = check_collision(pos, dx, dy, data);
+        let move_result = check_collision(pos, dx, dy, data, config);
 
         match (move_result.blocked, move_result.entity) {
             // both blocked by wall and by entity
@@ -975,9 +1072,51 @@ pub fn calculate_move(dir: Direction,
         }
     }
 
+    if movement.is_none() && config.wall_slide && dir.diag() &&
+       data.entities.typ[&entity_id] == EntityType::Player {
+        movement = wall_slide_move(dir, reach, entity_id, data, config);
+    }
+
     return movement;
 }
 
+/// A diagonal move that went nowhere instead moves along whichever of its two axes is open.
+/// If both axes are open, or neither is, there is no slide.
+fn wall_slide_move(dir: Direction,
+                   reach: Reach,
+                   entity_id: EntityId,
+                   data: &GameData,
+                   config: &Config) -> Option<Movement> {
+    let dir_pos = dir.into_move();
+    let horiz_dir = Direction::from_dxy(dir_pos.x, 0).unwrap();
+    let vert_dir = Direction::from_dxy(0, dir_pos.y).unwrap();
+
+    let horiz_move = calculate_move(horiz_dir, reach, entity_id, data, config);
+    let vert_move = calculate_move(vert_dir, reach, entity_id, data, config);
+
+    match (horiz_move, vert_move) {
+        (Some(movement), None) => return Some(movement),
+        (None, Some(movement)) => return Some(movement),
+        _ => return None,
+    }
+}
+
+/// All positions an entity could move to this turn with the given reach,
+/// including staying in place. Moves that would become attacks are not included.
+pub fn reachable_positions(entity_id: EntityId, reach: Reach, data: &GameData, config: &Config) -> Vec<Pos> {
+    let mut positions = vec!(data.entities.pos[&entity_id]);
+
+    for move_action in Direction::move_actions().iter() {
+        if let Some(movement) = calculate_move(*move_action, reach, entity_id, data, config) {
+            if movement.attack.is_none() && !positions.contains(&movement.pos) {
+                positions.push(movement.pos);
+            }
+        }
+    }
+
+    return positions;
+}
+
 pub fn direction(value: i32) -> i32 {
     if value == 0 {
         return 0;
@@ -986,3 +1125,133 @@ pub fn direction(value: i32) -> i32 {
     }
 }
 
+
+#[test]
+pub fn test_check_collision_thick_line() {
+    use crate::map::Tile;
+
+    let mut config = Config::from_file("../config.yaml");
+    let mut data = GameData::empty(10, 10);
+
+    // a wall next to the diagonal path, but not on it
+    data.map[(2, 1)] = Tile::wall();
+
+    let pos = Pos::new(0, 0);
+
+    config.thick_line_collision = false;
+    let thin_result = check_collision(pos, 3, 3, &data, &config);
+    assert!(thin_result.blocked.is_none());
+    assert_eq!(Pos::new(3, 3), thin_result.move_pos);
+
+    config.thick_line_collision = true;
+    let thick_result = check_collision(pos, 3, 3, &data, &config);
+    assert!(thick_result.blocked.is_some());
+    assert_eq!(Pos::new(1, 1), thick_result.move_pos);
+}
+
+#[test]
+pub fn test_reachable_positions() {
+    let config = Config::from_file("../config.yaml");
+    let mut data = GameData::empty(10, 10);
+
+    let pos = Pos::new(4, 4);
+    let entity_id = data.entities.create_entity(pos.x, pos.y, EntityType::Player, '@', Color::white(), EntityName::Player, true);
+
+    let mut positions = reachable_positions(entity_id, Reach::single(1), &data, &config);
+
+    let mut expected = Vec::new();
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            expected.push(Pos::new(pos.x + dx, pos.y + dy));
+        }
+    }
+
+    positions.sort_by_key(|pos| (pos.x, pos.y));
+    expected.sort_by_key(|pos| (pos.x, pos.y));
+    assert_eq!(expected, positions);
+}
+
+#[test]
+pub fn test_collision_nearest_entity_first() {
+    let config = Config::from_file("../config.yaml");
+    let mut data = GameData::empty(10, 10);
+
+    let pos = Pos::new(1, 4);
+    let entity_id = data.entities.create_entity(pos.x, pos.y, EntityType::Player, '@', Color::white(), EntityName::Player, true);
+
+    // the farther entity is created first, so it comes first in storage order
+    let far_id = data.entities.create_entity(4, 4, EntityType::Enemy, 'g', Color::white(), EntityName::Gol, true);
+    let near_id = data.entities.create_entity(3, 4, EntityType::Enemy, 'g', Color::white(), EntityName::Gol, true);
+    let result = check_collision(data.entities.pos[&entity_id], 3, 0, &data, &config);
+    assert_eq!(Some(near_id), result.entity);
+
+    // two entities on the same tile resolve to the lower id, regardless of storage order
+    data.entities.pos[&far_id] = Pos::new(3, 4);
+    let far_pos = data.entities.pos.swap_remove(&far_id).unwrap();
+    data.entities.pos.insert(far_id, far_pos);
+    let result = check_collision(data.entities.pos[&entity_id], 3, 0, &data, &config);
+    assert_eq!(Some(far_id), result.entity);
+}
+
+#[test]
+pub fn test_wall_slide() {
+    use crate::map::Tile;
+
+    let mut config = Config::from_file("../config.yaml");
+    let mut data = GameData::empty(10, 10);
+
+    let pos = Pos::new(4, 4);
+    let entity_id = data.entities.create_entity(pos.x, pos.y, EntityType::Player, '@', Color::white(), EntityName::Player, true);
+    data.entities.move_mode.insert(entity_id, MoveMode::Walk);
+    data.entities.stance.insert(entity_id, Stance::Standing);
+
+    // a wall above the player, with the tile to the right open
+    for x in 0..10 {
+        data.map[(x, 3)] = Tile::wall();
+    }
+
+    config.wall_slide = false;
+    assert_eq!(None, calculate_move(Direction::UpRight, Reach::single(1), entity_id, &data, &config));
+
+    config.wall_slide = true;
+    let movement = calculate_move(Direction::UpRight, Reach::single(1), entity_id, &data, &config);
+    assert_eq!(Some(Pos::new(5, 4)), movement.map(|movement| movement.pos));
+
+    // with both axes open there is nothing to slide along
+    for x in 0..10 {
+        data.map[(x, 3)] = Tile::empty();
+    }
+    data.map[(5, 3)] = Tile::wall();
+    assert_eq!(None, calculate_move(Direction::UpRight, Reach::single(1), entity_id, &data, &config));
+}
+
+#[test]
+pub fn test_strict_diagonals() {
+    use crate::map::Tile;
+
+    let mut config = Config::from_file("../config.yaml");
+    let mut data = GameData::empty(10, 10);
+
+    // walls on both sides of the diagonal from (1, 1) to (2, 2)
+    data.map[(2, 1)] = Tile::wall();
+    data.map[(1, 2)] = Tile::wall();
+
+    config.strict_diagonals = true;
+    let result = check_collision(Pos::new(1, 1), 1, 1, &data, &config);
+    assert!(result.blocked.is_some());
+    assert_eq!(Pos::new(1, 1), result.move_pos);
+
+    config.strict_diagonals = false;
+    let result = check_collision(Pos::new(1, 1), 1, 1, &data, &config);
+    assert!(result.blocked.is_none());
+    assert_eq!(Pos::new(2, 2), result.move_pos);
+
+    // with only one wall next to it, the diagonal is open either way
+    data.map[(1, 2)] = Tile::empty();
+    for strict in [true, false] {
+        config.strict_diagonals = strict;
+        let result = check_collision(Pos::new(1, 1), 1, 1, &data, &config);
+        assert!(result.blocked.is_none());
+        assert_eq!(Pos::new(2, 2), result.move_pos);
+    }
+}
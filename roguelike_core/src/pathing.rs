@@ -0,0 +1,139 @@
+use pathfinding::directed::astar::astar;
+
+use crate::types::*;
+use crate::movement::{Reach, Direction};
+use crate::utils::{add_pos, distance_maximum};
+#[cfg(test)]
+use crate::map::{Map, Tile, Wall};
+
+
+/// Find a path from start to goal, stepping one tile at a time in any direction.
+/// See astar_path_with_reach.
+pub fn astar_path(start: Pos, goal: Pos, data: &GameData) -> Vec<Pos> {
+    return astar_path_with_reach(start, goal, Reach::single(1), data);
+}
+
+/// Find a path from start to goal, stepping one tile at a time in the directions the reach allows.
+/// Paths avoid blocked tiles, walls, blocking entities, and armed traps. If the goal itself is
+/// blocked, such as by the entity being chased, the path ends next to the goal instead.
+///
+/// The path includes the start, and is empty if there is no path. Neighbors are always
+/// considered in the same order, so ties between equal paths are broken the same way every time.
+pub fn astar_path_with_reach(start: Pos, goal: Pos, reach: Reach, data: &GameData) -> Vec<Pos> {
+    let reach = reach.with_dist(1);
+    let goal_blocked = !tile_open(goal, start, data);
+
+    let reached = |pos: &Pos| {
+        if goal_blocked {
+            return distance_maximum(*pos, goal) == 1;
+        } else {
+            return *pos == goal;
+        }
+    };
+
+    if reached(&start) {
+        return vec!(start);
+    }
+
+    let maybe_results =
+        astar(&start,
+              |&pos| {
+                  let mut next_positions = Vec::with_capacity(8);
+
+                  for direction in Direction::move_actions() {
+                      if let Some(offset) = reach.move_with_reach(&direction) {
+                          let next_pos = add_pos(pos, offset);
+
+                          let blocked =
+                              if reach.jumps() {
+                                  data.map.is_within_bounds(next_pos) && data.map[next_pos].block_move
+                              } else {
+                                  data.map.path_blocked_move(pos, next_pos).is_some()
+                              };
+
+                          if data.map.is_within_bounds(next_pos) &&
+                             !blocked &&
+                             tile_open(next_pos, start, data) {
+                              next_positions.push((next_pos, 1));
+                          }
+                      }
+                  }
+
+                  return next_positions;
+              },
+              |&pos| {
+                  let dist = distance_maximum(pos, goal);
+                  if goal_blocked {
+                      return std::cmp::max(0, dist - 1);
+                  } else {
+                      return dist;
+                  }
+              },
+              |pos| reached(pos));
+
+    if let Some((results, _cost)) = maybe_results {
+        return results;
+    }
+
+    return Vec::new();
+}
+
+/// Whether a path can pass through a tile. The start is ignored as the mover is standing there.
+fn tile_open(pos: Pos, start: Pos, data: &GameData) -> bool {
+    if !data.map.is_within_bounds(pos) || data.map[pos].block_move {
+        return false;
+    }
+
+    if pos == start {
+        return true;
+    }
+
+    let armed_trap = data.has_trap(pos).map_or(false, |trap_id| data.entities.armed.get(&trap_id) == Some(&true));
+
+    return data.has_blocking_entity(pos).is_none() && !armed_trap;
+}
+
+#[test]
+fn test_astar_path_around_wall() {
+    let mut map = Map::from_dims(10, 10);
+    for y in 0..9 {
+        map[(5, y)] = Tile::wall();
+    }
+    let data = GameData::new(map, Entities::new());
+
+    let path = astar_path(Pos::new(3, 3), Pos::new(7, 3), &data);
+    assert_eq!(Some(&Pos::new(3, 3)), path.first());
+    assert_eq!(Some(&Pos::new(7, 3)), path.last());
+    assert!(path.contains(&Pos::new(5, 9)));
+
+    // each step moves a single tile, and never through a wall
+    for step in path.windows(2) {
+        assert_eq!(1, distance_maximum(step[0], step[1]));
+        assert!(data.map.path_blocked_move(step[0], step[1]).is_none());
+    }
+
+    // the same path is found every time
+    assert_eq!(path, astar_path(Pos::new(3, 3), Pos::new(7, 3), &data));
+}
+
+#[test]
+fn test_astar_path_blocked_goal() {
+    let mut map = Map::from_dims(10, 10);
+    map[(5, 5)].left_wall = Wall::ShortWall;
+    let mut entities = Entities::new();
+    let blocker = entities.create_entity(5, 2, EntityType::Enemy, 'g', Color::white(), EntityName::Gol, true);
+    let data = GameData::new(map, entities);
+
+    // a blocked goal is approached as closely as possible
+    let path = astar_path(Pos::new(5, 7), Pos::new(5, 2), &data);
+    assert_eq!(Pos::new(5, 3), *path.last().unwrap());
+    assert_eq!(Some(blocker), data.has_blocking_entity(Pos::new(5, 2)));
+
+    let path = astar_path(Pos::new(5, 3), Pos::new(5, 2), &data);
+    assert_eq!(vec!(Pos::new(5, 3)), path);
+
+    // walls are respected, so the path steps around the wall's edge
+    let path = astar_path(Pos::new(4, 5), Pos::new(6, 5), &data);
+    assert!(!path.windows(2).any(|step| step[0] == Pos::new(4, 5) && step[1] == Pos::new(5, 5)));
+    assert_eq!(Pos::new(6, 5), *path.last().unwrap());
+}
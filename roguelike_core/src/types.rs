@@ -1,4 +1,5 @@
 use std::collections::VecDeque;
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::default::Default;
 use std::fmt;
@@ -74,16 +75,26 @@ impl GameData {
                       let mut next_positions = Vec::with_capacity(10);
 
                       for direction in Direction::move_actions() {
-                          for offset in reach.move_with_reach(&direction) {
+                          if let Some(offset) = reach.move_with_reach(&direction) {
                               let next_pos = add_pos(pos, offset);
 
                               let mut can_move = false;
-                              let clear = self.clear_path(pos, next_pos, traps_block);
+                              let clear =
+                                  if reach.jumps() {
+                                      self.clear_jump(next_pos, traps_block)
+                                  } else {
+                                      self.clear_path(pos, next_pos, traps_block)
+                                  };
                               can_move |= clear;
 
                               if !can_move {
                                   if !must_reach && next_pos == end {
-                                      let not_blocked = self.map.path_blocked_move(pos, next_pos).is_none();
+                                      let not_blocked =
+                                          if reach.jumps() {
+                                              self.map.is_within_bounds(next_pos) && !self.map[next_pos].block_move
+                                          } else {
+                                              self.map.path_blocked_move(pos, next_pos).is_none()
+                                          };
                                       can_move |= not_blocked;
                                   }
                               }
@@ -117,16 +128,44 @@ impl GameData {
         return result;
     }
 
-    pub fn fov_radius(&self, entity_id: EntityId) -> i32 {
+    pub fn fov_radius(&self, entity_id: EntityId, config: &Config) -> i32 {
         let mut radius: i32 = self.entities.fov_radius[&entity_id];
 
         if let Some(status) = self.entities.status.get(&entity_id) {
             radius += status.extra_fov as i32;
         }
 
+        if config.carried_light_fov {
+            radius += self.carried_light(entity_id) as i32;
+        }
+
         return radius;
     }
 
+    /// The radius of the brightest light an entity carries in their inventory.
+    pub fn carried_light(&self, entity_id: EntityId) -> usize {
+        if let Some(inventory) = self.entities.inventory.get(&entity_id) {
+            return inventory.iter()
+                            .filter_map(|item_id| self.entities.status.get(item_id))
+                            .map(|status| status.illuminate)
+                            .max()
+                            .unwrap_or(0);
+        }
+
+        return 0;
+    }
+
+    /// Whether an entity sees a position by their own sight, ignoring light from other sources.
+    pub fn pos_in_direct_fov(&self, entity_id: EntityId, other_pos: Pos, config: &Config) -> bool {
+        if other_pos.x < 0 || other_pos.y < 0 {
+            return false;
+        }
+
+        let pos = self.entities.pos[&entity_id];
+        let crouching = self.entities.stance[&entity_id] == Stance::Crouching;
+        return self.map.is_in_fov_with(pos, other_pos, self.fov_radius(entity_id, config), crouching, config.fov_algorithm);
+    }
+
     pub fn is_in_fov(&self, entity_id: EntityId, other_id: EntityId, config: &Config) -> bool {
         let stance = self.entities.stance[&entity_id];
         let other_stance = self.entities.stance.get(&other_id).unwrap_or(&Stance::Standing);
@@ -144,17 +183,17 @@ impl GameData {
         return self.fov_check(entity_id, other_pos, crouching, config);
     }
 
-    fn fov_check(&self, entity_id: EntityId, other_pos: Pos, crouching: bool, _config: &Config) -> bool {
+    fn fov_check(&self, entity_id: EntityId, other_pos: Pos, crouching: bool, config: &Config) -> bool {
         if other_pos.x < 0 || other_pos.y < 0 {
             return false;
         }
 
         let pos = self.entities.pos[&entity_id];
 
-        let radius: i32 = self.fov_radius(entity_id);
+        let radius: i32 = self.fov_radius(entity_id, config);
 
         if self.entities.typ[&entity_id] == EntityType::Player {
-            let mut can_see = self.map.is_in_fov(pos, other_pos, radius, crouching);
+            let mut can_see = self.map.is_in_fov_with(pos, other_pos, radius, crouching, config.fov_algorithm);
 
             for id in self.entities.ids.iter() {
                 if can_see {
@@ -167,7 +206,7 @@ impl GameData {
                     let illuminate_radius = self.entities.status[id].illuminate as i32;
                     let illuminated = self.map.is_in_fov(illuminate_pos, other_pos, illuminate_radius, crouching);
 
-                    let illuminated_see = self.map.is_in_fov(pos, other_pos, ILLUMINATE_FOV_RADIUS, crouching);
+                    let illuminated_see = self.map.is_in_fov_with(pos, other_pos, ILLUMINATE_FOV_RADIUS, crouching, config.fov_algorithm);
                     let blocked = self.map[other_pos].block_sight;
 
                     can_see |= illuminated && illuminated_see && !blocked;
@@ -177,7 +216,13 @@ impl GameData {
             return can_see;
         } else {
             if let Some(dir) = self.entities.direction.get(&entity_id) {
-                return self.map.is_in_fov_direction(pos, other_pos, radius, *dir, crouching);
+                if config.fov_cone_monster {
+                    // monsters only see within an arc in front of them
+                    return self.map.is_in_fov_with(pos, other_pos, radius, crouching, config.fov_algorithm) &&
+                           visible_in_cone(pos, other_pos, *dir, config.fov_arc_monster);
+                }
+
+                return self.map.is_in_fov_direction(pos, other_pos, radius, *dir, crouching, config.fov_algorithm);
             } else {
                 panic!(format!("tried to perform is_in_fov on entity without facing"));
             }
@@ -215,6 +260,14 @@ impl GameData {
         return self.clear_path(start, up_to, traps_block);
     }
 
+    /// Whether a jump can land on the given position, regardless of what lies between.
+    pub fn clear_jump(&self, end: Pos, traps_block: bool) -> bool {
+        return self.map.is_within_bounds(end) &&
+               !self.map[end].block_move &&
+               self.has_blocking_entity(end).is_none() &&
+               !(traps_block && self.has_trap(end).is_some());
+    }
+
     pub fn clear_path(&self, start: Pos, end: Pos, traps_block: bool) -> bool {
         let line = line_inclusive(start, end);
 
@@ -273,16 +326,17 @@ impl GameData {
         return None;
     }
 
+    /// The blocking entity at a position. If there are several, the one with the
+    /// lowest id is returned so the result does not depend on storage order.
     pub fn has_blocking_entity(&self, pos: Pos) -> Option<EntityId> {
+        let mut blocker: Option<EntityId> = None;
         for (key, other_pos) in self.entities.pos.iter() {
-            if *other_pos == pos {
-                if self.entities.blocks[key] {
-                    return Some(*key);
-                }
+            if *other_pos == pos && self.entities.blocks[key] && blocker.is_none_or(|id| *key < id) {
+                blocker = Some(*key);
             }
         }
 
-        return None;
+        return blocker;
     }
 
     pub fn has_trap(&self, pos: Pos) -> Option<EntityId> {
@@ -319,6 +373,41 @@ impl GameData {
         return None;
     }
 
+    /// Predict the result of an attack without changing anything. This follows the same rules
+    /// as resolving an attack, so it can be used to warn about lethal hits or to weigh AI choices.
+    pub fn predict_attack(&self, attacker: EntityId, target: EntityId) -> AttackPrediction {
+        let target_hp = self.entities.fighter.get(&target).map_or(0, |f| f.hp);
+        let alive = self.entities.status.get(&target).map_or(false, |status| status.alive);
+
+        let outcome = attack_outcome(attacker, target, 1, self);
+
+        let damage;
+        let mut hit_chance = 1.0;
+        if outcome == AttackOutcome::Ignored || !alive {
+            damage = 0;
+            hit_chance = 0.0;
+        } else {
+            damage = match outcome {
+                AttackOutcome::Hammered => HAMMER_DAMAGE,
+                AttackOutcome::Slashed => SWORD_DAMAGE,
+                AttackOutcome::Damaged(damage) => std::cmp::max(0, damage),
+                // a shield pushes the attacker back instead of taking damage
+                _ => 0,
+            };
+        }
+
+        // hammers and swords always kill what they hit
+        let always_kills = damage > 0 &&
+                           (outcome == AttackOutcome::Hammered || outcome == AttackOutcome::Slashed);
+
+        return AttackPrediction {
+            min_damage: damage,
+            max_damage: damage,
+            lethal: always_kills || (damage > 0 && damage >= target_hp),
+            hit_chance,
+        };
+    }
+
     pub fn used_up_item(&mut self, entity_id: EntityId, item_id: EntityId) {
         if let Some(inventory) = self.entities.inventory.get_mut(&entity_id) {
             let item_index = inventory.iter()
@@ -409,6 +498,7 @@ pub enum Skill {
     Heal,
     FarSight,
     Sprint,
+    TimeStop,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize, Default)]
@@ -446,6 +536,7 @@ pub enum GameState {
     SkillMenu,
     ClassMenu,
     ConfirmQuit,
+    Intro,
     Exit,
 }
 
@@ -459,6 +550,7 @@ impl fmt::Display for GameState {
             GameState::SkillMenu => write!(f, "skillmenu"),
             GameState::ClassMenu => write!(f, "classmenu"),
             GameState::ConfirmQuit => write!(f, "confirmquit"),
+            GameState::Intro => write!(f, "intro"),
             GameState::Exit => write!(f, "exit"),
         }
     }
@@ -593,6 +685,22 @@ impl Item {
         }
     }
 
+    pub fn category(&self) -> ItemCategory {
+        match self {
+            Item::Stone => ItemCategory::Consumable,
+            Item::Key => ItemCategory::Misc,
+            Item::Dagger => ItemCategory::Weapon,
+            Item::Shield => ItemCategory::Weapon,
+            Item::Hammer => ItemCategory::Weapon,
+            Item::Sword => ItemCategory::Weapon,
+            Item::Lantern => ItemCategory::Misc,
+            Item::SpikeTrap => ItemCategory::Consumable,
+            Item::SoundTrap => ItemCategory::Consumable,
+            Item::BlinkTrap => ItemCategory::Consumable,
+            Item::FreezeTrap => ItemCategory::Consumable,
+        }
+    }
+
     pub fn name(&self) -> EntityName {
         match self {
             Item::Stone => EntityName::Stone,
@@ -616,6 +724,30 @@ pub enum ItemClass {
     Secondary,
 }
 
+/// The groups the inventory is sorted into, in the order they are listed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize)]
+pub enum ItemCategory {
+    Weapon,
+    Consumable,
+    Misc,
+}
+
+impl ItemCategory {
+    pub fn categories() -> Vec<ItemCategory> {
+        return vec!(ItemCategory::Weapon, ItemCategory::Consumable, ItemCategory::Misc);
+    }
+}
+
+impl fmt::Display for ItemCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ItemCategory::Weapon => write!(f, "weapons"),
+            ItemCategory::Consumable => write!(f, "consumables"),
+            ItemCategory::Misc => write!(f, "misc"),
+        }
+    }
+}
+
 pub type Hp = i32;
 
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
@@ -626,6 +758,64 @@ pub struct Fighter {
     pub power: i32,
 }
 
+/// The expected outcome of an attack, see GameData::predict_attack.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AttackPrediction {
+    pub min_damage: Hp,
+    pub max_damage: Hp,
+    pub lethal: bool,
+    pub hit_chance: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+pub enum DamageType {
+    Physical,
+    Fire,
+    Cold,
+    Poison,
+}
+
+impl Default for DamageType {
+    fn default() -> DamageType {
+        return DamageType::Physical;
+    }
+}
+
+// resistance to each damage type, as a percent of damage prevented
+pub type Resistances = HashMap<DamageType, i32>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LevelFeeling {
+    Quiet,
+    Rewarding,
+    Uneasy,
+    Dangerous,
+}
+
+impl fmt::Display for LevelFeeling {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelFeeling::Quiet => write!(f, "quiet"),
+            LevelFeeling::Rewarding => write!(f, "rewarding"),
+            LevelFeeling::Uneasy => write!(f, "uneasy"),
+            LevelFeeling::Dangerous => write!(f, "dangerous"),
+        }
+    }
+}
+
+/// The order in which visible enemies are cycled through when targeting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TargetOrder {
+    Distance, // nearest first
+    Threat,   // highest hp * power first
+}
+
+impl Default for TargetOrder {
+    fn default() -> TargetOrder {
+        return TargetOrder::Distance;
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize)]
 pub enum InventoryAction {
     Use,
@@ -638,6 +828,22 @@ impl Default for InventoryAction {
     }
 }
 
+/// Which way a patrolling monster is walking along its route.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PatrolDir {
+    Forward,
+    Reverse,
+}
+
+impl fmt::Display for PatrolDir {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatrolDir::Forward => write!(f, "forward"),
+            PatrolDir::Reverse => write!(f, "reverse"),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize)]
 pub enum Stance {
     Crouching,
@@ -693,6 +899,7 @@ pub enum EntityName {
     Column,
     Key,
     Exit,
+    UpStairs,
     Dagger,
     Hammer,
     Sword,
@@ -700,6 +907,7 @@ pub enum EntityName {
     Lantern,
     Spire,
     Armil,
+    Slime,
     SpikeTrap,
     BlinkTrap,
     FreezeTrap,
@@ -718,6 +926,16 @@ impl Default for EntityName {
     }
 }
 
+impl EntityName {
+    pub fn is_monster(&self) -> bool {
+        match self {
+            EntityName::Gol | EntityName::Pawn | EntityName::Rook |
+            EntityName::Spire | EntityName::Armil | EntityName::Slime => true,
+            _ => false,
+        }
+    }
+}
+
 impl fmt::Display for EntityName {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -728,6 +946,7 @@ impl fmt::Display for EntityName {
             EntityName::Column => write!(f, "column"),
             EntityName::Key => write!(f, "key"),
             EntityName::Exit => write!(f, "exit"),
+            EntityName::UpStairs => write!(f, "upstairs"),
             EntityName::Dagger => write!(f, "dagger"),
             EntityName::Hammer => write!(f, "hammer"),
             EntityName::Sword => write!(f, "sword"),
@@ -735,6 +954,7 @@ impl fmt::Display for EntityName {
             EntityName::Shield => write!(f, "shield"),
             EntityName::Spire => write!(f, "spire"),
             EntityName::Armil => write!(f, "armil"),
+            EntityName::Slime => write!(f, "slime"),
             EntityName::SpikeTrap => write!(f, "spiketrap"),
             EntityName::BlinkTrap => write!(f, "blinktrap"),
             EntityName::FreezeTrap => write!(f, "freezetrap"),
@@ -770,6 +990,8 @@ impl FromStr for EntityName {
             return Ok(EntityName::Key);
         } else if s == "exit" {
             return Ok(EntityName::Exit);
+        } else if s == "upstairs" {
+            return Ok(EntityName::UpStairs);
         } else if s == "dagger" {
             return Ok(EntityName::Dagger);
         } else if s == "hammer" {
@@ -784,6 +1006,8 @@ impl FromStr for EntityName {
             return Ok(EntityName::Spire);
         } else if s == "armil" {
             return Ok(EntityName::Armil);
+        } else if s == "slime" {
+            return Ok(EntityName::Slime);
         } else if s == "spiketrap" {
             return Ok(EntityName::SpikeTrap);
         } else if s == "blinktrap" {
@@ -905,18 +1129,70 @@ impl EntityClass {
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize)]
 pub struct StatusEffect {
-    pub frozen: usize, // turns
-    pub soft_steps: usize, // turns
     pub illuminate: usize, // radius
     pub extra_fov: usize, // amount
+    pub time_stop_cooldown: usize, // turns
+    pub lost_sight: usize, // turns
+    pub momentum: usize, // moves carried through an attack
+    pub asleep: bool,
     pub blinked: bool,
     pub active: bool,
     pub alive: bool,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+pub enum StatusKind {
+    Frozen,
+    SoftSteps,
+    Grace,
+    Poison,
+    Burning,
+}
+
+impl fmt::Display for StatusKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatusKind::Frozen => write!(f, "frozen"),
+            StatusKind::SoftSteps => write!(f, "soft_steps"),
+            StatusKind::Grace => write!(f, "grace"),
+            StatusKind::Poison => write!(f, "poison"),
+            StatusKind::Burning => write!(f, "burning"),
+        }
+    }
+}
+
+impl FromStr for StatusKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "frozen" {
+            return Ok(StatusKind::Frozen);
+        } else if s == "soft_steps" {
+            return Ok(StatusKind::SoftSteps);
+        } else if s == "grace" {
+            return Ok(StatusKind::Grace);
+        } else if s == "poison" {
+            return Ok(StatusKind::Poison);
+        } else if s == "burning" {
+            return Ok(StatusKind::Burning);
+        }
+
+        return Err(format!("StatusKind {} not expected!", s));
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusState {
+    pub duration: usize, // turns
+    pub stacks: usize,
+    pub fresh: bool, // applied this turn, so not counted down until the next
+}
+
+pub type Statuses = HashMap<StatusKind, StatusState>;
+
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Message {
-    Sound(EntityId, Pos),
+    Sound(EntityId, Pos, usize), // source, position, strength heard
     Attack(EntityId),
 }
 
@@ -934,6 +1210,8 @@ pub struct Entities {
     pub behavior: CompStore<Behavior>,
     pub fov_radius: CompStore<i32>,
     pub attack_type: CompStore<AttackType>,
+    pub damage_type: CompStore<DamageType>,
+    pub resistance: CompStore<Resistances>,
     pub item: CompStore<Item>,
     pub movement: CompStore<Reach>,
     pub attack: CompStore<Reach>,
@@ -941,13 +1219,23 @@ pub struct Entities {
     pub trap: CompStore<Trap>,
     pub armed: CompStore<bool>,
     pub energy: CompStore<u32>,
+    pub speed: CompStore<u32>, // energy gained per turn, NORMAL_SPEED if missing
+    pub turn_energy: CompStore<u32>,
+    pub durability: CompStore<usize>,
+    pub home: CompStore<Pos>,
+    pub patrol: CompStore<Vec<Pos>>, // waypoints of a patrol route
     pub count_down: CompStore<usize>,
+    pub pending_attack: CompStore<(EntityId, usize)>, // target, turns until the attack lands
+    pub pack: CompStore<EntityId>, // the pack's first member, shared by the whole pack
+    pub pack_target: CompStore<Pos>, // tile assigned by the pack coordination pass
     pub move_mode: CompStore<MoveMode>,
     pub direction: CompStore<Direction>,
     pub selected_item: CompStore<EntityId>,
     pub class: CompStore<EntityClass>,
     pub skills: CompStore<Vec<Skill>>,
     pub status: CompStore<StatusEffect>,
+    pub statuses: CompStore<Statuses>,
+    pub status_source: CompStore<EntityId>, // whose attack left the entity poisoned or burning
     pub gate_pos: CompStore<Option<Pos>>,
     pub stance: CompStore<Stance>,
     pub took_turn: CompStore<bool>,
@@ -972,6 +1260,25 @@ impl Entities {
         *self = Default::default();
     }
 
+    /// The item used by a quick-use slot. The first slots each use the first item of
+    /// one category, or the selected item if it is in that category. Any further
+    /// slots use the inventory in order.
+    pub fn item_in_slot(&self, entity_id: EntityId, slot: usize, selected: Option<EntityId>) -> Option<EntityId> {
+        let inventory = &self.inventory[&entity_id];
+
+        if let Some(category) = ItemCategory::categories().get(slot) {
+            if let Some(selected_id) = selected {
+                if inventory.contains(&selected_id) && self.item[&selected_id].category() == *category {
+                    return Some(selected_id);
+                }
+            }
+
+            return inventory.iter().find(|id| self.item[*id].category() == *category).map(|id| *id);
+        } else {
+            return inventory.get(slot).map(|id| *id);
+        }
+    }
+
     pub fn remove_item(&mut self, entity_id: EntityId, item_id: EntityId) {
         let index = self.inventory[&entity_id].iter().position(|id| *id == item_id).unwrap();
         self.inventory[&entity_id].remove(index);
@@ -995,7 +1302,15 @@ impl Entities {
             }
 
             ItemClass::Secondary => {
-                self.inventory[&entity_id].push_back(item_id);
+                // keep the inventory grouped by category
+                let category = item.category();
+                let index = self.inventory[&entity_id].iter()
+                                                      .position(|id| self.item[id].category() > category);
+                if let Some(index) = index {
+                    self.inventory[&entity_id].insert(index, item_id);
+                } else {
+                    self.inventory[&entity_id].push_back(item_id);
+                }
             }
         }
 
@@ -1017,6 +1332,12 @@ impl Entities {
         self.messages.insert(id,  Vec::new());
         self.needs_removal.insert(id,  false);
         self.status.insert(id,  StatusEffect::default());
+
+        // monsters remember where they were spawned, for leashing them to that area
+        if typ == EntityType::Enemy {
+            self.home.insert(id, Pos::new(x, y));
+        }
+        self.statuses.insert(id,  Statuses::new());
         self.took_turn.insert(id,  false);
 
         return id;
@@ -1058,6 +1379,18 @@ impl Entities {
         return ((dx.pow(2) + dy.pow(2)) as f32).sqrt();
     }
 
+    /// Reduce damage of the given type by the entity's resistance to it.
+    /// Entities without resistances take full damage.
+    pub fn resisted_damage(&self, entity: EntityId, damage: i32, damage_type: DamageType) -> i32 {
+        let resistance =
+            self.resistance.get(&entity)
+                           .and_then(|resistances| resistances.get(&damage_type))
+                           .map_or(0, |resistance| *resistance);
+        let resistance = std::cmp::max(0, std::cmp::min(100, resistance));
+
+        return damage * (100 - resistance) / 100;
+    }
+
     pub fn take_damage(&mut self, entity: EntityId, damage: i32) {
         if let Some(fighter) = self.fighter.get_mut(&entity) {
             if damage > 0 {
@@ -1095,7 +1428,7 @@ impl Entities {
     }
 
     pub fn heard_sound(&mut self, entity_id: EntityId) -> Option<Message> {
-        if let Some(index) = self.messages[&entity_id].iter().position(|msg| matches!(msg, Message::Sound(_, _))) {
+        if let Some(index) = self.messages[&entity_id].iter().position(|msg| matches!(msg, Message::Sound(_, _, _))) {
             return Some(self.messages[&entity_id].remove(index));
         } else {
             return None;
@@ -1180,11 +1513,125 @@ impl Entities {
         }
     }
 
+    /// Apply a status for the given number of turns. Applying a status the entity
+    /// already has adds a stack and keeps the longer duration.
+    pub fn apply_status(&mut self, entity_id: EntityId, kind: StatusKind, duration: usize) {
+        let statuses = self.statuses.entry(entity_id).or_insert(Statuses::new());
+        if let Some(state) = statuses.get_mut(&kind) {
+            state.duration = std::cmp::max(state.duration, duration);
+            state.stacks += 1;
+            state.fresh = true;
+        } else {
+            statuses.insert(kind, StatusState { duration, stacks: 1, fresh: true });
+        }
+    }
+
+    /// Remove a status before it runs out, returning whether the entity had it.
+    pub fn remove_status(&mut self, entity_id: EntityId, kind: StatusKind) -> bool {
+        return self.statuses.get_mut(&entity_id).map_or(false, |statuses| statuses.remove(&kind).is_some());
+    }
+
+    pub fn has_status(&self, entity_id: EntityId, kind: StatusKind) -> bool {
+        return self.statuses.get(&entity_id).map_or(false, |statuses| statuses.contains_key(&kind));
+    }
+
+    /// Statuses applied before the current turn count down at its end.
+    pub fn settle_statuses(&mut self) {
+        for statuses in self.statuses.values_mut() {
+            for state in statuses.values_mut() {
+                state.fresh = false;
+            }
+        }
+    }
+
+    /// Count down each entity's statuses, removing and returning those that expired.
+    /// Statuses applied during this turn last until the end of the next one.
+    pub fn count_down_statuses(&mut self) -> Vec<(EntityId, StatusKind)> {
+        let mut expired = Vec::new();
+
+        for entity_id in self.ids.iter() {
+            if let Some(statuses) = self.statuses.get_mut(entity_id) {
+                // sort for a consistent message order
+                let mut kinds = statuses.keys().copied().collect::<Vec<StatusKind>>();
+                kinds.sort();
+
+                for kind in kinds {
+                    let state = statuses.get_mut(&kind).unwrap();
+                    if state.fresh {
+                        continue;
+                    }
+
+                    state.duration = state.duration.saturating_sub(1);
+                    if state.duration == 0 {
+                        statuses.remove(&kind);
+                        expired.push((*entity_id, kind));
+                    }
+                }
+            }
+        }
+
+        return expired;
+    }
+
+    /// Give an entity a turn's worth of energy at its speed, returning how many
+    /// actions it can take this turn. Leftover energy carries to the next turn.
+    pub fn gain_turn_energy(&mut self, entity_id: EntityId) -> u32 {
+        let speed = self.speed.get(&entity_id).map(|speed| *speed).unwrap_or(NORMAL_SPEED);
+        let energy = self.turn_energy.entry(entity_id).or_insert(0);
+        *energy += speed;
+
+        let actions = *energy / NORMAL_SPEED;
+        *energy %= NORMAL_SPEED;
+
+        return actions;
+    }
+
+    /// Count down attacks waiting on their animation, returning the
+    /// (attacker, target) pairs whose attacks land this turn.
+    pub fn count_down_attacks(&mut self) -> Vec<(EntityId, EntityId)> {
+        let mut landed = Vec::new();
+        for (entity_id, (target_id, turns)) in self.pending_attack.iter_mut() {
+            *turns = turns.saturating_sub(1);
+            if *turns == 0 {
+                landed.push((*entity_id, *target_id));
+            }
+        }
+
+        for (entity_id, _target_id) in landed.iter() {
+            self.pending_attack.remove(entity_id);
+        }
+
+        return landed;
+    }
+
     // NOTE cloning entities may not remap all entity ids that an entity tracks!
     // this could cause subtle problems, so this is really only for level generation.
     pub fn clone_entity(&mut self, other: &Entities, entity_id: EntityId) {
         let new_id = OBJECT_ID_COUNT.fetch_add(1, Ordering::SeqCst);
+        self.copy_entity_as(other, entity_id, new_id);
+
+        // NOTE this might not work if entity IDs are left!
+        if let Some(inventory) = other.inventory.get(&entity_id) { 
+            assert_eq!(0, inventory.len());
+        }
+    }
+
+    /// Copy an entity from another Entities structure, keeping its id. This is used to
+    /// move an entity, like the player, between levels, so the inventory's ids stay valid
+    /// as long as the items are copied as well.
+    pub fn copy_entity(&mut self, other: &Entities, entity_id: EntityId) {
+        self.copy_entity_as(other, entity_id, entity_id);
+
+        if let Some(inventory) = other.inventory.get(&entity_id) { 
+            self.inventory.insert(entity_id, inventory.clone());
+        }
 
+        if let Some(armed) = other.armed.get(&entity_id) { 
+            self.armed.insert(entity_id, *armed);
+        }
+    }
+
+    fn copy_entity_as(&mut self, other: &Entities, entity_id: EntityId, new_id: EntityId) {
         self.ids.push(new_id);
 
         // this macro simply clones a component and inserts into the self
@@ -1206,12 +1653,23 @@ impl Entities {
         move_component!(behavior);
         move_component!(fov_radius);
         move_component!(attack_type);
+        move_component!(damage_type);
+        move_component!(resistance);
         move_component!(item);
         move_component!(movement);
         move_component!(attack);
         move_component!(trap);
         move_component!(energy);
+        move_component!(speed);
+        move_component!(turn_energy);
+        move_component!(durability);
+        move_component!(home);
+        move_component!(patrol);
         move_component!(count_down);
+        move_component!(pending_attack);
+        move_component!(status_source);
+        move_component!(pack);
+        move_component!(pack_target);
         move_component!(move_mode);
         move_component!(direction);
         move_component!(selected_item);
@@ -1220,17 +1678,13 @@ impl Entities {
         move_component!(sound);
         move_component!(typ);
         move_component!(status);
+        move_component!(statuses);
         move_component!(gate_pos);
         move_component!(took_turn);
         move_component!(color);
         move_component!(blocks);
         move_component!(needs_removal);
         move_component!(messages);
-
-        // NOTE this might not work if entity IDs are left!
-        if let Some(inventory) = other.inventory.get(&entity_id) { 
-            assert_eq!(0, inventory.len());
-        }
     }
 
     pub fn merge(&mut self, other: &Entities) {
@@ -1252,6 +1706,8 @@ impl Entities {
         self.behavior.remove(&id);
         self.fov_radius.remove(&id);
         self.attack_type.remove(&id);
+        self.damage_type.remove(&id);
+        self.resistance.remove(&id);
         self.item.remove(&id);
         self.movement.remove(&id);
         self.attack.remove(&id);
@@ -1259,7 +1715,16 @@ impl Entities {
         self.trap.remove(&id);
         self.armed.remove(&id);
         self.energy.remove(&id);
+        self.speed.remove(&id);
+        self.turn_energy.remove(&id);
+        self.durability.remove(&id);
+        self.home.remove(&id);
+        self.patrol.remove(&id);
         self.count_down.remove(&id);
+        self.pending_attack.remove(&id);
+        self.status_source.remove(&id);
+        self.pack.remove(&id);
+        self.pack_target.remove(&id);
         self.move_mode.remove(&id);
         self.direction.remove(&id);
         self.selected_item.remove(&id);
@@ -1268,6 +1733,7 @@ impl Entities {
         self.sound.remove(&id);
         self.typ.remove(&id);
         self.status.remove(&id);
+        self.statuses.remove(&id);
         self.gate_pos.remove(&id);
         self.took_turn.remove(&id);
         self.color.remove(&id);
@@ -1277,3 +1743,31 @@ impl Entities {
     }
 }
 
+
+#[test]
+fn test_predict_attack() {
+    let mut entities = Entities::new();
+    let player = entities.create_entity(0, 0, EntityType::Player, '@', Color::white(), EntityName::Player, true);
+    entities.fighter.insert(player, Fighter { max_hp: 10, hp: 10, defense: 0, power: 5 });
+    entities.status[&player].alive = true;
+
+    let gol = entities.create_entity(1, 1, EntityType::Enemy, 'g', Color::white(), EntityName::Gol, true);
+    entities.fighter.insert(gol, Fighter { max_hp: 10, hp: 3, defense: 1, power: 1 });
+    entities.status[&gol].alive = true;
+
+    let data = GameData::new(Map::from_dims(5, 5), entities);
+
+    let prediction = data.predict_attack(player, gol);
+    assert_eq!(4, prediction.min_damage);
+    assert_eq!(4, prediction.max_damage);
+    assert!(prediction.lethal);
+    assert_eq!(1.0, prediction.hit_chance);
+
+    // the gol's counter attack is much weaker
+    let prediction = data.predict_attack(gol, player);
+    assert_eq!(1, prediction.max_damage);
+    assert!(!prediction.lethal);
+
+    // predicting does not change anything
+    assert_eq!(3, data.entities.fighter[&gol].hp);
+}
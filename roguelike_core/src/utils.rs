@@ -1,5 +1,5 @@
-use std::collections::HashSet;
 use std::collections::HashMap;
+use std::collections::VecDeque;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 
@@ -55,6 +55,24 @@ pub fn choose<A: Copy>(rng: &mut Rand32, items: &Vec<A>) -> Option<A> {
     }
 }
 
+/// Choose an item with a chance proportional to its weight.
+pub fn choose_weighted<A: Copy>(rng: &mut Rand32, items: &Vec<(A, u32)>) -> Option<A> {
+    let total = items.iter().map(|(_item, weight)| *weight).sum::<u32>();
+    if total == 0 {
+        return None;
+    }
+
+    let mut roll = rng_range_u32(rng, 0, total);
+    for (item, weight) in items.iter() {
+        if roll < *weight {
+            return Some(*item);
+        }
+        roll -= *weight;
+    }
+
+    return None;
+}
+
 pub fn shuffle<A>(rng: &mut Rand32, items: &mut Vec<A>) {
     let len = items.len();
 
@@ -150,7 +168,7 @@ pub fn push_attack(entity_id: EntityId,
     let x_diff = signedness(push_dxy.x);
     let y_diff = signedness(push_dxy.y);
 
-    let move_result = check_collision(other_pos, x_diff, y_diff, data);
+    let move_result = check_collision(other_pos, x_diff, y_diff, data, config);
 
     let past_pos = move_by(other_pos, Pos::new(x_diff, y_diff));
 
@@ -162,8 +180,8 @@ pub fn push_attack(entity_id: EntityId,
 
         msg_log.log_front(Msg::Moved(target, MoveType::Move, past_pos));
     } else {
-        if data.entities.status[&target].frozen == 0 {
-            data.entities.status[&target].frozen = config.push_stun_turns;
+        if !data.entities.has_status(target, StatusKind::Frozen) {
+            apply_status(target, StatusKind::Frozen, config.push_stun_turns, data, msg_log);
         } else {
             // otherwise crush them against the wall/entity
             damage = data.entities.fighter[&target].hp;
@@ -197,8 +215,54 @@ pub fn crush(handle: EntityId, target: EntityId, entities: &mut Entities, msg_lo
     }
 }
 
+/// Apply a status to an entity, logging that it was applied.
+pub fn apply_status(entity_id: EntityId, kind: StatusKind, duration: usize, data: &mut GameData, msg_log: &mut MsgLog) {
+    data.entities.apply_status(entity_id, kind, duration);
+    msg_log.log(Msg::StatusApplied(entity_id, kind, duration));
+}
+
 pub fn attack(entity: EntityId, target: EntityId, data: &mut GameData, msg_log: &mut MsgLog) {
+    attack_with_multiplier(entity, target, 1, data, msg_log);
+}
+
+/// How an attack lands, decided by the attacker's and target's items and stats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttackOutcome {
+    Ignored,
+    Hammered,
+    Shielded,
+    Slashed,
+    Damaged(Hp),
+}
+
+/// The outcome of one entity attacking another, shared by attacks and their predictions.
+/// Damaged holds the damage after defense and resistances, which may be zero or less.
+pub fn attack_outcome(entity: EntityId, target: EntityId, multiplier: i32, data: &GameData) -> AttackOutcome {
+    // an entity with grace from a recent hit ignores further attacks
+    if data.entities.has_status(target, StatusKind::Grace) {
+        return AttackOutcome::Ignored;
+    }
+
     if data.using(entity, Item::Hammer).is_some() {
+        return AttackOutcome::Hammered;
+    } else if data.using(target, Item::Shield).is_some() {
+        return AttackOutcome::Shielded;
+    } else if data.using(entity, Item::Sword).is_some() {
+        return AttackOutcome::Slashed;
+    }
+
+    let damage = data.entities.fighter.get(&entity).map_or(0, |f| f.power) -
+                 data.entities.fighter.get(&target).map_or(0, |f| f.defense);
+    let damage = damage * multiplier;
+    let damage_type = data.entities.damage_type.get(&entity).map_or(DamageType::Physical, |typ| *typ);
+    return AttackOutcome::Damaged(data.entities.resisted_damage(target, damage, damage_type));
+}
+
+/// An attack whose normal damage is multiplied, such as a charged attack.
+pub fn attack_with_multiplier(entity: EntityId, target: EntityId, multiplier: i32, data: &mut GameData, msg_log: &mut MsgLog) {
+    let outcome = attack_outcome(entity, target, multiplier, data);
+
+    if outcome == AttackOutcome::Hammered {
         data.entities.status[&target].alive = false;
         data.entities.blocks[&target] = false;
 
@@ -213,7 +277,7 @@ pub fn attack(entity: EntityId, target: EntityId, data: &mut GameData, msg_log:
         if data.map[hit_pos].surface == Surface::Floor {
             data.map[hit_pos].surface = Surface::Rubble;
         }
-    } else if data.using(target, Item::Shield).is_some() {
+    } else if outcome == AttackOutcome::Shielded {
         let pos = data.entities.pos[&entity];
         let other_pos = data.entities.pos[&target];
         let diff = other_pos - pos;
@@ -230,13 +294,10 @@ pub fn attack(entity: EntityId, target: EntityId, data: &mut GameData, msg_log:
 
             data.entities.messages[&target].push(Message::Attack(entity));
         }
-    } else if data.using(entity, Item::Sword).is_some() {
+    } else if outcome == AttackOutcome::Slashed {
         msg_log.log(Msg::Attack(entity, target, SWORD_DAMAGE));
         msg_log.log(Msg::Killed(entity, target, SWORD_DAMAGE));
-    } else {
-        // NOTE could add another section for the sword- currently the same as normal attacks
-        let damage = data.entities.fighter.get(&entity).map_or(0, |f| f.power) -
-                     data.entities.fighter.get(&target).map_or(0, |f| f.defense);
+    } else if let AttackOutcome::Damaged(damage) = outcome {
         if damage > 0 && data.entities.status[&target].alive {
             data.entities.take_damage(target, damage);
 
@@ -538,53 +599,73 @@ pub fn aoe_fill(map: &Map, aoe_effect: AoeEffect, start: Pos, radius: usize, con
 }
 
 pub fn floodfill_sound(map: &Map, start: Pos, radius: usize, config: &Config) -> Vec<Pos> {
-    let mut flood: Vec<Pos> = Vec::new();
+    let strengths = propagate_sound(start, radius, map, config);
 
-    let mut seen: HashSet<(Pos, i32)> = HashSet::new();
-    let mut current: Vec<(Pos, i32)> = Vec::new();
-    current.push((start, 0));
-    seen.insert((start, 0));
-    flood.push(start);
+    // loudest first, with ties in a consistent order
+    let mut flood = strengths.into_iter().collect::<Vec<(Pos, usize)>>();
+    flood.sort_by_key(|(pos, strength)| (std::cmp::Reverse(*strength), pos.x, pos.y));
 
-    for _index in 0..radius {
-        let last = current.clone();
-        current.clear();
-        for (pos, cost) in last.iter() {
-            let adjacents = map.neighbors(*pos);
+    return flood.into_iter().map(|(pos, _strength)| pos).collect::<Vec<Pos>>();
+}
 
-            for next_pos in adjacents {
-                let new_cost = 1 + cost + sound_dampening(map, *pos, next_pos, config);
+/// How much of a sound's radius is left at each tile it reaches. Sound spreads out from
+/// its origin, losing 1 for each tile and the configured dampening for each wall or
+/// blocked tile it passes, so it travels around corners but is muffled by walls.
+pub fn propagate_sound(origin: Pos, radius: usize, map: &Map, config: &Config) -> HashMap<Pos, usize> {
+    let mut strengths: HashMap<Pos, usize> = HashMap::new();
+    strengths.insert(origin, radius);
 
-                if new_cost > radius as i32 {
-                    continue;
-                }
+    let mut current: VecDeque<Pos> = VecDeque::new();
+    current.push_back(origin);
 
-                // check if we have seen this position before
-                let maybe_seen = seen.iter()
-                                     .filter(|(last_pos, _last_cost)| *last_pos == next_pos)
-                                     .next()
-                                     .map(|pair| *pair);
-                if let Some((last_pos, last_cost)) = maybe_seen {
-                    // if we have seen it before, but we reached it with more force, still
-                    // mark as seen, but enqueue again.
-                    if last_cost > new_cost {
-                        seen.remove(&(last_pos, last_cost));
-                        seen.insert((next_pos, new_cost));
-                        current.push((next_pos, new_cost));
-
-                        // no need to queue to flood again- the position was already seen
-                    }
-                } else {
-                    // record having seen this position.
-                    seen.insert((next_pos, new_cost));
-                    current.push((next_pos, new_cost));
-                    flood.push(next_pos);
-                }
+    while let Some(pos) = current.pop_front() {
+        let strength = strengths[&pos];
+
+        for next_pos in map.neighbors(pos) {
+            let cost = (1 + sound_dampening(map, pos, next_pos, config)) as usize;
+            if cost > strength {
+                continue;
+            }
+
+            // positions reached more loudly by another route are visited again
+            let next_strength = strength - cost;
+            if strengths.get(&next_pos).map_or(true, |last_strength| *last_strength < next_strength) {
+                strengths.insert(next_pos, next_strength);
+                current.push_back(next_pos);
             }
         }
     }
 
-    return flood;
+    return strengths;
+}
+
+#[test]
+fn test_propagate_sound() {
+    let config = Config::from_file("../config.yaml");
+
+    // s...
+    // ###.
+    // x...
+    let mut map = Map::from_dims(10, 10);
+    for x in 0..3 {
+        map[(x, 1)].block_move = true;
+    }
+
+    let start = Pos::new(0, 0);
+    let strengths = propagate_sound(start, 8, &map, &config);
+    assert_eq!(8, strengths[&start]);
+    assert_eq!(7, strengths[&Pos::new(1, 0)]);
+
+    // the sound goes through the wall, losing the dampening for a blocked tile
+    let through_wall = 8 - (1 + config.dampen_blocked_tile as usize) - 1;
+    assert_eq!(through_wall, strengths[&Pos::new(0, 2)]);
+
+    // around the corner is longer, but not muffled
+    assert_eq!(8 - 4, strengths[&Pos::new(2, 2)]);
+
+    // sounds do not reach beyond their radius
+    assert!(strengths.get(&Pos::new(9, 9)).is_none());
+    assert!(strengths.values().all(|strength| *strength <= 8));
 }
 
 #[test]
@@ -866,6 +947,41 @@ pub fn visible_in_direction(start_pos: Pos, end_pos: Pos, dir: Direction) -> boo
     return false;
 }
 
+/// Check whether a position is within a cone with the given arc (in degrees),
+/// centered on the facing direction.
+pub fn visible_in_cone(start_pos: Pos, end_pos: Pos, dir: Direction, arc: f32) -> bool {
+    if start_pos == end_pos {
+        return true;
+    }
+
+    let pos_diff = sub_pos(end_pos, start_pos);
+    let facing = dir.into_move();
+
+    let facing_angle = (facing.y as f32).atan2(facing.x as f32);
+    let pos_angle = (pos_diff.y as f32).atan2(pos_diff.x as f32);
+
+    let mut angle_diff = (pos_angle - facing_angle).abs().to_degrees();
+    if angle_diff > 180.0 {
+        angle_diff = 360.0 - angle_diff;
+    }
+
+    return angle_diff <= arc / 2.0;
+}
+
+#[test]
+pub fn test_visible_in_cone() {
+    let start_pos = Pos::new(0, 0);
+
+    assert!(visible_in_cone(start_pos, Pos::new(0, -3), Direction::Up, 90.0));
+    assert!(visible_in_cone(start_pos, Pos::new(1, -3), Direction::Up, 90.0));
+    assert!(visible_in_cone(start_pos, Pos::new(2, -2), Direction::Up, 90.0));
+    assert!(!visible_in_cone(start_pos, Pos::new(3, -1), Direction::Up, 90.0));
+    assert!(!visible_in_cone(start_pos, Pos::new(0, 3), Direction::Up, 90.0));
+
+    assert!(visible_in_cone(start_pos, Pos::new(-3, 3), Direction::DownLeft, 45.0));
+    assert!(!visible_in_cone(start_pos, Pos::new(3, 3), Direction::DownLeft, 45.0));
+}
+
 #[test]
 pub fn test_visible_in_direction() {
     let dir = Direction::Up;
@@ -974,3 +1090,161 @@ pub fn test_cone_up() {
     assert_eq!(Pos::new(2, -2), positions[8]);
 }
 
+/// The fraction of a health bar to fill for a fighter, or None if no bar is drawn.
+pub fn health_bar_fraction(fighter: &Fighter, hide_full: bool) -> Option<f32> {
+    if fighter.max_hp <= 0 || (hide_full && fighter.hp >= fighter.max_hp) {
+        return None;
+    }
+
+    let fraction = fighter.hp as f32 / fighter.max_hp as f32;
+    return Some(fraction.max(0.0).min(1.0));
+}
+
+#[test]
+pub fn test_health_bar_fraction() {
+    let half = Fighter { max_hp: 10, hp: 5, defense: 0, power: 1 };
+    assert_eq!(Some(0.5), health_bar_fraction(&half, true));
+
+    let full = Fighter { max_hp: 10, hp: 10, defense: 0, power: 1 };
+    assert_eq!(None, health_bar_fraction(&full, true));
+    assert_eq!(Some(1.0), health_bar_fraction(&full, false));
+
+    let dead = Fighter { max_hp: 10, hp: -2, defense: 0, power: 1 };
+    assert_eq!(Some(0.0), health_bar_fraction(&dead, true));
+}
+
+/// Judge how a level feels from the threat of its monsters and the items lying around.
+/// Each living monster is as threatening as its hp plus its attack power.
+pub fn level_feeling(data: &GameData, config: &Config) -> LevelFeeling {
+    let mut threat = 0;
+    let mut num_items = 0;
+    for entity_id in data.entities.ids.iter() {
+        if data.entities.typ[entity_id] == EntityType::Enemy && data.entities.status[entity_id].alive {
+            if let Some(fighter) = data.entities.fighter.get(entity_id) {
+                threat += fighter.hp + fighter.power;
+            }
+        } else if data.entities.item.contains_key(entity_id) &&
+                  data.entities.item[entity_id] != Item::Key &&
+                  data.map.is_within_bounds(data.entities.pos[entity_id]) {
+            num_items += 1;
+        }
+    }
+
+    if threat >= config.feeling_dangerous_threat {
+        return LevelFeeling::Dangerous;
+    } else if threat >= config.feeling_uneasy_threat {
+        return LevelFeeling::Uneasy;
+    } else if num_items >= config.feeling_rewarding_items {
+        return LevelFeeling::Rewarding;
+    } else {
+        return LevelFeeling::Quiet;
+    }
+}
+
+/// Convert a number of days since the Unix epoch into a UTC (year, month, day) date.
+pub fn date_from_days(days: i64) -> (i64, u32, u32) {
+    // civil_from_days from Howard Hinnant's date algorithms
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let day_of_era = z - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    return (year, month, day);
+}
+
+/// The current UTC date as "YYYY-MM-DD".
+pub fn utc_date_string() -> String {
+    let secs = std::time::SystemTime::now()
+                   .duration_since(std::time::UNIX_EPOCH)
+                   .map_or(0, |duration| duration.as_secs());
+    let (year, month, day) = date_from_days((secs / 86400) as i64);
+    return format!("{:04}-{:02}-{:02}", year, month, day);
+}
+
+/// The seed for a daily challenge on the given date string ("YYYY-MM-DD").
+/// Every player must get the same seed on the same day.
+pub fn daily_seed(date: &str) -> u64 {
+    return stable_hash(date);
+}
+
+/// The 64 bit FNV-1a hash of a string's bytes. DefaultHasher is not used where a hash
+/// is shared or stored, because its output may change between Rust versions.
+pub fn stable_hash(text: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in text.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    return hash;
+}
+
+#[test]
+pub fn test_daily_seed() {
+    assert_eq!((1970, 1, 1), date_from_days(0));
+    assert_eq!((2000, 3, 1), date_from_days(11017));
+    assert_eq!((2024, 2, 29), date_from_days(19782));
+
+    let (year, month, day) = date_from_days(19782);
+    let date = format!("{:04}-{:02}-{:02}", year, month, day);
+    assert_eq!("2024-02-29", date);
+
+    // the seed is the same each time for a given date, and differs between days
+    assert_eq!(daily_seed(&date), daily_seed("2024-02-29"));
+    assert_ne!(daily_seed("2024-02-29"), daily_seed("2024-03-01"));
+
+    // FNV-1a reference value, so the seed doesn't change between builds
+    assert_eq!(0xcbf29ce484222325, daily_seed(""));
+    assert_eq!(0xaf63dc4c8601ec8c, daily_seed("a"));
+}
+
+/// Split text into lines of at most the given width, breaking between words where
+/// possible. Words longer than the width are split across lines.
+pub fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    if width == 0 {
+        return lines;
+    }
+
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let mut word = word.chars().collect::<Vec<char>>();
+
+        while word.len() > 0 {
+            let line_len = line.chars().count();
+            let space = if line_len > 0 { 1 } else { 0 };
+
+            if line_len + space + word.len() <= width {
+                if space > 0 {
+                    line.push(' ');
+                }
+                line.extend(word.drain(..));
+            } else if line_len > 0 {
+                lines.push(line);
+                line = String::new();
+            } else {
+                line.extend(word.drain(..width));
+                lines.push(line);
+                line = String::new();
+            }
+        }
+    }
+
+    if line.len() > 0 {
+        lines.push(line);
+    }
+
+    return lines;
+}
+
+#[test]
+pub fn test_wrap_text() {
+    assert_eq!(vec!("Gol takes 2", "burn damage"), wrap_text("Gol takes 2 burn damage", 12));
+    assert_eq!(vec!("short"), wrap_text("short", 12));
+    assert_eq!(vec!("abcdef", "ghij a", "b"), wrap_text("abcdefghij a b", 6));
+    assert_eq!(Vec::<String>::new(), wrap_text("", 12));
+}
@@ -30,8 +30,10 @@ pub enum InputAction {
     Pickup,
     DropItem,
     DropItemByIndex(usize),
+    DropAll,
     Yell,
     UseItem(Direction, usize),
+    UseSlot(usize),
     Interact(Option<Direction>),
     CursorMove(Direction, bool, bool), // move direction, is relative, is long
     CursorReturn,
@@ -49,36 +51,39 @@ pub enum InputAction {
     ForceExit,
     ExploreAll,
     RegenerateMap,
+    SaveGame,
+    LoadGame,
     GodMode,
     IncreaseMoveMode,
     DecreaseMoveMode,
     OverlayOn,
     OverlayOff,
+    ToggleOverlay(Overlay),
+    ToggleOverlays,
     SelectItem(usize),
+    ChargeAttack(usize),
+    CycleTarget,
+    Examine(Pos),
+    ScrollMessagesUp,
+    ScrollMessagesDown,
+    NextPage,
+    PrevPage,
+    Undo,
+    FullScreen,
     None,
 }
 
 impl fmt::Display for InputAction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            InputAction::Move(direction, move_mode) => {
-                match direction {
-                    Direction::Left => write!(f, "left {}", move_mode),
-                    Direction::Right => write!(f, "right {}", move_mode),
-                    Direction::Up => write!(f, "up {}", move_mode),
-                    Direction::Down => write!(f, "down {}", move_mode),
-                    Direction::DownLeft => write!(f, "downleft {}", move_mode),
-                    Direction::DownRight => write!(f, "downright {}", move_mode),
-                    Direction::UpLeft => write!(f, "upleft {}", move_mode),
-                    Direction::UpRight => write!(f, "upright {}", move_mode),
-                }
-            },
+            InputAction::Move(direction, move_mode) => write!(f, "{} {}", direction, move_mode),
             InputAction::Pass(move_mode) => write!(f, "pass {}", move_mode),
             InputAction::MapClick(loc, cell) => write!(f, "click {} {} {} {}", loc.x, loc.y, cell.x, cell.y),
-            InputAction::MouseButton(click, keydir) => write!(f, "mousebutton {:?} {:?}", click, keydir),
+            InputAction::MouseButton(click, keydir) => write!(f, "mousebutton {} {}", click, keydir),
             InputAction::Pickup => write!(f, "pickup"),
             InputAction::DropItem => write!(f, "drop"),
             InputAction::DropItemByIndex(target) => write!(f, "droptarget {}", target),
+            InputAction::DropAll => write!(f, "dropall"),
             InputAction::Inventory => write!(f, "inventory"),
             InputAction::SkillMenu => write!(f, "skill"),
             InputAction::ClassMenu => write!(f, "class"),
@@ -87,19 +92,34 @@ impl fmt::Display for InputAction {
             InputAction::ForceExit => write!(f, "force_exit"),
             InputAction::ExploreAll => write!(f, "exploreall"),
             InputAction::RegenerateMap => write!(f, "regenmap"),
+            InputAction::SaveGame => write!(f, "savegame"),
+            InputAction::LoadGame => write!(f, "loadgame"),
             InputAction::GodMode => write!(f, "godmode"),
             InputAction::Yell => write!(f, "yell"),
             InputAction::IncreaseMoveMode => write!(f, "faster"),
             InputAction::DecreaseMoveMode => write!(f, "slower"),
             InputAction::OverlayOn => write!(f, "overlayon"),
             InputAction::OverlayOff => write!(f, "overlayoff"),
+            InputAction::ToggleOverlay(overlay) => write!(f, "toggleoverlay {}", overlay),
+            InputAction::ToggleOverlays => write!(f, "toggleoverlays"),
             InputAction::SelectItem(item) => write!(f, "selectitem {}", item),
-            InputAction::UseItem(dir, target) => write!(f, "use, {:?} {}", dir, target),
-            InputAction::Interact(dir) => write!(f, "interact {:?}", dir),
-            InputAction::CursorApplyItem(action_mode, index) => write!(f, "cursorapplyitem {:?} {}", action_mode, index),
-            InputAction::CursorApplySkill(action_mode, index) => write!(f, "cursorapplyskill {:?} {}", action_mode, index),
-            InputAction::CursorApplyMove(action_mode) => write!(f, "cursorapplymove {:?}", action_mode),
-            InputAction::CursorMove(dir, relative, long) => write!(f, "cursormove {:?} {} {}", dir, relative, long),
+            InputAction::ChargeAttack(charge) => write!(f, "charge {}", charge),
+            InputAction::CycleTarget => write!(f, "cycletarget"),
+            InputAction::Examine(pos) => write!(f, "examine {} {}", pos.x, pos.y),
+            InputAction::ScrollMessagesUp => write!(f, "scrollmsgsup"),
+            InputAction::ScrollMessagesDown => write!(f, "scrollmsgsdown"),
+            InputAction::NextPage => write!(f, "nextpage"),
+            InputAction::PrevPage => write!(f, "prevpage"),
+            InputAction::Undo => write!(f, "undo"),
+            InputAction::FullScreen => write!(f, "fullscreen"),
+            InputAction::UseItem(dir, target) => write!(f, "use {} {}", dir, target),
+            InputAction::UseSlot(slot) => write!(f, "useslot {}", slot),
+            InputAction::Interact(Some(dir)) => write!(f, "interact {}", dir),
+            InputAction::Interact(None) => write!(f, "interact"),
+            InputAction::CursorApplyItem(action_mode, index) => write!(f, "cursorapplyitem {} {}", action_mode, index),
+            InputAction::CursorApplySkill(action_mode, index) => write!(f, "cursorapplyskill {} {}", action_mode, index),
+            InputAction::CursorApplyMove(action_mode) => write!(f, "cursorapplymove {}", action_mode),
+            InputAction::CursorMove(dir, relative, long) => write!(f, "cursormove {} {} {}", dir, relative, long),
             InputAction::CursorReturn => write!(f, "cursorreturn"),
             InputAction::CursorToggle => write!(f, "cursortoggle"),
             InputAction::None => write!(f, "none"),
@@ -107,70 +127,86 @@ impl fmt::Display for InputAction {
     }
 }
 
+/// Parse an action's argument, describing which argument was missing or malformed.
+fn parse_action_arg<T: FromStr>(args: &[&str], index: usize) -> Result<T, String> {
+    let arg = args.get(index).ok_or_else(|| format!("'{}' is missing argument {}", args[0], index))?;
+    return arg.parse::<T>().map_err(|_| format!("Could not parse argument {} of '{}' ('{}')", index, args[0], arg));
+}
+
+/// Actions are written one per line as their name followed by their arguments, separated by
+/// spaces, in the format produced by their Display.
 impl FromStr for InputAction {
     type Err = String;
 
     fn from_str(string: &str) -> Result<Self, Self::Err> {
         let s: &mut str = &mut string.to_string();
         s.make_ascii_lowercase();
-        let args = s.split(" ").collect::<Vec<&str>>();
-
-        if args[0] == "left" {
-            let move_mode = args[1].parse::<MoveMode>().unwrap();
-            return Ok(InputAction::Move(Direction::Left, move_mode));
-        } else if args[0] == "right" {
-            let move_mode = args[1].parse::<MoveMode>().unwrap();
-            return Ok(InputAction::Move(Direction::Right, move_mode));
-        } else if args[0] == "up" {
-            let move_mode = args[1].parse::<MoveMode>().unwrap();
-            return Ok(InputAction::Move(Direction::Up, move_mode));
-        } else if args[0] == "down" {
-            let move_mode = args[1].parse::<MoveMode>().unwrap();
-            return Ok(InputAction::Move(Direction::Down, move_mode));
-        } else if args[0] == "upleft" {
-            let move_mode = args[1].parse::<MoveMode>().unwrap();
-            return Ok(InputAction::Move(Direction::UpLeft, move_mode));
-        } else if args[0] == "upright" {
-            let move_mode = args[1].parse::<MoveMode>().unwrap();
-            return Ok(InputAction::Move(Direction::UpRight, move_mode));
-        } else if args[0] == "downleft" {
-            let move_mode = args[1].parse::<MoveMode>().unwrap();
-            return Ok(InputAction::Move(Direction::DownLeft, move_mode));
-        } else if args[0] == "downright" {
-            let move_mode = args[1].parse::<MoveMode>().unwrap();
-            return Ok(InputAction::Move(Direction::DownRight, move_mode));
+        let args = s.split_whitespace().collect::<Vec<&str>>();
+
+        if args.len() == 0 {
+            return Err("Could not parse an empty line as InputAction".to_string());
+        }
+
+        if let Ok(direction) = args[0].parse::<Direction>() {
+            let move_mode = parse_action_arg::<MoveMode>(&args, 1)?;
+            return Ok(InputAction::Move(direction, move_mode));
         } else if args[0] == "pass" {
-            let move_mode = args[1].parse::<MoveMode>().unwrap();
+            let move_mode = parse_action_arg::<MoveMode>(&args, 1)?;
             return Ok(InputAction::Pass(move_mode));
         } else if args[0] == "pickup" {
             return Ok(InputAction::Pickup);
         } else if args[0] == "drop" {
             return Ok(InputAction::DropItem);
         } else if args[0] == "droptarget" {
-            let target = args[1].parse::<usize>().unwrap();
+            let target = parse_action_arg::<usize>(&args, 1)?;
             return Ok(InputAction::DropItemByIndex(target));
+        } else if args[0] == "dropall" {
+            return Ok(InputAction::DropAll);
         } else if args[0] == "yell" {
             return Ok(InputAction::Yell);
         } else if args[0] == "inventory" {
             return Ok(InputAction::Inventory);
         } else if args[0] == "use" {
-            let direction = args[1].parse::<Direction>().unwrap();
-            let target = args[1].parse::<usize>().unwrap();
+            let direction = parse_action_arg::<Direction>(&args, 1)?;
+            let target = parse_action_arg::<usize>(&args, 2)?;
             return Ok(InputAction::UseItem(direction, target));
-        } else if s.starts_with("selectitem") {
-            let selection = args[1].parse::<usize>().unwrap();
+        } else if args[0] == "selectitem" {
+            let selection = parse_action_arg::<usize>(&args, 1)?;
             return Ok(InputAction::SelectItem(selection));
         } else if args[0] == "interact" {
-            let dir = args[1].parse::<Direction>().ok();
+            let dir =
+                if args.len() > 1 {
+                    Some(parse_action_arg::<Direction>(&args, 1)?)
+                } else {
+                    None
+                };
             return Ok(InputAction::Interact(dir));
+        } else if args[0] == "exit" {
+            return Ok(InputAction::Exit);
+        } else if args[0] == "exploreall" {
+            return Ok(InputAction::ExploreAll);
+        } else if args[0] == "regenmap" {
+            return Ok(InputAction::RegenerateMap);
+        } else if args[0] == "savegame" {
+            return Ok(InputAction::SaveGame);
+        } else if args[0] == "loadgame" {
+            return Ok(InputAction::LoadGame);
+        } else if args[0] == "overlayon" {
+            return Ok(InputAction::OverlayOn);
+        } else if args[0] == "overlayoff" {
+            return Ok(InputAction::OverlayOff);
         } else if args[0] == "godmode" {
             return Ok(InputAction::GodMode);
-        } else if s.starts_with("click") {
-            let loc_x = args[1].parse::<i32>().unwrap();
-            let loc_y = args[2].parse::<i32>().unwrap();
-            let cell_x = args[3].parse::<i32>().unwrap();
-            let cell_y = args[4].parse::<i32>().unwrap();
+        } else if args[0] == "click" {
+            let loc_x = parse_action_arg::<i32>(&args, 1)?;
+            let loc_y = parse_action_arg::<i32>(&args, 2)?;
+            let cell_x = parse_action_arg::<i32>(&args, 3)?;
+            let cell_y = parse_action_arg::<i32>(&args, 4)?;
             return Ok(InputAction::MapClick(Pos::new(loc_x, loc_y), Pos::new(cell_x, cell_y)));
+        } else if args[0] == "mousebutton" {
+            let click = parse_action_arg::<MouseClick>(&args, 1)?;
+            let keydir = parse_action_arg::<KeyDir>(&args, 2)?;
+            return Ok(InputAction::MouseButton(click, keydir));
         } else if args[0] == "skill" {
             return Ok(InputAction::SkillMenu);
         } else if args[0] == "class" {
@@ -184,25 +220,56 @@ impl FromStr for InputAction {
         } else if args[0] == "slower" {
             return Ok(InputAction::DecreaseMoveMode);
         } else if args[0] == "cursormove" {
-            let dir = Direction::from_str(args[1]).unwrap();
-            let relative = bool::from_str(args[2]).unwrap();
-            let long = bool::from_str(args[3]).unwrap();
+            let dir = parse_action_arg::<Direction>(&args, 1)?;
+            let relative = parse_action_arg::<bool>(&args, 2)?;
+            let long = parse_action_arg::<bool>(&args, 3)?;
             return Ok(InputAction::CursorMove(dir, relative, long));
         } else if args[0] == "cursorreturn" {
             return Ok(InputAction::CursorReturn);
         } else if args[0] == "cursorapplyitem" {
-            let mode = ActionMode::from_str(args[1]).unwrap();
-            let target = args[2].parse::<usize>().unwrap();
+            let mode = parse_action_arg::<ActionMode>(&args, 1)?;
+            let target = parse_action_arg::<usize>(&args, 2)?;
             return Ok(InputAction::CursorApplyItem(mode, target));
         } else if args[0] == "cursorapplyskill" {
-            let mode = ActionMode::from_str(args[1]).unwrap();
-            let target = args[2].parse::<usize>().unwrap();
+            let mode = parse_action_arg::<ActionMode>(&args, 1)?;
+            let target = parse_action_arg::<usize>(&args, 2)?;
             return Ok(InputAction::CursorApplySkill(mode, target));
         } else if args[0] == "cursorapplymove" {
-            let mode = ActionMode::from_str(args[1]).unwrap();
+            let mode = parse_action_arg::<ActionMode>(&args, 1)?;
             return Ok(InputAction::CursorApplyMove(mode));
         } else if args[0] == "cursortoggle" {
             return Ok(InputAction::CursorToggle);
+        } else if args[0] == "toggleoverlay" {
+            let overlay = parse_action_arg::<Overlay>(&args, 1)?;
+            return Ok(InputAction::ToggleOverlay(overlay));
+        } else if args[0] == "toggleoverlays" {
+            return Ok(InputAction::ToggleOverlays);
+        } else if args[0] == "examine" {
+            let x = parse_action_arg::<i32>(&args, 1)?;
+            let y = parse_action_arg::<i32>(&args, 2)?;
+            return Ok(InputAction::Examine(Pos::new(x, y)));
+        } else if args[0] == "cycletarget" {
+            return Ok(InputAction::CycleTarget);
+        } else if args[0] == "scrollmsgsup" {
+            return Ok(InputAction::ScrollMessagesUp);
+        } else if args[0] == "scrollmsgsdown" {
+            return Ok(InputAction::ScrollMessagesDown);
+        } else if args[0] == "nextpage" {
+            return Ok(InputAction::NextPage);
+        } else if args[0] == "prevpage" {
+            return Ok(InputAction::PrevPage);
+        } else if args[0] == "undo" {
+            return Ok(InputAction::Undo);
+        } else if args[0] == "fullscreen" {
+            return Ok(InputAction::FullScreen);
+        } else if args[0] == "useslot" {
+            let slot = parse_action_arg::<usize>(&args, 1)?;
+            return Ok(InputAction::UseSlot(slot));
+        } else if args[0] == "charge" {
+            let charge = parse_action_arg::<usize>(&args, 1)?;
+            return Ok(InputAction::ChargeAttack(charge));
+        } else if args[0] == "none" {
+            return Ok(InputAction::None);
         } else {
             return Err(format!("Could not parse '{}' as InputAction", s));
         }
@@ -212,12 +279,7 @@ impl FromStr for InputAction {
 pub fn handle_input_universal(input_action: InputAction, game: &mut Game) -> bool {
     match input_action {
         InputAction::ExploreAll => {
-            for x in 0..game.data.map.width() {
-                for y in 0..game.data.map.height() {
-                    let pos = Pos::new(x, y);
-                    game.data.map[pos].explored = true;
-                }
-            }
+            game.data.map.explore_all();
 
             return true;
         }
@@ -227,6 +289,17 @@ pub fn handle_input_universal(input_action: InputAction, game: &mut Game) -> boo
             return true;
         }
 
+        InputAction::SaveGame => {
+            game.save_or_report(&game.config.save_file.clone());
+            return true;
+        }
+
+        InputAction::LoadGame => {
+            // with no save to load the game just continues
+            game.load_or_report(&game.config.save_file.clone());
+            return true;
+        }
+
         InputAction::GodMode => {
             let god_mode_hp = 10000;
             let player_id = game.data.find_by_name(EntityName::Player).unwrap();
@@ -259,8 +332,45 @@ pub fn handle_input_universal(input_action: InputAction, game: &mut Game) -> boo
     }
 }
 
-pub fn handle_input_inventory(input: InputAction, settings: &mut GameSettings) {
+pub fn handle_input_inventory(input: InputAction,
+                              data: &GameData,
+                              settings: &mut GameSettings,
+                              msg_log: &mut MsgLog,
+                              config: &Config) {
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+    let num_items = data.entities.inventory[&player_id].len();
+    let page_size = std::cmp::max(1, config.inventory_page_size);
+
     match input {
+        InputAction::NextPage => {
+            if (settings.inventory_page + 1) * page_size < num_items {
+                settings.inventory_page += 1;
+            }
+        }
+
+        InputAction::PrevPage => {
+            settings.inventory_page = settings.inventory_page.saturating_sub(1);
+        }
+
+        InputAction::SelectItem(item_index) => {
+            if item_index < page_size {
+                let index = settings.inventory_page * page_size + item_index;
+                if let Some(item_id) = data.entities.inventory[&player_id].get(index) {
+                    match settings.inventory_action {
+                        InventoryAction::Use => {
+                            settings.selected_item = Some(*item_id);
+                        }
+
+                        InventoryAction::Drop => {
+                            msg_log.log(Msg::DropItem(player_id, *item_id));
+                        }
+                    }
+
+                    change_state(settings, GameState::Playing);
+                }
+            }
+        }
+
         InputAction::Inventory => {
             change_state(settings, GameState::Playing);
         }
@@ -365,6 +475,17 @@ pub fn handle_input_confirm_quit(input: InputAction, settings: &mut GameSettings
     }
 }
 
+pub fn handle_input_intro(input: InputAction, settings: &mut GameSettings) {
+    match input {
+        InputAction::Esc => {
+            change_state(settings, GameState::Playing);
+        }
+
+        _ => {
+        }
+    }
+}
+
 pub fn handle_input(input_action: InputAction,
                     data: &GameData,
                     settings: &mut GameSettings,
@@ -382,7 +503,7 @@ pub fn handle_input(input_action: InputAction,
         }
 
         GameState::Inventory => {
-            handle_input_inventory(input_action, settings);
+            handle_input_inventory(input_action, data, settings, msg_log, config);
         }
 
         GameState::SkillMenu => {
@@ -397,6 +518,10 @@ pub fn handle_input(input_action: InputAction,
             handle_input_confirm_quit(input_action, settings);
         }
 
+        GameState::Intro => {
+            handle_input_intro(input_action, settings);
+        }
+
         GameState::Exit => {
         }
     }
@@ -430,14 +555,28 @@ pub fn handle_input_playing(input_action: InputAction,
 
                 let dir_move: Pos = scale_pos(dir.into_move(), dist);
 
-                let new_pos;
+                let start_pos;
                 if relative {
-                    new_pos = add_pos(player_pos, dir_move);
+                    start_pos = player_pos;
                 } else {
-                    new_pos = add_pos(cursor_pos, dir_move);
+                    start_pos = cursor_pos;
+                }
+
+                let mut new_pos = data.map.clamp(add_pos(start_pos, dir_move));
+
+                if config.cursor_clamp_fov {
+                    // step one tile at a time, stopping at the last tile the player can see
+                    new_pos = start_pos;
+                    for _ in 0..dist {
+                        let next_pos = data.map.clamp(add_pos(new_pos, dir.into_move()));
+                        if !data.pos_in_fov(player_id, next_pos, config) {
+                            break;
+                        }
+                        new_pos = next_pos;
+                    }
                 }
 
-                settings.cursor = Some(data.map.clamp(new_pos));
+                settings.cursor = Some(new_pos);
             }
         }
 
@@ -449,7 +588,7 @@ pub fn handle_input_playing(input_action: InputAction,
 
         (InputAction::CursorApplyItem(mode, target), true) => {
             if let Some(cursor_pos) = settings.cursor {
-                chord_item(ActionLoc::Place(cursor_pos), mode, target, data, msg_log);
+                chord_item(ActionLoc::Place(cursor_pos), mode, target, settings.selected_item, data, msg_log);
             }
         }
 
@@ -472,9 +611,21 @@ pub fn handle_input_playing(input_action: InputAction,
             }
         }
 
+        (InputAction::CycleTarget, true) => {
+            if let Some(cursor_pos) = settings.cursor {
+                let targets = target_order(data, config);
+
+                // move to the target after the one under the cursor, or the first target
+                let current = targets.iter().position(|id| data.entities.pos[id] == cursor_pos);
+                let next = current.map_or(0, |index| (index + 1) % targets.len().max(1));
+                if let Some(target_id) = targets.get(next) {
+                    settings.cursor = Some(data.entities.pos[target_id]);
+                }
+            }
+        }
+
         (InputAction::DropItemByIndex(target), true) => {
-            if target < data.entities.inventory[&player_id].len() {
-                let item_id = data.entities.inventory[&player_id][target];
+            if let Some(item_id) = data.entities.item_in_slot(player_id, target, settings.selected_item) {
                 msg_log.log(Msg::DropItem(player_id, item_id));
             }
         }
@@ -491,21 +642,39 @@ pub fn handle_input_playing(input_action: InputAction,
 
         (InputAction::DropItem, true) => {
             settings.inventory_action = InventoryAction::Drop;
+            settings.inventory_page = 0;
             change_state(settings, GameState::Inventory);
         }
 
+        (InputAction::DropAll, true) => {
+            if data.entities.inventory[&player_id].len() > 0 {
+                msg_log.log(Msg::DropAll(player_id));
+            }
+        }
+
+        (InputAction::Examine(pos), true) => {
+            msg_log.log(Msg::Examined(player_id, pos));
+        }
+
         (InputAction::Pickup, true) => {
             msg_log.log(Msg::PickUp(player_id));
         }
 
-        // TODO this should be removeable
-        (InputAction::MapClick(_map_loc, _map_cell), _) => {
+        (InputAction::MapClick(_map_loc, map_cell), true) => {
+            // outside of cursor mode, clicking a tile travels to it
+            if settings.cursor.is_none() {
+                settings.travel = travel_path(player_id, map_cell, data, config);
+            }
         }
 
         (InputAction::Yell, true) => {
             msg_log.log(Msg::Yell(player_id));
         }
 
+        (InputAction::ChargeAttack(charge), true) => {
+            msg_log.log(Msg::ChargeAttack(player_id, charge));
+        }
+
         (InputAction::IncreaseMoveMode, true) => {
             msg_log.log(Msg::ChangeMoveMode(player_id, true));
         }
@@ -522,8 +691,25 @@ pub fn handle_input_playing(input_action: InputAction,
             settings.overlay = false;
         }
 
+        (InputAction::ToggleOverlay(overlay), _) => {
+            settings.toggle_overlay(overlay);
+        }
+
+        (InputAction::ToggleOverlays, _) => {
+            settings.toggle_all_overlays();
+        }
+
+        (InputAction::ScrollMessagesUp, _) => {
+            settings.msg_scroll += 1;
+        }
+
+        (InputAction::ScrollMessagesDown, _) => {
+            settings.msg_scroll = settings.msg_scroll.saturating_sub(1);
+        }
+
         (InputAction::Inventory, true) => {
             settings.inventory_action = InventoryAction::Use;
+            settings.inventory_page = 0;
             change_state(settings, GameState::Inventory);
         }
 
@@ -551,8 +737,19 @@ pub fn handle_input_playing(input_action: InputAction,
         (InputAction::UseItem(dir, target), _) => {
             let pos = data.entities.pos[&player_id];
             let use_pos = dir.offset_pos(pos, 1);
-            if let Some(item_id) = data.entities.inventory[&player_id].get(target as usize) {
-                msg_log.log(Msg::UseItem(player_id, use_pos, *item_id));
+            if let Some(item_id) = data.entities.item_in_slot(player_id, target, settings.selected_item) {
+                msg_log.log(Msg::UseItem(player_id, use_pos, item_id));
+            }
+        }
+
+        (InputAction::UseSlot(slot), true) => {
+            // use the item towards the direction the player is facing
+            let pos = data.entities.pos[&player_id];
+            let use_pos = data.entities.direction[&player_id].offset_pos(pos, 1);
+            if let Some(item_id) = data.entities.item_in_slot(player_id, slot, settings.selected_item) {
+                msg_log.log(Msg::UseItem(player_id, use_pos, item_id));
+            } else {
+                msg_log.log(Msg::EmptySlot(player_id, slot));
             }
         }
 
@@ -561,13 +758,100 @@ pub fn handle_input_playing(input_action: InputAction,
     }
 }
 
+/// Find the path the player would travel to reach a clicked tile.
+/// The tile must be visible and reachable- otherwise the path is empty.
+pub fn travel_path(player_id: EntityId, target_pos: Pos, data: &GameData, config: &Config) -> Vec<Pos> {
+    let player_pos = data.entities.pos[&player_id];
+
+    if !data.map.is_within_bounds(target_pos) ||
+       target_pos == player_pos ||
+       data.map[target_pos].block_move ||
+       !data.pos_in_fov(player_id, target_pos, config) {
+        return Vec::new();
+    }
+
+    let traps_block = true;
+    let path = data.path_between(player_pos, target_pos, Reach::single(1), true, traps_block, None);
+
+    // the path starts at the player's own position
+    return path.into_iter().skip(1).collect::<Vec<Pos>>();
+}
+
+/// Visible, living enemies in the order they are cycled through by CycleTarget.
+pub fn target_order(data: &GameData, config: &Config) -> Vec<EntityId> {
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = data.entities.pos[&player_id];
+
+    let mut targets = data.entities.ids.iter()
+                          .filter(|id| data.entities.typ[*id] == EntityType::Enemy)
+                          .filter(|id| data.entities.status[*id].alive)
+                          .filter(|id| data.pos_in_fov(player_id, data.entities.pos[*id], config))
+                          .map(|id| *id)
+                          .collect::<Vec<EntityId>>();
+
+    let dist = |id: &EntityId| distance(player_pos, data.entities.pos[id]);
+    let threat = |id: &EntityId| data.entities.fighter.get(id).map_or(0, |f| f.hp * f.power);
+
+    match config.target_order {
+        TargetOrder::Distance => {
+            targets.sort_by_key(|id| (dist(id), *id));
+        }
+
+        TargetOrder::Threat => {
+            targets.sort_by_key(|id| (-threat(id), dist(id), *id));
+        }
+    }
+
+    return targets;
+}
+
+/// Find the path previewed for the tile under the mouse, if the player could travel there.
+pub fn path_preview(data: &GameData, settings: &GameSettings, config: &Config) -> Vec<Pos> {
+    if settings.cursor.is_some() || settings.state != GameState::Playing {
+        return Vec::new();
+    }
+
+    if let Some(hover_pos) = settings.hover {
+        let player_id = data.find_by_name(EntityName::Player).unwrap();
+        return travel_path(player_id, hover_pos, data, config);
+    }
+
+    return Vec::new();
+}
+
+/// Take the next step along the player's travel path, stopping if an enemy is visible.
+pub fn travel_step(data: &GameData, settings: &mut GameSettings, config: &Config) -> InputAction {
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = data.entities.pos[&player_id];
+
+    let enemy_visible = data.entities.ids.iter().any(|id| {
+        data.entities.typ[id] == EntityType::Enemy &&
+        data.entities.status[id].alive &&
+        data.is_in_fov(player_id, *id, config)
+    });
+
+    if enemy_visible || settings.state != GameState::Playing {
+        settings.travel.clear();
+        return InputAction::None;
+    }
+
+    let next_pos = settings.travel.remove(0);
+    if let Some(direction) = Direction::from_positions(player_pos, next_pos) {
+        return InputAction::Move(direction, MoveMode::Walk);
+    }
+
+    // the player is no longer on the path, so stop travelling
+    settings.travel.clear();
+    return InputAction::None;
+}
+
 pub fn handle_skill(skill_index: usize,
                     action_loc: ActionLoc,
                     action_mode: ActionMode,
                     data: &GameData, 
                     _settings: &mut GameSettings, 
                     msg_log: &mut MsgLog,
-                    _config: &Config) {
+                    config: &Config) {
     let player_id = data.find_by_name(EntityName::Player).unwrap();
 
     /* Check for Valid Skill Use */
@@ -611,18 +895,23 @@ pub fn handle_skill(skill_index: usize,
     let player_id = data.find_by_name(EntityName::Player).unwrap();
     let player_pos = data.entities.pos[&player_id];
     let dxy = sub_pos(skill_pos, player_pos);
-    let direction: Option<Direction> = Direction::from_dxy(dxy.x, dxy.y);
+    let mut direction: Option<Direction> = Direction::from_dxy(dxy.x, dxy.y);
+
+    // skills used on the player's own tile go in the direction the player is facing
+    if direction.is_none() && config.auto_face_player {
+        direction = Some(data.entities.direction[&player_id]);
+    }
 
     /* Carry Out Skill */
     match data.entities.skills[&player_id][skill_index] {
         Skill::GrassThrow => {
-            if let Some(direction) = Direction::from_dxy(dxy.x, dxy.y) {
+            if let Some(direction) = direction {
                 msg_log.log(Msg::GrassThrow(player_id, direction));
             }
         }
 
         Skill::GrassBlade => {
-            if let Some(direction) = Direction::from_dxy(dxy.x, dxy.y) {
+            if let Some(direction) = direction {
                 msg_log.log(Msg::GrassBlade(player_id, action_mode, direction));
             }
         }
@@ -704,6 +993,10 @@ pub fn handle_skill(skill_index: usize,
                 msg_log.log(Msg::Sprint(player_id, direction, SKILL_SPRINT_AMOUNT));
             }
         }
+
+        Skill::TimeStop => {
+            msg_log.log(Msg::TimeStop(player_id, config.time_stop_turns));
+        }
     }
 }
 
@@ -755,17 +1048,17 @@ fn chord_move(loc: ActionLoc,
 fn chord_item(loc: ActionLoc,
               mode: ActionMode,
               target: usize,
+              selected: Option<EntityId>,
               data: &GameData,
               msg_log: &mut MsgLog) {
     let player_id = data.find_by_name(EntityName::Player).unwrap();
 
-    let num_items_in_inventory = data.entities.inventory[&player_id].len();
-
-    if target >= num_items_in_inventory {
+    let item_id;
+    if let Some(slot_item_id) = data.entities.item_in_slot(player_id, target, selected) {
+        item_id = slot_item_id;
+    } else {
         return;
     }
-
-    let item_id = data.entities.inventory[&player_id][target as usize];
     
     match mode {
         ActionMode::Primary => {
@@ -831,6 +1124,10 @@ fn change_state(settings: &mut GameSettings, new_state: GameState) {
                 println!("CONSOLE: Confirm quit");
             }
 
+            GameState::Intro => {
+                println!("CONSOLE: Playing intro");
+            }
+
             GameState::Win => {
                 println!("CONSOLE: Won Level!");
             }
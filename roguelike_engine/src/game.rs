@@ -1,4 +1,10 @@
 use std::default::Default;
+use std::str::FromStr;
+use std::fmt;
+use std::time::Instant;
+use std::collections::HashMap;
+
+use log::warn;
 
 use oorandom::Rand32;
 
@@ -7,13 +13,14 @@ use serde::{Serialize, Deserialize};
 use roguelike_core::types::*;
 use roguelike_core::config::*;
 use roguelike_core::map::*;
-use roguelike_core::messaging::MsgLog;
+use roguelike_core::messaging::{Msg, MsgLog};
+use roguelike_core::utils::stable_hash;
 
 use crate::actions;
 use crate::actions::InputAction;
 use crate::generation::*;
 use crate::make_map::make_map;
-use crate::step::step_logic;
+use crate::step::{step_logic, logic_budget_warning};
 use crate::input::*;
 use crate::vault::*;
 
@@ -27,6 +34,7 @@ pub struct Game {
     pub rng: Rand32,
     pub vaults: Vec<Vault>,
     pub input: Input,
+    pub levels: HashMap<usize, GameData>,
 }
 
 impl Game {
@@ -34,7 +42,7 @@ impl Game {
         let entities = Entities::new();
         let rng: Rand32 = Rand32::new(seed);
 
-        let mut msg_log = MsgLog::new();
+        let mut msg_log = MsgLog::with_history(config.msg_history_limit);
 
         let map = Map::empty();
 
@@ -53,6 +61,7 @@ impl Game {
             rng: rng,
             vaults,
             input: Input::new(),
+            levels: HashMap::new(),
         };
 
         return state;
@@ -70,10 +79,261 @@ impl Game {
         }
     }
 
+    pub fn save(&self, file_name: &str) -> Result<(), String> {
+        let save_game = SaveGame::new(self);
+        let save_string =
+            serde_yaml::to_string(&save_game).map_err(|err| format!("Could not serialize game: {}", err))?;
+        return std::fs::write(file_name, save_string).map_err(|err| format!("Could not write save file {}: {}", file_name, err));
+    }
+
+    /// Save the game, telling the player if the save failed rather than ending the game.
+    pub fn save_or_report(&mut self, file_name: &str) {
+        if let Err(err) = self.save(file_name) {
+            eprintln!("{}", err);
+            self.msg_log.log(Msg::SaveFailed);
+        }
+    }
+
+    /// Load a save file, telling the player if there was no save to load.
+    /// Returns whether the game was replaced.
+    pub fn load_or_report(&mut self, file_name: &str) -> bool {
+        if let Err(err) = self.load_save(file_name) {
+            eprintln!("{}", err);
+            self.msg_log.log(Msg::LoadFailed);
+            return false;
+        }
+
+        return true;
+    }
+
+    /// Create a game from a save file. Vaults are not saved, so they must be loaded again
+    /// before generating new levels.
+    pub fn load(file_name: &str, config: Config) -> Result<Game, String> {
+        let mut game = Game::new(0, config);
+        game.load_save(file_name)?;
+        return Ok(game);
+    }
+
+    /// Replace the current game with the one in a save file, keeping the config and vaults.
+    pub fn load_save(&mut self, file_name: &str) -> Result<(), String> {
+        let save_game = SaveGame::from_file(file_name)?;
+
+        self.data = save_game.data;
+        self.settings = save_game.settings;
+        self.levels = save_game.levels;
+        self.rng = Rand32::from_state(save_game.rng_state);
+        self.msg_log.clear();
+        self.input = Input::new();
+
+        return Ok(());
+    }
+
+    /// A shareable code for the current level: the level number, the rng state the level
+    /// was generated from, and a hash of the config it was generated with.
+    pub fn layout_code(&self) -> String {
+        let (state, inc) = self.settings.level_rng;
+        return format!("{:x}-{:x}-{:x}-{:x}", self.settings.level_num, state, inc, config_hash(&self.config));
+    }
+
+    /// Regenerate the level described by a layout code. The code must come from a game
+    /// using the same config, as the config decides how the level is generated.
+    pub fn load_layout_code(&mut self, code: &str) -> Result<(), String> {
+        let parts = code.trim().split('-')
+                        .map(|part| u64::from_str_radix(part, 16))
+                        .collect::<Result<Vec<u64>, _>>()
+                        .map_err(|err| format!("invalid layout code '{}': {}", code, err))?;
+
+        if parts.len() != 4 {
+            return Err(format!("invalid layout code '{}'", code));
+        }
+
+        if parts[3] != config_hash(&self.config) {
+            return Err(format!("layout code '{}' was made with a different config", code));
+        }
+
+        self.settings.level_num = parts[0] as usize;
+        self.rng = Rand32::from_state((parts[1], parts[2]));
+        make_map(&self.config.map_load.clone(), self);
+
+        return Ok(());
+    }
+
+    /// Move to the given level. With persistent levels the current level is cached, and a
+    /// visited level is restored as it was left, with the player carried over.
+    pub fn change_level(&mut self, level_num: usize) {
+        let player_id = self.data.find_by_name(EntityName::Player).unwrap();
+
+        self.settings.state = GameState::Playing;
+
+        let descending = level_num > self.settings.level_num;
+
+        if self.config.persistent_levels {
+            self.levels.insert(self.settings.level_num, self.data.clone());
+        }
+
+        self.settings.level_num = level_num;
+
+        let cached_level = if self.config.persistent_levels { self.levels.remove(&level_num) } else { None };
+        if let Some(level) = cached_level {
+            let current = std::mem::replace(&mut self.data, level);
+
+            // the player arrives where they left the level, with their current state. The items they
+            // held when they left go with the old copy of the player, as they may have been dropped since.
+            let player_pos = self.data.entities.pos[&player_id];
+            let mut held = self.data.entities.inventory[&player_id].iter().copied().collect::<Vec<EntityId>>();
+            held.push(player_id);
+            for id in held.iter() {
+                if self.data.entities.ids.contains(id) {
+                    self.data.entities.remove_entity(*id);
+                }
+            }
+
+            let mut carried = current.entities.inventory[&player_id].iter().copied().collect::<Vec<EntityId>>();
+            carried.push(player_id);
+            for id in carried.iter() {
+                if self.data.entities.ids.contains(id) {
+                    self.data.entities.remove_entity(*id);
+                }
+                self.data.entities.copy_entity(&current.entities, *id);
+            }
+            self.data.entities.set_pos(player_id, player_pos);
+        } else {
+            make_map(&self.config.map_load.clone(), self);
+
+            if self.config.persistent_levels && level_num > 0 {
+                let player_pos = self.data.entities.pos[&player_id];
+                make_up_stairs(&mut self.data.entities, &self.config, player_pos, &mut self.msg_log);
+            }
+        }
+
+        // the player rests on the way down to the next level
+        if descending && self.config.level_rest_fraction > 0.0 {
+            let fighter = &mut self.data.entities.fighter[&player_id];
+            let healing = (fighter.max_hp as f32 * self.config.level_rest_fraction).ceil() as i32;
+            fighter.hp = std::cmp::min(fighter.max_hp, fighter.hp + healing);
+        }
+
+        self.start_level_banner();
+
+        if self.config.level_transition {
+            self.settings.level_transition = Some(self.settings.time);
+        }
+
+        if self.config.autosave_on_level {
+            self.save_or_report(&self.config.save_file.clone());
+        }
+    }
+
+    /// Give the player experience, leveling them up for each level threshold passed.
+    pub fn gain_xp(&mut self, amount: usize) {
+        let player_id = self.data.find_by_name(EntityName::Player).unwrap();
+
+        self.settings.xp += amount;
+
+        // the first level needs no experience, so thresholds start at the second level
+        while let Some(threshold) = self.config.xp_levels.get(self.settings.xp_level - 1) {
+            if self.settings.xp < *threshold {
+                break;
+            }
+
+            self.settings.xp_level += 1;
+
+            let fighter = &mut self.data.entities.fighter[&player_id];
+            fighter.max_hp += self.config.level_up_hp;
+            fighter.hp += self.config.level_up_hp;
+            fighter.power += self.config.level_up_power;
+
+            self.msg_log.log(Msg::LevelUp(player_id, self.settings.xp_level));
+        }
+    }
+
+    pub fn start_level_banner(&mut self) {
+        let banner = LevelBanner::new(self.settings.level_num,
+                                      self.config.map_load.to_string(),
+                                      self.settings.time);
+        self.settings.level_banner = Some(banner);
+    }
+
+    /// Play the configured intro messages before handing control to the player.
+    pub fn start_intro(&mut self) {
+        if self.config.intro_messages.len() > 0 {
+            self.settings.state = GameState::Intro;
+            self.settings.intro_index = 0;
+            self.settings.intro_time = 0.0;
+        }
+    }
+
+    pub fn intro_message(&self) -> Option<&String> {
+        if self.settings.state != GameState::Intro {
+            return None;
+        }
+
+        return self.config.intro_messages.get(self.settings.intro_index);
+    }
+
+    /// The tile the camera pans to for the current intro message, if it has one.
+    pub fn intro_camera(&self) -> Option<Pos> {
+        if self.settings.state != GameState::Intro {
+            return None;
+        }
+
+        return self.config.intro_camera.get(self.settings.intro_index).copied();
+    }
+
     pub fn step_game(&mut self, input_action: InputAction, dt: f32) -> bool {
+        let mut input_action = input_action;
+
         self.settings.dt = dt;
         self.settings.time += dt;
 
+        // the level banner only displays for a limited time, and does not block input
+        if let Some(banner) = &self.settings.level_banner {
+            if self.settings.time - banner.start_time >= self.config.level_banner_duration {
+                self.settings.level_banner = None;
+            }
+        }
+
+        // after winning a level the revealed map is shown for a moment before moving on
+        if let Some(reveal_start) = self.settings.level_reveal {
+            if self.settings.time - reveal_start >= self.config.reveal_map_duration {
+                self.settings.level_reveal = None;
+                self.change_level(self.settings.level_num + 1);
+            }
+            return self.settings.state != GameState::Exit;
+        }
+
+        // a new level fades in, and input is ignored until it has
+        if let Some(transition_start) = self.settings.level_transition {
+            if self.settings.time - transition_start < self.config.level_transition_duration {
+                return self.settings.state != GameState::Exit;
+            }
+            self.settings.level_transition = None;
+        }
+
+        // each intro message is shown for a fixed time, and the intro ends after the last one
+        if self.settings.state == GameState::Intro {
+            self.settings.intro_time += dt;
+            if self.settings.intro_time >= self.config.intro_message_duration {
+                self.settings.intro_time = 0.0;
+                self.settings.intro_index += 1;
+                if self.settings.intro_index >= self.config.intro_messages.len() {
+                    self.settings.state = GameState::Playing;
+                }
+            }
+        }
+
+        // any input cancels travel, otherwise take the next step towards the travel target
+        if input_action != InputAction::None {
+            self.settings.travel.clear();
+        } else if self.settings.travel.len() > 0 {
+            // travel steps are spaced out so the player can see each move
+            self.settings.travel_time += dt;
+            if self.settings.travel_time >= self.config.travel_delay {
+                self.settings.travel_time = 0.0;
+                input_action = actions::travel_step(&self.data, &mut self.settings, &self.config);
+            }
+        }
+
         let input_handled = actions::handle_input_universal(input_action, self);
 
         if !input_handled {
@@ -85,25 +345,86 @@ impl Game {
         }
 
         if self.msg_log.messages.len() > 0 {
+            let player_id = self.data.find_by_name(EntityName::Player).unwrap();
+            let start_pos = self.data.entities.pos[&player_id];
+
+            let logic_start = Instant::now();
             let finished_level = step_logic(self);
 
+            let elapsed_ms = logic_start.elapsed().as_secs_f32() * 1000.0;
+            let num_ais = self.data.entities.active_ais().len();
+            if let Some(warning) = logic_budget_warning(elapsed_ms, self.settings.turn_count, num_ais, &self.config) {
+                warn!("{}", warning);
+            }
+
+            // stairs to visited levels are used by stepping onto them
+            let player_pos = self.data.entities.pos[&player_id];
+            let moved_onto = |name| {
+                player_pos != start_pos &&
+                self.data.find_by_name(name).map_or(false, |id| self.data.entities.pos[&id] == player_pos)
+            };
+            let returned_up = self.config.persistent_levels && self.settings.level_num > 0 &&
+                              moved_onto(EntityName::UpStairs);
+            let returned_down = self.config.persistent_levels &&
+                                self.levels.contains_key(&(self.settings.level_num + 1)) &&
+                                moved_onto(EntityName::Exit);
+
             if finished_level {
                 // NOTE this is not a very general way to handle ending a level.
-                let player_id = self.data.find_by_name(EntityName::Player).unwrap();
                 let key_id = self.data.is_in_inventory(player_id, Item::Key).expect("Won level without key!");
                 self.data.entities.remove_item(player_id, key_id);
 
-                self.settings.state = GameState::Playing;
-
-                self.settings.level_num += 1;
-
-                make_map(&self.config.map_load.clone(), self);
+                if self.config.reveal_map_on_win {
+                    self.data.map.explore_all();
+                    self.settings.level_reveal = Some(self.settings.time);
+                } else {
+                    self.change_level(self.settings.level_num + 1);
+                }
+            } else if returned_down {
+                self.change_level(self.settings.level_num + 1);
+            } else if returned_up {
+                self.change_level(self.settings.level_num - 1);
             }
         }
 
         /* Check for explored tiles */
+        let newly_explored = self.explore_visible();
+
+        if self.config.xp_per_tile > 0 && newly_explored > 0 {
+            self.gain_xp(newly_explored * self.config.xp_per_tile);
+        }
+
+        if self.config.overlay_path_preview {
+            self.update_path_preview();
+        }
+
+        return self.settings.state != GameState::Exit;
+    }
+
+    /// Find the path preview again only when the hovered tile or the player has moved,
+    /// as the search is too slow to run every frame.
+    fn update_path_preview(&mut self) {
+        if self.settings.cursor.is_some() || self.settings.state != GameState::Playing {
+            self.settings.path_preview.clear();
+            self.settings.path_preview_from = None;
+            return;
+        }
+
         let player_id = self.data.find_by_name(EntityName::Player).unwrap();
-        // TODO make this to a map function like 'explore_from_position'.
+        let player_pos = self.data.entities.pos[&player_id];
+        let preview_from = self.settings.hover.map(|hover_pos| (hover_pos, player_pos));
+
+        if preview_from != self.settings.path_preview_from {
+            self.settings.path_preview = actions::path_preview(&self.data, &self.settings, &self.config);
+            self.settings.path_preview_from = preview_from;
+        }
+    }
+
+    /// Mark the tiles in the player's FOV as explored, returning how many
+    /// tiles were newly explored.
+    pub fn explore_visible(&mut self) -> usize {
+        let player_id = self.data.find_by_name(EntityName::Player).unwrap();
+        let mut newly_explored = 0;
         for pos in self.data.map.get_all_pos() {
             let visible =
                 self.data.pos_in_fov(player_id, pos, &self.config) ||
@@ -112,10 +433,51 @@ impl Game {
             // careful not to set map if not needed- this will clear the fov cache
             if visible && !self.data.map[pos].explored {
                 self.data.map[pos].explored = visible;
+                newly_explored += 1;
             }
         }
 
-        return self.settings.state != GameState::Exit;
+        return newly_explored;
+    }
+}
+
+/// Hash the parts of a config that decide how a level is generated, through their
+/// serialized form. The hash is stable across builds so layout codes can be shared.
+pub fn config_hash(config: &Config) -> u64 {
+    let generation_config = (&config.map_load,
+                             &config.spawn_table,
+                             &config.spawn_budget,
+                             &config.pack_monsters,
+                             config.stairs_safe_radius,
+                             config.monster_sleep);
+    let config_string = serde_yaml::to_string(&generation_config).expect("Could not serialize config!");
+    return stable_hash(&config_string);
+}
+
+/// The parts of a game that are written to a save file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub data: GameData,
+    pub settings: GameSettings,
+    pub rng_state: (u64, u64),
+    #[serde(default)]
+    pub levels: HashMap<usize, GameData>,
+}
+
+impl SaveGame {
+    pub fn new(game: &Game) -> SaveGame {
+        return SaveGame {
+            data: game.data.clone(),
+            settings: game.settings.clone(),
+            rng_state: game.rng.state(),
+            levels: game.levels.clone(),
+        };
+    }
+
+    pub fn from_file(file_name: &str) -> Result<SaveGame, String> {
+        let save_string =
+            std::fs::read_to_string(file_name).map_err(|err| format!("Could not read save file {}: {}", file_name, err))?;
+        return serde_yaml::from_str(&save_string).map_err(|err| format!("Could not parse save file {}: {}", file_name, err));
     }
 }
 
@@ -133,6 +495,23 @@ pub struct GameSettings {
     pub level_num: usize,
     pub running: bool,
     pub cursor: Option<Pos>,
+    pub level_banner: Option<LevelBanner>,
+    pub travel: Vec<Pos>,
+    pub travel_time: f32,
+    pub hover: Option<Pos>,
+    pub path_preview: Vec<Pos>,
+    pub path_preview_from: Option<(Pos, Pos)>, // hovered tile and player position of the preview
+    pub overlays: u32,
+    pub intro_index: usize,
+    pub intro_time: f32,
+    pub level_reveal: Option<f32>,
+    pub level_transition: Option<f32>,
+    pub level_rng: (u64, u64),
+    pub xp: usize,
+    pub xp_level: usize,
+    pub msg_scroll: usize, // lines scrolled back from the newest message
+    pub inventory_page: usize,
+    pub selected_item: Option<EntityId>, // item chosen in the inventory menu for its category's slot
 }
 
 impl GameSettings {
@@ -150,7 +529,106 @@ impl GameSettings {
             level_num: 0,
             running: true,
             cursor: None,
+            level_banner: None,
+            travel: Vec::new(),
+            travel_time: 0.0,
+            hover: None,
+            path_preview: Vec::new(),
+            path_preview_from: None,
+            overlays: 0,
+            intro_index: 0,
+            intro_time: 0.0,
+            level_reveal: None,
+            level_transition: None,
+            level_rng: (0, 0),
+            xp: 0,
+            xp_level: 1,
+            msg_scroll: 0,
+            inventory_page: 0,
+            selected_item: None,
         };
     }
+
+    /// An overlay is shown if it is individually enabled, or while all overlays are held on.
+    pub fn overlay_enabled(&self, overlay: Overlay) -> bool {
+        return self.overlay || (self.overlays & overlay.bit()) != 0;
+    }
+
+    pub fn toggle_overlay(&mut self, overlay: Overlay) {
+        self.overlays ^= overlay.bit();
+    }
+
+    /// The master toggle clears all overlays if any are enabled, and otherwise enables them all.
+    pub fn toggle_all_overlays(&mut self) {
+        if self.overlays != 0 {
+            self.overlays = 0;
+        } else {
+            for overlay in Overlay::all().iter() {
+                self.overlays |= overlay.bit();
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Overlay {
+    Attack,
+    Movement,
+    Sound,
+    Fov,
+}
+
+impl Overlay {
+    pub fn all() -> Vec<Overlay> {
+        return vec!(Overlay::Attack, Overlay::Movement, Overlay::Sound, Overlay::Fov);
+    }
+
+    pub fn bit(&self) -> u32 {
+        return 1 << (*self as u32);
+    }
+}
+
+impl fmt::Display for Overlay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Overlay::Attack => write!(f, "attack"),
+            Overlay::Movement => write!(f, "movement"),
+            Overlay::Sound => write!(f, "sound"),
+            Overlay::Fov => write!(f, "fov"),
+        }
+    }
 }
 
+impl FromStr for Overlay {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let s: &mut str = &mut string.to_string();
+        s.make_ascii_lowercase();
+
+        if s == "attack" {
+            return Ok(Overlay::Attack);
+        } else if s == "movement" {
+            return Ok(Overlay::Movement);
+        } else if s == "sound" {
+            return Ok(Overlay::Sound);
+        } else if s == "fov" {
+            return Ok(Overlay::Fov);
+        }
+
+        return Err(format!("Could not parse '{}' as Overlay", s));
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct LevelBanner {
+    pub level_num: usize,
+    pub theme: String,
+    pub start_time: f32,
+}
+
+impl LevelBanner {
+    pub fn new(level_num: usize, theme: String, start_time: f32) -> LevelBanner {
+        return LevelBanner { level_num, theme, start_time };
+    }
+}
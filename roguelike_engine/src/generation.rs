@@ -1,4 +1,4 @@
-use std::collections::VecDeque;
+use std::collections::{VecDeque, BTreeMap};
 
 use oorandom::Rand32;
 
@@ -11,7 +11,7 @@ use roguelike_core::constants::*;
 use roguelike_core::movement::*;
 use roguelike_core::messaging::*;
 use roguelike_core::config::*;
-use roguelike_core::utils::{rand_from_pos, distance, rng_range_u32, rng_range_i32, choose};
+use roguelike_core::utils::{rand_from_pos, distance, rng_range_u32, rng_range_i32, rng_trial, rng_bool, choose, choose_weighted, add_pos, step_towards};
 
 use crate::game::*;
 use crate::procgen::*;
@@ -82,20 +82,22 @@ pub fn make_dagger(entities: &mut Entities, _config: &Config, pos: Pos, msg_log:
     return entity_id;
 }
 
-pub fn make_hammer(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+pub fn make_hammer(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Item, ENTITY_HAMMER as char, Color::white(), EntityName::Hammer, false);
 
     entities.item.insert(entity_id,  Item::Hammer);
+    entities.durability.insert(entity_id, config.hammer_durability);
 
     msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, EntityName::Hammer, entities.direction[&entity_id]));
 
     return entity_id;
 }
 
-pub fn make_sword(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+pub fn make_sword(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Item, ENTITY_SWORD as char, Color::white(), EntityName::Sword, false);
 
     entities.item.insert(entity_id,  Item::Sword);
+    entities.durability.insert(entity_id, config.sword_durability);
 
     msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, EntityName::Sword, entities.direction[&entity_id]));
 
@@ -147,6 +149,13 @@ pub fn make_cursor(entities: &mut Entities, _config: &Config, pos: Pos, msg_log:
     return entity_id;
 }
 
+/// Give a monster the damage type configured for its kind, if any.
+fn set_damage_type(entities: &mut Entities, config: &Config, entity_id: EntityId) {
+    if let Some(damage_type) = config.monster_damage_types.get(&entities.name[&entity_id]) {
+        entities.damage_type.insert(entity_id, *damage_type);
+    }
+}
+
 pub fn make_gol(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Enemy, '\u{98}', Color::white(), EntityName::Gol, true);
 
@@ -161,6 +170,7 @@ pub fn make_gol(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mu
     entities.move_mode.insert(entity_id,  MoveMode::Walk);
     entities.attack_type.insert(entity_id,  AttackType::Ranged);
     entities.fov_radius.insert(entity_id,  config.fov_radius_monster);
+    set_damage_type(entities, config, entity_id);
 
     msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, EntityName::Gol, entities.direction[&entity_id]));
     
@@ -181,6 +191,7 @@ pub fn make_spire(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &
     entities.move_mode.insert(entity_id,  MoveMode::Walk);
     entities.attack_type.insert(entity_id,  AttackType::Melee);
     entities.fov_radius.insert(entity_id,  config.fov_radius_monster);
+    set_damage_type(entities, config, entity_id);
 
     msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, EntityName::Spire, entities.direction[&entity_id]));
 
@@ -201,6 +212,7 @@ pub fn make_pawn(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &m
     entities.move_mode.insert(entity_id,  MoveMode::Walk);
     entities.attack_type.insert(entity_id,  AttackType::Melee);
     entities.fov_radius.insert(entity_id,  config.fov_radius_monster);
+    set_damage_type(entities, config, entity_id);
 
     msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, EntityName::Pawn, entities.direction[&entity_id]));
 
@@ -221,13 +233,14 @@ pub fn make_rook(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &m
     entities.move_mode.insert(entity_id,  MoveMode::Walk);
     entities.attack_type.insert(entity_id,  AttackType::Melee);
     entities.fov_radius.insert(entity_id,  config.fov_radius_monster);
+    set_damage_type(entities, config, entity_id);
 
     msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, EntityName::Rook, entities.direction[&entity_id]));
 
     return entity_id;
 }
 
-pub fn make_armil(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+pub fn make_armil(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Enemy, '\u{98}', Color::white(), EntityName::Armil, true);
 
     entities.fighter.insert(entity_id,  Fighter { max_hp: 10, hp: 10, defense: 0, power: 1, });
@@ -240,12 +253,34 @@ pub fn make_armil(entities: &mut Entities, _config: &Config, pos: Pos, msg_log:
     entities.move_mode.insert(entity_id,  MoveMode::Walk);
     entities.attack_type.insert(entity_id,  AttackType::Push);
     entities.fov_radius.insert(entity_id,  0);
+    set_damage_type(entities, config, entity_id);
 
     msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, EntityName::Armil, entities.direction[&entity_id]));
     
     return entity_id;
 } 
 
+pub fn make_slime(entities: &mut Entities, config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let entity_id = entities.create_entity(pos.x, pos.y, EntityType::Enemy, 'o', Color::white(), EntityName::Slime, true);
+
+    entities.fighter.insert(entity_id,  Fighter { max_hp: 10, hp: 10, defense: 0, power: 1, });
+    entities.ai.insert(entity_id,  Ai::Basic);
+    entities.behavior.insert(entity_id,  Behavior::Idle);
+    entities.movement.insert(entity_id,  Reach::Single(SLIME_MOVE_DISTANCE));
+    entities.attack.insert(entity_id,  Reach::Single(SLIME_ATTACK_DISTANCE));
+    entities.status[&entity_id].alive = true;
+    entities.direction.insert(entity_id,  Direction::from_f32(rand_from_pos(pos)));
+    entities.stance.insert(entity_id,  Stance::Standing);
+    entities.move_mode.insert(entity_id,  MoveMode::Walk);
+    entities.attack_type.insert(entity_id,  AttackType::Melee);
+    entities.fov_radius.insert(entity_id,  config.fov_radius_monster);
+    set_damage_type(entities, config, entity_id);
+
+    msg_log.log(Msg::SpawnedObject(entity_id, entities.typ[&entity_id], pos, EntityName::Slime, entities.direction[&entity_id]));
+
+    return entity_id;
+}
+
 pub fn make_sound_trap(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let sound = entities.create_entity(pos.x, pos.y, EntityType::Item, ENTITY_TRAP_SOUND as char, Color::white(), EntityName::SoundTrap, false);
 
@@ -312,6 +347,14 @@ pub fn make_exit(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &
     return exit;
 }
 
+pub fn make_up_stairs(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
+    let stairs = entities.create_entity(pos.x, pos.y, EntityType::Item, '<', Color::white(), EntityName::UpStairs, false);
+
+    msg_log.log(Msg::SpawnedObject(stairs, entities.typ[&stairs], pos, EntityName::UpStairs, entities.direction[&stairs]));
+
+    return stairs;
+}
+
 pub fn make_stone(entities: &mut Entities, _config: &Config, pos: Pos, msg_log: &mut MsgLog) -> EntityId {
     let stone = entities.create_entity(pos.x, pos.y, EntityType::Item, ENTITY_STONE as char, Color::white(), EntityName::Stone, true);
 
@@ -355,12 +398,16 @@ pub fn make_entity(entities: &mut Entities, config: &Config, entity_name: Entity
         EntityName::Gol => make_gol(entities, config, pos, msg_log),
         EntityName::Spire => make_spire(entities, config, pos, msg_log),
         EntityName::Pawn => make_pawn(entities, config, pos, msg_log),
+        EntityName::Rook => make_rook(entities, config, pos, msg_log),
+        EntityName::Armil => make_armil(entities, config, pos, msg_log),
+        EntityName::Slime => make_slime(entities, config, pos, msg_log),
         EntityName::SoundTrap => make_sound_trap(entities, config, pos, msg_log),
         EntityName::SpikeTrap => make_spike_trap(entities, config, pos, msg_log),
         EntityName::FreezeTrap => make_freeze_trap(entities, config, pos, msg_log),
         EntityName::BlinkTrap => make_blink_trap(entities, config, pos, msg_log),
         EntityName::GateTrigger => make_gate_trigger(entities, config, pos, msg_log),
         EntityName::Exit => make_exit(entities, config, pos, msg_log),
+        EntityName::UpStairs => make_up_stairs(entities, config, pos, msg_log),
         EntityName::Stone => make_stone(entities, config, pos, msg_log),
         _ => {
             panic!(format!("Cannot create {:?} this way", entity_name));
@@ -374,6 +421,9 @@ pub fn make_island(data: &mut GameData,
                    rng: &mut Rand32) -> Pos {
     let center = Pos::new(data.map.width() / 2, data.map.height() / 2);
 
+    // leave a ring of water inside the map, so the island has an edge for the exit
+    let radius = std::cmp::min(ISLAND_RADIUS, std::cmp::min(center.x, center.y) - 1);
+
     let mut water_tile_positions = Vec::new();
 
     /* Create Island */
@@ -382,7 +432,7 @@ pub fn make_island(data: &mut GameData,
         for y in 0..data.map.height() {
             let pos = Pos::new(x, y);
 
-            if distance(pos, center) <= ISLAND_RADIUS {
+            if distance(pos, center) <= radius {
                 data.map[pos] = Tile::empty();
             } else {
                 data.map[pos] = Tile::water();
@@ -395,7 +445,7 @@ pub fn make_island(data: &mut GameData,
     let obstacles: Vec<Obstacle> = Obstacle::all_obstacles();
 
     for _ in 0..ISLAND_NUM_OBSTACLES {
-        let rand_pos = random_offset(rng, ISLAND_RADIUS);
+        let rand_pos = random_offset(rng, radius);
         let pos = Pos::new(center.x + rand_pos.x, center.y + rand_pos.y);
 
         let obstacle = choose(rng, &obstacles).unwrap();
@@ -408,14 +458,14 @@ pub fn make_island(data: &mut GameData,
 
     /* add buildings */
     for _ in 0..rng_range_u32(rng, 3, 5) {
-        let rand_pos = random_offset(rng, ISLAND_RADIUS);
+        let rand_pos = random_offset(rng, radius);
         let pos = Pos::new(center.x + rand_pos.x, center.y + rand_pos.y);
         add_obstacle(&mut data.map, pos, Obstacle::Building, rng);
     }
 
     /* random subtraction */
     for _ in 0..ISLAND_NUM_SUBTRACTIONS_ATTEMPTS {
-        let pos = pos_in_radius(center, ISLAND_RADIUS, rng);
+        let pos = pos_in_radius(center, radius, rng);
 
         if data.map[pos].tile_type == TileType::Wall {
             data.map[pos] = Tile::empty();
@@ -424,7 +474,7 @@ pub fn make_island(data: &mut GameData,
 
     /* random additions */
     for _ in 0..ISLAND_NUM_ADDITION_ATTEMPTS {
-        let pos = pos_in_radius(center, ISLAND_RADIUS, rng);
+        let pos = pos_in_radius(center, radius, rng);
         let obstacle = choose(rng, &obstacles).unwrap();
 
         if data.map[pos].tile_type == TileType::Wall {
@@ -434,47 +484,13 @@ pub fn make_island(data: &mut GameData,
 
     /* random stones */
     for _ in 0..10 {
-        let pos = pos_in_radius(center, ISLAND_RADIUS, rng);
+        let pos = pos_in_radius(center, radius, rng);
 
         if data.map.is_empty(pos) {
             make_stone(&mut data.entities, config, pos, msg_log);
         }
     }
 
-    /* add monsters */
-    for _ in 0..0 {
-        loop {
-            let pos = pos_in_radius(center, ISLAND_RADIUS, rng);
-
-            if !data.has_blocking_entity(pos).is_some()  {
-                make_gol(&mut data.entities, config, pos, msg_log);
-                break;
-            }
-        }
-    }
-
-    for _ in 0..1 {
-        loop {
-            let pos = pos_in_radius(center, ISLAND_RADIUS, rng);
-
-            if !data.has_blocking_entity(pos).is_some()  {
-                make_pawn(&mut data.entities, config, pos, msg_log);
-                break;
-            }
-        }
-    }
-    
-    for _ in 0..0 {
-        loop {
-            let pos = pos_in_radius(center, ISLAND_RADIUS, rng);
-
-            if !data.has_blocking_entity(pos).is_some() {
-                make_spire(&mut data.entities, config, pos, msg_log);
-                break;
-            }
-        }
-    }
-
     let x = rng_range_i32(rng, 0, data.map.width());
     let y = rng_range_i32(rng, 0, data.map.height());
     let pos = Pos::new(x, y);
@@ -484,10 +500,10 @@ pub fn make_island(data: &mut GameData,
     }
 
     /* add key object */
-    let mut pos = pos_in_radius(center, ISLAND_RADIUS, rng);
+    let mut pos = pos_in_radius(center, radius, rng);
 
     while !data.map.is_empty(pos) {
-        pos = pos_in_radius(center, ISLAND_RADIUS, rng);
+        pos = pos_in_radius(center, radius, rng);
     }
     make_key(&mut data.entities, &config, pos, msg_log);
 
@@ -519,6 +535,381 @@ pub fn make_island(data: &mut GameData,
     return center;
 }
 
+/// Generate a cave by filling the map randomly with walls and smoothing it with the 4-5 rule.
+/// Separate caves are joined by corridors, and the player and exit are placed in the two
+/// largest caves. Returns the player's position.
+pub fn make_cellular_map(data: &mut GameData,
+                         config: &Config,
+                         fill_percent: f32,
+                         iterations: usize,
+                         msg_log: &mut MsgLog,
+                         rng: &mut Rand32) -> Pos {
+    let (width, height) = data.map.size();
+    let is_edge = |pos: Pos| pos.x == 0 || pos.y == 0 || pos.x == width - 1 || pos.y == height - 1;
+
+    /* Seed the map */
+    for pos in data.map.get_all_pos() {
+        if is_edge(pos) || rng_trial(rng, fill_percent) {
+            data.map[pos] = Tile::wall();
+        } else {
+            data.map[pos] = Tile::empty();
+        }
+    }
+
+    /* Smooth into caves */
+    for _ in 0..iterations {
+        let prev_map = data.map.clone();
+
+        for pos in prev_map.get_all_pos() {
+            if is_edge(pos) {
+                continue;
+            }
+
+            let num_walls = prev_map.neighbors(pos).iter().filter(|pos| prev_map[**pos].block_move).count();
+            if num_walls > 4 {
+                data.map[pos] = Tile::wall();
+            } else if num_walls < 4 {
+                data.map[pos] = Tile::empty();
+            }
+        }
+    }
+
+    /* Connect the caves */
+    let mut regions = open_regions(&data.map);
+    if regions.len() == 0 {
+        // nothing survived smoothing, so open up the center for the player
+        let center = Pos::new(width / 2, height / 2);
+        data.map[center] = Tile::empty();
+        regions.push(vec!(center));
+    }
+
+    let mut connected = regions[0].clone();
+    for region in regions.iter().skip(1) {
+        // carve from the region towards the nearest tile already connected
+        let start = region[0];
+        let target = *connected.iter().min_by_key(|pos| distance(start, **pos)).unwrap();
+
+        let mut pos = start;
+        while pos != target {
+            if pos.x != target.x {
+                pos.x += (target.x - pos.x).signum();
+            } else {
+                pos.y += (target.y - pos.y).signum();
+            }
+
+            if data.map[pos].block_move {
+                data.map[pos] = Tile::empty();
+                connected.push(pos);
+            }
+        }
+
+        connected.extend(region.iter());
+    }
+
+    /* Place the player, key, and exit */
+    let player_pos = choose(rng, &regions[0]).unwrap();
+
+    let exit_region = regions.get(1).unwrap_or(&regions[0]);
+    let exit_positions = exit_region.iter().map(|pos| *pos).filter(|pos| *pos != player_pos).collect::<Vec<Pos>>();
+    if let Some(exit_pos) = choose(rng, &exit_positions) {
+        make_exit(&mut data.entities, config, exit_pos, msg_log);
+    }
+
+    let key_positions = regions[0].iter().map(|pos| *pos).filter(|pos| *pos != player_pos).collect::<Vec<Pos>>();
+    if let Some(key_pos) = choose(rng, &key_positions) {
+        make_key(&mut data.entities, config, key_pos, msg_log);
+    }
+
+    return player_pos;
+}
+
+/// Spawn the level's budget of monsters, chosen from the spawn table, on clear tiles away
+/// from the player and the exit.
+pub fn spawn_from_table(data: &mut GameData,
+                        config: &Config,
+                        level_num: usize,
+                        player_pos: Pos,
+                        msg_log: &mut MsgLog,
+                        rng: &mut Rand32) {
+    let budget = config.spawn_budget.get(level_num).or(config.spawn_budget.last()).map_or(0, |budget| *budget);
+    let table = config.spawn_table.iter().map(|(name, weight)| (*name, *weight)).collect::<Vec<(EntityName, u32)>>();
+
+    let exit_positions = data.entities.ids.iter()
+                                      .filter(|id| data.entities.name[*id] == EntityName::Exit)
+                                      .map(|id| data.entities.pos[id])
+                                      .collect::<Vec<Pos>>();
+    let mut positions = data.get_clear_pos()
+                            .iter()
+                            .filter(|pos| distance(player_pos, **pos) > 4)
+                            .filter(|pos| exit_positions.iter().all(|exit_pos| distance(*exit_pos, **pos) > config.stairs_safe_radius))
+                            .filter(|pos| data.has_entities(**pos).is_empty())
+                            .map(|pos| *pos)
+                            .collect::<Vec<Pos>>();
+
+    // each pack is identified by its first member
+    let mut packs: BTreeMap<EntityName, EntityId> = BTreeMap::new();
+
+    for _ in 0..budget {
+        if positions.len() == 0 {
+            break;
+        }
+
+        if let Some(name) = choose_weighted(rng, &table) {
+            let index = rng_range_u32(rng, 0, positions.len() as u32) as usize;
+            let pos = positions.remove(index);
+            let id = make_entity(&mut data.entities, config, name, pos, msg_log);
+
+            if config.pack_monsters.contains(&name) {
+                let pack_id = *packs.entry(name).or_insert(id);
+                data.entities.pack.insert(id, pack_id);
+            }
+        }
+    }
+}
+
+/// Carve corridors by walking randomly from a starting position, which defaults to the center
+/// of the map. Each step has a small chance to move towards the center so the walk does not
+/// hug the edges. The player starts at the start of the walk and the exit is placed on the
+/// carved tile farthest from it. Returns the player's position.
+pub fn make_drunkard_walk_map(data: &mut GameData,
+                              config: &Config,
+                              steps: usize,
+                              start: Option<Pos>,
+                              msg_log: &mut MsgLog,
+                              rng: &mut Rand32) -> Pos {
+    let (width, height) = data.map.size();
+    let center = Pos::new(width / 2, height / 2);
+
+    for pos in data.map.get_all_pos() {
+        data.map[pos] = Tile::wall();
+    }
+
+    // keep the outer edge of the map as walls
+    let clamp_inside = |pos: Pos| {
+        Pos::new(std::cmp::max(1, std::cmp::min(width - 2, pos.x)),
+                 std::cmp::max(1, std::cmp::min(height - 2, pos.y)))
+    };
+
+    let start_pos = clamp_inside(start.unwrap_or(center));
+    data.map[start_pos] = Tile::empty();
+
+    /* Walk the map */
+    let mut pos = start_pos;
+    for _ in 0..steps {
+        let offset;
+        if rng_trial(rng, DRUNKARD_WALK_CENTER_BIAS) && pos != center {
+            let dx = center.x - pos.x;
+            let dy = center.y - pos.y;
+            if dx.abs() > dy.abs() {
+                offset = (dx.signum(), 0);
+            } else {
+                offset = (0, dy.signum());
+            }
+        } else {
+            offset = [(1, 0), (-1, 0), (0, 1), (0, -1)][rng_range_u32(rng, 0, 4) as usize];
+        }
+
+        pos = clamp_inside(Pos::new(pos.x + offset.0, pos.y + offset.1));
+        data.map[pos] = Tile::empty();
+    }
+
+    /* Place the exit and key */
+    let mut dists = vec![vec![None; height as usize]; width as usize];
+    let mut queue = VecDeque::new();
+    let mut carved = Vec::new();
+    dists[start_pos.x as usize][start_pos.y as usize] = Some(0);
+    queue.push_back(start_pos);
+    while let Some(pos) = queue.pop_front() {
+        let dist = dists[pos.x as usize][pos.y as usize].unwrap();
+        carved.push((pos, dist));
+
+        for offset in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            let next = Pos::new(pos.x + offset.0, pos.y + offset.1);
+            if data.map.is_within_bounds(next) &&
+               !data.map[next].block_move &&
+               dists[next.x as usize][next.y as usize].is_none() {
+                dists[next.x as usize][next.y as usize] = Some(dist + 1);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    // the first tile found at the greatest distance, so ties are broken by search order
+    let (exit_pos, _) = carved.iter().fold((start_pos, 0), |farthest, (pos, dist)| if *dist > farthest.1 { (*pos, *dist) } else { farthest });
+    if exit_pos != start_pos {
+        make_exit(&mut data.entities, config, exit_pos, msg_log);
+    }
+
+    let key_positions = carved.iter().map(|(pos, _)| *pos).filter(|pos| *pos != start_pos && *pos != exit_pos).collect::<Vec<Pos>>();
+    if let Some(key_pos) = choose(rng, &key_positions) {
+        make_key(&mut data.entities, config, key_pos, msg_log);
+    }
+
+    return start_pos;
+}
+
+/// Generate rooms and corridors by splitting the map into a binary space partition and
+/// placing a room in each leaf, joining sibling partitions with L-shaped corridors. The player
+/// starts in the first room and the exit is placed in the last room. Returns the player's position.
+pub fn make_bsp_map(data: &mut GameData,
+                    config: &Config,
+                    min_room: usize,
+                    max_depth: usize,
+                    msg_log: &mut MsgLog,
+                    rng: &mut Rand32) -> Pos {
+    let (width, height) = data.map.size();
+
+    for pos in data.map.get_all_pos() {
+        data.map[pos] = Tile::wall();
+    }
+
+    let min_room = std::cmp::max(1, min_room as i32);
+    let mut rooms = Vec::new();
+    bsp_split(data, (0, 0, width, height), 0, min_room, max_depth, &mut rooms, rng);
+
+    /* Place the player, key, and exit */
+    let room_center = |room: &(i32, i32, i32, i32)| Pos::new(room.0 + room.2 / 2, room.1 + room.3 / 2);
+    let player_pos = room_center(&rooms[0]);
+
+    let mut exit_pos = room_center(&rooms[rooms.len() - 1]);
+    if exit_pos == player_pos {
+        // with a single room, the exit goes in its corner instead
+        exit_pos = Pos::new(rooms[0].0, rooms[0].1);
+    }
+    if exit_pos != player_pos {
+        make_exit(&mut data.entities, config, exit_pos, msg_log);
+    }
+
+    let key_positions = data.map.get_empty_pos().into_iter()
+                                                .filter(|pos| *pos != player_pos && *pos != exit_pos)
+                                                .collect::<Vec<Pos>>();
+    if let Some(key_pos) = choose(rng, &key_positions) {
+        make_key(&mut data.entities, config, key_pos, msg_log);
+    }
+
+    return player_pos;
+}
+
+/// Split an area, given as (x, y, width, height), until it is too small or deep enough,
+/// carving a room in each leaf. Rooms are added in order from the first leaf to the last.
+/// Returns a position within one of the area's rooms, used to connect it to its sibling.
+fn bsp_split(data: &mut GameData,
+             area: (i32, i32, i32, i32),
+             depth: usize,
+             min_room: i32,
+             max_depth: usize,
+             rooms: &mut Vec<(i32, i32, i32, i32)>,
+             rng: &mut Rand32) -> Pos {
+    let (x, y, width, height) = area;
+
+    // each side of a split needs space for a room and the walls around it
+    let min_size = min_room + 2;
+    let can_split_x = width >= 2 * min_size;
+    let can_split_y = height >= 2 * min_size;
+
+    if depth < max_depth && (can_split_x || can_split_y) {
+        let split_x =
+            if can_split_x && can_split_y {
+                if width as f32 > height as f32 * 1.25 {
+                    true
+                } else if height as f32 > width as f32 * 1.25 {
+                    false
+                } else {
+                    rng_bool(rng)
+                }
+            } else {
+                can_split_x
+            };
+
+        let (first, second);
+        if split_x {
+            let split = rng_range_i32(rng, min_size, width - min_size + 1);
+            first = (x, y, split, height);
+            second = (x + split, y, width - split, height);
+        } else {
+            let split = rng_range_i32(rng, min_size, height - min_size + 1);
+            first = (x, y, width, split);
+            second = (x, y + split, width, height - split);
+        }
+
+        let first_pos = bsp_split(data, first, depth + 1, min_room, max_depth, rooms, rng);
+        let second_pos = bsp_split(data, second, depth + 1, min_room, max_depth, rooms, rng);
+
+        /* Connect the two halves with an L-shaped corridor */
+        let corner =
+            if rng_bool(rng) {
+                Pos::new(second_pos.x, first_pos.y)
+            } else {
+                Pos::new(first_pos.x, second_pos.y)
+            };
+        for (start, end) in [(first_pos, corner), (corner, second_pos)] {
+            let mut pos = start;
+            data.map[pos] = Tile::empty();
+            while pos != end {
+                pos = add_pos(pos, step_towards(pos, end));
+                data.map[pos] = Tile::empty();
+            }
+        }
+
+        return first_pos;
+    }
+
+    /* Carve a room within the leaf, leaving a wall around it */
+    let room_width = rng_range_i32(rng, std::cmp::min(min_room, width - 2), width - 2 + 1);
+    let room_height = rng_range_i32(rng, std::cmp::min(min_room, height - 2), height - 2 + 1);
+    let room_x = rng_range_i32(rng, x + 1, x + width - room_width);
+    let room_y = rng_range_i32(rng, y + 1, y + height - room_height);
+
+    for room_pos_x in room_x..(room_x + room_width) {
+        for room_pos_y in room_y..(room_y + room_height) {
+            data.map[(room_pos_x, room_pos_y)] = Tile::empty();
+        }
+    }
+
+    let room = (room_x, room_y, room_width, room_height);
+    rooms.push(room);
+
+    return Pos::new(room_x + room_width / 2, room_y + room_height / 2);
+}
+
+/// The separate open areas of a map, largest first.
+fn open_regions(map: &Map) -> Vec<Vec<Pos>> {
+    let mut regions: Vec<Vec<Pos>> = Vec::new();
+    let mut seen = vec![vec![false; map.height() as usize]; map.width() as usize];
+
+    for start in map.get_all_pos() {
+        if map[start].block_move || seen[start.x as usize][start.y as usize] {
+            continue;
+        }
+
+        let mut region = Vec::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+        seen[start.x as usize][start.y as usize] = true;
+
+        while let Some(pos) = queue.pop_front() {
+            region.push(pos);
+
+            for offset in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                let next = Pos::new(pos.x + offset.0, pos.y + offset.1);
+                if map.is_within_bounds(next) &&
+                   !map[next].block_move &&
+                   !seen[next.x as usize][next.y as usize] {
+                    seen[next.x as usize][next.y as usize] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        regions.push(region);
+    }
+
+    // stable sort so regions of equal size keep the order they were found in
+    regions.sort_by_key(|region| std::cmp::Reverse(region.len()));
+
+    return regions;
+}
+
 pub fn make_player_test_map(entities: &mut Entities,
                             config: &Config,
                             msg_log: &mut MsgLog) -> (Map, Pos) {
@@ -544,6 +935,30 @@ pub fn make_player_test_map(entities: &mut Entities,
     return (map, Pos::from(position));
 }
 
+pub fn make_arena_map(entities: &mut Entities,
+                      config: &Config,
+                      spawns: &[(EntityName, Pos)],
+                      msg_log: &mut MsgLog) -> (Map, Pos) {
+    let mut map = Map::from_dims(ARENA_WIDTH as u32, ARENA_HEIGHT as u32);
+    let position = Pos::new(ARENA_WIDTH / 2, ARENA_HEIGHT - 2);
+
+    // surround the arena with walls
+    for x in 0..ARENA_WIDTH {
+        map[(x, 0)] = Tile::wall();
+        map[(x, ARENA_HEIGHT - 1)] = Tile::wall();
+    }
+    for y in 0..ARENA_HEIGHT {
+        map[(0, y)] = Tile::wall();
+        map[(ARENA_WIDTH - 1, y)] = Tile::wall();
+    }
+
+    for (name, pos) in spawns.iter() {
+        make_entity(entities, config, *name, *pos, msg_log);
+    }
+
+    return (map, position);
+}
+
 pub fn make_test_map(game: &mut Game) {
     //right down left should work
     //down will hit wall
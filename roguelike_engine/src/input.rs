@@ -2,20 +2,47 @@ use std::time::Instant;
 use std::collections::HashMap;
 use std::cmp::Ord;
 use std::str::FromStr;
+use std::fmt;
 
 use serde::{Serialize, Deserialize};
 
 use roguelike_core::types::*;
 use roguelike_core::movement::Direction;
-use roguelike_core::config::Config;
+use roguelike_core::config::{Config, MouseAction};
 use roguelike_core::movement::MoveMode;
+use roguelike_core::constants::*;
 
 use crate::game::*;
 use crate::actions::*;
 
 
-const SKILL_KEYS: &[char] = &['a', 's', 'd'];
-const ITEM_KEYS: &[char] = &['z', 'x', 'c'];
+// descriptions for the actions that can be bound to keys in the config's keybindings
+const ACTION_DESCRIPTIONS: &[(InputAction, &str)] = &[
+    (InputAction::Exit, "exit the game"),
+    (InputAction::Pickup, "pick up an item"),
+    (InputAction::DropItem, "drop an item"),
+    (InputAction::DropAll, "drop the whole inventory"),
+    (InputAction::Inventory, "open the inventory"),
+    (InputAction::Yell, "yell, alerting nearby monsters"),
+    (InputAction::ExploreAll, "explore the whole map"),
+    (InputAction::GodMode, "toggle god mode"),
+    (InputAction::RegenerateMap, "regenerate the map"),
+    (InputAction::SaveGame, "quicksave to the save file"),
+    (InputAction::LoadGame, "load the quicksave"),
+    (InputAction::IncreaseMoveMode, "move faster"),
+    (InputAction::DecreaseMoveMode, "move slower"),
+    (InputAction::OverlayOff, "hide overlays when released"),
+    (InputAction::SkillMenu, "open the skill menu"),
+    (InputAction::ClassMenu, "open the class menu"),
+    (InputAction::ToggleOverlays, "toggle all overlays"),
+    (InputAction::CycleTarget, "move the cursor to the next enemy"),
+    (InputAction::ScrollMessagesUp, "scroll the message log back"),
+    (InputAction::ScrollMessagesDown, "scroll the message log forward"),
+    (InputAction::NextPage, "next page of the inventory"),
+    (InputAction::PrevPage, "previous page of the inventory"),
+    (InputAction::Undo, "undo the last move in god mode"),
+    (InputAction::FullScreen, "toggle fullscreen"),
+];
 
 
 #[derive(Clone, Debug, Copy, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
@@ -40,7 +67,17 @@ impl FromStr for KeyDir {
             return Ok(KeyDir::Up);
         }
 
-        panic!(format!("KeyDir {} unexpected", s));
+        return Err(format!("Could not parse '{}' as KeyDir", s));
+    }
+}
+
+impl fmt::Display for KeyDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyDir::Up => write!(f, "up"),
+            KeyDir::Held => write!(f, "held"),
+            KeyDir::Down => write!(f, "down"),
+        }
     }
 }
 
@@ -67,6 +104,35 @@ pub enum MouseClick {
     Middle,
 }
 
+impl FromStr for MouseClick {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let s: &mut str = &mut string.to_string();
+        s.make_ascii_lowercase();
+
+        if s == "left" {
+            return Ok(MouseClick::Left);
+        } else if s == "right" {
+            return Ok(MouseClick::Right);
+        } else if s == "middle" {
+            return Ok(MouseClick::Middle);
+        }
+
+        return Err(format!("Could not parse '{}' as MouseClick", s));
+    }
+}
+
+impl fmt::Display for MouseClick {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MouseClick::Left => write!(f, "left"),
+            MouseClick::Right => write!(f, "right"),
+            MouseClick::Middle => write!(f, "middle"),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Copy, PartialEq, PartialOrd)]
 pub struct HeldState {
     down_time: Instant,
@@ -89,7 +155,7 @@ pub enum InputEvent {
     Ctrl(KeyDir),
     Shift(KeyDir),
     Alt(KeyDir),
-    MousePos(i32, i32),
+    MousePos(Pos, Option<Pos>), // mouse position, screen square
     MouseButton(MouseClick, Pos, Option<Pos>, KeyDir), // button clicked, mouse position, screen square, keydir
     Esc,
     Tab,
@@ -151,8 +217,9 @@ impl Input {
         }
 
         match event {
-            InputEvent::MousePos(_, _) => {
-                // we don't use the mouse position within the game
+            InputEvent::MousePos(_, target_pos) => {
+                // track the hovered tile for previewing travel paths
+                settings.hover = target_pos;
             }
 
             InputEvent::Quit => {
@@ -190,54 +257,62 @@ impl Input {
             }
 
             InputEvent::MouseButton(clicked, mouse_pos, target_pos, dir) => {
-                action = self.handle_mouse_button(clicked, mouse_pos, target_pos, dir);
+                action = self.handle_mouse_button(clicked, mouse_pos, target_pos, dir, config);
             }
         }
 
         return action;
     }
 
-    fn handle_mouse_button(&mut self, clicked: MouseClick, mouse_pos: Pos, target_pos: Option<Pos>, dir: KeyDir) -> InputAction {
+    fn handle_mouse_button(&mut self, clicked: MouseClick, mouse_pos: Pos, target_pos: Option<Pos>, dir: KeyDir, config: &Config) -> InputAction {
         let mut action = InputAction::MouseButton(clicked, dir);
 
-        let down = dir == KeyDir::Down;
-        match clicked {
-            MouseClick::Left => {
+        let mouse_action = match clicked {
+            MouseClick::Left => config.mouse_bindings.left,
+            MouseClick::Middle => config.mouse_bindings.middle,
+            MouseClick::Right => config.mouse_bindings.right,
+        };
 
-                if down {
-                    if let Some(target_pos) = target_pos {
+        let down = dir == KeyDir::Down;
+        if down {
+            if let Some(target_pos) = target_pos {
+                match mouse_action {
+                    MouseAction::MapClick => {
                         action = InputAction::MapClick(mouse_pos, target_pos);
                     }
-                }
-            }
 
-            MouseClick::Middle => {
-                action = InputAction::MouseButton(clicked, dir);
-            }
+                    MouseAction::Examine => {
+                        action = InputAction::Examine(target_pos);
+                    }
 
-            MouseClick::Right => {
-                action = InputAction::MouseButton(clicked, dir);
+                    MouseAction::Button => {
+                    }
+                }
             }
         }
 
         return action;
     }
 
-    fn handle_char_up(&mut self, chr: char, settings: &GameSettings) -> InputAction {
+    fn handle_char_up(&mut self, chr: char, settings: &GameSettings, config: &Config) -> InputAction {
+        // releasing the charge key releases the attack, with the charge built up while held
+        if chr == config.charge_key && !self.cursor {
+            let charge = self.char_held.remove(&chr).map_or(0, |held_state| held_state.repetitions);
+            return InputAction::ChargeAttack(charge);
+        }
+
         // if key was held, do nothing when it is up to avoid a final press
         if self.is_held(chr) {
             return InputAction::None;
         }
         self.char_held.remove(&chr);
 
-        match chr {
-            'z' => return self.use_item(0),
-            'x' => return self.use_item(1),
-            'c' => return self.use_item(2),
-            'a' => return self.use_skill(0),
-            's' => return self.use_skill(1),
-            'd' => return self.use_skill(2),
-            _ => return self.key_to_action(chr, settings),
+        if let Some(index) = item_slot(chr, config) {
+            return self.use_item(index);
+        } else if let Some(index) = skill_slot(chr, config) {
+            return self.use_skill(index);
+        } else {
+            return self.key_to_action(chr, settings, config);
         }
     }
 
@@ -247,9 +322,12 @@ impl Input {
 
             // alternate is used so you throw items
             return InputAction::CursorApplyItem(ActionMode::Alternate, item_index);
+        } else if self.target == Some(Target::item(item_index)) {
+            // releasing the item key without choosing a direction uses the item straight away.
+            // if a direction was chosen the item was already used, and the target cleared.
+            self.target = None;
+            return InputAction::UseSlot(item_index);
         } else {
-            // item use only works outside of cursor mode when an arrow key is released,
-            // not when the item key is released.
             return InputAction::None;
         }
     }
@@ -267,10 +345,11 @@ impl Input {
         }
     }
 
-    fn handle_char_down(&mut self, chr: char) -> InputAction {
+    fn handle_char_down(&mut self, chr: char, config: &Config) -> InputAction {
         let mut action = InputAction::None;
 
-        if chr == 'o' {
+        // the overlay key shows overlays while held, and hides them when released
+        if alpha_up_to_action(chr, config) == InputAction::OverlayOff {
             action = InputAction::OverlayOn;
         }
 
@@ -279,12 +358,12 @@ impl Input {
             action = InputAction::CursorToggle;
         }
 
-        if let Some(index) = SKILL_KEYS.iter().position(|key| *key == chr) {
-            self.target = Some(Target::skill(index as usize));
+        if let Some(index) = skill_slot(chr, config) {
+            self.target = Some(Target::skill(index));
         }
 
-        if let Some(index) = ITEM_KEYS.iter().position(|key| *key == chr) {
-            self.target = Some(Target::item(index as usize));
+        if let Some(index) = item_slot(chr, config) {
+            self.target = Some(Target::item(index));
         }
 
         return action;
@@ -297,15 +376,20 @@ impl Input {
             let held_state = *held_state;
             let time_since = time.duration_since(held_state.down_time).as_secs_f32();
 
-            let new_repeats = (time_since / config.repeat_delay) as usize;
-            if new_repeats > held_state.repetitions {
-                action = self.key_to_action(chr, settings);
+            let new_repeats = (time_since / repeat_delay(chr, config)) as usize;
+            if chr == config.charge_key && !self.cursor {
+                // holding the charge key builds up charge instead of repeating
+                let charge = std::cmp::min(new_repeats, config.charge_max);
+                self.char_held.insert(chr, HeldState::new(held_state.down_time, charge));
+            } else if new_repeats > held_state.repetitions {
+                action = self.key_to_action(chr, settings, config);
 
                 if action == InputAction::OverlayOff   ||
                    action == InputAction::Inventory    ||
                    action == InputAction::SkillMenu    ||
                    action == InputAction::Exit         ||
                    action == InputAction::CursorToggle ||
+                   action == InputAction::ToggleOverlays ||
                    action == InputAction::ClassMenu {
                     action = InputAction::None;
                 } else {
@@ -320,11 +404,11 @@ impl Input {
     fn handle_char(&mut self, chr: char, dir: KeyDir, time: Instant, settings: &GameSettings, config: &Config) -> InputAction {
         match dir {
             KeyDir::Up => {
-                return self.handle_char_up(chr, settings);
+                return self.handle_char_up(chr, settings, config);
             }
 
             KeyDir::Down => {
-                return self.handle_char_down(chr);
+                return self.handle_char_down(chr, config);
             }
 
             KeyDir::Held => {
@@ -333,14 +417,14 @@ impl Input {
         }
     }
 
-    fn key_to_action(&mut self, chr: char, settings: &GameSettings) -> InputAction {
+    fn key_to_action(&mut self, chr: char, settings: &GameSettings, config: &Config) -> InputAction {
         let action;
 
-        // handle numeric characters first
-        if chr.is_ascii_digit() {
-            if settings.state.is_menu() {
-                action = InputAction::SelectItem(chr.to_digit(10).unwrap() as usize);
-            } else if chr == '5' {
+        // menus are navigated with numeric characters, and movement keys are handled next
+        if chr.is_ascii_digit() && settings.state.is_menu() {
+            action = InputAction::SelectItem(chr.to_digit(10).unwrap() as usize);
+        } else if chr == config.keybindings.pass || direction_key(chr, config).is_some() {
+            if chr == config.keybindings.pass {
                 if self.alt {
                     action = InputAction::Interact(None);
                 } else {
@@ -350,7 +434,7 @@ impl Input {
                         action = InputAction::Pass(self.move_mode());
                     }
                 }
-            } else if let Some(dir) = from_digit(chr) {
+            } else if let Some(dir) = direction_key(chr, config) {
                 if self.cursor {
                    action = InputAction::CursorMove(dir, self.ctrl, self.shift);
                 } else if self.alt {
@@ -367,88 +451,331 @@ impl Input {
         } else if chr == ' ' {
             action = InputAction::None;
         } else {
-            action = alpha_up_to_action(chr);
+            action = alpha_up_to_action(chr, config);
         }
 
         return action;
     }
 }
 
-pub fn alpha_up_to_action(chr: char) -> InputAction {
-    let input_action: InputAction;
-
-    match chr {
-        'q' => {
-            input_action = InputAction::Exit;
-        }
+/// The skill slot bound to a key, if any. Only the first MAX_SKILL_SLOTS keys are used.
+pub fn skill_slot(chr: char, config: &Config) -> Option<usize> {
+    return config.skill_keys.iter().take(MAX_SKILL_SLOTS).position(|key| *key == chr);
+}
 
-        'g' => {
-            input_action = InputAction::Pickup;
-        }
+/// The item slot bound to a key, if any. Only the first MAX_ITEM_SLOTS keys are used.
+pub fn item_slot(chr: char, config: &Config) -> Option<usize> {
+    return config.item_keys.iter().take(MAX_ITEM_SLOTS).position(|key| *key == chr);
+}
 
-        'd' => {
-            input_action = InputAction::DropItem;
-        }
+/// The movement direction bound to a key, if any.
+pub fn direction_key(chr: char, config: &Config) -> Option<Direction> {
+    return config.keybindings.directions.get(&chr).copied();
+}
 
-        'i' => {
-            input_action = InputAction::Inventory;
-        }
+/// How long a key must be held before it repeats, falling back to
+/// the global repeat delay for keys without their own.
+pub fn repeat_delay(chr: char, config: &Config) -> f32 {
+    return config.repeat_delays.get(&chr).copied().unwrap_or(config.repeat_delay);
+}
 
-        'y' => {
-            input_action = InputAction::Yell;
-        }
+/// The action bound to a key in the config. Unbound keys, and keys bound to
+/// an action that does not parse, do nothing.
+pub fn alpha_up_to_action(chr: char, config: &Config) -> InputAction {
+    if let Some(action_name) = config.keybindings.actions.get(&chr) {
+        return action_name.parse::<InputAction>().unwrap_or(InputAction::None);
+    }
 
-        'l' => {
-            input_action = InputAction::ExploreAll;
-        }
+    return InputAction::None;
+}
 
-        't' => {
-            input_action = InputAction::GodMode;
-        }
+/// The key bound to an action in the config, if any.
+pub fn action_key(action: InputAction, config: &Config) -> Option<char> {
+    return config.keybindings.actions.keys().find(|chr| alpha_up_to_action(**chr, config) == action).map(|chr| *chr);
+}
 
-        'p' => {
-            input_action = InputAction::RegenerateMap;
-        }
+/// An action along with its default key and a short description, for help screens and tools.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActionBinding {
+    pub action: InputAction,
+    pub key: String,
+    pub description: String,
+}
 
-        'e' => {
-            input_action = InputAction::IncreaseMoveMode;
-        }
+impl ActionBinding {
+    pub fn new(action: InputAction, key: &str, description: &str) -> ActionBinding {
+        return ActionBinding { action, key: key.to_string(), description: description.to_string() };
+    }
+}
 
-        'w' => {
-            input_action = InputAction::DecreaseMoveMode;
-        }
+/// List all input actions with their key bindings.
+pub fn action_bindings(config: &Config) -> Vec<ActionBinding> {
+    let mut bindings = Vec::new();
 
-        'o' => {
-            input_action = InputAction::OverlayOff;
-        }
+    for (chr, dir) in config.keybindings.directions.iter() {
+        let description = format!("move {}", dir);
+        bindings.push(ActionBinding::new(InputAction::Move(*dir, MoveMode::Walk), &chr.to_string(), &description));
+    }
+    let pass = config.keybindings.pass;
+    bindings.push(ActionBinding::new(InputAction::Pass(MoveMode::Walk), &pass.to_string(), "pass a turn"));
+    bindings.push(ActionBinding::new(InputAction::Interact(None), &format!("alt+{}", pass), "interact with the current tile"));
+
+    for chr in config.keybindings.actions.keys() {
+        let action = alpha_up_to_action(*chr, config);
+        let description = ACTION_DESCRIPTIONS.iter()
+                                             .find(|(described, _)| *described == action)
+                                             .map_or(action.to_string(), |(_, description)| description.to_string());
+        bindings.push(ActionBinding::new(action, &chr.to_string(), &description));
+    }
 
-        'j' => {
-            input_action = InputAction::SkillMenu;
-        }
+    bindings.push(ActionBinding::new(InputAction::CursorToggle, "space", "toggle cursor mode"));
+    bindings.push(ActionBinding::new(InputAction::CursorReturn, "tab", "return the cursor to the player"));
+    bindings.push(ActionBinding::new(InputAction::OverlayOn, "o (held)", "show all overlays"));
+    bindings.push(ActionBinding::new(InputAction::Esc, "esc", "leave the current menu"));
+    bindings.push(ActionBinding::new(InputAction::ChargeAttack(0), &format!("{} (held)", config.charge_key), "charge an attack in the facing direction"));
 
-        'h' => {
-            input_action = InputAction::ClassMenu;
-        }
+    for (index, chr) in config.item_keys.iter().take(MAX_ITEM_SLOTS).enumerate() {
+        let description = format!("select item {}", index);
+        bindings.push(ActionBinding::new(InputAction::CursorApplyItem(ActionMode::Alternate, index), &chr.to_string(), &description));
+        bindings.push(ActionBinding::new(InputAction::UseSlot(index), &chr.to_string(), &format!("use item {}", index)));
+    }
 
-        _ => {
-            input_action = InputAction::None;
-        }
+    for (index, chr) in config.skill_keys.iter().take(MAX_SKILL_SLOTS).enumerate() {
+        let description = format!("select skill {}", index);
+        bindings.push(ActionBinding::new(InputAction::CursorApplySkill(ActionMode::Alternate, index), &chr.to_string(), &description));
     }
 
-    return input_action;
+    return bindings;
+}
+
+#[test]
+pub fn test_action_bindings() {
+    let config = Config::from_file("../config.yaml");
+    let bindings = action_bindings(&config);
+
+    let find = |action: InputAction| bindings.iter().find(|binding| binding.action == action).unwrap().clone();
+
+    assert_eq!("8", find(InputAction::Move(Direction::Up, MoveMode::Walk)).key);
+    assert_eq!("3", find(InputAction::Move(Direction::DownRight, MoveMode::Walk)).key);
+    assert_eq!("g", find(InputAction::Pickup).key);
+    assert_eq!("i", find(InputAction::Inventory).key);
+
+    assert!(bindings.iter().all(|binding| binding.description.len() > 0));
+
+    // the table drives the key mapping itself
+    assert_eq!(InputAction::Pickup, alpha_up_to_action('g', &config));
+    assert_eq!(InputAction::None, alpha_up_to_action('m', &config));
+}
+
+#[test]
+pub fn test_configured_skill_slots() {
+    let mut config = Config::from_file("../config.yaml");
+    config.skill_keys = vec!('a', 's', 'd', 'f');
+
+    let mut input = Input::new();
+    let mut settings = GameSettings::new(0, false);
+    let time = Instant::now();
+
+    // skills are applied in cursor mode
+    input.handle_event(&mut settings, InputEvent::Char(' ', KeyDir::Down), time, &config);
+    input.handle_event(&mut settings, InputEvent::Char(' ', KeyDir::Up), time, &config);
+
+    input.handle_event(&mut settings, InputEvent::Char('f', KeyDir::Down), time, &config);
+    let action = input.handle_event(&mut settings, InputEvent::Char('f', KeyDir::Up), time, &config);
+    assert_eq!(InputAction::CursorApplySkill(ActionMode::Alternate, 3), action);
+
+    // the fourth slot is listed in the bindings as well
+    let bindings = action_bindings(&config);
+    assert!(bindings.iter().any(|binding| binding.action == InputAction::CursorApplySkill(ActionMode::Alternate, 3) && binding.key == "f"));
 }
 
-fn from_digit(chr: char) -> Option<Direction> {
-    match chr {
-        '4' => Some(Direction::Left),
-        '6' => Some(Direction::Right),
-        '8' => Some(Direction::Up),
-        '2' => Some(Direction::Down),
-        '1' => Some(Direction::DownLeft),
-        '3' => Some(Direction::DownRight),
-        '7' => Some(Direction::UpLeft),
-        '9' => Some(Direction::UpRight),
-        _ => None,
+#[test]
+pub fn test_quick_item_use() {
+    let config = Config::from_file("../config.yaml");
+    let item_key = config.item_keys[0];
+
+    let mut input = Input::new();
+    let mut settings = GameSettings::new(0, false);
+    let time = Instant::now();
+
+    // releasing the item key on its own uses the item
+    input.handle_event(&mut settings, InputEvent::Char(item_key, KeyDir::Down), time, &config);
+    let action = input.handle_event(&mut settings, InputEvent::Char(item_key, KeyDir::Up), time, &config);
+    assert_eq!(InputAction::UseSlot(0), action);
+
+    // choosing a direction uses the item in that direction instead
+    input.handle_event(&mut settings, InputEvent::Char(item_key, KeyDir::Down), time, &config);
+    input.handle_event(&mut settings, InputEvent::Char('6', KeyDir::Down), time, &config);
+    let action = input.handle_event(&mut settings, InputEvent::Char('6', KeyDir::Up), time, &config);
+    assert_eq!(InputAction::UseItem(Direction::Right, 0), action);
+    let action = input.handle_event(&mut settings, InputEvent::Char(item_key, KeyDir::Up), time, &config);
+    assert_eq!(InputAction::None, action);
+
+    // in cursor mode the item key still throws
+    input.handle_event(&mut settings, InputEvent::Char(' ', KeyDir::Down), time, &config);
+    input.handle_event(&mut settings, InputEvent::Char(' ', KeyDir::Up), time, &config);
+    input.handle_event(&mut settings, InputEvent::Char(item_key, KeyDir::Down), time, &config);
+    let action = input.handle_event(&mut settings, InputEvent::Char(item_key, KeyDir::Up), time, &config);
+    assert_eq!(InputAction::CursorApplyItem(ActionMode::Alternate, 0), action);
+}
+
+#[test]
+pub fn test_configured_keybindings() {
+    let mut config = Config::from_file("../config.yaml");
+    config.keybindings.actions.clear();
+    config.keybindings.actions.insert('k', "pickup".to_string());
+    config.keybindings.directions.insert('h', Direction::Left);
+
+    let mut input = Input::new();
+    let mut settings = GameSettings::new(0, false);
+    let time = Instant::now();
+
+    input.handle_event(&mut settings, InputEvent::Char('k', KeyDir::Down), time, &config);
+    let action = input.handle_event(&mut settings, InputEvent::Char('k', KeyDir::Up), time, &config);
+    assert_eq!(InputAction::Pickup, action);
+
+    input.handle_event(&mut settings, InputEvent::Char('h', KeyDir::Down), time, &config);
+    let action = input.handle_event(&mut settings, InputEvent::Char('h', KeyDir::Up), time, &config);
+    assert_eq!(InputAction::Move(Direction::Left, MoveMode::Walk), action);
+
+    // the default binding is gone, so the key does nothing
+    input.handle_event(&mut settings, InputEvent::Char('g', KeyDir::Down), time, &config);
+    let action = input.handle_event(&mut settings, InputEvent::Char('g', KeyDir::Up), time, &config);
+    assert_eq!(InputAction::None, action);
+
+    let bindings = action_bindings(&config);
+    assert!(bindings.iter().any(|binding| binding.action == InputAction::Pickup && binding.key == "k"));
+}
+
+#[test]
+pub fn test_default_keybindings_leave_recording_keys() {
+    let config = Config::from_file("../config.yaml");
+
+    // '[' and ']' step backwards and forwards through a recording
+    assert_eq!(InputAction::None, alpha_up_to_action('[', &config));
+    assert_eq!(InputAction::None, alpha_up_to_action(']', &config));
+    assert_eq!(Some('-'), action_key(InputAction::ScrollMessagesUp, &config));
+    assert_eq!(Some('='), action_key(InputAction::ScrollMessagesDown, &config));
+}
+
+#[test]
+pub fn test_mouse_bindings() {
+    let mut config = Config::from_file("../config.yaml");
+    let mut input = Input::new();
+    let mut settings = GameSettings::new(0, false);
+    let time = Instant::now();
+
+    let mouse_pos = Pos::new(100, 100);
+    let tile = Pos::new(3, 4);
+    let right_click = InputEvent::MouseButton(MouseClick::Right, mouse_pos, Some(tile), KeyDir::Down);
+
+    let action = input.handle_event(&mut settings, right_click, time, &config);
+    assert_eq!(InputAction::MouseButton(MouseClick::Right, KeyDir::Down), action);
+
+    config.mouse_bindings.right = MouseAction::Examine;
+    let action = input.handle_event(&mut settings, right_click, time, &config);
+    assert_eq!(InputAction::Examine(tile), action);
+
+    // the left button still clicks on the map
+    let left_click = InputEvent::MouseButton(MouseClick::Left, mouse_pos, Some(tile), KeyDir::Down);
+    let action = input.handle_event(&mut settings, left_click, time, &config);
+    assert_eq!(InputAction::MapClick(mouse_pos, tile), action);
+}
+
+#[test]
+pub fn test_input_action_round_trip() {
+    let actions = vec!(InputAction::Move(Direction::UpLeft, MoveMode::Run),
+                       InputAction::Pass(MoveMode::Sneak),
+                       InputAction::Pickup,
+                       InputAction::DropItem,
+                       InputAction::DropItemByIndex(2),
+                       InputAction::DropAll,
+                       InputAction::Yell,
+                       InputAction::UseItem(Direction::Down, 1),
+                       InputAction::UseSlot(3),
+                       InputAction::Interact(Some(Direction::Left)),
+                       InputAction::Interact(None),
+                       InputAction::CursorMove(Direction::Right, true, false),
+                       InputAction::CursorReturn,
+                       InputAction::CursorToggle,
+                       InputAction::CursorApplyItem(ActionMode::Alternate, 1),
+                       InputAction::CursorApplySkill(ActionMode::Primary, 0),
+                       InputAction::CursorApplyMove(ActionMode::Primary),
+                       InputAction::MapClick(Pos::new(1, 2), Pos::new(3, -4)),
+                       InputAction::MouseButton(MouseClick::Middle, KeyDir::Held),
+                       InputAction::Inventory,
+                       InputAction::SkillMenu,
+                       InputAction::ClassMenu,
+                       InputAction::Exit,
+                       InputAction::Esc,
+                       InputAction::ForceExit,
+                       InputAction::ExploreAll,
+                       InputAction::RegenerateMap,
+                       InputAction::SaveGame,
+                       InputAction::LoadGame,
+                       InputAction::GodMode,
+                       InputAction::IncreaseMoveMode,
+                       InputAction::DecreaseMoveMode,
+                       InputAction::OverlayOn,
+                       InputAction::OverlayOff,
+                       InputAction::ToggleOverlay(Overlay::Sound),
+                       InputAction::ToggleOverlays,
+                       InputAction::SelectItem(4),
+                       InputAction::ChargeAttack(2),
+                       InputAction::CycleTarget,
+                       InputAction::Examine(Pos::new(5, 6)),
+                       InputAction::ScrollMessagesUp,
+                       InputAction::ScrollMessagesDown,
+                       InputAction::NextPage,
+                       InputAction::PrevPage,
+                       InputAction::Undo,
+                       InputAction::FullScreen,
+                       InputAction::None);
+
+    for action in actions.iter() {
+        assert_eq!(Ok(*action), action.to_string().parse::<InputAction>(), "{}", action);
     }
+
+    // malformed lines are errors that say what was wrong
+    assert_eq!(Err("'click' is missing argument 3".to_string()), "click 1 2".parse::<InputAction>());
+    assert_eq!(Err("'toggleoverlay' is missing argument 1".to_string()), "toggleoverlay".parse::<InputAction>());
+    assert_eq!(Err("Could not parse argument 1 of 'up' ('fast')".to_string()), "up fast".parse::<InputAction>());
+    assert!("".parse::<InputAction>().is_err());
+    assert!("dance".parse::<InputAction>().is_err());
 }
 
+#[test]
+pub fn test_repeat_delays() {
+    use std::time::Duration;
+
+    let mut config = Config::from_file("../config.yaml");
+    config.repeat_delay = 0.5;
+    config.repeat_delays.insert('6', 0.2);
+    config.repeat_delays.insert('i', 0.1);
+
+    // hold a key for a second, a frame at a time, and count the repeated actions
+    let repeats = |chr: char| {
+        let mut input = Input::new();
+        let mut settings = GameSettings::new(0, false);
+
+        let start = Instant::now();
+        let mut time = start;
+        let mut count = 0;
+        input.handle_event(&mut settings, InputEvent::Char(chr, KeyDir::Down), time, &config);
+        for _ in 0..10 {
+            time += Duration::from_millis(105);
+            let action = input.handle_event(&mut settings, InputEvent::Char(chr, KeyDir::Held), time, &config);
+            if action != InputAction::None {
+                count += 1;
+            }
+        }
+        return count;
+    };
+
+    // keys with their own delay repeat at that rate, and other keys use the global delay
+    assert_eq!(5, repeats('6'));
+    assert_eq!(2, repeats('4'));
+
+    // keys that are never repeated stay that way whatever their delay
+    assert_eq!(0, repeats('i'));
+}
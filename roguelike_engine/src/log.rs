@@ -1,41 +1,88 @@
 use std::fmt;
 use std::fs::File;
-use std::io::{stdout, Write};
+use std::io::{stdout, Write, Seek, SeekFrom};
 
 use serde::{Serialize, Deserialize};
 
+use roguelike_core::messaging::Msg;
+use roguelike_core::types::GameData;
+
 use crate::actions::*;
 
+/// The file recording which map a game was started with, saved alongside the logs.
+pub const MAP_CONFIG_NAME: &str = "map_config.txt";
+
 pub struct Log {
     pub action_log: File,
     pub message_log: File,
+    pub transcript: File,
+    pub turn: usize,
+    pub undo_points: Vec<(u64, u64, u64)>, // action, message and transcript log lengths before each action
 }
 
 impl Log {
     pub const ACTION_LOG_NAME: &'static str = "action_log.txt";
     pub const MESSAGE_LOG_NAME: &'static str = "message_log.txt";
+    pub const TRANSCRIPT_NAME: &'static str = "transcript.txt";
 
     pub fn new() -> Log {
         let action_log = File::create(Log::ACTION_LOG_NAME)
                               .expect("Could not open action_log.txt");
         let message_log = File::create(Log::MESSAGE_LOG_NAME)
                                .expect("Could not open messages.txt");
+        let transcript = File::create(Log::TRANSCRIPT_NAME)
+                              .expect("Could not open transcript.txt");
 
         let log = Log {
             action_log,
             message_log,
+            transcript,
+            turn: 0,
+            undo_points: Vec::new(),
         };
 
         return log;
     }
 
+    /// Log an action, marking the start of its turn in the message log so that
+    /// a record can be compared turn by turn.
     pub fn log_action(&mut self, input_action: InputAction) {
         if input_action != InputAction::None {
-            self.action_log.write(input_action.to_string().as_bytes()).unwrap();
-            self.action_log.write("\n".as_bytes()).unwrap();
+            let undo_point = (self.action_log.stream_position().unwrap(),
+                              self.message_log.stream_position().unwrap(),
+                              self.transcript.stream_position().unwrap());
+            self.undo_points.push(undo_point);
+
+            self.action_log.write_all(input_action.to_string().as_bytes()).unwrap();
+            self.action_log.write_all("\n".as_bytes()).unwrap();
+
+            self.turn += 1;
+            self.log(LogMsgType::Turn, &self.turn.to_string());
+        }
+    }
+
+    /// Remove the last action from the logs, along with everything logged during its turn,
+    /// so that replaying the logs plays the game as it was after an undo.
+    pub fn undo_action(&mut self) {
+        if let Some((action_len, message_len, transcript_len)) = self.undo_points.pop() {
+            for (file, len) in [(&mut self.action_log, action_len),
+                                (&mut self.message_log, message_len),
+                                (&mut self.transcript, transcript_len)] {
+                file.set_len(len).unwrap();
+                file.seek(SeekFrom::Start(len)).unwrap();
+            }
+
+            self.turn -= 1;
         }
     }
 
+    /// Add a readable line for the current turn to the transcript.
+    pub fn log_transcript<'a>(&mut self, input_action: InputAction, msgs: impl Iterator<Item=&'a Msg>, data: &GameData) {
+        let line = transcript_line(self.turn, input_action, msgs, data);
+        self.transcript.write_all(line.as_bytes()).unwrap();
+        self.transcript.write_all("\n".as_bytes()).unwrap();
+    }
+
     pub fn log_output(&mut self, log_message: &str) {
         self.log(LogMsgType::Output, log_message);
     }
@@ -48,13 +95,18 @@ impl Log {
         self.log(LogMsgType::Msg, log_message);
     }
 
+    /// Log a message's variant and fields, used to check records without depending on message text.
+    pub fn log_semantic(&mut self, msg: &Msg) {
+        self.log(LogMsgType::Semantic, &LogCompare::Semantic.msg_line(msg));
+    }
+
     pub fn log_key(&mut self, log_message: &str) {
         self.log(LogMsgType::Key, log_message);
     }
 
     pub fn log(&mut self, typ: LogMsgType, log_message: &str) {
-        let log_msg = format!("{}: {}\n", typ, log_message);
-        self.message_log.write(log_msg.as_bytes()).unwrap();
+        let log_msg = format!("{}\n", log_line(typ, log_message));
+        self.message_log.write_all(log_msg.as_bytes()).unwrap();
         print!("{}", log_msg);
         stdout().flush().unwrap();
     }
@@ -66,6 +118,8 @@ pub enum LogMsgType {
     Console,
     Msg,
     Key,
+    Semantic,
+    Turn,
 }
 
 impl fmt::Display for LogMsgType {
@@ -75,7 +129,177 @@ impl fmt::Display for LogMsgType {
             LogMsgType::Console => write!(f, "CONSOLE"),
             LogMsgType::Msg => write!(f, "MSG"),
             LogMsgType::Key => write!(f, "KEY"),
+            LogMsgType::Semantic => write!(f, "SEM"),
+            LogMsgType::Turn => write!(f, "TURN"),
+        }
+    }
+}
+
+/// How the messages of a recorded session are compared when checking it. Text compares
+/// the rendered lines, while Semantic compares each Msg's variant and fields so that
+/// rewording a message does not invalidate existing records.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LogCompare {
+    Text,
+    Semantic,
+}
+
+impl LogCompare {
+    pub fn log_type(&self) -> LogMsgType {
+        match self {
+            LogCompare::Text => LogMsgType::Msg,
+            LogCompare::Semantic => LogMsgType::Semantic,
+        }
+    }
+
+    pub fn msg_line(&self, msg: &Msg) -> String {
+        match self {
+            LogCompare::Text => msg.to_string(),
+            LogCompare::Semantic => format!("{:?}", msg),
+        }
+    }
+}
+
+/// A line of the message log, tagged with its type.
+pub fn log_line(typ: LogMsgType, log_message: &str) -> String {
+    return format!("{}: {}", typ, log_message);
+}
+
+/// Describe a turn as its action followed by the text of each message
+/// it produced, such as "Turn 12: move right -> Player moved to (5, 4)".
+pub fn transcript_line<'a>(turn: usize, input_action: InputAction, msgs: impl Iterator<Item=&'a Msg>, data: &GameData) -> String {
+    let msg_lines = msgs.map(|msg| msg.msg_line(data))
+                        .filter(|msg_line| !msg_line.is_empty())
+                        .collect::<Vec<String>>();
+
+    if msg_lines.is_empty() {
+        return format!("Turn {}: {}", turn, input_action);
+    } else {
+        return format!("Turn {}: {} -> {}", turn, input_action, msg_lines.join("; "));
+    }
+}
+
+/// Split the lines of a message log into the messages of each turn. Turn 0 holds any
+/// messages logged before the first action. Returns None for logs recorded without turn markers.
+pub fn split_turns(lines: &Vec<String>, log_type: LogMsgType) -> Option<Vec<Vec<String>>> {
+    let turn_prefix = format!("{}: ", LogMsgType::Turn);
+    let prefix = format!("{}: ", log_type);
+
+    let mut turns = vec!(Vec::new());
+    for line in lines.iter() {
+        if line.starts_with(&turn_prefix) {
+            turns.push(Vec::new());
+        } else if line.starts_with(&prefix) {
+            turns.last_mut().unwrap().push(line[prefix.len()..].to_string());
         }
     }
+
+    if turns.len() == 1 {
+        return None;
+    }
+
+    return Some(turns);
+}
+
+/// The index of the first line (or turn) that differs between two logs, if any.
+pub fn first_difference<T: PartialEq>(old_lines: &Vec<T>, new_lines: &Vec<T>) -> Option<usize> {
+    let num_lines = std::cmp::max(old_lines.len(), new_lines.len());
+    return (0..num_lines).find(|index| old_lines.get(*index) != new_lines.get(*index));
+}
+
+#[test]
+pub fn test_semantic_log_compare() {
+    use roguelike_core::config::Config;
+    use roguelike_core::movement::{Direction, MoveMode};
+    use roguelike_core::map::MapLoadConfig;
+    use crate::game::Game;
+    use crate::make_map::make_map;
+
+    let config = Config::from_file("../config.yaml");
+    let actions = vec!(InputAction::Move(Direction::Right, MoveMode::Walk),
+                       InputAction::Move(Direction::Down, MoveMode::Walk));
+
+    let mut game = Game::new(0, config);
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    let mut turns = Vec::new();
+    for action in actions.iter() {
+        game.step_game(*action, 0.1);
+        turns.push(game.msg_log.turn_messages.clone());
+        game.msg_log.clear();
+    }
+
+    // write the message log lines the game would write, using the given wording of a Msg
+    let write_log = |msg_text: &dyn Fn(&Msg) -> String| {
+        let mut lines = Vec::new();
+        for (turn, msgs) in turns.iter().enumerate() {
+            lines.push(log_line(LogMsgType::Turn, &(turn + 1).to_string()));
+            for msg in msgs.iter() {
+                lines.push(log_line(LogMsgType::Msg, &msg_text(msg)));
+                lines.push(log_line(LogMsgType::Semantic, &LogCompare::Semantic.msg_line(msg)));
+            }
+        }
+        return lines;
+    };
+
+    // a record made by an older version, whose Moved messages were worded differently
+    let record = write_log(&|msg| {
+        let text = LogCompare::Text.msg_line(msg);
+        if let Msg::Moved(..) = msg { text.replace("moved", "walked") } else { text }
+    });
+    let replayed = write_log(&|msg| LogCompare::Text.msg_line(msg));
+    assert_ne!(record, replayed);
+
+    // the text check fails on the first turn, where the wording changed
+    let old_text = split_turns(&record, LogMsgType::Msg).unwrap();
+    let new_text = split_turns(&replayed, LogMsgType::Msg).unwrap();
+    assert_eq!(Some(1), first_difference(&old_text, &new_text));
+
+    // while the semantic check still passes
+    let old_semantic = split_turns(&record, LogMsgType::Semantic).unwrap();
+    let new_semantic = split_turns(&replayed, LogMsgType::Semantic).unwrap();
+    assert_eq!(None, first_difference(&old_semantic, &new_semantic));
 }
 
+
+#[test]
+pub fn test_split_turns() {
+    let lines = vec!("MSG: spawned".to_string(),
+                     "TURN: 1".to_string(),
+                     "MSG: moved".to_string(),
+                     "SEM: Moved".to_string(),
+                     "CONSOLE: Player moved".to_string(),
+                     "TURN: 2".to_string());
+
+    let turns = split_turns(&lines, LogMsgType::Msg).unwrap();
+    assert_eq!(vec!(vec!("spawned".to_string()), vec!("moved".to_string()), vec!()), turns);
+
+    let mut changed = turns.clone();
+    changed[1].push("attacked".to_string());
+    assert_eq!(Some(1), first_difference(&turns, &changed));
+
+    // older records have no turn markers
+    assert_eq!(None, split_turns(&lines[0..1].to_vec(), LogMsgType::Msg));
+}
+
+#[test]
+pub fn test_transcript_line() {
+    use roguelike_core::config::Config;
+    use roguelike_core::movement::{Direction, MoveType, MoveMode};
+    use roguelike_core::types::*;
+    use crate::game::Game;
+    use crate::make_map::make_map;
+    use roguelike_core::map::MapLoadConfig;
+
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config);
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+
+    let msgs = vec!(Msg::Moved(player_id, MoveType::Move, Pos::new(5, 4)), Msg::Sound(player_id, Pos::new(5, 4), 1, true));
+    let line = transcript_line(12, InputAction::Move(Direction::Right, MoveMode::Walk), msgs.iter(), &game.data);
+    let move_line = msgs[0].msg_line(&game.data);
+    assert_eq!(format!("Turn 12: {} -> {}", InputAction::Move(Direction::Right, MoveMode::Walk), move_line), line);
+
+    assert_eq!(format!("Turn 1: {}", InputAction::Pass(MoveMode::Walk)), transcript_line(1, InputAction::Pass(MoveMode::Walk), Vec::new().iter(), &game.data));
+}
@@ -11,7 +11,7 @@ use roguelike_core::messaging::*;
 use roguelike_core::map::*;
 use roguelike_core::types::*;
 use roguelike_core::config::*;
-use roguelike_core::utils::tile_fill_metric;
+use roguelike_core::utils::{tile_fill_metric, level_feeling};
 
 use crate::generation::*;
 use crate::game::*;
@@ -22,6 +22,9 @@ use crate::vault::*;
 pub fn make_map(map_load_config: &MapLoadConfig, game: &mut Game) {
     let player_position: Pos;
 
+    // remember where the rng was so this level can be shared as a layout code
+    game.settings.level_rng = game.rng.state();
+
     let player_id = game.data.find_by_name(EntityName::Player).unwrap();
     game.data.clear_except(vec!(player_id));
 
@@ -39,17 +42,17 @@ pub fn make_map(map_load_config: &MapLoadConfig, game: &mut Game) {
         }
 
         MapLoadConfig::ProcGen(procgen_params) => {
-            let file_name = format!("resources/procgen/{}", procgen_params);
+            let file_name = format!("{}/procgen/{}", game.config.resource_dir, procgen_params);
             let cmds = ProcCmd::from_file(&file_name);
 
-            let mut template_file = "resources/wfc_seed_2.png".to_string();
+            let mut template_file = format!("{}/wfc_seed_2.png", game.config.resource_dir);
             for param in cmds.iter() {
                 if let ProcCmd::SeedFile(file_name) = param {
-                    template_file = format!("resources/{}", file_name);
+                    template_file = format!("{}/{}", game.config.resource_dir, file_name);
                 }
             }
 
-            game.data.map = generate_bare_map(20, 20, &template_file, &mut game.rng);
+            game.data.map = generate_bare_map(20, 20, &template_file, game.config.write_wfc_map, &mut game.rng);
             player_position = saturate_map(game, &cmds);
         }
 
@@ -82,7 +85,7 @@ pub fn make_map(map_load_config: &MapLoadConfig, game: &mut Game) {
         }
 
         MapLoadConfig::VaultFile(file_name) => {
-            let vault: Vault = parse_vault(&format!("resources/{}", file_name), &game.config);
+            let vault: Vault = parse_vault(&format!("{}/{}", game.config.resource_dir, file_name), &game.config);
             let (vault_width, vault_height) = vault.data.map.size();
             let map_width = 3 * vault_width;
             let map_height = 3 * vault_height;
@@ -98,13 +101,13 @@ pub fn make_map(map_load_config: &MapLoadConfig, game: &mut Game) {
         }
 
         MapLoadConfig::FromFile(file_name) => {
-            let maps: Vec<String> = parse_map_file(&format!("resources/{}", file_name));
+            let maps: Vec<String> = parse_map_file(&format!("{}/{}", game.config.resource_dir, file_name));
 
             if game.settings.level_num >= maps.len() {
                 panic!(format!("Map index {} too large ({} available", game.settings.level_num, maps.len()));
             }
 
-            let map_name = format!("resources/{}", maps[game.settings.level_num]);
+            let map_name = format!("{}/{}", game.config.resource_dir, maps[game.settings.level_num]);
             let mut position =
                 read_map_xp(&game.config, &mut game.data, &mut game.msg_log, &map_name);
             if position == (0, 0) {
@@ -117,6 +120,7 @@ pub fn make_map(map_load_config: &MapLoadConfig, game: &mut Game) {
             game.data.map = Map::from_dims(MAP_WIDTH as u32, MAP_HEIGHT as u32);
             let starting_position = make_island(&mut game.data, &game.config, &mut game.msg_log, &mut game.rng);
             player_position = Pos::from(starting_position);
+            spawn_from_table(&mut game.data, &game.config, game.settings.level_num, player_position, &mut game.msg_log, &mut game.rng);
         }
 
         MapLoadConfig::TestWall => {
@@ -142,6 +146,30 @@ pub fn make_map(map_load_config: &MapLoadConfig, game: &mut Game) {
             game.data.map = new_map;
             player_position = Pos::from(position);
         }
+
+        MapLoadConfig::Cellular { fill_percent, iterations } => {
+            game.data.map = Map::from_dims(MAP_WIDTH as u32, MAP_HEIGHT as u32);
+            player_position = make_cellular_map(&mut game.data, &game.config, *fill_percent, *iterations, &mut game.msg_log, &mut game.rng);
+            spawn_from_table(&mut game.data, &game.config, game.settings.level_num, player_position, &mut game.msg_log, &mut game.rng);
+        }
+
+        MapLoadConfig::DrunkardWalk { steps, start } => {
+            game.data.map = Map::from_dims(MAP_WIDTH as u32, MAP_HEIGHT as u32);
+            player_position = make_drunkard_walk_map(&mut game.data, &game.config, *steps, *start, &mut game.msg_log, &mut game.rng);
+            spawn_from_table(&mut game.data, &game.config, game.settings.level_num, player_position, &mut game.msg_log, &mut game.rng);
+        }
+
+        MapLoadConfig::Bsp { min_room, max_depth } => {
+            game.data.map = Map::from_dims(MAP_WIDTH as u32, MAP_HEIGHT as u32);
+            player_position = make_bsp_map(&mut game.data, &game.config, *min_room, *max_depth, &mut game.msg_log, &mut game.rng);
+            spawn_from_table(&mut game.data, &game.config, game.settings.level_num, player_position, &mut game.msg_log, &mut game.rng);
+        }
+
+        MapLoadConfig::Arena(spawns) => {
+            let (new_map, position) = make_arena_map(&mut game.data.entities, &game.config, spawns, &mut game.msg_log);
+            game.data.map = new_map;
+            player_position = position;
+        }
     }
 
     if game.data.find_by_name(EntityName::Mouse).is_none() {
@@ -152,6 +180,14 @@ pub fn make_map(map_load_config: &MapLoadConfig, game: &mut Game) {
     //game.msg_log.log(Msg::Moved(player_id, MoveType::Move, player_position));
     game.data.entities.set_pos(player_id, player_position);
 
+    if game.config.monster_sleep {
+        for entity_id in game.data.entities.ids.iter() {
+            if game.data.entities.ai.get(entity_id).is_some() {
+                game.data.entities.status[entity_id].asleep = true;
+            }
+        }
+    }
+
     if game.config.write_map_distribution {
         let max = (2 * TILE_FILL_METRIC_DIST + 1).pow(2);
         let mut counts = vec![0; max + 1];
@@ -166,6 +202,51 @@ pub fn make_map(map_load_config: &MapLoadConfig, game: &mut Game) {
             write!(file, "{} {}\n", index, count).unwrap();
         }
     }
+
+    // give a hint about what the player is walking into. this is logged with the map so
+    // that replays and headless runs see it as well as the game
+    let feeling = level_feeling(&game.data, &game.config);
+    game.msg_log.log(Msg::LevelFeeling(feeling));
+}
+
+/// List the entities a level would spawn with, and where, for the given seed and map
+/// configuration. The level is generated on a bare game state with no display or output
+/// files, so this is quick enough to use while tuning level generation.
+pub fn spawn_preview(seed: u64, map_load_config: &MapLoadConfig, config: &Config) -> Vec<(EntityName, Pos)> {
+    let game = generate_bare_game(seed, map_load_config, config);
+
+    let mut spawns = Vec::new();
+    for entity_id in game.data.entities.ids.iter() {
+        let name = game.data.entities.name[entity_id];
+        if name != EntityName::Player && name != EntityName::Mouse {
+            spawns.push((name, game.data.entities.pos[entity_id]));
+        }
+    }
+
+    return spawns;
+}
+
+/// Generate the map for a map configuration with a given seed, without keeping the rest of the game.
+pub fn generate_map_with_seed(map_load_config: &MapLoadConfig, seed: u64, config: &Config) -> Map {
+    let game = generate_bare_game(seed, map_load_config, config);
+    return game.data.map;
+}
+
+fn generate_bare_game(seed: u64, map_load_config: &MapLoadConfig, config: &Config) -> Game {
+    let mut config = config.clone();
+    config.write_map_distribution = false;
+    config.write_wfc_map = false;
+
+    let mut game = Game::new(seed, config);
+
+    // vaults are only needed by the map configurations that place them
+    if matches!(map_load_config, MapLoadConfig::ProcGen(_) | MapLoadConfig::TestVaults) {
+        game.load_vaults(&format!("{}/vaults/", game.config.resource_dir));
+    }
+
+    make_map(map_load_config, &mut game);
+
+    return game;
 }
 
 pub fn read_map_xp(config: &Config,
@@ -460,3 +541,265 @@ pub fn parse_map_file(file_name: &str) -> Vec<String> {
     return file_contents.lines().map(|s| s.to_string()).collect::<Vec<String>>();
 }
 
+#[test]
+pub fn test_arena_map() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config);
+
+    let spawns = vec!((EntityName::Gol, Pos::new(3, 3)), (EntityName::Gol, Pos::new(7, 3)));
+    make_map(&MapLoadConfig::Arena(spawns), &mut game);
+
+    let gols = game.data.entities.ids.iter()
+                                     .filter(|id| game.data.entities.name[*id] == EntityName::Gol)
+                                     .map(|id| game.data.entities.pos[id])
+                                     .collect::<Vec<Pos>>();
+    assert_eq!(vec!(Pos::new(3, 3), Pos::new(7, 3)), gols);
+
+    // only the player, the mouse, and the two gols exist
+    assert_eq!(4, game.data.entities.ids.len());
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    assert_eq!(Pos::new(ARENA_WIDTH / 2, ARENA_HEIGHT - 2), game.data.entities.pos[&player_id]);
+}
+
+#[test]
+pub fn test_arena_map_parse() {
+    let map_config = "arena gol 3 3 pawn 5 6".parse::<MapLoadConfig>().unwrap();
+    let spawns = vec!((EntityName::Gol, Pos::new(3, 3)), (EntityName::Pawn, Pos::new(5, 6)));
+    assert_eq!(map_config.to_string(), MapLoadConfig::Arena(spawns).to_string());
+}
+
+#[test]
+pub fn test_cellular_map() {
+    use roguelike_core::utils::floodfill;
+
+    let config = Config::from_file("../config.yaml");
+    let map_config = "cellular 0.45 4".parse::<MapLoadConfig>().unwrap();
+    assert_eq!("cellular 0.45 4", map_config.to_string());
+
+    let mut game = Game::new(0, config.clone());
+    make_map(&map_config, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = game.data.entities.pos[&player_id];
+    let exit_id = game.data.find_by_name(EntityName::Exit).unwrap();
+    let exit_pos = game.data.entities.pos[&exit_id];
+    assert!(!game.data.map[player_pos].block_move);
+    assert!(!game.data.map[exit_pos].block_move);
+    assert_ne!(player_pos, exit_pos);
+
+    // the caves are joined, so every open tile can be reached from the player
+    let open_tiles = game.data.map.get_all_pos().iter().filter(|pos| !game.data.map[**pos].block_move).count();
+    let reachable = floodfill(&game.data.map, player_pos, (MAP_WIDTH * MAP_HEIGHT) as usize);
+    assert_eq!(open_tiles, reachable.len());
+
+    // the same seed gives the same caves
+    let mut other_game = Game::new(0, config);
+    make_map(&map_config, &mut other_game);
+    assert_eq!(game.data.map.tiles, other_game.data.map.tiles);
+}
+
+#[test]
+pub fn test_spawn_preview() {
+    let config = Config::from_file("../config.yaml");
+
+    let map_config = "cellular 0.45 4".parse::<MapLoadConfig>().unwrap();
+    for seed in 0..3 {
+        let preview = spawn_preview(seed, &map_config, &config);
+        assert!(preview.len() > 0);
+
+        // the preview matches the level as the game generates it
+        let mut game = Game::new(seed, config.clone());
+        make_mouse(&mut game.data.entities, &game.config, &mut game.msg_log);
+        make_map(&map_config, &mut game);
+
+        let spawned = game.data.entities.ids.iter()
+                                            .filter(|id| game.data.entities.typ[*id] != EntityType::Player)
+                                            .filter(|id| game.data.entities.name[*id] != EntityName::Mouse)
+                                            .map(|id| (game.data.entities.name[id], game.data.entities.pos[id]))
+                                            .collect::<Vec<(EntityName, Pos)>>();
+        assert_eq!(spawned, preview);
+    }
+}
+
+#[test]
+pub fn test_spawn_preview_procgen() {
+    let mut config = Config::from_file("../config.yaml");
+    config.resource_dir = "../resources".to_string();
+    config.write_wfc_map = false;
+
+    // the default map configuration places vaults, which the preview loads itself
+    let map_config = config.map_load.clone();
+    assert!(matches!(map_config, MapLoadConfig::ProcGen(_)));
+
+    let preview = spawn_preview(0, &map_config, &config);
+    assert!(preview.len() > 0);
+
+    let mut game = Game::new(0, config.clone());
+    game.load_vaults("../resources/vaults/");
+    make_mouse(&mut game.data.entities, &game.config, &mut game.msg_log);
+    make_map(&map_config, &mut game);
+
+    let spawned = game.data.entities.ids.iter()
+                                        .filter(|id| game.data.entities.typ[*id] != EntityType::Player)
+                                        .filter(|id| game.data.entities.name[*id] != EntityName::Mouse)
+                                        .map(|id| (game.data.entities.name[id], game.data.entities.pos[id]))
+                                        .collect::<Vec<(EntityName, Pos)>>();
+    assert_eq!(spawned, preview);
+}
+
+#[test]
+pub fn test_drunkard_walk_map() {
+    use roguelike_core::utils::floodfill;
+
+    let config = Config::from_file("../config.yaml");
+    let map_config = "drunkard_walk 400".parse::<MapLoadConfig>().unwrap();
+    assert_eq!("drunkard_walk 400", map_config.to_string());
+    assert_eq!("drunkard_walk 400 3 4", "drunkard_walk 400 3 4".parse::<MapLoadConfig>().unwrap().to_string());
+    assert!("drunkard_walk 400 3".parse::<MapLoadConfig>().is_err());
+
+    let mut game = Game::new(0, config.clone());
+    make_map(&map_config, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = game.data.entities.pos[&player_id];
+    let exit_id = game.data.find_by_name(EntityName::Exit).unwrap();
+    let exit_pos = game.data.entities.pos[&exit_id];
+    assert!(!game.data.map[player_pos].block_move);
+    assert!(!game.data.map[exit_pos].block_move);
+    assert_ne!(player_pos, exit_pos);
+
+    // the walk is one connected corridor, and nothing is carved on the map's edge
+    let (width, height) = game.data.map.size();
+    let open_tiles = game.data.map.get_all_pos().iter().filter(|pos| !game.data.map[**pos].block_move).map(|pos| *pos).collect::<Vec<Pos>>();
+    assert!(open_tiles.iter().all(|pos| pos.x > 0 && pos.y > 0 && pos.x < width - 1 && pos.y < height - 1));
+    let reachable = floodfill(&game.data.map, player_pos, (MAP_WIDTH * MAP_HEIGHT) as usize);
+    assert_eq!(open_tiles.len(), reachable.len());
+
+    // the same seed gives the same corridors
+    let mut other_game = Game::new(0, config.clone());
+    make_map(&map_config, &mut other_game);
+    assert_eq!(game.data.map.tiles, other_game.data.map.tiles);
+
+    // even without any steps the player has a tile to stand on
+    let mut game = Game::new(0, config);
+    make_map(&"drunkard_walk 0 5 5".parse::<MapLoadConfig>().unwrap(), &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    assert_eq!(Pos::new(5, 5), game.data.entities.pos[&player_id]);
+    assert!(!game.data.map[(5, 5)].block_move);
+}
+
+#[test]
+pub fn test_bsp_map() {
+    use roguelike_core::utils::floodfill;
+
+    let config = Config::from_file("../config.yaml");
+    let map_config = "bsp 3 3".parse::<MapLoadConfig>().unwrap();
+    assert_eq!("bsp 3 3", map_config.to_string());
+    assert!("bsp 3".parse::<MapLoadConfig>().is_err());
+
+    for seed in 0..5 {
+        let mut game = Game::new(seed, config.clone());
+        make_map(&map_config, &mut game);
+
+        let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+        let player_pos = game.data.entities.pos[&player_id];
+        let exit_id = game.data.find_by_name(EntityName::Exit).unwrap();
+        let exit_pos = game.data.entities.pos[&exit_id];
+        let key_id = game.data.find_by_name(EntityName::Key).unwrap();
+        let key_pos = game.data.entities.pos[&key_id];
+        assert_ne!(player_pos, exit_pos);
+
+        // every room and corridor is connected, so the key and exit can be reached
+        let open_tiles = game.data.map.get_all_pos().iter().filter(|pos| !game.data.map[**pos].block_move).count();
+        let reachable = floodfill(&game.data.map, player_pos, (MAP_WIDTH * MAP_HEIGHT) as usize);
+        assert_eq!(open_tiles, reachable.len());
+        assert!(reachable.contains(&exit_pos));
+        assert!(reachable.contains(&key_pos));
+
+        // the outer edge of the map stays solid
+        let (width, height) = game.data.map.size();
+        assert!(reachable.iter().all(|pos| pos.x > 0 && pos.y > 0 && pos.x < width - 1 && pos.y < height - 1));
+    }
+
+    // the same seed gives the same dungeon
+    let mut game = Game::new(0, config.clone());
+    make_map(&map_config, &mut game);
+    let mut other_game = Game::new(0, config);
+    make_map(&map_config, &mut other_game);
+    assert_eq!(game.data.map.tiles, other_game.data.map.tiles);
+}
+
+#[test]
+pub fn test_generation_checksums() {
+    use roguelike_core::utils::floodfill;
+
+    let mut config = Config::from_file("../config.yaml");
+    config.resource_dir = "../resources".to_string();
+    config.write_wfc_map = false;
+
+    let generators = vec!(("random", 0, 3055246255592618480),
+                          ("procgen map1.yaml", 0, 738510531643015806),
+                          ("cellular 0.45 4", 0, 13884189926206057569),
+                          ("drunkard_walk 400", 0, 6190663193716199121),
+                          ("bsp 3 3", 0, 11631034828044716849));
+    for (map_config, seed, checksum) in generators.iter() {
+        let map_config = map_config.parse::<MapLoadConfig>().unwrap();
+        let map = generate_map_with_seed(&map_config, *seed, &config);
+        assert_eq!(*checksum, map.checksum(), "{} changed", map_config);
+
+        let mut game = Game::new(*seed, config.clone());
+        game.load_vaults("../resources/vaults/");
+        make_map(&map_config, &mut game);
+        assert_eq!(map.tiles, game.data.map.tiles);
+
+        // the player can always reach the exit. the island marks its exit with a tile instead
+        let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+        let player_pos = game.data.entities.pos[&player_id];
+        let exit_pos = match game.data.find_by_name(EntityName::Exit) {
+            Some(exit_id) => game.data.entities.pos[&exit_id],
+            None => game.data.map.get_all_pos().into_iter().find(|pos| game.data.map[*pos] == Tile::exit()).unwrap(),
+        };
+        let reachable = floodfill(&game.data.map, player_pos, (MAP_WIDTH * MAP_HEIGHT) as usize);
+        assert!(reachable.contains(&exit_pos), "{} exit unreachable", map_config);
+    }
+
+    // obstacles near the island's edge stay within the map
+    for seed in 0..20 {
+        generate_map_with_seed(&MapLoadConfig::Random, seed, &config);
+    }
+}
+
+#[test]
+pub fn test_spawn_table() {
+    use roguelike_core::utils::distance;
+
+    let mut config = Config::from_file("../config.yaml");
+    config.spawn_table = vec!((EntityName::Pawn, 1), (EntityName::Gol, 0)).into_iter().collect();
+    config.spawn_budget = vec!(2, 5);
+
+    // the island spawns from the table as the other generators do
+    for map_config in vec!("bsp 3 3", "random") {
+        let map_config = map_config.parse::<MapLoadConfig>().unwrap();
+        for level_num in 0..3 {
+            let mut game = Game::new(0, config.clone());
+            game.settings.level_num = level_num;
+            make_map(&map_config, &mut game);
+
+            // levels past the end of the budget use its last entry
+            let expected = if level_num == 0 { 2 } else { 5 };
+            let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+            let player_pos = game.data.entities.pos[&player_id];
+            let pawns = game.data.entities.ids.iter()
+                                              .filter(|id| game.data.entities.name[*id] == EntityName::Pawn)
+                                              .map(|id| game.data.entities.pos[id])
+                                              .collect::<Vec<Pos>>();
+            assert_eq!(expected, pawns.len(), "{}", map_config);
+            assert!(pawns.iter().all(|pos| distance(player_pos, *pos) > 4));
+            assert!(game.data.find_by_name(EntityName::Gol).is_none());
+        }
+
+        // the same table and seed give the same monsters
+        assert_eq!(spawn_preview(1, &map_config, &config), spawn_preview(1, &map_config, &config));
+    }
+}
@@ -81,7 +81,7 @@ impl ProcCmd {
     }
 }
 
-pub fn generate_bare_map(width: u32, height: u32, template_file: &str, rng: &mut Rand32) -> Map {
+pub fn generate_bare_map(width: u32, height: u32, template_file: &str, write_image: bool, rng: &mut Rand32) -> Map {
     let mut new_map = Map::from_dims(width, height);
 
     let file = File::open(template_file).unwrap();
@@ -107,7 +107,9 @@ pub fn generate_bare_map(width: u32, height: u32, template_file: &str, rng: &mut
                                            ForbidNothing,
                                            wfc_image::retry::NumTimes(3),
                                            &mut small_rng).unwrap();
-    map_image.save("wfc_map.png").unwrap();
+    if write_image {
+        map_image.save("wfc_map.png").unwrap();
+    }
 
     for x in 0..width {
         for y in 0..height {
@@ -190,6 +192,9 @@ pub fn saturate_map(game: &mut Game, cmds: &Vec<ProcCmd>) -> Pos {
 
     place_monsters(game, player_id, cmds);
 
+    // vaults can bring their own monsters, which may be next to the stairs
+    clear_safe_zones(game);
+
     place_traps(game, cmds);
 
     place_triggers(game, cmds);
@@ -378,7 +383,9 @@ fn place_triggers(game: &mut Game, cmds: &Vec<ProcCmd>) {
         return None;
     }).map(|n| *n).next().unwrap_or(0);
 
+    // sorted so the same seed always places the same gates
     let mut gate_positions = near_walls.iter().map(|p| *p).collect::<Vec<Pos>>();
+    gate_positions.sort_by_key(|pos| (pos.x, pos.y));
 
     // if there are no possible positions, exit early
     if gate_positions.len() == 0 {
@@ -438,11 +445,13 @@ fn place_traps(game: &mut Game, cmds: &Vec<ProcCmd>) {
 fn place_monsters(game: &mut Game, player_id: EntityId, cmds: &Vec<ProcCmd>) {
     let player_pos = game.data.entities.pos[&player_id];
 
-    // get empty positions, but make sure they are not close to the player
+    // get empty positions, but make sure they are not close to the player or the stairs
+    let safe_zones = stairs_positions(game);
     let mut potential_pos = 
         game.data.get_clear_pos()
                  .iter()
                  .filter(|p| distance(player_pos, **p) > 4)
+                 .filter(|p| !in_safe_zone(**p, &safe_zones, game.config.stairs_safe_radius))
                  .map(|p| *p)
                  .collect::<Vec<Pos>>();
 
@@ -467,6 +476,7 @@ fn place_monsters(game: &mut Game, player_id: EntityId, cmds: &Vec<ProcCmd>) {
                     EntityName::Spire => { id = Some(make_spire(&mut game.data.entities, &game.config, pos, &mut game.msg_log)); },
                     EntityName::Armil => { id = Some(make_armil(&mut game.data.entities, &game.config, pos, &mut game.msg_log)); },
                     EntityName::Rook => { id = Some(make_rook(&mut game.data.entities, &game.config, pos, &mut game.msg_log)); },
+                    EntityName::Slime => { id = Some(make_slime(&mut game.data.entities, &game.config, pos, &mut game.msg_log)); },
                     _ => { id = None; },
                 }
                 if let Some(id) = id {
@@ -482,8 +492,79 @@ fn place_monsters(game: &mut Game, player_id: EntityId, cmds: &Vec<ProcCmd>) {
     }
 }
 
+/// The positions of the stairs on a level. The player arrives on the up stairs,
+/// so their starting position is included.
+fn stairs_positions(game: &Game) -> Vec<Pos> {
+    let mut positions = Vec::new();
+
+    for entity_id in game.data.entities.ids.iter() {
+        let name = game.data.entities.name[entity_id];
+        if name == EntityName::Exit || name == EntityName::UpStairs || name == EntityName::Player {
+            positions.push(game.data.entities.pos[entity_id]);
+        }
+    }
+
+    return positions;
+}
+
+fn in_safe_zone(pos: Pos, safe_zones: &Vec<Pos>, radius: i32) -> bool {
+    return safe_zones.iter().any(|stairs_pos| distance(*stairs_pos, pos) <= radius);
+}
+
+/// Remove any monsters that ended up within the safe zone around the stairs.
+fn clear_safe_zones(game: &mut Game) {
+    let safe_zones = stairs_positions(game);
+
+    let in_zone = game.data.entities.ids.iter()
+                      .filter(|id| game.data.entities.typ[*id] == EntityType::Enemy)
+                      .filter(|id| in_safe_zone(game.data.entities.pos[*id], &safe_zones, game.config.stairs_safe_radius))
+                      .map(|id| *id)
+                      .collect::<Vec<EntityId>>();
+
+    for entity_id in in_zone {
+        game.data.entities.remove_entity(entity_id);
+    }
+}
+
+#[test]
+fn test_stairs_safe_zone() {
+    use roguelike_core::config::Config;
+
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+    game.data.map = Map::from_dims(20, 20);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(2, 2);
+    let exit_pos = Pos::new(15, 15);
+    make_exit(&mut game.data.entities, &config, exit_pos, &mut game.msg_log);
+
+    // a monster from a vault sitting next to the exit
+    make_gol(&mut game.data.entities, &config, Pos::new(14, 15), &mut game.msg_log);
+
+    let cmds = vec!(ProcCmd::Entities(EntityName::Gol, 100, 100));
+    place_monsters(&mut game, player_id, &cmds);
+    clear_safe_zones(&mut game);
+
+    let num_monsters = game.data.entities.ids.iter().filter(|id| game.data.entities.typ[*id] == EntityType::Enemy).count();
+    assert!(num_monsters > 0);
+
+    for entity_id in game.data.entities.ids.iter() {
+        if game.data.entities.typ[entity_id] == EntityType::Enemy {
+            let pos = game.data.entities.pos[entity_id];
+            assert!(distance(pos, exit_pos) > config.stairs_safe_radius);
+            assert!(distance(pos, Pos::new(2, 2)) > config.stairs_safe_radius);
+        }
+    }
+}
+
 // TODO choose based on frequency given by tags
 fn place_vaults(game: &mut Game, cmds: &Vec<ProcCmd>) {
+    if game.vaults.len() == 0 {
+        eprintln!("No vaults loaded, so none are placed");
+        return;
+    }
+
     for cmd in cmds.iter() {
         if let ProcCmd::Vaults(max) = cmd {
             for _ in 0..*max {
@@ -544,15 +625,30 @@ pub fn place_vault_with(data: &mut GameData, vault: &Vault, offset: Pos, rotatio
 
     // move entities to their new place in the map
     let mut entities = actual_vault.data.entities.clone();
-    for id in actual_vault.data.entities.ids.iter() {
-        let mut entity_pos = entities.pos[id];
+    let place_pos = |pos: Pos| {
+        let mut pos = pos;
         if mirror {
-            entity_pos = mirror_in_x(entity_pos, width);
+            pos = mirror_in_x(pos, width);
         }
-        entity_pos = rotation.rotate(entity_pos, width, height);
-        entity_pos = add_pos(offset, entity_pos);
+        pos = rotation.rotate(pos, width, height);
+        return add_pos(offset, pos);
+    };
+
+    for id in actual_vault.data.entities.ids.iter() {
+        let entity_pos = place_pos(entities.pos[id]);
         if data.map.is_within_bounds(entity_pos) && !data.map[entity_pos].block_move {
             entities.pos[id] = entity_pos;
+
+            // patrol routes are turned with the vault
+            if let Some(route) = entities.patrol.get(id) {
+                let route = route.iter().map(|pos| place_pos(*pos)).collect::<Vec<Pos>>();
+                entities.patrol[id] = route;
+            }
+
+            // monsters leashed to their home return to it in the map, not in the vault
+            if let Some(home) = entities.home.get(id) {
+                entities.home[id] = place_pos(*home);
+            }
         } else {
             vault_entities_to_remove.push(*id);
             continue;
@@ -896,6 +992,25 @@ fn test_find_complex_structures() {
     assert_eq!(5, structures[0].blocks.len());
 }
 
+#[test]
+fn test_place_vault_moves_home() {
+    use roguelike_core::config::Config;
+
+    let config = Config::from_file("../config.yaml");
+
+    let path = std::env::temp_dir().join("test_place_vault_moves_home.csv");
+    std::fs::write(&path, " . g . . \n         \n::patrol 1 0 3 0\n").unwrap();
+    let vault = parse_vault(path.to_str().unwrap(), &config);
+
+    let mut data = GameData::new(Map::from_dims(10, 10), Entities::new());
+    place_vault_with(&mut data, &vault, Pos::new(4, 5), Rotation::Degrees0, false);
+
+    let gol = data.find_by_name(EntityName::Gol).unwrap();
+    assert_eq!(Pos::new(5, 5), data.entities.pos[&gol]);
+    assert_eq!(Pos::new(5, 5), data.entities.home[&gol]);
+    assert_eq!(vec!(Pos::new(5, 5), Pos::new(7, 5)), data.entities.patrol[&gol]);
+}
+
 
 pub fn place_block(map: &mut Map, start: Pos, width: i32, tile: Tile) -> Vec<Pos> {
     let mut positions = Vec::new();
@@ -903,8 +1018,10 @@ pub fn place_block(map: &mut Map, start: Pos, width: i32, tile: Tile) -> Vec<Pos
     for x in 0..width {
         for y in 0..width {
             let pos = start + Vector2D::new(x, y);
-            map[pos] = tile;
-            positions.push(pos);
+            if map.is_within_bounds(pos) {
+                map[pos] = tile;
+                positions.push(pos);
+            }
         }
     }
 
@@ -928,7 +1045,7 @@ pub fn place_line(map: &mut Map, start: Pos, end: Pos, tile: Tile) -> Vec<Pos> {
 pub fn add_obstacle(map: &mut Map, pos: Pos, obstacle: Obstacle, rng: &mut Rand32) {
     match obstacle {
         Obstacle::Block => {
-            map.tiles[pos.x as usize][pos.y as usize] = Tile::wall();
+            place_block(map, pos, 1, Tile::wall());
         }
 
         Obstacle::Wall => {
@@ -959,16 +1076,14 @@ pub fn add_obstacle(map: &mut Map, pos: Pos, obstacle: Obstacle, rng: &mut Rand3
                 dir = -1;
             }
 
+            // obstacles near the edge of the map are cut off rather than placed out of bounds
+            place_block(map, pos, 1, Tile::wall());
             if rng_trial(rng, 0.5) {
-                for x in 0..3 {
-                    map.tiles[pos.x as usize + x][pos.y as usize] = Tile::wall();
-                }
-                map.tiles[pos.x as usize][(pos.y + dir) as usize] = Tile::wall();
+                place_line(map, pos, move_x(pos, 2), Tile::wall());
+                place_line(map, pos, move_y(pos, dir), Tile::wall());
             } else {
-                for y in 0..3 {
-                    map.tiles[pos.x as usize][pos.y as usize + y] = Tile::wall();
-                }
-                map.tiles[(pos.x + dir) as usize][pos.y as usize] = Tile::wall();
+                place_line(map, pos, move_y(pos, 2), Tile::wall());
+                place_line(map, pos, move_x(pos, dir), Tile::wall());
             }
         }
 
@@ -4,7 +4,7 @@ use log::{trace, error};
 use oorandom::Rand32;
 
 use roguelike_core::types::*;
-use roguelike_core::ai::{Behavior, ai_move_to_attack_pos, ai_can_hit_target, ai_take_turn, ai_is_in_fov};
+use roguelike_core::ai::{Behavior, ai_move_to_attack_pos, ai_attempt_step, ai_can_hit_target, ai_take_turn, ai_is_in_fov, ai_move_towards_target, ai_beyond_leash};
 use roguelike_core::map::{Surface, AoeEffect};
 use roguelike_core::messaging::{MsgLog, Msg};
 use roguelike_core::constants::*;
@@ -15,7 +15,7 @@ use roguelike_core::utils::*;
 use roguelike_core::map::*;
 use roguelike_core::line::line;
 
-use crate::generation::{make_energy, make_light};
+use crate::generation::{make_energy, make_light, make_slime};
 
 
 pub fn resolve_messages(data: &mut GameData,
@@ -40,16 +40,19 @@ pub fn resolve_messages(data: &mut GameData,
             }
 
             Msg::Sound(cause_id, source_pos, radius, _should_animate) => {
-                let sound_aoe =
-                    aoe_fill(&data.map, AoeEffect::Sound, source_pos, radius, config);
+                let sound_strengths =
+                    propagate_sound(source_pos, radius, &data.map, config);
 
-                let who_heard =
-                    data.within_aoe(&sound_aoe);
+                let who_heard = data.entities.ids.iter()
+                                                 .filter(|id| sound_strengths.contains_key(&data.entities.pos[*id]))
+                                                 .map(|id| *id)
+                                                 .collect::<Vec<EntityId>>();
 
                 for obj_id in who_heard {
                     if obj_id != cause_id {
                         // TODO replace with an Alerted message
-                        data.entities.messages[&obj_id].push(Message::Sound(cause_id, source_pos));
+                        let strength = sound_strengths[&data.entities.pos[&obj_id]];
+                        data.entities.messages[&obj_id].push(Message::Sound(cause_id, source_pos, strength));
                     }
                 }
             }
@@ -76,6 +79,14 @@ pub fn resolve_messages(data: &mut GameData,
                 pushed_entity(pusher, pushed, direction, push_amount, move_into, data, config, msg_log);
             }
 
+            Msg::Knockback(attacker, attacked, pos) => {
+                knockback(attacker, attacked, pos, data, config, msg_log);
+            }
+
+            Msg::ChargeAttack(entity_id, charge) => {
+                resolve_charge_attack(entity_id, charge, data, msg_log, config);
+            }
+
             Msg::Yell(entity_id) => {
                 let pos = data.entities.pos[&entity_id];
                 msg_log.log_front(Msg::Sound(entity_id, pos, config.yell_radius, true));
@@ -90,6 +101,27 @@ pub fn resolve_messages(data: &mut GameData,
                 // TODO move attack function here, and remove push Msg::Attack in attack function
                 let pos = data.entities.pos[&attacked];
                 msg_log.log_front(Msg::Sound(attacker, pos, config.sound_radius_attack, true)); 
+
+                if data.entities.name[&attacked] == EntityName::Slime {
+                    split_slime(attacked, data, rng, msg_log, config);
+                }
+
+                // poisonous attacks leave their target poisoned
+                if data.entities.damage_type.get(&attacker) == Some(&DamageType::Poison) && config.poison_turns > 0 {
+                    apply_status(attacked, StatusKind::Poison, config.poison_turns, data, msg_log);
+                    data.entities.status_source.insert(attacked, attacker);
+                }
+
+                // fire attacks set their target burning
+                if data.entities.damage_type.get(&attacker) == Some(&DamageType::Fire) && config.burning_turns > 0 {
+                    apply_status(attacked, StatusKind::Burning, config.burning_turns, data, msg_log);
+                    data.entities.status_source.insert(attacked, attacker);
+                }
+
+                // after a hit, the player is briefly protected from further attacks
+                if attacked == player_id && config.grace_turns > 0 && !data.entities.has_status(attacked, StatusKind::Grace) {
+                    apply_status(attacked, StatusKind::Grace, config.grace_turns, data, msg_log);
+                }
             }
 
             Msg::SwordSwing(entity_id, item_id, pos) => {
@@ -114,7 +146,7 @@ pub fn resolve_messages(data: &mut GameData,
             }
 
             Msg::TryMove(entity_id, direction, amount, move_mode) => {
-                resolve_try_move(entity_id, direction, amount, move_mode, data, msg_log);
+                resolve_try_move(entity_id, direction, amount, move_mode, data, msg_log, config);
             }
 
             Msg::PickUp(entity_id) => {
@@ -161,7 +193,7 @@ pub fn resolve_messages(data: &mut GameData,
 
             Msg::Froze(entity_id, num_turns) => {
                 if entity_id == player_id || data.entities.ai.get(&entity_id).is_some() {
-                    data.entities.status[&entity_id].frozen = num_turns;
+                    apply_status(entity_id, StatusKind::Frozen, num_turns, data, msg_log);
                 }
             }
 
@@ -205,6 +237,7 @@ pub fn resolve_messages(data: &mut GameData,
                     EntityClass::Clockwork => {
                         data.entities.class[&player_id] = class;
                         data.entities.add_skill(player_id, Skill::Push);
+                        data.entities.add_skill(player_id, Skill::TimeStop);
                     }
 
                     EntityClass::Hierophant => {
@@ -240,8 +273,16 @@ pub fn resolve_messages(data: &mut GameData,
                 change_move_mode(entity_id, increase, data, msg_log);
             }
 
-            Msg::DropItem(entity_id, item_index) => {
-                inventory_drop_item(entity_id, item_index as usize, data, msg_log);
+            Msg::DropItem(entity_id, item_id) => {
+                if inventory_drop_item(entity_id, item_id, data) {
+                    data.entities.took_turn[&entity_id] = true;
+                } else {
+                    msg_log.log(Msg::DropFailed(entity_id));
+                }
+            }
+
+            Msg::DropAll(entity_id) => {
+                inventory_drop_all(entity_id, data, msg_log);
             }
 
             Msg::GrassThrow(entity_id, direction) => {
@@ -260,7 +301,7 @@ pub fn resolve_messages(data: &mut GameData,
 
             Msg::GrassShoes(entity_id, _action_mode) => {
                 if use_energy(entity_id, data) {
-                    data.entities.status[&entity_id].soft_steps = SKILL_GRASS_SHOES_TURNS;
+                    apply_status(entity_id, StatusKind::SoftSteps, SKILL_GRASS_SHOES_TURNS, data, msg_log);
                     data.entities.took_turn[&entity_id] = true;
                 }
             }
@@ -298,6 +339,20 @@ pub fn resolve_messages(data: &mut GameData,
                 }
             }
 
+            Msg::TimeStop(entity_id, turns) => {
+                if data.entities.status[&entity_id].time_stop_cooldown == 0 && use_energy(entity_id, data) {
+                    let ai_ids = data.entities.ai.keys().copied().collect::<Vec<EntityId>>();
+                    for ai_id in ai_ids {
+                        if data.entities.status[&ai_id].alive {
+                            apply_status(ai_id, StatusKind::Frozen, turns, data, msg_log);
+                        }
+                    }
+
+                    data.entities.status[&entity_id].time_stop_cooldown = config.time_stop_cooldown;
+                    data.entities.took_turn[&entity_id] = true;
+                }
+            }
+
             Msg::Rubble(entity_id, rubble_pos) => {
                 let pos = data.entities.pos[&entity_id];
                 let blocked = data.map.path_blocked_move(pos, rubble_pos);
@@ -426,7 +481,7 @@ pub fn resolve_messages(data: &mut GameData,
 
     /* Process Player Messages */
     for message in data.entities.messages[&player_id].iter() {
-        if let Message::Sound(obj_id, _pos) = message {
+        if let Message::Sound(obj_id, _pos, _strength) = message {
             if *obj_id == player_id {
                 panic!("Player sent themselves a message?")
             }
@@ -444,11 +499,11 @@ fn hammer_swing(entity_id: EntityId, item_id: EntityId, pos: Pos, data: &mut Gam
 
     if let Some(blocked) = data.map.path_blocked_move(entity_pos, pos) {
         msg_log.log_front(Msg::HammerHitWall(entity_id, blocked));
-        data.used_up_item(entity_id, item_id);
+        wear_item(entity_id, item_id, data, msg_log);
     } else if let Some(hit_entity) = data.has_blocking_entity(pos) {
         // we hit another entity!
         msg_log.log_front(Msg::HammerHitEntity(entity_id, hit_entity));
-        data.used_up_item(entity_id, item_id);
+        wear_item(entity_id, item_id, data, msg_log);
     }
 
     data.entities.took_turn[&entity_id] = true;
@@ -485,12 +540,53 @@ fn sword_swing(entity_id: EntityId, item_id: EntityId, pos: Pos, data: &mut Game
     }
 
     if any_hit_entity {
-        data.used_up_item(entity_id, item_id);
+        wear_item(entity_id, item_id, data, msg_log);
     }
 
     data.entities.took_turn[&entity_id] = true;
 }
 
+/// Use up one point of an item's durability, breaking it when none is left.
+/// Items without durability break on their first use.
+fn wear_item(entity_id: EntityId, item_id: EntityId, data: &mut GameData, msg_log: &mut MsgLog) {
+    if let Some(durability) = data.entities.durability.get_mut(&item_id) {
+        *durability = durability.saturating_sub(1);
+        if *durability > 0 {
+            return;
+        }
+    }
+
+    data.used_up_item(entity_id, item_id);
+    msg_log.log(Msg::ItemBroke(entity_id, item_id));
+}
+
+/// Trigger a trap for the entity that stepped on it. Traps are used up
+/// when triggered unless traps are configured to be reusable.
+fn trigger_trap(trap: EntityId, entity_id: EntityId, data: &mut GameData, msg_log: &mut MsgLog, config: &Config) {
+    match data.entities.trap[&trap] {
+        Trap::Spikes => {
+            msg_log.log(Msg::SpikeTrapTriggered(trap, entity_id));
+        }
+
+        Trap::Sound => {
+            msg_log.log(Msg::SoundTrapTriggered(trap, entity_id));
+        }
+
+        Trap::Blink => {
+            data.entities.status[&entity_id].blinked = true;
+            msg_log.log(Msg::BlinkTrapTriggered(trap, entity_id));
+        }
+
+        Trap::Freeze => {
+            msg_log.log(Msg::FreezeTrapTriggered(trap, entity_id));
+        }
+    }
+
+    if !config.reusable_traps {
+        data.entities.mark_for_removal(trap);
+    }
+}
+
 fn freeze_trap_triggered(trap: EntityId, cause_id: EntityId, data: &mut GameData, msg_log: &mut MsgLog, config: &Config) {
     let source_pos = data.entities.pos[&trap];
 
@@ -500,6 +596,9 @@ fn freeze_trap_triggered(trap: EntityId, cause_id: EntityId, data: &mut GameData
     let who_hit =
         data.within_aoe(&freeze_aoe);
 
+    // the entity that set off the trap is always caught in it
+    msg_log.log(Msg::Froze(cause_id, FREEZE_TRAP_NUM_TURNS));
+
     for obj_id in who_hit {
         // TODO probably need to filter out a bit more
         if obj_id != cause_id && data.entities.status[&obj_id].alive {
@@ -545,16 +644,43 @@ fn resolve_attack(entity_id: EntityId,
                   attack_pos: Pos,
                   data: &mut GameData,
                   msg_log: &mut MsgLog,
-                  _config: &Config) {
+                  config: &Config) {
     let entity_pos = data.entities.pos[&entity_id];
 
-    // any time an entity attacks, they change to standing stance
-    data.entities.stance[&entity_id] = Stance::Standing;
+    // any time an entity attacks, they change to standing stance, unless their momentum carries them on
+    let mut momentum = data.entities.status[&entity_id].momentum;
+    data.entities.status[&entity_id].momentum = 0;
+    if momentum == 0 {
+        data.entities.stance[&entity_id] = Stance::Standing;
+    }
+
+    let target_id = match attack_info {
+        Attack::Attack(target_id) => target_id,
+        Attack::Stab(target_id, _) => target_id,
+        Attack::Push(target_id, _, _) => target_id,
+    };
+    let target_pos = data.entities.pos[&target_id];
 
     // we already checked that this unwrap is safe before calling this function
     match attack_info {
         Attack::Attack(target_id) => {
-            attack(entity_id, target_id, data, msg_log);
+            if config.animate_attacks {
+                // the attack lands once its animation has played out
+                if !data.entities.pending_attack.contains_key(&entity_id) {
+                    data.entities.pending_attack.insert(entity_id, (target_id, config.attack_delay_turns));
+                    msg_log.log(Msg::AttackStarted(entity_id, target_id));
+                }
+            } else {
+                attack(entity_id, target_id, data, msg_log);
+            }
+
+            // a heavy attack spends the attacker's momentum knocking the target back
+            if momentum > 0 && config.knockback_attacks && data.entities.status[&target_id].alive {
+                let dxy = sub_pos(target_pos, entity_pos);
+                let knock_pos = move_by(target_pos, Pos::new(dxy.x.signum(), dxy.y.signum()));
+                msg_log.log(Msg::Knockback(entity_id, target_id, knock_pos));
+                momentum = 0;
+            }
         }
 
         Attack::Stab(target_id, move_into) => {
@@ -577,6 +703,15 @@ fn resolve_attack(entity_id: EntityId,
             msg_log.log(Msg::Pushed(entity_id, target_id, direction, amount, true));
         }
     }
+
+    // the rest of the move continues after the attack is resolved
+    if momentum > 0 {
+        let dxy = sub_pos(target_pos, entity_pos);
+        if let Some(direction) = Direction::from_dxy(dxy.x.signum(), dxy.y.signum()) {
+            let move_mode = data.entities.move_mode[&entity_id];
+            msg_log.log(Msg::TryMove(entity_id, direction, momentum, move_mode));
+        }
+    }
 }
 
 fn resolve_try_move(entity_id: EntityId,
@@ -584,12 +719,19 @@ fn resolve_try_move(entity_id: EntityId,
                     amount: usize,
                     move_mode: MoveMode,
                     data: &mut GameData,
-                    msg_log: &mut MsgLog) {
+                    msg_log: &mut MsgLog,
+                    config: &Config) {
     // blinking uses up movement
     if data.entities.status[&entity_id].blinked {
         return;
     }
 
+    // the player turns towards their move even if it becomes an attack or is blocked
+    let player_id = data.find_by_name(EntityName::Player).unwrap();
+    if entity_id == player_id && config.auto_face_player && amount > 0 {
+        data.entities.direction[&entity_id] = direction;
+    }
+
     data.entities.move_mode[&entity_id] = move_mode;
 
     let reach = data.entities.movement[&entity_id];
@@ -599,11 +741,15 @@ fn resolve_try_move(entity_id: EntityId,
         if amount == 0 {
             Some(Movement::pass(data.entities.pos[&entity_id]))
         } else {
-            movement::calculate_move(direction, reach, entity_id, data)
+            movement::calculate_move(direction, reach, entity_id, data, config)
         };
 
     if let Some(movement) = maybe_movement {
         if let Some(attack) = movement.attack {
+            if config.momentum_through_attacks && amount > 1 {
+                data.entities.status[&entity_id].momentum = std::cmp::min(amount - 1, config.momentum_max);
+            }
+
             // if the movement creates an attack, attempt to perform the attack
             msg_log.log(Msg::TryAttack(entity_id, attack, movement.pos));
         } else {
@@ -801,6 +947,46 @@ fn hammer_hit_wall(entity: EntityId, blocked: Blocked, data: &mut GameData, msg_
     }
 }
 
+fn split_slime(slime_id: EntityId, data: &mut GameData, rng: &mut Rand32, msg_log: &mut MsgLog, config: &Config) {
+    // only slimes that survive a hit which actually damaged them will split
+    let fighter = data.entities.fighter[&slime_id];
+    if !data.entities.status[&slime_id].alive || fighter.hp <= 0 || fighter.hp >= fighter.max_hp {
+        return;
+    }
+
+    let split_hp = fighter.hp / 2;
+    if split_hp == 0 {
+        return;
+    }
+
+    let slime_pos = data.entities.pos[&slime_id];
+    let mut free_positions = Vec::new();
+    for pos in data.map.reachable_neighbors(slime_pos) {
+        if data.map.is_within_bounds(pos) && data.has_blocking_entity(pos).is_none() {
+            free_positions.push(pos);
+        }
+    }
+
+    // a slime without room for both copies stays whole
+    if free_positions.len() < 2 {
+        return;
+    }
+
+    shuffle(rng, &mut free_positions);
+
+    for pos in free_positions.iter().take(2) {
+        let split_id = make_slime(&mut data.entities, config, *pos, msg_log);
+        data.entities.fighter[&split_id].hp = split_hp;
+        data.entities.fighter[&split_id].max_hp = split_hp;
+        data.entities.behavior[&split_id] = data.entities.behavior[&slime_id];
+    }
+
+    // the original slime is replaced by its copies
+    data.entities.status[&slime_id].alive = false;
+    data.entities.blocks[&slime_id] = false;
+    data.entities.mark_for_removal(slime_id);
+}
+
 fn killed_entity(attacked: EntityId, data: &mut GameData, msg_log: &mut MsgLog, config: &Config) {
     let attacked_pos = data.entities.pos[&attacked];
 
@@ -867,6 +1053,29 @@ fn pushed_entity(pusher: EntityId,
     data.entities.took_turn[&pusher] = true;
 }
 
+fn knockback(attacker: EntityId,
+             attacked: EntityId,
+             knock_pos: Pos,
+             data: &mut GameData,
+             config: &Config,
+             msg_log: &mut MsgLog) {
+    if !data.entities.status[&attacked].alive {
+        return;
+    }
+
+    let attacked_pos = data.entities.pos[&attacked];
+    let dxy = sub_pos(knock_pos, attacked_pos);
+    let move_result = movement::check_collision(attacked_pos, dxy.x, dxy.y, data, config);
+
+    if move_result.no_collision() {
+        msg_log.log_front(Msg::Moved(attacked, MoveType::Move, knock_pos));
+    } else if move_result.hit_wall() {
+        // knocked into a wall, the target is crushed against it
+        crushed(attacker, attacked_pos, data, msg_log, config);
+    }
+    // knocked into another entity, the target stops against it
+}
+
 fn crushed(entity_id: EntityId, pos: Pos, data: &mut GameData, msg_log: &mut MsgLog, config: &Config) {
     data.map[pos].surface = Surface::Rubble;
 
@@ -1027,39 +1236,56 @@ fn change_move_mode(entity_id: EntityId,
     }
 }
 
+/// Drop an item from an entity's inventory, returning whether there was room to drop it.
 fn inventory_drop_item(entity_id: EntityId,
-                       item_index: usize,
-                       data: &mut GameData,
-                       msg_log: &mut MsgLog) {
+                       item_id: EntityId,
+                       data: &mut GameData) -> bool {
     let player_pos = data.entities.pos[&entity_id];
 
-    if let Some(item_id) = data.entities.inventory[&entity_id].get(item_index).map(|v| *v) {
-        // Find a place to drop the item, without placing it on the same tile
-        // as another item.
-        let mut found_tile = false;
-        let mut dist = 1;
-        while !found_tile && dist < 10 {
-            let positions = floodfill(&data.map, player_pos, dist);
+    if !data.entities.inventory[&entity_id].contains(&item_id) {
+        return false;
+    }
 
-            for pos in positions {
-                if data.item_at_pos(pos).is_none() {
-                    data.entities.remove_item(entity_id, item_id);
-                    data.entities.set_pos(item_id, pos);
+    // Find a place to drop the item, without placing it on the same tile
+    // as another item.
+    let mut dist = 1;
+    while dist < 10 {
+        let positions = floodfill(&data.map, player_pos, dist);
 
-                    found_tile = true;
-                    break;
-                }
-            }
+        for pos in positions {
+            if data.item_at_pos(pos).is_none() {
+                data.entities.remove_item(entity_id, item_id);
+                data.entities.set_pos(item_id, pos);
 
-            dist += 1;
+                return true;
+            }
         }
 
-        if found_tile {
-            data.entities.took_turn[&entity_id] = true;
+        dist += 1;
+    }
+
+    return false;
+}
+
+/// Drop every item in an entity's inventory, spreading them out so they do not stack.
+/// If there is no room left, the remaining items are stacked on the entity's tile.
+fn inventory_drop_all(entity_id: EntityId,
+                      data: &mut GameData,
+                      msg_log: &mut MsgLog) {
+    let pos = data.entities.pos[&entity_id];
+    let items = data.entities.inventory[&entity_id].iter().map(|id| *id).collect::<Vec<EntityId>>();
+
+    for item_id in items {
+        if inventory_drop_item(entity_id, item_id, data) {
+            msg_log.log(Msg::DroppedItem(entity_id, item_id));
         } else {
-            msg_log.log(Msg::DropFailed(entity_id));
+            data.entities.remove_item(entity_id, item_id);
+            data.entities.set_pos(item_id, pos);
+            msg_log.log(Msg::ItemStacked(entity_id, item_id));
         }
     }
+
+    data.entities.took_turn[&entity_id] = true;
 }
 
 fn process_interaction(entity_id: EntityId,
@@ -1163,11 +1389,16 @@ fn make_move_sound(entity_id: EntityId,
     if data.map[pos].surface == Surface::Rubble {
         sound_radius += config.sound_rubble_radius;
     } else if data.map[pos].surface == Surface::Grass {
-        sound_radius -= config.sound_grass_radius;
+        sound_radius = sound_radius.saturating_sub(config.sound_grass_radius);
     }
 
-    if data.entities.status[&entity_id].soft_steps > 0 {
-        sound_radius -= 1;
+    if data.entities.has_status(entity_id, StatusKind::SoftSteps) {
+        sound_radius = sound_radius.saturating_sub(1);
+    }
+
+    // a silent step, such as sneaking on a plain floor, makes no sound at all
+    if sound_radius == 0 {
+        return;
     }
 
     msg_log.log_front(Msg::Sound(entity_id, pos, sound_radius, true));
@@ -1220,7 +1451,7 @@ fn process_moved_message(entity_id: EntityId,
     }
 
     if original_pos != pos {
-        resolve_triggered_traps(entity_id, original_pos, data, msg_log);
+        resolve_triggered_traps(entity_id, original_pos, data, msg_log, config);
     }
 
     // if entity is a monster, which is also alert, and there is a path to the player,
@@ -1229,7 +1460,7 @@ fn process_moved_message(entity_id: EntityId,
         if data.could_see(entity_id, target_pos, config) {
             msg_log.log_front(Msg::FaceTowards(entity_id, target_pos));
         }
-    } else {
+    } else if entity_id != player_id || config.auto_face_player {
         let diff = sub_pos(pos, original_pos);
         if let Some(dir) = Direction::from_dxy(diff.x, diff.y) {
             msg_log.log_front(Msg::SetFacing(entity_id, dir));
@@ -1240,35 +1471,14 @@ fn process_moved_message(entity_id: EntityId,
 fn resolve_triggered_traps(entity_id: EntityId,
                            original_pos: Pos,
                            data: &mut GameData,
-                           msg_log: &mut MsgLog) {
+                           msg_log: &mut MsgLog,
+                           config: &Config) {
     // get a list of triggered traps
     let traps: Vec<EntityId> = data.entities.triggered_traps(data.entities.pos[&entity_id]);
 
     // Check if the entity hit a trap
     for trap in traps.iter() {
-        match data.entities.trap[trap] {
-            Trap::Spikes => {
-                msg_log.log(Msg::SpikeTrapTriggered(*trap, entity_id));
-                data.entities.mark_for_removal(*trap);
-            }
-
-            Trap::Sound => {
-                msg_log.log(Msg::SoundTrapTriggered(*trap, entity_id));
-                data.entities.needs_removal[trap] = true;
-                data.entities.mark_for_removal(*trap);
-            }
-
-            Trap::Blink => {
-                data.entities.status[&entity_id].blinked = true;
-                msg_log.log(Msg::BlinkTrapTriggered(*trap, entity_id));
-                data.entities.mark_for_removal(*trap);
-            }
-
-            Trap::Freeze => {
-                msg_log.log(Msg::FreezeTrapTriggered(*trap, entity_id));
-                data.entities.mark_for_removal(*trap);
-            }
-        }
+        trigger_trap(*trap, entity_id, data, msg_log, config);
     }
 
     // Resolve triggers
@@ -1299,6 +1509,25 @@ fn resolve_triggered_traps(entity_id: EntityId,
 }
 
 
+/// A charged attack hits whatever is in front of the entity, with each charge adding
+/// the entity's base damage again.
+fn resolve_charge_attack(entity_id: EntityId, charge: usize, data: &mut GameData, msg_log: &mut MsgLog, config: &Config) {
+    // charges from outside of input handling, such as replayed actions, are capped as well
+    let charge = std::cmp::min(charge, config.charge_max);
+
+    let pos = data.entities.pos[&entity_id];
+    let direction = data.entities.direction[&entity_id];
+    let target_pos = direction.offset_pos(pos, 1);
+
+    data.entities.took_turn[&entity_id] = true;
+
+    if let Some(target_id) = data.has_blocking_entity(target_pos) {
+        if data.entities.fighter.get(&target_id).is_some() && data.entities.status[&target_id].alive {
+            attack_with_multiplier(entity_id, target_id, 1 + charge as i32, data, msg_log);
+        }
+    }
+}
+
 fn resolve_ai_attack(entity_id: EntityId,
                      target_id: EntityId,
                      data: &mut GameData,
@@ -1310,19 +1539,36 @@ fn resolve_ai_attack(entity_id: EntityId,
     let can_hit_target =
         ai_can_hit_target(data, entity_id, target_pos, &attack_reach, config);
 
+    let target_in_fov = ai_is_in_fov(entity_id, target_id, data, config);
+    if target_in_fov {
+        data.entities.status[&entity_id].lost_sight = 0;
+    }
+
     if data.entities.is_dead(target_id) {
         data.entities.took_turn[&entity_id] = true;
         msg_log.log(Msg::StateChange(entity_id, Behavior::Investigating(target_pos)));
     } else if let Some(_hit_pos) = can_hit_target {
         let attack_info = Attack::Attack(target_id);
         msg_log.log(Msg::TryAttack(entity_id, attack_info, target_pos));
-    } else if !ai_is_in_fov(entity_id, target_id, data, config) {
+    } else if ai_beyond_leash(entity_id, target_pos, data, config) {
+        // the target has escaped the leash, so give up the chase and head home
+        let home = data.entities.home[&entity_id];
+        msg_log.log(Msg::StateChange(entity_id, Behavior::Investigating(home)));
+    } else if !target_in_fov && data.entities.status[&entity_id].lost_sight < config.sight_persistence_turns {
+        // keep following the target for a few turns after losing sight of them
+        data.entities.status[&entity_id].lost_sight += 1;
+        ai_move_towards_target(target_pos, entity_id, data, msg_log);
+    } else if !target_in_fov {
         // if we lose the target, end the turn
+        data.entities.status[&entity_id].lost_sight = 0;
         data.entities.took_turn[&entity_id] = true;
         msg_log.log(Msg::StateChange(entity_id, Behavior::Investigating(target_pos)));
     } else {
-        // can see target, but can't hit them. try to move to a position where we can hit them
-        let maybe_pos = ai_move_to_attack_pos(entity_id, target_id, data, config);
+        // can see target, but can't hit them. try to move to a position where we can hit them,
+        // preferring the tile assigned to us by our pack
+        let maybe_pos = data.entities.pack_target.get(&entity_id)
+                                                 .and_then(|pack_pos| ai_attempt_step(entity_id, *pack_pos, data))
+                                                 .or_else(|| ai_move_to_attack_pos(entity_id, target_id, data, config));
 
         if let Some(move_pos) = maybe_pos {
             // try to move in the given direction
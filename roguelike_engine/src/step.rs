@@ -6,8 +6,11 @@ use roguelike_core::ai::*;
 use roguelike_core::map::*;
 use roguelike_core::messaging::{Msg, MsgLog};
 use roguelike_core::movement::{Direction, MoveMode};
-#[cfg(test)]
 use roguelike_core::utils::*;
+#[cfg(test)]
+use roguelike_core::movement::{Attack, Reach, calculate_move};
+#[cfg(test)]
+use roguelike_core::constants::NORMAL_SPEED;
 
 
 use crate::game::*;
@@ -16,6 +19,8 @@ use crate::generation::*;
 use crate::resolve::resolve_messages;
 #[cfg(test)]
 use crate::make_map::*;
+#[cfg(test)]
+use crate::input::{InputEvent, KeyDir};
 
 
 pub fn step_logic(game: &mut Game) -> bool {
@@ -28,6 +33,10 @@ pub fn step_logic(game: &mut Game) -> bool {
         game.data.entities.took_turn[id] = false;
         game.data.entities.status[id].blinked = false;
     }
+    game.data.entities.settle_statuses();
+
+    // messages from before this turn may not have been cleared yet
+    let turn_start = game.msg_log.turn_messages.len();
 
     game.msg_log.log_front(Msg::StartTurn);
 
@@ -41,6 +50,10 @@ pub fn step_logic(game: &mut Game) -> bool {
        game.data.entities.status[&player_id].alive &&
        !won_level {
         step_ai(game);
+
+        if game.config.retaliate && game.data.entities.status[&player_id].alive {
+            retaliate(player_id, turn_start, game);
+        }
     }
     drop(monster);
 
@@ -49,15 +62,29 @@ pub fn step_logic(game: &mut Game) -> bool {
     game.msg_log.log(Msg::PlayerTurn);
     resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
 
+    // attacks whose animation has finished now do their damage
+    for (entity_id, target_id) in game.data.entities.count_down_attacks() {
+        if game.data.entities.ids.contains(&target_id) &&
+           game.data.entities.status[&entity_id].alive &&
+           game.data.entities.status[&target_id].alive {
+            attack(entity_id, target_id, &mut game.data, &mut game.msg_log);
+        }
+    }
+
+    // poison and burning do their damage before counting down, so they hurt on each turn they last
+    status_damage(&mut game.data, &mut game.msg_log, &game.config);
+    resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+
+    // count down status effects, letting each expire on its own schedule
+    for (entity_id, kind) in game.data.entities.count_down_statuses() {
+        game.msg_log.log(Msg::StatusExpired(entity_id, kind));
+    }
+
     // check status effects
     for entity_id in game.data.entities.ids.iter() {
         if let Some(mut status) = game.data.entities.status.get_mut(entity_id) {
-            if status.frozen > 0 {
-                status.frozen -= 1;
-            }
-
-            if status.soft_steps > 0 {
-                status.soft_steps -= 1;
+            if status.time_stop_cooldown > 0 {
+                status.time_stop_cooldown -= 1;
             }
         }
     }
@@ -66,12 +93,79 @@ pub fn step_logic(game: &mut Game) -> bool {
         game.settings.turn_count += 1;
     }
 
+    // the player gains experience for each kill they made this turn. dying is not a kill.
+    let kills = game.msg_log.turn_messages.iter().skip(turn_start)
+                    .filter(|msg| matches!(msg, Msg::Killed(attacker, attacked, _) if *attacker == player_id && *attacked != player_id))
+                    .count();
+    if kills > 0 {
+        game.gain_xp(kills * game.config.xp_per_kill);
+    }
+
     // perform count down of entities waiting to be removed
     game.data.entities.count_down();
 
+    // expired statuses and level ups are logged after the last resolve above
+    resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+
     return level_exit_condition_met(&game.data);
 }
 
+fn status_damage(data: &mut GameData, msg_log: &mut MsgLog, config: &Config) {
+    let affected = data.entities.ids.iter()
+                                    .filter(|id| data.entities.status[*id].alive)
+                                    .filter(|id| data.entities.fighter.get(*id).is_some())
+                                    .map(|id| *id)
+                                    .collect::<Vec<EntityId>>();
+
+    for entity_id in affected {
+        // standing in water puts out a fire before it does any damage
+        if data.entities.has_status(entity_id, StatusKind::Burning) {
+            let pos = data.entities.pos[&entity_id];
+            if data.map[pos].tile_type == TileType::Water {
+                data.entities.remove_status(entity_id, StatusKind::Burning);
+                msg_log.log(Msg::StatusExpired(entity_id, StatusKind::Burning));
+            }
+        }
+
+        if data.entities.has_status(entity_id, StatusKind::Poison) {
+            data.entities.take_damage(entity_id, config.poison_damage);
+            msg_log.log(Msg::PoisonDamage(entity_id, config.poison_damage));
+            status_damage_killed(entity_id, config.poison_damage, data, msg_log);
+        }
+
+        if data.entities.status[&entity_id].alive && data.entities.has_status(entity_id, StatusKind::Burning) {
+            data.entities.take_damage(entity_id, config.burning_damage);
+            msg_log.log(Msg::BurnDamage(entity_id, config.burning_damage));
+            status_damage_killed(entity_id, config.burning_damage, data, msg_log);
+        }
+    }
+}
+
+fn status_damage_killed(entity_id: EntityId, damage: Hp, data: &mut GameData, msg_log: &mut MsgLog) {
+    // take_damage has already marked the entity dead, so only its hp tells us it just died
+    if data.entities.fighter[&entity_id].hp <= 0 {
+        data.entities.status[&entity_id].alive = false;
+        data.entities.blocks[&entity_id] = false;
+
+        // the kill goes to whoever poisoned or burned the entity, if they are still around
+        let source_id = data.entities.status_source.get(&entity_id)
+                                                   .filter(|source_id| data.entities.ids.contains(source_id))
+                                                   .map_or(entity_id, |source_id| *source_id);
+        msg_log.log(Msg::Killed(source_id, entity_id, damage));
+    }
+}
+
+/// Check whether a turn's logic went over the configured time budget,
+/// returning a warning to log if so.
+pub fn logic_budget_warning(elapsed_ms: f32, turn_count: usize, num_ais: usize, config: &Config) -> Option<String> {
+    if elapsed_ms > config.logic_budget_ms {
+        return Some(format!("Turn {} logic took {:.2} ms (budget {:.2} ms) with {} active AIs",
+                            turn_count, elapsed_ms, config.logic_budget_ms, num_ais));
+    }
+
+    return None;
+}
+
 /// Check whether the exit condition for the game is met.
 fn level_exit_condition_met(data: &GameData) -> bool {
     // loop over objects in inventory, and check whether any
@@ -223,16 +317,56 @@ pub fn test_hammer_small_wall() {
     assert_ne!(Surface::Rubble, game.data.map[pawn_pos].surface);
 }
 
+/// Counter-attack each monster that attacked the player this turn and ended its turn next to them.
+fn retaliate(player_id: EntityId, turn_start: usize, game: &mut Game) {
+    let player_pos = game.data.entities.pos[&player_id];
+
+    let mut attackers: Vec<EntityId> = Vec::new();
+    for msg in game.msg_log.turn_messages.iter().skip(turn_start) {
+        if let Msg::Attack(attacker_id, attacked_id, _damage) = msg {
+            if *attacked_id == player_id && !attackers.contains(attacker_id) {
+                attackers.push(*attacker_id);
+            }
+        }
+    }
+
+    for attacker_id in attackers {
+        if game.data.entities.ids.contains(&attacker_id) &&
+           game.data.entities.status[&attacker_id].alive &&
+           distance_maximum(player_pos, game.data.entities.pos[&attacker_id]) == 1 {
+            attack(player_id, attacker_id, &mut game.data, &mut game.msg_log);
+        }
+    }
+
+    resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+}
+
 fn step_ai(game: &mut Game) {
     let ai_ids: Vec<EntityId> = game.data.entities.active_ais();
 
+    // packs decide where each member should go before anyone moves
+    ai_coordinate_packs(&mut game.data, &game.config);
+
     for key in ai_ids.iter() {
-       ai_take_turn(*key, &mut game.data, &game.config, &mut game.msg_log);
+       // slow monsters save up energy over several turns, while fast ones may act more than once
+       let num_actions = game.data.entities.gain_turn_energy(*key);
+
+       for action_index in 0..num_actions {
+           if !game.data.entities.ids.contains(key) || !game.data.entities.status[key].alive {
+               break;
+           }
+
+           if action_index > 0 {
+               game.data.entities.took_turn[key] = false;
+           }
+
+           ai_take_turn(*key, &mut game.data, &game.config, &mut game.msg_log);
 
-       resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+           resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
 
-       // if there are remaining messages for an entity, clear them
-       game.data.entities.messages[key].clear();
+           // if there are remaining messages for an entity, clear them
+           game.data.entities.messages[key].clear();
+       }
     }
 }
 
@@ -318,7 +452,7 @@ fn test_ai_idle_heard_sound() {
 
     // if the monster hears a sound, they investigate
     let sound_pos = Pos::new(0, 1);
-    game.data.entities.messages[&gol].push(Message::Sound(player_id, sound_pos));
+    game.data.entities.messages[&gol].push(Message::Sound(player_id, sound_pos, 1));
     ai_idle(gol, &mut game.data, &mut game.msg_log, &game.config);
 
     assert_eq!(2, game.msg_log.messages.len());
@@ -377,7 +511,7 @@ fn test_ai_investigate_not_in_fov_heard_sound() {
 
     // if the monster hears a sound, they investigate
     let sound_pos = Pos::new(0, 1);
-    game.data.entities.messages[&gol].push(Message::Sound(player_id, sound_pos));
+    game.data.entities.messages[&gol].push(Message::Sound(player_id, sound_pos, 1));
     ai_investigate(player_pos, gol, &mut game.data, &mut game.msg_log, &game.config);
 
     assert_eq!(1, game.msg_log.messages.len());
@@ -405,7 +539,7 @@ fn test_ai_investigate_moves() {
     // if the monster hears a sound, they investigate
     let sound_pos = Pos::new(0, 1);
     game.msg_log.clear();
-    game.data.entities.messages[&gol].push(Message::Sound(player_id, sound_pos));
+    game.data.entities.messages[&gol].push(Message::Sound(player_id, sound_pos, 1));
     ai_investigate(sound_pos, gol, &mut game.data, &mut game.msg_log, &game.config);
 
     assert_eq!(1, game.msg_log.messages.len());
@@ -433,3 +567,1724 @@ fn clean_entities(entities: &mut Entities, msg_log: &mut MsgLog) {
     }
 }
 
+#[test]
+pub fn test_logic_budget_warning() {
+    let mut config = Config::from_file("../config.yaml");
+    config.logic_budget_ms = 5.0;
+
+    assert!(logic_budget_warning(1.0, 3, 10, &config).is_none());
+    assert!(logic_budget_warning(5.0, 3, 10, &config).is_none());
+
+    let warning = logic_budget_warning(12.5, 3, 10, &config);
+    assert!(warning.is_some());
+    let warning = warning.unwrap();
+    assert!(warning.contains("Turn 3"));
+    assert!(warning.contains("10 active AIs"));
+}
+
+#[test]
+pub fn test_logic_budget_large_map() {
+    let mut config = Config::from_file("../config.yaml");
+    config.logic_budget_ms = 0.0;
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game);
+    for x in 1..10 {
+        for y in 1..4 {
+            make_gol(&mut game.data.entities, &game.config, Pos::new(x, y), &mut game.msg_log);
+        }
+    }
+
+    let start = std::time::Instant::now();
+    game.msg_log.log(Msg::Pass);
+    step_logic(&mut game);
+    let elapsed_ms = start.elapsed().as_secs_f32() * 1000.0;
+
+    let num_ais = game.data.entities.active_ais().len();
+    assert!(logic_budget_warning(elapsed_ms, game.settings.turn_count, num_ais, &game.config).is_some());
+
+    game.config.logic_budget_ms = 1_000_000.0;
+    assert!(logic_budget_warning(elapsed_ms, game.settings.turn_count, num_ais, &game.config).is_none());
+}
+
+#[test]
+pub fn test_slime_splits() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(4, 4);
+
+    let slime_id = make_slime(&mut game.data.entities, &config, Pos::new(5, 4), &mut game.msg_log);
+    let slime_hp = game.data.entities.fighter[&slime_id].hp;
+
+    // the player's attack does partial damage to the slime
+    attack(player_id, slime_id, &mut game.data, &mut game.msg_log);
+    resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+
+    let slimes = game.data.entities.ids.iter()
+                                       .filter(|id| game.data.entities.name[*id] == EntityName::Slime)
+                                       .filter(|id| !game.data.entities.needs_removal[*id])
+                                       .map(|id| *id)
+                                       .collect::<Vec<EntityId>>();
+    assert_eq!(2, slimes.len());
+    assert!(!slimes.contains(&slime_id));
+
+    for split_id in slimes {
+        assert!(game.data.entities.fighter[&split_id].hp < slime_hp);
+        assert!(distance_maximum(Pos::new(5, 4), game.data.entities.pos[&split_id]) == 1);
+    }
+}
+
+#[test]
+pub fn test_slime_needs_room_to_split() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(4, 4);
+
+    // wall in the slime, leaving a single free tile next to it
+    let slime_id = make_slime(&mut game.data.entities, &config, Pos::new(5, 4), &mut game.msg_log);
+    for pos in &[(4, 3), (5, 3), (6, 3), (4, 5), (5, 5), (6, 5)] {
+        game.data.map[*pos] = Tile::wall();
+    }
+    let slime_hp = game.data.entities.fighter[&slime_id].hp;
+
+    attack(player_id, slime_id, &mut game.data, &mut game.msg_log);
+    resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+
+    // the slime stays whole rather than being replaced by a single copy
+    let slimes = game.data.entities.ids.iter()
+                                       .filter(|id| game.data.entities.name[*id] == EntityName::Slime)
+                                       .filter(|id| !game.data.entities.needs_removal[*id])
+                                       .map(|id| *id)
+                                       .collect::<Vec<EntityId>>();
+    assert_eq!(vec!(slime_id), slimes);
+    assert!(game.data.entities.status[&slime_id].alive);
+    assert!(game.data.entities.fighter[&slime_id].hp < slime_hp);
+}
+
+#[test]
+pub fn test_damage_resistance() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(4, 4);
+    game.data.entities.fighter[&player_id].power = 4;
+
+    let gol = make_gol(&mut game.data.entities, &config, Pos::new(5, 4), &mut game.msg_log);
+    let mut resistances = Resistances::new();
+    resistances.insert(DamageType::Fire, 50);
+    game.data.entities.resistance.insert(gol, resistances);
+    let gol_hp = game.data.entities.fighter[&gol].hp;
+
+    // physical attacks are not resisted
+    attack(player_id, gol, &mut game.data, &mut game.msg_log);
+    assert_eq!(gol_hp - 4, game.data.entities.fighter[&gol].hp);
+
+    // fire attacks do half damage
+    game.data.entities.damage_type.insert(player_id, DamageType::Fire);
+    attack(player_id, gol, &mut game.data, &mut game.msg_log);
+    assert_eq!(gol_hp - 6, game.data.entities.fighter[&gol].hp);
+}
+
+#[test]
+pub fn test_level_banner() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.level_banner_duration = 1.0;
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+
+    // place the key in the player's inventory and the exit next to them
+    let key = make_key(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, key);
+    make_exit(&mut game.data.entities, &config, Pos::new(1, 0), &mut game.msg_log);
+
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+
+    assert_eq!(1, game.settings.level_num);
+    let banner = game.settings.level_banner.clone().unwrap();
+    assert_eq!(1, banner.level_num);
+
+    // the banner does not block input
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.5);
+    assert!(game.settings.level_banner.is_some());
+    assert_eq!(2, game.settings.turn_count);
+
+    game.step_game(InputAction::None, 0.5);
+    assert!(game.settings.level_banner.is_none());
+}
+
+#[test]
+pub fn test_travel_map_click() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+
+    let target_pos = Pos::new(4, 0);
+    game.step_game(InputAction::MapClick(target_pos, target_pos), 0.1);
+    assert_eq!(4, game.settings.travel.len());
+    assert_eq!(Pos::new(0, 0), game.data.entities.pos[&player_id]);
+
+    // the player moves one step along the path each turn
+    game.step_game(InputAction::None, 0.1);
+    assert_eq!(Pos::new(1, 0), game.data.entities.pos[&player_id]);
+    game.step_game(InputAction::None, 0.1);
+    assert_eq!(Pos::new(2, 0), game.data.entities.pos[&player_id]);
+
+    // a gol comes into view, and travel stops
+    make_gol(&mut game.data.entities, &config, Pos::new(2, 4), &mut game.msg_log);
+    game.step_game(InputAction::None, 0.1);
+    assert_eq!(Pos::new(2, 0), game.data.entities.pos[&player_id]);
+    assert!(game.settings.travel.is_empty());
+}
+
+#[test]
+pub fn test_toggle_overlays() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    game.step_game(InputAction::ToggleOverlay(Overlay::Attack), 0.1);
+    game.step_game(InputAction::ToggleOverlay(Overlay::Fov), 0.1);
+
+    assert_eq!(Overlay::Attack.bit() | Overlay::Fov.bit(), game.settings.overlays);
+    assert!(game.settings.overlay_enabled(Overlay::Attack));
+    assert!(game.settings.overlay_enabled(Overlay::Fov));
+    assert!(!game.settings.overlay_enabled(Overlay::Movement));
+    assert!(!game.settings.overlay_enabled(Overlay::Sound));
+
+    // the master toggle clears all overlays
+    game.step_game(InputAction::ToggleOverlays, 0.1);
+    assert_eq!(0, game.settings.overlays);
+    for overlay in Overlay::all() {
+        assert!(!game.settings.overlay_enabled(overlay));
+    }
+}
+
+#[test]
+pub fn test_autosave_on_level() {
+    let save_file = std::env::temp_dir().join("test_autosave_on_level.yaml");
+    let save_file = save_file.to_str().unwrap().to_string();
+    let _ = std::fs::remove_file(&save_file);
+
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.autosave_on_level = true;
+    config.save_file = save_file.clone();
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+
+    let key = make_key(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, key);
+    make_exit(&mut game.data.entities, &config, Pos::new(1, 0), &mut game.msg_log);
+
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert_eq!(1, game.settings.level_num);
+
+    let save_game = SaveGame::from_file(&save_file).unwrap();
+    assert_eq!(1, save_game.settings.level_num);
+    assert_eq!(game.data.entities.ids, save_game.data.entities.ids);
+
+    std::fs::remove_file(&save_file).unwrap();
+}
+
+#[test]
+pub fn test_save_failure_reported() {
+    let save_file = std::env::temp_dir().join("missing_dir").join("test_save_failure_reported.yaml");
+
+    let mut config = Config::from_file("../config.yaml");
+    config.save_file = save_file.to_str().unwrap().to_string();
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    // a save that can't be written is reported to the player instead of ending the game
+    assert!(game.save(&config.save_file).is_err());
+    game.step_game(InputAction::SaveGame, 0.1);
+    assert!(game.msg_log.turn_messages.contains(&Msg::SaveFailed));
+    assert_eq!(0, game.settings.turn_count);
+}
+
+#[test]
+pub fn test_load_failure_reported() {
+    let save_file = std::env::temp_dir().join("missing_dir").join("test_load_failure_reported.yaml");
+
+    let mut config = Config::from_file("../config.yaml");
+    config.save_file = save_file.to_str().unwrap().to_string();
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    // with no save to load the game continues, and the player is told the load failed
+    game.step_game(InputAction::LoadGame, 0.1);
+    assert!(game.msg_log.turn_messages.contains(&Msg::LoadFailed));
+    assert_eq!(0, game.settings.turn_count);
+}
+
+#[test]
+pub fn test_cone_fov_monster() {
+    let mut config = Config::from_file("../config.yaml");
+    config.fov_cone_monster = true;
+    config.fov_arc_monster = 90.0;
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+
+    let gol = make_gol(&mut game.data.entities, &config, Pos::new(5, 5), &mut game.msg_log);
+    game.data.entities.direction[&gol] = Direction::Up;
+
+    // behind the guard
+    game.data.entities.pos[&player_id] = Pos::new(5, 8);
+    assert!(!ai_is_in_fov(gol, player_id, &mut game.data, &game.config));
+
+    // to the side, outside of the arc
+    game.data.entities.pos[&player_id] = Pos::new(8, 4);
+    assert!(!ai_is_in_fov(gol, player_id, &mut game.data, &game.config));
+
+    // in front of the guard
+    game.data.entities.pos[&player_id] = Pos::new(6, 2);
+    assert!(ai_is_in_fov(gol, player_id, &mut game.data, &game.config));
+}
+
+#[test]
+pub fn test_time_stop() {
+    let mut config = Config::from_file("../config.yaml");
+    config.time_stop_turns = 2;
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = Pos::new(4, 4);
+    game.data.entities.pos[&player_id] = player_pos;
+    game.data.entities.skills[&player_id].clear();
+    game.data.entities.add_skill(player_id, Skill::TimeStop);
+
+    let gol = make_gol(&mut game.data.entities, &config, Pos::new(4, 5), &mut game.msg_log);
+    game.data.entities.behavior[&gol] = Behavior::Attacking(player_id);
+
+    let start_hp = game.data.entities.fighter[&player_id].hp;
+    let gol_pos = game.data.entities.pos[&gol];
+
+    // casting the skill stops the gol from acting for two player turns
+    game.settings.cursor = Some(player_pos);
+    game.step_game(InputAction::CursorApplySkill(ActionMode::Alternate, 0), 0.1);
+    game.settings.cursor = None;
+    assert!(game.data.entities.has_status(gol, StatusKind::Frozen));
+    assert!(game.data.entities.status[&player_id].time_stop_cooldown > 0);
+
+    for _ in 0..2 {
+        game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+        assert_eq!(start_hp, game.data.entities.fighter[&player_id].hp);
+        assert_eq!(gol_pos, game.data.entities.pos[&gol]);
+    }
+
+    // afterwards the gol acts again
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert!(!game.data.entities.has_status(gol, StatusKind::Frozen));
+    assert!(game.data.entities.pos[&gol] != gol_pos ||
+            game.data.entities.fighter[&player_id].hp < start_hp);
+}
+
+#[test]
+pub fn test_sight_persistence() {
+    use roguelike_core::pathing::astar_path;
+
+    let mut config = Config::from_file("../config.yaml");
+    config.sight_persistence_turns = 2;
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+
+    // a wall between the gol and the player
+    for y in 0..8 {
+        game.data.map[(4, y)] = Tile::wall();
+    }
+
+    game.data.entities.pos[&player_id] = Pos::new(6, 2);
+    let gol = make_gol(&mut game.data.entities, &config, Pos::new(1, 1), &mut game.msg_log);
+    game.data.entities.behavior[&gol] = Behavior::Attacking(player_id);
+
+    // the player moves out of sight, but for each turn of persistence the gol
+    // takes a step towards where the player is now
+    for lost_sight in 1..=2 {
+        let gol_pos = game.data.entities.pos[&gol];
+        let player_pos = add_pos(game.data.entities.pos[&player_id], Pos::new(0, 1));
+
+        let mut moved = game.data.clone();
+        moved.entities.pos[&player_id] = player_pos;
+        let path = astar_path(gol_pos, player_pos, &moved);
+
+        game.step_game(InputAction::Move(Direction::Down, MoveMode::Walk), 0.1);
+        assert_eq!(player_pos, game.data.entities.pos[&player_id]);
+        assert_eq!(Behavior::Attacking(player_id), game.data.entities.behavior[&gol]);
+        assert_eq!(lost_sight, game.data.entities.status[&gol].lost_sight);
+        assert_eq!(path[1], game.data.entities.pos[&gol]);
+    }
+
+    // once persistence runs out the gol gives up and investigates where the player was
+    let player_pos = game.data.entities.pos[&player_id];
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_eq!(Behavior::Investigating(player_pos), game.data.entities.behavior[&gol]);
+    assert_eq!(0, game.data.entities.status[&gol].lost_sight);
+}
+
+#[test]
+pub fn test_auto_face() {
+    use crate::actions::{handle_skill, ActionLoc};
+
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(4, 4);
+    game.data.entities.skills[&player_id].clear();
+    game.data.entities.add_skill(player_id, Skill::Push);
+
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    game.step_game(InputAction::Move(Direction::Up, MoveMode::Walk), 0.1);
+    assert_eq!(Direction::Up, game.data.entities.direction[&player_id]);
+
+    // a directional skill with no target location goes the way the player is facing
+    game.msg_log.clear();
+    handle_skill(0, ActionLoc::None, ActionMode::Primary, &game.data, &mut game.settings, &mut game.msg_log, &game.config);
+    assert!(game.msg_log.messages.contains(&Msg::Push(player_id, Direction::Up, 1)));
+
+    // with auto-face off, moving does not change the player's facing
+    game.config.auto_face_player = false;
+    game.step_game(InputAction::Move(Direction::Left, MoveMode::Walk), 0.1);
+    assert_eq!(Pos::new(4, 3), game.data.entities.pos[&player_id]);
+    assert_eq!(Direction::Up, game.data.entities.direction[&player_id]);
+}
+
+#[test]
+pub fn test_persistent_levels() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.persistent_levels = true;
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+
+    let key = make_key(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, key);
+    let dagger = make_dagger(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, dagger);
+    let stone = make_stone(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, stone);
+    make_exit(&mut game.data.entities, &config, Pos::new(1, 0), &mut game.msg_log);
+    let gol = make_gol(&mut game.data.entities, &config, Pos::new(5, 5), &mut game.msg_log);
+
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert_eq!(1, game.settings.level_num);
+    assert!(game.data.find_by_name(EntityName::UpStairs).is_some());
+    assert_eq!(Pos::new(0, 0), game.data.entities.pos[&player_id]);
+
+    // the first level is kept as it was when the player left it
+    let level = game.levels[&0].clone();
+    let mut level_ids = level.entities.ids.clone();
+    level_ids.sort();
+
+    // leave the stone behind on the second level
+    game.data.entities.remove_item(player_id, stone);
+    game.data.entities.set_pos(stone, Pos::new(3, 3));
+
+    // step off the stairs and back onto them to return to the first level
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    game.step_game(InputAction::Move(Direction::Left, MoveMode::Walk), 0.1);
+    assert_eq!(0, game.settings.level_num);
+
+    // tiles around the player are explored again on arrival
+    let layout = |map: &Map| map.tiles.iter().flatten().map(|tile| Tile { explored: false, ..*tile }).collect::<Vec<Tile>>();
+    assert_eq!(layout(&level.map), layout(&game.data.map));
+    let mut restored_ids = game.data.entities.ids.clone();
+    restored_ids.sort();
+    level_ids.retain(|id| *id != stone);
+    assert_eq!(level_ids, restored_ids);
+    assert_eq!(level.entities.pos[&gol], game.data.entities.pos[&gol]);
+    assert_eq!(Pos::new(1, 0), game.data.entities.pos[&player_id]);
+
+    // the player's inventory came along with them
+    assert!(game.data.entities.inventory[&player_id].contains(&dagger));
+    assert_eq!(Item::Dagger, game.data.entities.item[&dagger]);
+
+    // the stone stays on the level it was dropped on
+    assert!(!game.data.entities.inventory[&player_id].contains(&stone));
+    assert!(!game.data.entities.ids.contains(&stone));
+    assert!(game.levels[&1].entities.ids.contains(&stone));
+}
+
+#[test]
+pub fn test_animated_attack_delay() {
+    let mut config = Config::from_file("../config.yaml");
+    config.animate_attacks = true;
+    config.attack_delay_turns = 2;
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(4, 4);
+
+    let gol = make_gol(&mut game.data.entities, &config, Pos::new(5, 4), &mut game.msg_log);
+    game.data.entities.apply_status(gol, StatusKind::Frozen, 10);
+    let gol_hp = game.data.entities.fighter[&gol].hp;
+
+    game.msg_log.log(Msg::TryAttack(player_id, Attack::Attack(gol), Pos::new(5, 4)));
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_eq!(gol_hp, game.data.entities.fighter[&gol].hp);
+
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert!(game.data.entities.fighter[&gol].hp < gol_hp);
+}
+
+#[test]
+pub fn test_path_preview() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+    game.data.map[(1, 0)] = Tile::water();
+    game.data.map[(1, 1)] = Tile::water();
+
+    // the preview follows the path around the water
+    let hover_pos = Pos::new(2, 0);
+    game.settings.hover = Some(hover_pos);
+    game.step_game(InputAction::None, 0.1);
+
+    let path = game.data.path_between(Pos::new(0, 0), hover_pos, Reach::single(1), true, true, None);
+    assert!(game.settings.path_preview.len() > 0);
+    assert_eq!(path[1..].to_vec(), game.settings.path_preview);
+
+    // the preview is kept while neither the hovered tile nor the player moves
+    game.settings.path_preview.clear();
+    game.step_game(InputAction::None, 0.1);
+    assert!(game.settings.path_preview.is_empty());
+
+    // water can't be travelled to
+    game.settings.hover = Some(Pos::new(1, 0));
+    game.step_game(InputAction::None, 0.1);
+    assert!(game.settings.path_preview.is_empty());
+
+    game.settings.hover = Some(hover_pos);
+    game.step_game(InputAction::None, 0.1);
+    assert_eq!(path[1..].to_vec(), game.settings.path_preview);
+
+    // without the overlay, no preview is searched for
+    game.config.overlay_path_preview = false;
+    game.settings.hover = Some(Pos::new(3, 3));
+    game.step_game(InputAction::None, 0.1);
+    assert_eq!(path[1..].to_vec(), game.settings.path_preview);
+}
+
+#[test]
+pub fn test_hammer_durability() {
+    let mut config = Config::from_file("../config.yaml");
+    config.hammer_durability = 2;
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = Pos::new(4, 4);
+    game.data.entities.pos[&player_id] = player_pos;
+
+    let gol = make_gol(&mut game.data.entities, &config, Pos::new(4, 5), &mut game.msg_log);
+    let pawn = make_pawn(&mut game.data.entities, &config, Pos::new(3, 4), &mut game.msg_log);
+    let hammer = make_hammer(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    game.data.entities.inventory[&player_id].push_front(hammer);
+
+    // the first hit wears the hammer down
+    game.step_game(InputAction::UseItem(Direction::Down, 0), 0.1);
+    assert!(game.data.entities.is_dead(gol));
+    assert!(game.data.entities.inventory[&player_id].contains(&hammer));
+    assert_eq!(1, game.data.entities.durability[&hammer]);
+    assert!(!game.msg_log.turn_messages.iter().any(|msg| matches!(msg, Msg::ItemBroke(_, _))));
+
+    // the second hit breaks it
+    game.step_game(InputAction::UseItem(Direction::Left, 0), 0.1);
+    assert!(game.data.entities.is_dead(pawn));
+    assert!(!game.data.entities.inventory[&player_id].contains(&hammer));
+    assert!(game.msg_log.turn_messages.iter().any(|msg| *msg == Msg::ItemBroke(player_id, hammer)));
+}
+
+#[test]
+pub fn test_move_mode_noise() {
+    let config = Config::from_file("../config.yaml");
+
+    let move_sound = |move_mode: MoveMode| -> Option<usize> {
+        let mut game = Game::new(0, config.clone());
+        make_map(&MapLoadConfig::Empty, &mut game);
+        let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+        game.data.entities.pos[&player_id] = Pos::new(2, 4);
+
+        game.step_game(InputAction::Move(Direction::Right, move_mode), 0.1);
+        assert_ne!(Pos::new(2, 4), game.data.entities.pos[&player_id]);
+
+        return game.msg_log.turn_messages.iter().filter_map(|msg| {
+            match msg {
+                Msg::Sound(entity_id, _pos, radius, _animate) if *entity_id == player_id => Some(*radius),
+                _ => None,
+            }
+        }).max();
+    };
+
+    let walk_radius = move_sound(MoveMode::Walk).unwrap();
+    let run_radius = move_sound(MoveMode::Run).unwrap();
+    assert!(run_radius > walk_radius);
+    assert_eq!(None, move_sound(MoveMode::Sneak));
+}
+
+#[test]
+pub fn test_level_feeling() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    // making the level logs how it feels
+    assert_eq!(LevelFeeling::Quiet, level_feeling(&game.data, &config));
+    assert_eq!(Some(&Msg::LevelFeeling(LevelFeeling::Quiet)), game.msg_log.messages.back());
+
+    // fill the level with strong monsters
+    for x in 0..8 {
+        make_pawn(&mut game.data.entities, &config, Pos::new(x, 5), &mut game.msg_log);
+    }
+    assert_eq!(LevelFeeling::Dangerous, level_feeling(&game.data, &config));
+}
+
+#[test]
+pub fn test_status_durations() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let gol = make_gol(&mut game.data.entities, &config, Pos::new(8, 8), &mut game.msg_log);
+    apply_status(gol, StatusKind::Frozen, 1, &mut game.data, &mut game.msg_log);
+    apply_status(player_id, StatusKind::SoftSteps, 3, &mut game.data, &mut game.msg_log);
+    assert!(game.msg_log.messages.contains(&Msg::StatusApplied(gol, StatusKind::Frozen, 1)));
+    assert!(game.msg_log.messages.contains(&Msg::StatusApplied(player_id, StatusKind::SoftSteps, 3)));
+
+    let expired = |game: &Game, entity_id: EntityId, kind: StatusKind| {
+        game.msg_log.turn_messages.contains(&Msg::StatusExpired(entity_id, kind))
+    };
+
+    // the short status expires first
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert!(expired(&game, gol, StatusKind::Frozen));
+    assert!(!game.data.entities.has_status(gol, StatusKind::Frozen));
+    assert!(game.data.entities.has_status(player_id, StatusKind::SoftSteps));
+
+    game.msg_log.clear();
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert!(!expired(&game, player_id, StatusKind::SoftSteps));
+    assert!(game.data.entities.has_status(player_id, StatusKind::SoftSteps));
+
+    game.msg_log.clear();
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert!(expired(&game, player_id, StatusKind::SoftSteps));
+    assert!(!game.data.entities.has_status(player_id, StatusKind::SoftSteps));
+}
+
+#[test]
+pub fn test_momentum_through_attacks() {
+    let mut config = Config::from_file("../config.yaml");
+
+    for carry in [false, true] {
+        config.momentum_through_attacks = carry;
+        let mut game = Game::new(0, config.clone());
+        make_map(&MapLoadConfig::Empty, &mut game);
+
+        let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+        game.data.entities.pos[&player_id] = Pos::new(2, 4);
+        for _ in 0..2 {
+            let dagger = make_dagger(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+            game.data.entities.inventory[&player_id].push_front(dagger);
+        }
+
+        let gol1 = make_gol(&mut game.data.entities, &config, Pos::new(3, 4), &mut game.msg_log);
+        let gol2 = make_gol(&mut game.data.entities, &config, Pos::new(4, 4), &mut game.msg_log);
+
+        game.step_game(InputAction::Move(Direction::Right, MoveMode::Run), 0.1);
+        assert!(game.data.entities.is_dead(gol1));
+
+        // only with momentum does the charge carry on to the second gol
+        assert_eq!(carry, game.data.entities.is_dead(gol2));
+    }
+
+    // no momentum is carried when the maximum is zero
+    config.momentum_through_attacks = true;
+    config.momentum_max = 0;
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(2, 4);
+    let dagger = make_dagger(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    game.data.entities.inventory[&player_id].push_front(dagger);
+
+    let gol1 = make_gol(&mut game.data.entities, &config, Pos::new(3, 4), &mut game.msg_log);
+    let gol2 = make_gol(&mut game.data.entities, &config, Pos::new(4, 4), &mut game.msg_log);
+
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Run), 0.1);
+    assert!(game.data.entities.is_dead(gol1));
+    assert!(!game.data.entities.is_dead(gol2));
+}
+
+#[test]
+pub fn test_intro_sequence() {
+    let mut config = Config::from_file("../config.yaml");
+    config.intro_messages = vec!("You wake in the dark.".to_string(), "Find the key.".to_string());
+    config.intro_message_duration = 1.0;
+    config.intro_camera = vec!(Pos::new(10, 3));
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = game.data.entities.pos[&player_id];
+
+    game.start_intro();
+    assert_eq!(GameState::Intro, game.settings.state);
+    assert_eq!(Some(&config.intro_messages[0]), game.intro_message());
+    assert_eq!(Some(Pos::new(10, 3)), game.intro_camera());
+
+    // the intro holds control from the player while it plays
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 1.0);
+    assert_eq!(player_pos, game.data.entities.pos[&player_id]);
+    assert_eq!(Some(&config.intro_messages[1]), game.intro_message());
+
+    // messages without a camera position leave the camera on the player
+    assert_eq!(None, game.intro_camera());
+
+    game.step_game(InputAction::Esc, 0.1);
+    assert_eq!(GameState::Playing, game.settings.state);
+    assert_eq!(None, game.intro_message());
+}
+
+#[test]
+pub fn test_leash() {
+    let mut config = Config::from_file("../config.yaml");
+    config.leash_distance = 3;
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(8, 7);
+
+    // the gol has been lured to the edge of its leash
+    let home = Pos::new(1, 5);
+    let gol = make_gol(&mut game.data.entities, &config, home, &mut game.msg_log);
+    game.data.entities.pos[&gol] = Pos::new(3, 5);
+    game.data.entities.behavior[&gol] = Behavior::Attacking(player_id);
+
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+
+    assert_eq!(Behavior::Investigating(home), game.data.entities.behavior[&gol]);
+    assert_eq!(Pos::new(2, 5), game.data.entities.pos[&gol]);
+}
+
+#[test]
+pub fn test_charge_attack() {
+    use std::time::{Duration, Instant};
+
+    let mut config = Config::from_file("../config.yaml");
+    config.repeat_delay = 0.5;
+    config.charge_max = 3;
+
+    // hold the charge key for the given time, a frame at a time, and return the damage dealt
+    let charge_damage = |held_secs: f32| {
+        let mut game = Game::new(0, config.clone());
+        make_map(&MapLoadConfig::Empty, &mut game);
+
+        let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+        game.data.entities.pos[&player_id] = Pos::new(4, 4);
+        game.data.entities.direction[&player_id] = Direction::Right;
+
+        let gol = make_gol(&mut game.data.entities, &config, Pos::new(5, 4), &mut game.msg_log);
+        game.data.entities.fighter[&gol].hp = 100;
+        game.data.entities.fighter[&gol].max_hp = 100;
+
+        let key = config.charge_key;
+        let start = Instant::now();
+        let mut time = start;
+        game.input.handle_event(&mut game.settings, InputEvent::Char(key, KeyDir::Down), time, &config);
+        while time.duration_since(start).as_secs_f32() < held_secs {
+            time += Duration::from_millis(100);
+            let action = game.input.handle_event(&mut game.settings, InputEvent::Char(key, KeyDir::Held), time, &config);
+            assert_eq!(InputAction::None, action);
+        }
+        let action = game.input.handle_event(&mut game.settings, InputEvent::Char(key, KeyDir::Up), time, &config);
+
+        game.step_game(action, 0.1);
+        return 100 - game.data.entities.fighter[&gol].hp;
+    };
+
+    let tap = charge_damage(0.0);
+    assert!(tap > 0);
+    assert_eq!(2 * tap, charge_damage(0.55));
+    assert_eq!(3 * tap, charge_damage(1.05));
+
+    // the charge is capped
+    assert_eq!(4 * tap, charge_damage(5.0));
+
+    // including charges that did not come from holding the key
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(4, 4);
+    game.data.entities.direction[&player_id] = Direction::Right;
+    let gol = make_gol(&mut game.data.entities, &config, Pos::new(5, 4), &mut game.msg_log);
+    game.data.entities.fighter[&gol].hp = 100;
+    game.step_game(InputAction::ChargeAttack(10), 0.1);
+    assert_eq!(100 - 4 * tap, game.data.entities.fighter[&gol].hp);
+}
+
+#[test]
+pub fn test_cursor_clamp_fov() {
+    let mut config = Config::from_file("../config.yaml");
+    config.cursor_clamp_fov = true;
+    config.cursor_long = 8;
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(1, 4);
+
+    // a wall blocks the view to the right
+    for y in 0..10 {
+        game.data.map[(4, y)] = Tile::wall();
+    }
+    assert!(!game.data.pos_in_fov(player_id, Pos::new(5, 4), &config));
+
+    game.step_game(InputAction::CursorToggle, 0.1);
+    game.step_game(InputAction::CursorMove(Direction::Right, false, true), 0.1);
+    assert_eq!(Some(Pos::new(4, 4)), game.settings.cursor);
+
+    // further moves towards unseen tiles don't move the cursor
+    game.step_game(InputAction::CursorMove(Direction::Right, false, false), 0.1);
+    assert_eq!(Some(Pos::new(4, 4)), game.settings.cursor);
+}
+
+#[test]
+pub fn test_retaliate() {
+    let mut config = Config::from_file("../config.yaml");
+
+    for retaliate in [false, true] {
+        config.retaliate = retaliate;
+        let mut game = Game::new(0, config.clone());
+        make_map(&MapLoadConfig::Empty, &mut game);
+
+        let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+        game.data.entities.pos[&player_id] = Pos::new(4, 4);
+        let player_hp = game.data.entities.fighter[&player_id].hp;
+
+        // gols attack diagonally
+        let gol = make_gol(&mut game.data.entities, &config, Pos::new(5, 5), &mut game.msg_log);
+        game.data.entities.behavior[&gol] = Behavior::Attacking(player_id);
+        let gol_hp = game.data.entities.fighter[&gol].hp;
+
+        game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+        assert!(game.data.entities.fighter[&player_id].hp < player_hp);
+
+        // the counter-attack lands within the same step, before the next input
+        assert_eq!(retaliate, game.data.entities.fighter[&gol].hp < gol_hp);
+    }
+}
+
+#[test]
+pub fn test_reveal_map_on_win() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.reveal_map_on_win = true;
+    config.reveal_map_duration = 1.0;
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+
+    // a wall hides part of the level from the player
+    for y in 0..10 {
+        game.data.map[(5, y)] = Tile::wall();
+    }
+
+    let key = make_key(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, key);
+    make_exit(&mut game.data.entities, &config, Pos::new(1, 0), &mut game.msg_log);
+
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+
+    // the completed level is fully revealed before moving on
+    assert_eq!(0, game.settings.level_num);
+    assert!(game.data.map.get_all_pos().iter().all(|pos| game.data.map[*pos].explored));
+
+    game.step_game(InputAction::None, 1.0);
+    assert_eq!(1, game.settings.level_num);
+}
+
+#[test]
+pub fn test_level_rest() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.level_rest_fraction = 1.0;
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.fighter[&player_id].hp = 1;
+
+    let key = make_key(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, key);
+    make_exit(&mut game.data.entities, &config, Pos::new(1, 0), &mut game.msg_log);
+
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+
+    assert_eq!(1, game.settings.level_num);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let fighter = game.data.entities.fighter[&player_id];
+    assert_eq!(fighter.max_hp, fighter.hp);
+}
+
+#[test]
+pub fn test_target_order() {
+    let mut config = Config::from_file("../config.yaml");
+
+    for order in [TargetOrder::Distance, TargetOrder::Threat] {
+        config.target_order = order;
+        let mut game = Game::new(0, config.clone());
+        make_map(&MapLoadConfig::Empty, &mut game);
+
+        let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+        let player_pos = Pos::new(1, 4);
+        game.data.entities.pos[&player_id] = player_pos;
+
+        let weak_pos = Pos::new(3, 4);
+        let weak = make_gol(&mut game.data.entities, &config, weak_pos, &mut game.msg_log);
+        game.data.entities.fighter[&weak].hp = 2;
+
+        let strong_pos = Pos::new(5, 6);
+        let strong = make_gol(&mut game.data.entities, &config, strong_pos, &mut game.msg_log);
+        game.data.entities.fighter[&strong].power = 5;
+
+        game.settings.cursor = Some(player_pos);
+        game.step_game(InputAction::CycleTarget, 0.1);
+
+        let first_pos = if order == TargetOrder::Threat { strong_pos } else { weak_pos };
+        assert_eq!(Some(first_pos), game.settings.cursor);
+
+        // cycling again moves on to the other enemy
+        game.step_game(InputAction::CycleTarget, 0.1);
+        assert_ne!(Some(first_pos), game.settings.cursor);
+    }
+}
+
+#[test]
+pub fn test_grace_turns() {
+    let mut config = Config::from_file("../config.yaml");
+
+    for grace_turns in [0, 1] {
+        config.grace_turns = grace_turns;
+        let mut game = Game::new(0, config.clone());
+        make_map(&MapLoadConfig::Empty, &mut game);
+
+        let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+        game.data.entities.pos[&player_id] = Pos::new(4, 4);
+        let player_hp = game.data.entities.fighter[&player_id].hp;
+
+        for gol_pos in [Pos::new(5, 5), Pos::new(3, 3)] {
+            let gol = make_gol(&mut game.data.entities, &config, gol_pos, &mut game.msg_log);
+            game.data.entities.behavior[&gol] = Behavior::Attacking(player_id);
+        }
+
+        game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+
+        // each gol hits for one damage, but with grace only the first hit lands
+        let hits = if grace_turns > 0 { 1 } else { 2 };
+        assert_eq!(player_hp - hits, game.data.entities.fighter[&player_id].hp);
+    }
+
+    // grace wears off by the next turn
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    apply_status(player_id, StatusKind::Grace, 1, &mut game.data, &mut game.msg_log);
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert!(!game.data.entities.has_status(player_id, StatusKind::Grace));
+}
+
+#[test]
+pub fn test_monster_infighting() {
+    let mut config = Config::from_file("../config.yaml");
+
+    for infighting in [false, true] {
+        config.monster_infighting = infighting;
+        let mut game = Game::new(0, config.clone());
+        make_map(&MapLoadConfig::Empty, &mut game);
+
+        let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+        game.data.entities.pos[&player_id] = Pos::new(9, 9);
+
+        let gol = make_gol(&mut game.data.entities, &config, Pos::new(3, 3), &mut game.msg_log);
+        let pawn = make_pawn(&mut game.data.entities, &config, Pos::new(4, 4), &mut game.msg_log);
+        let pawn_hp = game.data.entities.fighter[&pawn].hp;
+
+        // an armil has no attack, so it never joins in
+        let armil = make_armil(&mut game.data.entities, &config, Pos::new(5, 5), &mut game.msg_log);
+        ai_take_turn(armil, &mut game.data, &game.config, &mut game.msg_log);
+        resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+        assert_eq!(pawn_hp, game.data.entities.fighter[&pawn].hp);
+
+        ai_take_turn(gol, &mut game.data, &game.config, &mut game.msg_log);
+        resolve_messages(&mut game.data, &mut game.msg_log, &mut game.rng, &game.config);
+
+        // the gol only turns on the pawn when infighting is enabled
+        let hits = if infighting { 1 } else { 0 };
+        assert_eq!(pawn_hp - hits, game.data.entities.fighter[&pawn].hp);
+    }
+}
+
+#[test]
+pub fn test_use_slot_from_playing() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(4, 4);
+    game.data.entities.direction[&player_id] = Direction::Right;
+
+    let stone = make_stone(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    game.data.entities.inventory[&player_id].push_front(stone);
+
+    // the stone is the first consumable, used by the second slot
+    game.step_game(InputAction::UseSlot(1), 0.1);
+
+    // the stone is thrown without going through the inventory menu
+    assert_eq!(GameState::Playing, game.settings.state);
+    assert!(!game.data.entities.inventory[&player_id].contains(&stone));
+    assert_ne!(Pos::new(4, 4), game.data.entities.pos[&stone]);
+
+    // an empty slot just reports that it is empty
+    game.msg_log.clear();
+    game.step_game(InputAction::UseSlot(1), 0.1);
+    assert!(game.msg_log.turn_messages.contains(&Msg::EmptySlot(player_id, 1)));
+}
+
+#[test]
+pub fn test_poison() {
+    let mut config = Config::from_file("../config.yaml");
+    config.poison_damage = 1;
+    config.monster_damage_types.insert(EntityName::Slime, DamageType::Poison);
+
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let player_hp = game.data.entities.fighter[&player_id].hp;
+
+    apply_status(player_id, StatusKind::Poison, 3, &mut game.data, &mut game.msg_log);
+    for turn in 1..=3 {
+        game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+        assert_eq!(player_hp - turn, game.data.entities.fighter[&player_id].hp);
+    }
+    assert!(!game.data.entities.has_status(player_id, StatusKind::Poison));
+
+    // once expired, the poison does no more damage
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_eq!(player_hp - 3, game.data.entities.fighter[&player_id].hp);
+
+    // poison can kill
+    let gol = make_gol(&mut game.data.entities, &config, Pos::new(7, 7), &mut game.msg_log);
+    game.data.entities.fighter[&gol].hp = 1;
+    apply_status(gol, StatusKind::Poison, 3, &mut game.data, &mut game.msg_log);
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert!(!game.data.entities.status[&gol].alive);
+
+    // slimes configured with poison damage poison what they hit
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+
+    let slime = make_slime(&mut game.data.entities, &config, Pos::new(1, 1), &mut game.msg_log);
+    assert_eq!(Some(&DamageType::Poison), game.data.entities.damage_type.get(&slime));
+    attack(slime, player_id, &mut game.data, &mut game.msg_log);
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert!(game.data.entities.has_status(player_id, StatusKind::Poison));
+}
+
+#[test]
+pub fn test_burning() {
+    let mut config = Config::from_file("../config.yaml");
+    config.burning_damage = 2;
+    config.monster_damage_types.insert(EntityName::Spire, DamageType::Fire);
+
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.fighter[&player_id].hp = 10;
+    let player_hp = game.data.entities.fighter[&player_id].hp;
+
+    apply_status(player_id, StatusKind::Burning, 2, &mut game.data, &mut game.msg_log);
+    for turn in 1..=2 {
+        game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+        assert_eq!(player_hp - 2 * turn, game.data.entities.fighter[&player_id].hp);
+    }
+    assert!(!game.data.entities.has_status(player_id, StatusKind::Burning));
+
+    // standing in water puts the fire out without taking damage
+    let player_pos = game.data.entities.pos[&player_id];
+    game.data.map[player_pos] = Tile::water();
+    apply_status(player_id, StatusKind::Burning, 3, &mut game.data, &mut game.msg_log);
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert!(!game.data.entities.has_status(player_id, StatusKind::Burning));
+    assert_eq!(player_hp - 4, game.data.entities.fighter[&player_id].hp);
+
+    // spires configured with fire damage set what they hit burning
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.fighter[&player_id].hp = 10;
+
+    let spire = make_spire(&mut game.data.entities, &config, Pos::new(1, 1), &mut game.msg_log);
+    assert_eq!(Some(&DamageType::Fire), game.data.entities.damage_type.get(&spire));
+    attack(spire, player_id, &mut game.data, &mut game.msg_log);
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert!(game.data.entities.has_status(player_id, StatusKind::Burning));
+
+    // a death by burning is credited to whoever set the fire
+    game.data.entities.fighter[&player_id].hp = 2;
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert!(game.msg_log.turn_messages.contains(&Msg::Killed(spire, player_id, 2)));
+}
+
+#[test]
+pub fn test_level_up_from_kills() {
+    let mut config = Config::from_file("../config.yaml");
+    config.xp_per_kill = 10;
+    config.xp_per_tile = 0;
+    config.xp_levels = vec!(10, 30);
+    config.level_up_hp = 3;
+    config.level_up_power = 2;
+
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(4, 4);
+    let fighter = game.data.entities.fighter[&player_id];
+
+    let gol = make_gol(&mut game.data.entities, &config, Pos::new(5, 4), &mut game.msg_log);
+
+    // stab the gol with a dagger to kill it
+    let dagger = make_dagger(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    game.data.entities.inventory[&player_id].push_front(dagger);
+
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert!(!game.data.entities.status[&gol].alive);
+
+    assert_eq!(10, game.settings.xp);
+    assert_eq!(2, game.settings.xp_level);
+    assert_eq!(fighter.max_hp + 3, game.data.entities.fighter[&player_id].max_hp);
+    assert_eq!(fighter.power + 2, game.data.entities.fighter[&player_id].power);
+    assert!(game.msg_log.turn_messages.contains(&Msg::LevelUp(player_id, 2)));
+
+    // the next level needs more experience than a single kill
+    game.gain_xp(10);
+    assert_eq!(2, game.settings.xp_level);
+    game.gain_xp(10);
+    assert_eq!(3, game.settings.xp_level);
+    assert_eq!(fighter.power + 4, game.data.entities.fighter[&player_id].power);
+
+    // dying from poison is not a kill
+    let xp = game.settings.xp;
+    game.data.entities.fighter[&player_id].hp = 1;
+    apply_status(player_id, StatusKind::Poison, 3, &mut game.data, &mut game.msg_log);
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert!(!game.data.entities.status[&player_id].alive);
+    assert_eq!(xp, game.settings.xp);
+}
+
+#[test]
+pub fn test_drop_all() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(4, 4);
+
+    let items = vec!(make_stone(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log),
+                     make_stone(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log),
+                     make_dagger(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log));
+    for item_id in items.iter() {
+        game.data.entities.inventory[&player_id].push_back(*item_id);
+    }
+
+    game.msg_log.clear();
+    game.step_game(InputAction::DropAll, 0.1);
+
+    // each item lands on its own tile
+    assert_eq!(0, game.data.entities.inventory[&player_id].len());
+    let mut positions = items.iter().map(|id| game.data.entities.pos[id]).collect::<Vec<Pos>>();
+    positions.sort_by_key(|pos| (pos.x, pos.y));
+    positions.dedup();
+    assert_eq!(3, positions.len());
+    for item_id in items.iter() {
+        assert!(game.msg_log.turn_messages.contains(&Msg::DroppedItem(player_id, *item_id)));
+    }
+
+    // when walled in, the items that do not fit are stacked
+    for item_id in items.iter() {
+        game.data.entities.set_xy(*item_id, -1, -1);
+        game.data.entities.inventory[&player_id].push_back(*item_id);
+    }
+    for pos in floodfill(&game.data.map, Pos::new(4, 4), 1) {
+        if pos != Pos::new(4, 4) {
+            game.data.map[pos] = Tile::wall();
+        }
+    }
+
+    game.msg_log.clear();
+    game.step_game(InputAction::DropAll, 0.1);
+
+    assert_eq!(0, game.data.entities.inventory[&player_id].len());
+    for item_id in items.iter() {
+        assert_eq!(Pos::new(4, 4), game.data.entities.pos[item_id]);
+    }
+    assert!(game.msg_log.turn_messages.contains(&Msg::ItemStacked(player_id, items[1])));
+}
+
+#[test]
+pub fn test_monster_sleep() {
+    let mut config = Config::from_file("../config.yaml");
+    config.monster_sleep = true;
+    config.wake_radius = 2;
+
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(1, 1);
+
+    let gol = make_gol(&mut game.data.entities, &config, Pos::new(5, 5), &mut game.msg_log);
+    game.data.entities.direction[&gol] = Direction::UpLeft;
+    game.data.entities.status[&gol].asleep = true;
+
+    // the gol is facing the player, but sleeps through their turns
+    for _ in 0..3 {
+        game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    }
+    assert!(game.data.entities.status[&gol].asleep);
+    assert_eq!(Behavior::Idle, game.data.entities.behavior[&gol]);
+    assert_eq!(Pos::new(5, 5), game.data.entities.pos[&gol]);
+
+    // stepping within the wake radius wakes the gol up
+    game.data.entities.pos[&player_id] = Pos::new(3, 3);
+    game.msg_log.clear();
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert!(!game.data.entities.status[&gol].asleep);
+    assert!(game.msg_log.turn_messages.contains(&Msg::WokeUp(gol)));
+    assert_ne!(Behavior::Idle, game.data.entities.behavior[&gol]);
+}
+
+#[test]
+pub fn test_save_and_load_game() {
+    let save_file = std::env::temp_dir().join("test_save_and_load_game.yaml");
+    let save_file = save_file.to_str().unwrap().to_string();
+    let _ = std::fs::remove_file(&save_file);
+
+    let mut config = Config::from_file("../config.yaml");
+    config.save_file = save_file.clone();
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(4, 4);
+    make_gol(&mut game.data.entities, &config, Pos::new(7, 7), &mut game.msg_log);
+
+    assert!(Game::load(&save_file, config.clone()).is_err());
+
+    // explore from the starting position before saving
+    game.step_game(InputAction::None, 0.1);
+    game.step_game(InputAction::SaveGame, 0.1);
+    let saved_pos = game.data.entities.pos[&player_id];
+    let saved_rng = game.rng.state();
+
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    let moved_data = game.data.clone();
+    let moved_rng = game.rng.state();
+
+    // loading restores the game as it was saved, including the rng
+    game.step_game(InputAction::LoadGame, 0.1);
+    assert_eq!(saved_pos, game.data.entities.pos[&player_id]);
+    assert_eq!(saved_rng, game.rng.state());
+
+    // so replaying the same action gives the same result
+    let mut loaded = Game::load(&save_file, config.clone()).unwrap();
+    loaded.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert_eq!(moved_data.entities.pos, loaded.data.entities.pos);
+    assert_eq!(moved_data.map.tiles, loaded.data.map.tiles);
+    assert_eq!(moved_rng, loaded.rng.state());
+
+    std::fs::remove_file(&save_file).unwrap();
+}
+
+#[test]
+pub fn test_level_transition() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::Empty;
+    config.level_transition = true;
+    config.level_transition_duration = 1.0;
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+
+    let key = make_key(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, key);
+    make_exit(&mut game.data.entities, &config, Pos::new(1, 0), &mut game.msg_log);
+
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert_eq!(1, game.settings.level_num);
+    assert!(game.settings.level_transition.is_some());
+
+    // input is ignored while the new level fades in
+    let start_pos = game.data.entities.pos[&player_id];
+    game.step_game(InputAction::Move(Direction::Down, MoveMode::Walk), 0.5);
+    assert_eq!(start_pos, game.data.entities.pos[&player_id]);
+
+    // once the transition is over the player can move again
+    game.step_game(InputAction::Move(Direction::Down, MoveMode::Walk), 0.5);
+    assert!(game.settings.level_transition.is_none());
+    assert_eq!(move_y(start_pos, 1), game.data.entities.pos[&player_id]);
+}
+
+#[test]
+pub fn test_ai_flee() {
+    let mut config = Config::from_file("../config.yaml");
+    config.flee_hp_fraction = 0.5;
+
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(5, 5);
+
+    // a badly hurt gol runs from the player
+    let gol = make_gol(&mut game.data.entities, &config, Pos::new(5, 4), &mut game.msg_log);
+    game.data.entities.behavior[&gol] = Behavior::Attacking(player_id);
+    game.data.entities.direction[&gol] = Direction::Down;
+    game.data.entities.fighter[&gol].hp = 1;
+
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_eq!(Behavior::Fleeing(Pos::new(5, 5)), game.data.entities.behavior[&gol]);
+    assert_eq!(Pos::new(5, 3), game.data.entities.pos[&gol]);
+
+    // when the player moves, it runs from their new position in the same turn, moving only once
+    game.data.entities.pos[&gol] = Pos::new(5, 5);
+    game.data.entities.direction[&gol] = Direction::Right;
+    game.data.entities.pos[&player_id] = Pos::new(6, 5);
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_eq!(Behavior::Fleeing(Pos::new(6, 5)), game.data.entities.behavior[&gol]);
+    assert!(game.msg_log.turn_messages.contains(&Msg::StateChange(gol, Behavior::Fleeing(Pos::new(6, 5)))));
+    assert_eq!(Pos::new(4, 5), game.data.entities.pos[&gol]);
+
+    // with nowhere left to run, it turns to fight
+    game.data.entities.pos[&gol] = Pos::new(0, 0);
+    game.data.entities.direction[&gol] = Direction::DownRight;
+    game.data.entities.pos[&player_id] = Pos::new(1, 1);
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_eq!(Behavior::Attacking(player_id), game.data.entities.behavior[&gol]);
+    assert_eq!(Pos::new(0, 0), game.data.entities.pos[&gol]);
+}
+
+#[test]
+fn test_ai_idle_ignores_faint_sounds() {
+    let mut config = Config::from_file("../config.yaml");
+    config.sound_investigate_strength = 2;
+
+    let mut game = Game::new(0, config);
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(8, 8);
+
+    let gol = make_gol(&mut game.data.entities, &game.config, Pos::new(1, 1), &mut game.msg_log);
+    game.data.entities.direction[&gol] = Direction::Up;
+    game.msg_log.clear();
+
+    let sound_pos = Pos::new(0, 4);
+    game.data.entities.messages[&gol].push(Message::Sound(player_id, sound_pos, 1));
+    ai_idle(gol, &mut game.data, &mut game.msg_log, &game.config);
+    assert_eq!(0, game.msg_log.messages.len());
+
+    game.data.entities.messages[&gol].push(Message::Sound(player_id, sound_pos, 2));
+    ai_idle(gol, &mut game.data, &mut game.msg_log, &game.config);
+    assert_eq!(game.msg_log.messages[1], Msg::StateChange(gol, Behavior::Investigating(sound_pos)));
+}
+
+#[test]
+pub fn test_ai_patrol() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 9);
+
+    let gol = make_gol(&mut game.data.entities, &config, Pos::new(2, 2), &mut game.msg_log);
+    game.data.entities.patrol.insert(gol, vec!(Pos::new(2, 2), Pos::new(4, 2)));
+    game.data.entities.behavior[&gol] = Behavior::Patrolling(0, PatrolDir::Forward);
+
+    // the gol walks to the end of its route, pausing at each waypoint, and then walks back
+    let mut positions = Vec::new();
+    for _ in 0..8 {
+        // keep the gol from noticing the player
+        game.data.entities.direction[&gol] = Direction::Up;
+        game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+        positions.push(game.data.entities.pos[&gol].x);
+    }
+    assert_eq!(vec!(2, 3, 4, 4, 3, 2, 2, 3), positions);
+    assert_eq!(Behavior::Patrolling(1, PatrolDir::Forward), game.data.entities.behavior[&gol]);
+
+    // seeing the player ends the patrol
+    game.data.entities.pos[&player_id] = Pos::new(3, 5);
+    game.data.entities.direction[&gol] = Direction::Down;
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_eq!(Behavior::Attacking(player_id), game.data.entities.behavior[&gol]);
+}
+
+#[test]
+pub fn test_patrol_next() {
+    assert_eq!((1, PatrolDir::Forward), patrol_next(0, PatrolDir::Forward, 3));
+    assert_eq!((1, PatrolDir::Reverse), patrol_next(2, PatrolDir::Forward, 3));
+    assert_eq!((0, PatrolDir::Reverse), patrol_next(1, PatrolDir::Reverse, 3));
+    assert_eq!((1, PatrolDir::Forward), patrol_next(0, PatrolDir::Reverse, 3));
+
+    // a single waypoint is guarded in place
+    assert_eq!((0, PatrolDir::Reverse), patrol_next(0, PatrolDir::Forward, 1));
+    assert_eq!((0, PatrolDir::Forward), patrol_next(0, PatrolDir::Reverse, 1));
+}
+
+#[test]
+pub fn test_light_sources() {
+    let mut config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+    let radius = game.data.fov_radius(player_id, &config);
+
+    // a carried lantern only lets the player see further when configured to
+    let lantern = make_lantern(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    game.data.entities.inventory[&player_id].push_back(lantern);
+    assert_eq!(radius, game.data.fov_radius(player_id, &config));
+    config.carried_light_fov = true;
+    assert_eq!(radius + roguelike_core::constants::LANTERN_ILLUMINATE_RADIUS as i32, game.data.fov_radius(player_id, &config));
+    game.data.entities.remove_item(player_id, lantern);
+    assert_eq!(radius, game.data.fov_radius(player_id, &config));
+
+    // a lantern on the ground lights tiles the player can't otherwise see
+    let lit_pos = Pos::new(radius + 2, 0);
+    assert!(!game.data.pos_in_fov(player_id, lit_pos, &config));
+    game.data.entities.pos[&lantern] = Pos::new(radius + 3, 0);
+    assert!(game.data.pos_in_fov(player_id, lit_pos, &config));
+    assert!(!game.data.pos_in_direct_fov(player_id, lit_pos, &config));
+    assert!(game.data.pos_in_direct_fov(player_id, Pos::new(1, 0), &config));
+}
+
+#[test]
+pub fn test_trigger_freeze_trap() {
+    let mut config = Config::from_file("../config.yaml");
+    config.reusable_traps = false;
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+    let trap = make_freeze_trap(&mut game.data.entities, &config, Pos::new(1, 0), &mut game.msg_log);
+
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert_eq!(Pos::new(1, 0), game.data.entities.pos[&player_id]);
+    assert!(game.data.entities.has_status(player_id, StatusKind::Frozen));
+    assert!(game.msg_log.turn_messages.contains(&Msg::FreezeTrapTriggered(trap, player_id)));
+
+    // the trap is used up
+    assert!(game.data.entities.needs_removal[&trap]);
+
+    // reusable traps stay armed after triggering
+    config.reusable_traps = true;
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 0);
+    let trap = make_spike_trap(&mut game.data.entities, &config, Pos::new(1, 0), &mut game.msg_log);
+
+    game.step_game(InputAction::Move(Direction::Right, MoveMode::Walk), 0.1);
+    assert!(game.msg_log.turn_messages.contains(&Msg::SpikeTrapTriggered(trap, player_id)));
+    assert!(!game.data.entities.needs_removal[&trap]);
+    assert_eq!(Some(&true), game.data.entities.armed.get(&trap));
+}
+
+#[test]
+pub fn test_knight_jumps_over_walls() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(9, 9);
+    let pawn = make_pawn(&mut game.data.entities, &config, Pos::new(2, 2), &mut game.msg_log);
+    game.data.entities.movement[&pawn] = Reach::Knight;
+
+    // surround the jump with walls and a blocking entity that a line would hit
+    game.data.map[(3, 2)] = Tile::wall();
+    game.data.map[(3, 3)] = Tile::wall();
+    game.data.map[(4, 2)].left_wall = Wall::ShortWall;
+    make_column(&mut game.data.entities, &config, Pos::new(2, 3), &mut game.msg_log);
+
+    let movement = calculate_move(Direction::Right, Reach::Knight, pawn, &game.data, &game.config).unwrap();
+    assert_eq!(Pos::new(4, 3), movement.pos);
+
+    // the landing tile must be open
+    game.data.map[(4, 3)] = Tile::wall();
+    assert!(calculate_move(Direction::Right, Reach::Knight, pawn, &game.data, &game.config).is_none());
+
+    // paths are found through jumps as well
+    let path = game.data.path_between(Pos::new(2, 2), Pos::new(6, 4), Reach::Knight, true, false, None);
+    assert_eq!(Some(&Pos::new(6, 4)), path.last());
+}
+
+#[test]
+pub fn test_monster_speed() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(0, 9);
+
+    let slow = make_gol(&mut game.data.entities, &config, Pos::new(1, 1), &mut game.msg_log);
+    game.data.entities.speed.insert(slow, NORMAL_SPEED / 2);
+    game.data.entities.patrol.insert(slow, vec!(Pos::new(1, 1), Pos::new(9, 1)));
+    game.data.entities.behavior[&slow] = Behavior::Patrolling(1, PatrolDir::Forward);
+
+    let fast = make_gol(&mut game.data.entities, &config, Pos::new(1, 3), &mut game.msg_log);
+    game.data.entities.speed.insert(fast, NORMAL_SPEED * 2);
+    game.data.entities.patrol.insert(fast, vec!(Pos::new(1, 3), Pos::new(9, 3)));
+    game.data.entities.behavior[&fast] = Behavior::Patrolling(1, PatrolDir::Forward);
+
+    let mut slow_positions = Vec::new();
+    let mut fast_positions = Vec::new();
+    for _ in 0..4 {
+        // keep the gols from noticing the player
+        game.data.entities.direction[&slow] = Direction::Up;
+        game.data.entities.direction[&fast] = Direction::Up;
+        game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+        slow_positions.push(game.data.entities.pos[&slow].x);
+        fast_positions.push(game.data.entities.pos[&fast].x);
+    }
+
+    // the slow gol moves every other turn, and the fast one moves twice a turn
+    assert_eq!(vec!(1, 2, 2, 3), slow_positions);
+    assert_eq!(vec!(3, 5, 7, 9), fast_positions);
+}
+
+#[test]
+pub fn test_inventory_categories() {
+    let mut config = Config::from_file("../config.yaml");
+    config.inventory_page_size = 2;
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let key = make_key(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    let stone = make_stone(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    let dagger = make_dagger(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    let other_stone = make_stone(&mut game.data.entities, &config, Pos::new(-1, -1), &mut game.msg_log);
+    game.data.entities.pick_up_item(player_id, key);
+    game.data.entities.pick_up_item(player_id, stone);
+    game.data.entities.pick_up_item(player_id, dagger);
+    game.data.entities.pick_up_item(player_id, other_stone);
+
+    // items are kept grouped by category
+    let inventory = game.data.entities.inventory[&player_id].iter().map(|id| *id).collect::<Vec<EntityId>>();
+    assert_eq!(vec!(dagger, stone, other_stone, key), inventory);
+
+    // the quick slots use the first item of each category
+    assert_eq!(Some(dagger), game.data.entities.item_in_slot(player_id, 0, None));
+    assert_eq!(Some(stone), game.data.entities.item_in_slot(player_id, 1, None));
+    assert_eq!(Some(key), game.data.entities.item_in_slot(player_id, 2, None));
+
+    let time = std::time::Instant::now();
+    let press = |game: &mut Game, chr: char| {
+        game.input.handle_event(&mut game.settings, InputEvent::Char(chr, KeyDir::Down), time, &config);
+        let action = game.input.handle_event(&mut game.settings, InputEvent::Char(chr, KeyDir::Up), time, &config);
+        game.step_game(action, 0.1);
+    };
+
+    // the menu pages through the inventory with the page keys, and selecting an item
+    // uses it for its category's slot
+    press(&mut game, 'i');
+    assert_eq!(GameState::Inventory, game.settings.state);
+    press(&mut game, '.');
+    assert_eq!(1, game.settings.inventory_page);
+    press(&mut game, '.');
+    assert_eq!(1, game.settings.inventory_page);
+    press(&mut game, ',');
+    assert_eq!(0, game.settings.inventory_page);
+    press(&mut game, '.');
+    press(&mut game, '0');
+
+    assert_eq!(GameState::Playing, game.settings.state);
+    assert_eq!(Some(other_stone), game.settings.selected_item);
+    assert_eq!(Some(other_stone), game.data.entities.item_in_slot(player_id, 1, game.settings.selected_item));
+    assert_eq!(Some(dagger), game.data.entities.item_in_slot(player_id, 0, game.settings.selected_item));
+}
+
+#[test]
+pub fn test_knockback_attack() {
+    let mut config = Config::from_file("../config.yaml");
+    config.knockback_attacks = true;
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(4, 4);
+
+    let gol = make_gol(&mut game.data.entities, &config, Pos::new(5, 4), &mut game.msg_log);
+    game.data.entities.apply_status(gol, StatusKind::Frozen, 10);
+    game.data.entities.fighter[&gol].hp = 100;
+
+    // an attack with momentum knocks the target back, using up the momentum
+    game.data.entities.status[&player_id].momentum = 1;
+    game.msg_log.log(Msg::TryAttack(player_id, Attack::Attack(gol), Pos::new(5, 4)));
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_eq!(Pos::new(6, 4), game.data.entities.pos[&gol]);
+    assert_eq!(Pos::new(4, 4), game.data.entities.pos[&player_id]);
+    assert!(game.msg_log.turn_messages.iter().any(|msg| matches!(msg, Msg::Knockback(_, _, _))));
+
+    // without momentum the target stays put
+    game.data.entities.pos[&player_id] = Pos::new(5, 4);
+    game.msg_log.log(Msg::TryAttack(player_id, Attack::Attack(gol), Pos::new(6, 4)));
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_eq!(Pos::new(6, 4), game.data.entities.pos[&gol]);
+
+    // knocked into another entity, the target stops against it rather than being crushed
+    let pawn = make_pawn(&mut game.data.entities, &config, Pos::new(7, 4), &mut game.msg_log);
+    game.data.entities.apply_status(pawn, StatusKind::Frozen, 10);
+    game.data.entities.status[&player_id].momentum = 1;
+    game.msg_log.log(Msg::TryAttack(player_id, Attack::Attack(gol), Pos::new(6, 4)));
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert_eq!(Pos::new(6, 4), game.data.entities.pos[&gol]);
+    assert!(!game.data.entities.is_dead(gol));
+    assert!(!game.data.entities.is_dead(pawn));
+    assert_eq!(Pos::new(7, 4), game.data.entities.pos[&pawn]);
+    game.data.entities.mark_for_removal(pawn);
+
+    // knocked into a wall, the target is crushed, even when standing in grass
+    game.data.map[(7, 4)] = Tile::wall();
+    game.data.map[(6, 4)].surface = Surface::Grass;
+    game.data.entities.status[&player_id].momentum = 1;
+    game.msg_log.log(Msg::TryAttack(player_id, Attack::Attack(gol), Pos::new(6, 4)));
+    game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    assert!(game.data.entities.is_dead(gol));
+    assert_eq!(Surface::Rubble, game.data.map[(6, 4)].surface);
+}
+
+#[test]
+pub fn test_yell_alerts_monsters() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = Pos::new(2, 4);
+    game.data.entities.pos[&player_id] = player_pos;
+
+    // the gol faces away from the player, so it can only hear them
+    let gol = make_gol(&mut game.data.entities, &config, Pos::new(5, 4), &mut game.msg_log);
+    game.data.entities.direction[&gol] = Direction::Right;
+    assert_eq!(Behavior::Idle, game.data.entities.behavior[&gol]);
+
+    // hearing the yell, the gol turns to investigate and then sees the player
+    game.step_game(InputAction::Yell, 0.1);
+    assert!(game.msg_log.turn_messages.contains(&Msg::StateChange(gol, Behavior::Investigating(player_pos))));
+    assert_ne!(Behavior::Idle, game.data.entities.behavior[&gol]);
+    assert_eq!(1, game.settings.turn_count);
+
+    // a gol outside the yell's radius is not alerted
+    let far_gol = make_gol(&mut game.data.entities, &config, Pos::new(9, 9), &mut game.msg_log);
+    game.data.entities.direction[&far_gol] = Direction::Right;
+    game.config.yell_radius = 2;
+    game.step_game(InputAction::Yell, 0.1);
+    assert_eq!(Behavior::Idle, game.data.entities.behavior[&far_gol]);
+}
+
+#[test]
+pub fn test_pack_spreads_out() {
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(5, 5);
+
+    let mut pack = Vec::new();
+    for pos in [Pos::new(1, 5), Pos::new(0, 5), Pos::new(2, 5)] {
+        let gol = make_gol(&mut game.data.entities, &config, pos, &mut game.msg_log);
+        game.data.entities.direction[&gol] = Direction::Right;
+        game.data.entities.behavior[&gol] = Behavior::Attacking(player_id);
+        game.data.entities.pack.insert(gol, pack.first().map_or(gol, |id| *id));
+        pack.push(gol);
+    }
+
+    let solo = make_gol(&mut game.data.entities, &config, Pos::new(9, 5), &mut game.msg_log);
+    game.data.entities.direction[&solo] = Direction::Left;
+    game.data.entities.behavior[&solo] = Behavior::Attacking(player_id);
+
+    // each member of the pack is given its own tile, while the solo gol is left alone
+    ai_coordinate_packs(&mut game.data, &game.config);
+    let targets = pack.iter().map(|id| game.data.entities.pack_target[id]).collect::<Vec<Pos>>();
+    assert!(targets.iter().all(|pos| targets.iter().filter(|other| *other == pos).count() == 1));
+    assert_eq!(None, game.data.entities.pack_target.get(&solo));
+
+    // an armil hunting with the pack has no attack, so it is given no tile
+    let armil = make_armil(&mut game.data.entities, &config, Pos::new(5, 8), &mut game.msg_log);
+    game.data.entities.behavior[&armil] = Behavior::Attacking(player_id);
+    game.data.entities.pack.insert(armil, pack[0]);
+    ai_coordinate_packs(&mut game.data, &game.config);
+    assert_eq!(None, game.data.entities.pack_target.get(&armil));
+    assert_eq!(pack.len(), game.data.entities.pack_target.len());
+    game.data.entities.pack.remove(&armil);
+    game.data.entities.behavior[&armil] = Behavior::Idle;
+
+    // the pack closes in from both sides rather than queueing along one path
+    for _ in 0..3 {
+        game.step_game(InputAction::Pass(MoveMode::Walk), 0.1);
+    }
+    let positions = pack.iter().map(|id| game.data.entities.pos[id]).collect::<Vec<Pos>>();
+    assert!(positions.iter().any(|pos| pos.y < 5), "{:?}", positions);
+    assert!(positions.iter().any(|pos| pos.y > 5), "{:?}", positions);
+
+    // a pack with a single hunter falls back to solo behavior
+    game.data.entities.pack.remove(&pack[1]);
+    game.data.entities.pack.remove(&pack[2]);
+    ai_coordinate_packs(&mut game.data, &game.config);
+    assert_eq!(None, game.data.entities.pack_target.get(&pack[0]));
+}
@@ -5,6 +5,7 @@ use roguelike_core::messaging::*;
 use roguelike_core::map::*;
 use roguelike_core::types::*;
 use roguelike_core::config::*;
+use roguelike_core::ai::Behavior;
 
 use crate::generation::*;
 
@@ -100,7 +101,9 @@ fn test_remove_commas() {
     assert_eq!("% %".to_string(), remove_commas("%,,%".to_string()));
 }
 
-/// Read Vault file into Vault structure
+/// Read Vault file into Vault structure.
+/// A line '::patrol x y x y ...' gives a patrol route in vault coordinates, which is given
+/// to the monster placed on the route's first waypoint.
 pub fn parse_vault(file_name: &str, config: &Config) -> Vault {
     //eprintln!("{}", file_name);
 
@@ -109,10 +112,19 @@ pub fn parse_vault(file_name: &str, config: &Config) -> Vault {
 
     let mut lines = Vec::new();
     let mut tags: Vec<VaultTag> = Vec::new();
+    let mut routes: Vec<Vec<Pos>> = Vec::new();
 
     for line in file_contents.lines() {
         let cleaned_line = remove_commas(line.to_string());
 
+        if cleaned_line.starts_with("::patrol") {
+            match parse_patrol_route(&cleaned_line) {
+                Ok(route) => routes.push(route),
+                Err(err) => eprintln!("{} in vault {}", err, file_name),
+            }
+            continue;
+        }
+
         if cleaned_line.starts_with("::") {
             for tag_str in cleaned_line.split_at(2).1.trim().split(" ") {
                 if tag_str.starts_with("::") {
@@ -127,11 +139,41 @@ pub fn parse_vault(file_name: &str, config: &Config) -> Vault {
         lines.push(char_line);
     }
 
-    let vault = parse_ascii_chars(lines, config);
+    let mut vault = parse_ascii_chars(lines, config);
+
+    for route in routes {
+        add_patrol_route(&mut vault, route);
+    }
 
     return vault;
 }
 
+fn parse_patrol_route(line: &str) -> Result<Vec<Pos>, String> {
+    let coords = line.trim_start_matches("::patrol")
+                     .split_whitespace()
+                     .map(|coord| coord.parse::<i32>().map_err(|_| format!("Could not parse patrol route '{}'", line)))
+                     .collect::<Result<Vec<i32>, String>>()?;
+
+    if coords.len() == 0 || coords.len() % 2 != 0 {
+        return Err(format!("Patrol route '{}' needs x y pairs", line));
+    }
+
+    return Ok(coords.chunks(2).map(|pair| Pos::new(pair[0], pair[1])).collect::<Vec<Pos>>());
+}
+
+fn add_patrol_route(vault: &mut Vault, route: Vec<Pos>) {
+    let guard = vault.data.get_entities_at_pos(route[0])
+                          .into_iter()
+                          .find(|id| vault.data.entities.ai.get(id).is_some());
+
+    if let Some(guard_id) = guard {
+        vault.data.entities.patrol.insert(guard_id, route);
+        vault.data.entities.behavior[&guard_id] = Behavior::Patrolling(0, PatrolDir::Forward);
+    } else {
+        eprintln!("No monster at the start of patrol route {}", route[0]);
+    }
+}
+
 fn parse_ascii_chars(lines: Vec<Vec<char>>, config: &Config) -> Vault {
     let height = lines.len() / 2;
     let width = (lines[0].len() - 1) / 2;
@@ -232,3 +274,25 @@ fn tile_from_ascii(tile_chr: char, left_wall: char, bottom_wall: char, pos: Pos,
     return tile;
 }
 
+
+#[test]
+fn test_parse_vault_patrol() {
+    let config = Config::from_file("../config.yaml");
+
+    let path = std::env::temp_dir().join("test_parse_vault_patrol.csv");
+    std::fs::write(&path, " . g . . \n         \n::patrol 1 0 3 0\n").unwrap();
+    let vault = parse_vault(path.to_str().unwrap(), &config);
+
+    let gol = vault.data.find_by_name(EntityName::Gol).unwrap();
+    assert_eq!(Some(&vec!(Pos::new(1, 0), Pos::new(3, 0))), vault.data.entities.patrol.get(&gol));
+    assert_eq!(Behavior::Patrolling(0, PatrolDir::Forward), vault.data.entities.behavior[&gol]);
+
+    assert!(parse_patrol_route("::patrol 1 0 3").is_err());
+    assert!(parse_patrol_route("::patrol a b").is_err());
+
+    // a bad route is skipped rather than failing to load the vault
+    std::fs::write(&path, " . g . . \n         \n::patrol 1 0 3\n").unwrap();
+    let vault = parse_vault(path.to_str().unwrap(), &config);
+    let gol = vault.data.find_by_name(EntityName::Gol).unwrap();
+    assert_eq!(None, vault.data.entities.patrol.get(&gol));
+}
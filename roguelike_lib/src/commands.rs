@@ -1,5 +1,9 @@
 use std::str::FromStr;
+use std::fmt;
 use std::time::Instant;
+use std::collections::BTreeMap;
+
+use serde::Serialize;
 
 use roguelike_core::types::*;
 use roguelike_core::movement::*;
@@ -9,6 +13,8 @@ use roguelike_core::messaging::*;
 use roguelike_engine::input::*;
 use roguelike_engine::game::*;
 use roguelike_engine::generation::*;
+use roguelike_engine::actions::InputAction;
+use roguelike_engine::log::{Log, MAP_CONFIG_NAME};
 
 
 // TODO
@@ -50,119 +56,265 @@ pub enum GameCmd {
     Remove(u64),
     Kill(u64),
     Give(Item),
+    Teleport(i32, i32),
     ListEntities,
     ListEntitiesPos(i32, i32),
+    Entities,
     Key(char, KeyDir),
     Ctrl(KeyDir),
     Alt(KeyDir),
     Shift(KeyDir),
+    ReplayInfo(String),
+    ExportFov(String),
+    LayoutCode,
+    LoadLayout(String),
+    State,
+    Help,
     Exit,
 }
 
+/// The syntax of each command, used to check argument counts and to
+/// describe commands in error messages and 'help'. Bracketed arguments
+/// are optional.
+pub const COMMAND_SYNTAX: &[(&str, &str)] = &[
+    ("player_id", "player_id"),
+    ("pos", "pos id"),
+    ("set_pos", "set_pos id x y"),
+    ("hp", "hp id [hp]"),
+    ("facing", "facing id [direction]"),
+    ("map_size", "map_size"),
+    ("tile_walls", "tile_walls x y"),
+    ("set_tile_walls", "set_tile_walls x y type left bottom"),
+    ("surface", "surface x y"),
+    ("set_surface", "set_surface x y surface"),
+    ("entity_name", "entity_name id"),
+    ("entity_type", "entity_type id"),
+    ("make", "make name x y"),
+    ("remove", "remove id"),
+    ("kill", "kill id"),
+    ("give", "give item"),
+    ("teleport", "teleport x y"),
+    ("ids", "ids"),
+    ("ids_pos", "ids_pos x y"),
+    ("entities", "entities"),
+    ("key", "key chr dir"),
+    ("ctrl", "ctrl dir"),
+    ("alt", "alt dir"),
+    ("shift", "shift dir"),
+    ("replay_info", "replay_info path"),
+    ("export_fov", "export_fov path"),
+    ("layout_code", "layout_code"),
+    ("load_layout", "load_layout code"),
+    ("state", "state"),
+    ("help", "help"),
+    ("exit", "exit"),
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CmdParseError {
+    UnknownCommand(String),
+    WrongArgCount(String, usize, usize, usize), // command, min, max, found
+    BadArgument(String, usize, String), // command, argument position, argument
+}
+
+impl fmt::Display for CmdParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CmdParseError::UnknownCommand(cmd) => write!(f, "unknown command '{}'", cmd),
+            CmdParseError::WrongArgCount(cmd, min, max, found) => {
+                if min == max {
+                    write!(f, "'{}' expects {} arguments but got {}", cmd, min, found)
+                } else {
+                    write!(f, "'{}' expects {} to {} arguments but got {}", cmd, min, max, found)
+                }
+            }
+            CmdParseError::BadArgument(cmd, position, arg) => write!(f, "argument {} of '{}' is invalid: '{}'", position, cmd, arg),
+        }
+    }
+}
+
+pub fn command_syntax(cmd: &str) -> Option<&'static str> {
+    return COMMAND_SYNTAX.iter().find(|(name, _)| *name == cmd).map(|(_, syntax)| *syntax);
+}
+
+/// The command whose name is the fewest edits away from the given name.
+pub fn closest_command(cmd: &str) -> &'static str {
+    return COMMAND_SYNTAX.iter()
+                         .map(|(name, _)| *name)
+                         .min_by_key(|name| edit_distance(cmd, name))
+                         .unwrap();
+}
+
+fn edit_distance(first: &str, second: &str) -> usize {
+    let second = second.chars().collect::<Vec<char>>();
+    let mut prev_row = (0..=second.len()).collect::<Vec<usize>>();
+
+    for (first_index, first_chr) in first.chars().enumerate() {
+        let mut row = vec![first_index + 1];
+        for (second_index, second_chr) in second.iter().enumerate() {
+            let substitute = prev_row[second_index] + if first_chr == *second_chr { 0 } else { 1 };
+            let insert = row[second_index] + 1;
+            let delete = prev_row[second_index + 1] + 1;
+            row.push(substitute.min(insert).min(delete));
+        }
+        prev_row = row;
+    }
+
+    return prev_row[second.len()];
+}
+
+/// Describe a failed parse of the given line, including the syntax of
+/// the command that was most likely intended.
+pub fn format_parse_error(line: &str, err: &CmdParseError) -> String {
+    if let CmdParseError::UnknownCommand(cmd) = err {
+        let closest = closest_command(cmd);
+        return format!("error '{}': {}, did you mean '{}'? (try 'help')", line, err, command_syntax(closest).unwrap());
+    } else {
+        let cmd = line.split_whitespace().next().unwrap_or("").to_ascii_lowercase();
+        let syntax = command_syntax(&cmd).unwrap_or("");
+        return format!("error '{}': {}, usage '{}'", line, err, syntax);
+    }
+}
+
+/// Parse the argument at the given position, where position 0 is the command itself.
+fn parse_arg<T: FromStr>(args: &[&str], position: usize) -> Result<T, CmdParseError> {
+    return args[position].parse::<T>()
+                         .map_err(|_| CmdParseError::BadArgument(args[0].to_string(), position, args[position].to_string()));
+}
+
 impl FromStr for GameCmd {
-    type Err = String;
+    type Err = CmdParseError;
 
     fn from_str(string: &str) -> Result<Self, Self::Err> {
         let s: &mut str = &mut string.to_string();
         s.make_ascii_lowercase();
 
         // TODO probably next() for cmd and have only arguments in args
-        let args = s.split(" ").collect::<Vec<&str>>();
-        let cmd = args[0];
+        let args = s.split_whitespace().collect::<Vec<&str>>();
+        let cmd = args.get(0).map(|cmd| *cmd).unwrap_or("");
+
+        let syntax = command_syntax(cmd).ok_or(CmdParseError::UnknownCommand(cmd.to_string()))?;
+        let max_args = syntax.split(" ").count() - 1;
+        let min_args = max_args - syntax.matches('[').count();
+        let num_args = args.len() - 1;
+        if num_args < min_args || num_args > max_args {
+            return Err(CmdParseError::WrongArgCount(cmd.to_string(), min_args, max_args, num_args));
+        }
 
         if cmd == "player_id" {
             return Ok(GameCmd::PlayerId);
         } else if cmd == "pos" {
-            let id = args[1].parse::<u64>().unwrap();
+            let id = parse_arg::<u64>(&args, 1)?;
             return Ok(GameCmd::Pos(id));
         } else if cmd == "set_pos" {
-            let id = args[1].parse::<u64>().unwrap();
-            let x  = args[2].parse::<i32>().unwrap();
-            let y  = args[3].parse::<i32>().unwrap();
+            let id = parse_arg::<u64>(&args, 1)?;
+            let x  = parse_arg::<i32>(&args, 2)?;
+            let y  = parse_arg::<i32>(&args, 3)?;
             return Ok(GameCmd::SetPos(id, x, y));
         } else if cmd == "hp" {
-            let id = args[1].parse::<u64>().unwrap();
+            let id = parse_arg::<u64>(&args, 1)?;
             if args.len() == 2 {
                 return Ok(GameCmd::Hp(id));
             } else {
-                let hp  = args[2].parse::<i32>().unwrap();
+                let hp  = parse_arg::<i32>(&args, 2)?;
                 return Ok(GameCmd::SetHp(id, hp));
             }
         } else if cmd == "facing" {
-            let id = args[1].parse::<u64>().unwrap();
+            let id = parse_arg::<u64>(&args, 1)?;
             if args.len() == 2 {
                 return Ok(GameCmd::Facing(id));
             } else {
-                let dir  = args[2].parse::<Direction>().unwrap();
+                let dir  = parse_arg::<Direction>(&args, 2)?;
                 return Ok(GameCmd::SetFacing(id, dir));
             }
         } else if cmd == "map_size" {
             return Ok(GameCmd::MapSize);
         } else if cmd == "tile_walls" {
-            let x  = args[1].parse::<i32>().unwrap();
-            let y  = args[2].parse::<i32>().unwrap();
+            let x  = parse_arg::<i32>(&args, 1)?;
+            let y  = parse_arg::<i32>(&args, 2)?;
             return Ok(GameCmd::TileWalls(x, y));
         } else if cmd == "set_tile_walls" {
-            let x       = args[1].parse::<i32>().unwrap();
-            let y       = args[2].parse::<i32>().unwrap();
-            let typ     = args[3].parse::<TileType>().unwrap();
-            let left    = args[4].parse::<Wall>().unwrap();
-            let bottom  = args[5].parse::<Wall>().unwrap();
+            let x       = parse_arg::<i32>(&args, 1)?;
+            let y       = parse_arg::<i32>(&args, 2)?;
+            let typ     = parse_arg::<TileType>(&args, 3)?;
+            let left    = parse_arg::<Wall>(&args, 4)?;
+            let bottom  = parse_arg::<Wall>(&args, 5)?;
             return Ok(GameCmd::SetTileWalls(x, y, typ, left, bottom));
         } else if cmd == "surface" {
-            let x  = args[1].parse::<i32>().unwrap();
-            let y  = args[2].parse::<i32>().unwrap();
+            let x  = parse_arg::<i32>(&args, 1)?;
+            let y  = parse_arg::<i32>(&args, 2)?;
             return Ok(GameCmd::Surface(x, y));
         } else if cmd == "set_surface" {
-            let x  = args[1].parse::<i32>().unwrap();
-            let y  = args[2].parse::<i32>().unwrap();
-            let surface  = args[3].parse::<Surface>().unwrap();
+            let x  = parse_arg::<i32>(&args, 1)?;
+            let y  = parse_arg::<i32>(&args, 2)?;
+            let surface  = parse_arg::<Surface>(&args, 3)?;
             return Ok(GameCmd::SetSurface(x, y, surface));
         } else if cmd == "entity_name" {
-            let id = args[1].parse::<u64>().unwrap();
+            let id = parse_arg::<u64>(&args, 1)?;
             return Ok(GameCmd::EntityName(id));
         } else if cmd == "entity_type" {
-            let id = args[1].parse::<u64>().unwrap();
+            let id = parse_arg::<u64>(&args, 1)?;
             return Ok(GameCmd::EntityType(id));
         } else if cmd == "make" {
-            let entity_name = args[1].parse::<EntityName>().unwrap();
-            let x  = args[2].parse::<i32>().unwrap();
-            let y  = args[3].parse::<i32>().unwrap();
+            let entity_name = parse_arg::<EntityName>(&args, 1)?;
+            let x  = parse_arg::<i32>(&args, 2)?;
+            let y  = parse_arg::<i32>(&args, 3)?;
 
             return Ok(GameCmd::Make(entity_name, x, y));
         } else if cmd == "remove" {
-            let id = args[1].parse::<u64>().unwrap();
+            let id = parse_arg::<u64>(&args, 1)?;
             return Ok(GameCmd::Remove(id));
         } else if cmd == "kill" {
-            let id = args[1].parse::<u64>().unwrap();
+            let id = parse_arg::<u64>(&args, 1)?;
             return Ok(GameCmd::Kill(id));
         } else if cmd == "give" {
-            let item = args[1].parse::<Item>().unwrap();
+            let item = parse_arg::<Item>(&args, 1)?;
             return Ok(GameCmd::Give(item));
+        } else if cmd == "teleport" {
+            let x = parse_arg::<i32>(&args, 1)?;
+            let y = parse_arg::<i32>(&args, 2)?;
+            return Ok(GameCmd::Teleport(x, y));
         } else if cmd == "ids" {
             return Ok(GameCmd::ListEntities);
         } else if cmd == "ids_pos" {
-            let x  = args[1].parse::<i32>().unwrap();
-            let y  = args[2].parse::<i32>().unwrap();
+            let x  = parse_arg::<i32>(&args, 1)?;
+            let y  = parse_arg::<i32>(&args, 2)?;
             return Ok(GameCmd::ListEntitiesPos(x, y));
+        } else if cmd == "entities" {
+            return Ok(GameCmd::Entities);
         } else if cmd == "key" {
-            let chr = args[1].parse::<char>().unwrap();
-            let dir = args[2].parse::<KeyDir>().unwrap();
+            let chr = parse_arg::<char>(&args, 1)?;
+            let dir = parse_arg::<KeyDir>(&args, 2)?;
             return Ok(GameCmd::Key(chr, dir));
         } else if cmd == "ctrl" {
-            let dir = args[1].parse::<KeyDir>().unwrap();
+            let dir = parse_arg::<KeyDir>(&args, 1)?;
             return Ok(GameCmd::Ctrl(dir));
         } else if cmd == "alt" {
-            let dir = args[1].parse::<KeyDir>().unwrap();
+            let dir = parse_arg::<KeyDir>(&args, 1)?;
             return Ok(GameCmd::Alt(dir));
         } else if cmd == "shift" {
-            let dir = args[1].parse::<KeyDir>().unwrap();
+            let dir = parse_arg::<KeyDir>(&args, 1)?;
             return Ok(GameCmd::Shift(dir));
+        } else if cmd == "replay_info" {
+            // use the original string so the path's case is kept
+            let path = string.split_whitespace().nth(1).unwrap();
+            return Ok(GameCmd::ReplayInfo(path.to_string()));
+        } else if cmd == "export_fov" {
+            let path = string.split_whitespace().nth(1).unwrap();
+            return Ok(GameCmd::ExportFov(path.to_string()));
+        } else if cmd == "layout_code" {
+            return Ok(GameCmd::LayoutCode);
+        } else if cmd == "load_layout" {
+            return Ok(GameCmd::LoadLayout(args[1].to_string()));
+        } else if cmd == "state" {
+            return Ok(GameCmd::State);
+        } else if cmd == "help" {
+            return Ok(GameCmd::Help);
         } else if cmd == "exit" {
             return Ok(GameCmd::Exit);
         }
 
-        return Err("OUTPUT: error '{}' not expected!".to_string());
+        return Err(CmdParseError::UnknownCommand(cmd.to_string()));
     }
 }
 
@@ -202,10 +354,14 @@ impl GameCmd {
             return "kill";
         } else if matches!(self, GameCmd::Give(_)) {
             return "give";
+        } else if matches!(self, GameCmd::Teleport(_, _)) {
+            return "teleport";
         } else if matches!(self, GameCmd::ListEntities) {
             return "ids";
         } else if matches!(self, GameCmd::ListEntitiesPos(_, _)) {
             return "ids_pos";
+        } else if matches!(self, GameCmd::Entities) {
+            return "entities";
         } else if matches!(self, GameCmd::Key(_, _)) {
             return "key";
         } else if matches!(self, GameCmd::Ctrl(_)) {
@@ -214,6 +370,18 @@ impl GameCmd {
             return "alt";
         } else if matches!(self, GameCmd::Shift(_)) {
             return "shift";
+        } else if matches!(self, GameCmd::ReplayInfo(_)) {
+            return "replay_info";
+        } else if matches!(self, GameCmd::ExportFov(_)) {
+            return "export_fov";
+        } else if matches!(self, GameCmd::LayoutCode) {
+            return "layout_code";
+        } else if matches!(self, GameCmd::LoadLayout(_)) {
+            return "load_layout";
+        } else if matches!(self, GameCmd::State) {
+            return "state";
+        } else if matches!(self, GameCmd::Help) {
+            return "help";
         } else if matches!(self, GameCmd::Exit) {
             return "exit";
         } else {
@@ -318,7 +486,20 @@ pub fn execute_game_command(command: &GameCmd, game: &mut Game) -> String {
             let pos = game.data.entities.pos[&player_id];
             let item_id = make_item(&mut game.data.entities, &game.config, *item, pos, &mut game.msg_log);
             game.data.entities.pick_up_item(player_id, item_id);
-            return "".to_string();
+            return format!("{} {} {}", name, item, item_id);
+        }
+
+        GameCmd::Teleport(x, y) => {
+            let pos = Pos::new(*x, *y);
+            if !game.data.map.is_within_bounds(pos) {
+                let (width, height) = game.data.map.size();
+                return format!("{} error ({}, {}) is outside the {}x{} map", name, x, y, width, height);
+            }
+
+            let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+            game.data.entities.set_pos(player_id, pos);
+            game.explore_visible();
+            return format!("{} {} {}", name, x, y);
         }
 
         GameCmd::EntityName(id) => {
@@ -347,6 +528,26 @@ pub fn execute_game_command(command: &GameCmd, game: &mut Game) -> String {
             return format!("{} {}", name, ids);
         }
 
+        GameCmd::Entities => {
+            let mut table = Vec::new();
+            table.push(format!("{} {:<6} {:<10} {:>4} {:>4} {:>4} {:<16} {}", name, "id", "name", "x", "y", "hp", "behavior", "alive"));
+
+            for id in game.data.entities.ids.iter() {
+                let entity_name = game.data.entities.name[id];
+                let pos = game.data.entities.pos[id];
+                let hp = game.data.entities.fighter.get(id)
+                                                   .map_or("-".to_string(), |fighter| fighter.hp.to_string());
+                let behavior = game.data.entities.behavior.get(id)
+                                                          .map_or("-".to_string(), |behavior| behavior.to_string());
+                let alive = game.data.entities.status[id].alive;
+
+                table.push(format!("{} {:<6} {:<10} {:>4} {:>4} {:>4} {:<16} {}",
+                                   name, id, entity_name, pos.x, pos.y, hp, behavior, alive));
+            }
+
+            return table.join("\n");
+        }
+
         GameCmd::Key(chr, dir) => {
             // TODO this isn't really correct...
             let time = Instant::now();
@@ -388,8 +589,410 @@ pub fn execute_game_command(command: &GameCmd, game: &mut Game) -> String {
             return "".to_string();
         }
 
+        GameCmd::ReplayInfo(path) => {
+            match replay_info(path) {
+                Ok(info) => {
+                    let counts = info.action_counts.iter()
+                                     .map(|(action_name, count)| format!("{}:{}", action_name, count))
+                                     .collect::<Vec<String>>()
+                                     .join(" ");
+                    return format!("{} actions {} turns {} map {} counts {}",
+                                   name, info.num_actions, info.num_turns, info.map_config, counts);
+                }
+
+                Err(err) => {
+                    return format!("{} error {}", name, err);
+                }
+            }
+        }
+
+        GameCmd::ExportFov(path) => {
+            let grid = fov_grid(game).join("\n");
+            match std::fs::write(path, grid) {
+                Ok(()) => return format!("{} {}", name, path),
+                Err(err) => return format!("{} error {}", name, err),
+            }
+        }
+
+        GameCmd::LayoutCode => {
+            return format!("{} {}", name, game.layout_code());
+        }
+
+        GameCmd::LoadLayout(code) => {
+            match game.load_layout_code(code) {
+                Ok(()) => return format!("{} {}", name, code),
+                Err(err) => return format!("{} error {}", name, err),
+            }
+        }
+
+        GameCmd::State => {
+            match serde_json::to_string(&state_snapshot(game)) {
+                Ok(json) => return format!("{} {}", name, json),
+                Err(err) => return format!("{} error {}", name, err),
+            }
+        }
+
+        GameCmd::Help => {
+            let syntax = COMMAND_SYNTAX.iter().map(|(_, syntax)| *syntax).collect::<Vec<&str>>().join(", ");
+            return format!("{} {}", name, syntax);
+        }
+
         // let action = game.input.handle_event(&mut game.settings, event, frame_time, &game.config);
         // game.step_game(input_action, dt);
     }
 }
 
+/// The player's FOV for the current turn as rows of characters:
+/// '@' is the player, '#' blocks sight, '.' is visible, '-' is explored
+/// but not visible, and '?' has not been seen.
+pub fn fov_grid(game: &Game) -> Vec<String> {
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = game.data.entities.pos[&player_id];
+
+    let mut rows = Vec::new();
+    for y in 0..game.data.map.height() {
+        let mut row = String::new();
+        for x in 0..game.data.map.width() {
+            let pos = Pos::new(x, y);
+            let tile = game.data.map[pos];
+
+            let chr =
+                if pos == player_pos {
+                    '@'
+                } else if tile.block_sight {
+                    '#'
+                } else if game.data.pos_in_fov(player_id, pos, &game.config) {
+                    '.'
+                } else if tile.explored {
+                    '-'
+                } else {
+                    '?'
+                };
+            row.push(chr);
+        }
+        rows.push(row);
+    }
+
+    return rows;
+}
+
+/// An entity as reported by the 'state' command.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct EntitySnapshot {
+    pub id: EntityId,
+    pub name: String,
+    pub typ: String,
+    pub x: i32,
+    pub y: i32,
+    pub hp: Option<i32>,
+    pub max_hp: Option<i32>,
+    pub behavior: Option<String>,
+    pub alive: bool,
+}
+
+/// The game state as reported by the 'state' command. Only the tiles the player
+/// can see are included, so the snapshot stays small on large maps.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct StateSnapshot {
+    pub turn: usize,
+    pub player_id: EntityId,
+    pub entities: Vec<EntitySnapshot>,
+    pub visible: Vec<(i32, i32)>,
+}
+
+pub fn state_snapshot(game: &Game) -> StateSnapshot {
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+
+    let mut entities = Vec::new();
+    for id in game.data.entities.ids.iter() {
+        let pos = game.data.entities.pos[id];
+        let fighter = game.data.entities.fighter.get(id);
+        entities.push(EntitySnapshot {
+            id: *id,
+            name: game.data.entities.name[id].to_string(),
+            typ: game.data.entities.typ[id].to_string(),
+            x: pos.x,
+            y: pos.y,
+            hp: fighter.map(|fighter| fighter.hp),
+            max_hp: fighter.map(|fighter| fighter.max_hp),
+            behavior: game.data.entities.behavior.get(id).map(|behavior| behavior.to_string()),
+            alive: game.data.entities.status[id].alive,
+        });
+    }
+
+    let visible = game.data.map.get_all_pos()
+                               .iter()
+                               .filter(|pos| game.data.pos_in_fov(player_id, **pos, &game.config))
+                               .map(|pos| (pos.x, pos.y))
+                               .collect::<Vec<(i32, i32)>>();
+
+    return StateSnapshot { turn: game.settings.turn_count, player_id, entities, visible };
+}
+
+/// Summary of a recorded game, used to triage recordings without replaying them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayInfo {
+    pub num_actions: usize,
+    pub num_turns: usize,
+    pub map_config: String,
+    pub action_counts: BTreeMap<String, usize>,
+}
+
+/// Summarize the recording in the given directory, which contains an action log,
+/// a message log, and the map config used for the recording.
+pub fn replay_info(path: &str) -> Result<ReplayInfo, String> {
+    let action_path = format!("{}/{}", path, Log::ACTION_LOG_NAME);
+    let action_string = std::fs::read_to_string(&action_path)
+                                 .map_err(|err| format!("Could not read '{}': {}", action_path, err))?;
+
+    let mut num_actions = 0;
+    let mut action_counts = BTreeMap::new();
+    for line in action_string.lines() {
+        if let Ok(action) = InputAction::from_str(line) {
+            // the action type is its variant name, without any arguments
+            let debug_name = format!("{:?}", action);
+            let action_name = debug_name.split('(').next().unwrap().to_string();
+
+            *action_counts.entry(action_name).or_insert(0) += 1;
+            num_actions += 1;
+        }
+    }
+
+    // each turn the player takes is recorded in the message log
+    let message_path = format!("{}/{}", path, Log::MESSAGE_LOG_NAME);
+    let num_turns = std::fs::read_to_string(&message_path)
+                             .map(|messages| messages.lines().filter(|line| *line == "MSG: player_turn").count())
+                             .unwrap_or(0);
+
+    let map_config_path = format!("{}/{}", path, MAP_CONFIG_NAME);
+    let map_config = std::fs::read_to_string(&map_config_path)
+                              .map(|map_config| map_config.trim().to_string())
+                              .unwrap_or("unknown".to_string());
+
+    return Ok(ReplayInfo { num_actions, num_turns, map_config, action_counts });
+}
+
+#[test]
+pub fn test_replay_info() {
+    let info = replay_info("../resources/test_logs/short_wall").unwrap();
+
+    // the final exit is recorded as an action, but does not take a turn
+    assert_eq!(15, info.num_actions);
+    assert_eq!(14, info.num_turns);
+    assert_eq!("test_wall", info.map_config);
+    assert_eq!(Some(&11), info.action_counts.get("Move"));
+    assert_eq!(Some(&3), info.action_counts.get("Pass"));
+    assert_eq!(Some(&1), info.action_counts.get("Exit"));
+
+    // the command parses a path without changing its case
+    let cmd = "replay_info ../resources/test_logs/short_wall".parse::<GameCmd>().unwrap();
+    assert!(matches!(cmd, GameCmd::ReplayInfo(ref path) if path == "../resources/test_logs/short_wall"));
+
+    assert!(replay_info("../resources/test_logs/missing").is_err());
+}
+
+
+#[test]
+pub fn test_entities_table() {
+    use roguelike_core::config::Config;
+    use roguelike_engine::make_map::make_map;
+
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let gol1 = make_gol(&mut game.data.entities, &config, Pos::new(3, 4), &mut game.msg_log);
+    let gol2 = make_gol(&mut game.data.entities, &config, Pos::new(7, 2), &mut game.msg_log);
+
+    let cmd = "entities".parse::<GameCmd>().unwrap();
+    let output = execute_game_command(&cmd, &mut game);
+    let lines = output.lines().collect::<Vec<&str>>();
+
+    // a header and a row per entity
+    assert_eq!(game.data.entities.ids.len() + 1, lines.len());
+
+    let row = |id: EntityId| lines.iter().find(|line| line.split_whitespace().nth(1) == Some(&id.to_string())).unwrap().to_string();
+
+    let player_row = row(player_id).split_whitespace().map(|s| s.to_string()).collect::<Vec<String>>();
+    assert_eq!("player", player_row[2]);
+    assert_eq!("0", player_row[3]);
+    assert_eq!("0", player_row[4]);
+
+    for (gol, x, y) in [(gol1, 3, 4), (gol2, 7, 2)].iter() {
+        let gol_row = row(*gol).split_whitespace().map(|s| s.to_string()).collect::<Vec<String>>();
+        assert_eq!("gol", gol_row[2]);
+        assert_eq!(x.to_string(), gol_row[3]);
+        assert_eq!(y.to_string(), gol_row[4]);
+        assert_eq!("idle", gol_row[6]);
+        assert_eq!("true", gol_row[7]);
+    }
+}
+
+#[test]
+pub fn test_export_fov() {
+    use roguelike_core::config::Config;
+    use roguelike_engine::make_map::make_map;
+
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    game.data.entities.pos[&player_id] = Pos::new(1, 5);
+    game.data.map[(3, 5)] = Tile::wall();
+
+    let path = std::env::temp_dir().join("test_export_fov.txt");
+    let cmd = format!("export_fov {}", path.display()).parse::<GameCmd>().unwrap();
+    execute_game_command(&cmd, &mut game);
+
+    let grid = std::fs::read_to_string(&path).unwrap();
+    let rows = grid.lines().map(|row| row.chars().collect::<Vec<char>>()).collect::<Vec<Vec<char>>>();
+    assert_eq!('@', rows[5][1]);
+    assert_eq!('.', rows[5][2]);
+    assert_eq!('#', rows[5][3]);
+
+    // the pillar casts a shadow behind it
+    assert_ne!('.', rows[5][4]);
+    assert_ne!('.', rows[5][5]);
+}
+
+#[test]
+pub fn test_layout_code() {
+    use roguelike_core::config::Config;
+    use roguelike_engine::make_map::make_map;
+
+    // the cellular generator draws from the rng, so the code has to restore its state
+    let map_config = "cellular 0.45 4".parse::<MapLoadConfig>().unwrap();
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = map_config.clone();
+
+    let mut game = Game::new(1, config.clone());
+    make_map(&map_config, &mut game);
+    let map = game.data.map.clone();
+    let rng_state = game.rng.state();
+
+    let output = execute_game_command(&GameCmd::LayoutCode, &mut game);
+    let code = output.split_whitespace().nth(1).unwrap().to_string();
+
+    let mut other = Game::new(2, config.clone());
+    other.rng.rand_u32();
+    let cmd = format!("load_layout {}", code).parse::<GameCmd>().unwrap();
+    let output = execute_game_command(&cmd, &mut other);
+    assert_eq!(format!("load_layout {}", code), output);
+
+    // the level is regenerated from the same rng state, so it is the same level
+    assert_eq!(rng_state, other.rng.state());
+    assert_eq!(code, other.layout_code());
+    assert_eq!(map.tiles, other.data.map.tiles);
+
+    // a different rng state gives a different level
+    let mut reseeded = Game::new(3, config.clone());
+    make_map(&map_config, &mut reseeded);
+    assert_ne!(map.tiles, reseeded.data.map.tiles);
+
+    // settings that don't change generation don't change the code
+    config.frame_rate += 1;
+    let mut display_change = Game::new(1, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut display_change);
+    assert!(display_change.load_layout_code(&code).is_ok());
+
+    // codes only load with the generation settings they were made with
+    config.spawn_budget.push(10);
+    let mut different = Game::new(1, config);
+    make_map(&MapLoadConfig::Empty, &mut different);
+    assert!(different.load_layout_code(&code).is_err());
+    assert!(different.load_layout_code("not a code").is_err());
+}
+
+#[test]
+pub fn test_give_and_teleport() {
+    use roguelike_core::config::Config;
+    use roguelike_engine::make_map::make_map;
+
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+
+    let cmd = "give stone".parse::<GameCmd>().unwrap();
+    let output = execute_game_command(&cmd, &mut game);
+    assert!(output.starts_with("give stone"));
+    assert!(game.data.is_in_inventory(player_id, Item::Stone).is_some());
+
+    assert!("give teapot".parse::<GameCmd>().is_err());
+    assert!("give".parse::<GameCmd>().is_err());
+
+    let cmd = "teleport 7 8".parse::<GameCmd>().unwrap();
+    assert_eq!("teleport 7 8", execute_game_command(&cmd, &mut game));
+    assert_eq!(Pos::new(7, 8), game.data.entities.pos[&player_id]);
+    assert!(game.data.map[(7, 8)].explored);
+
+    // out of bounds positions leave the player where they are
+    let cmd = "teleport 20 3".parse::<GameCmd>().unwrap();
+    assert!(execute_game_command(&cmd, &mut game).starts_with("teleport error"));
+    assert_eq!(Pos::new(7, 8), game.data.entities.pos[&player_id]);
+
+    assert!("teleport 1".parse::<GameCmd>().is_err());
+    assert!("teleport a 1".parse::<GameCmd>().is_err());
+}
+
+#[test]
+pub fn test_state_snapshot() {
+    use roguelike_core::config::Config;
+    use roguelike_engine::make_map::make_map;
+
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let gol = make_gol(&mut game.data.entities, &config, Pos::new(3, 4), &mut game.msg_log);
+
+    let snapshot = state_snapshot(&game);
+    assert_eq!(player_id, snapshot.player_id);
+    assert_eq!(game.data.entities.ids.len(), snapshot.entities.len());
+
+    let gol_snapshot = snapshot.entities.iter().find(|entity| entity.id == gol).unwrap();
+    assert_eq!("gol", gol_snapshot.name);
+    assert_eq!((3, 4), (gol_snapshot.x, gol_snapshot.y));
+    assert_eq!(Some(game.data.entities.fighter[&gol].hp), gol_snapshot.hp);
+    assert_eq!(Some("idle".to_string()), gol_snapshot.behavior);
+
+    // only visible tiles are reported
+    assert!(snapshot.visible.contains(&(0, 0)));
+    assert!(snapshot.visible.iter().all(|(x, y)| game.data.pos_in_fov(player_id, Pos::new(*x, *y), &game.config)));
+
+    let cmd = "state".parse::<GameCmd>().unwrap();
+    let output = execute_game_command(&cmd, &mut game);
+    assert!(output.starts_with("state {"));
+    assert!(output.contains("\"player_id\":"));
+    assert!(!output.contains('\n'));
+}
+
+#[test]
+pub fn test_command_parse_errors() {
+    use roguelike_core::config::Config;
+    use roguelike_engine::make_map::make_map;
+
+    assert_eq!(Err(CmdParseError::UnknownCommand("st_pos".to_string())), "st_pos 1 2 3".parse::<GameCmd>().map(|_| ()));
+    assert_eq!("set_pos", closest_command("st_pos"));
+    assert_eq!(Err(CmdParseError::WrongArgCount("set_pos".to_string(), 3, 3, 1)), "set_pos 1".parse::<GameCmd>().map(|_| ()));
+    assert_eq!(Err(CmdParseError::WrongArgCount("hp".to_string(), 1, 2, 3)), "hp 1 2 3".parse::<GameCmd>().map(|_| ()));
+    assert_eq!(Err(CmdParseError::BadArgument("teleport".to_string(), 2, "a".to_string())), "teleport 1 a".parse::<GameCmd>().map(|_| ()));
+    assert!("hp 1".parse::<GameCmd>().is_ok());
+    assert!("hp 1 2".parse::<GameCmd>().is_ok());
+
+    let err = "st_pos 1 2 3".parse::<GameCmd>().err().unwrap();
+    assert!(format_parse_error("st_pos 1 2 3", &err).contains("set_pos id x y"));
+    let err = "teleport 1 a".parse::<GameCmd>().err().unwrap();
+    assert!(format_parse_error("teleport 1 a", &err).contains("teleport x y"));
+
+    let config = Config::from_file("../config.yaml");
+    let mut game = Game::new(0, config.clone());
+    make_map(&MapLoadConfig::Empty, &mut game);
+    let output = execute_game_command(&"help".parse::<GameCmd>().unwrap(), &mut game);
+    for (_, syntax) in COMMAND_SYNTAX.iter() {
+        assert!(output.contains(syntax));
+    }
+}
@@ -16,10 +16,10 @@ use crate::commands::*;
 const CONFIG_NAME: &str = "config.yaml";
 
 pub fn main() {
-    let config = Config::from_file(CONFIG_NAME);
+    let config = load_config(CONFIG_NAME, user_config_dir());
     let seed = 1;
     let mut game = Game::new(seed, config.clone());
-    game.load_vaults("resources/vaults/");
+    game.load_vaults(&format!("{}/vaults/", config.resource_dir));
 
     make_map(&config.map_load, &mut game);
 
@@ -30,9 +30,13 @@ pub fn main() {
     while game.settings.running {
 
         if let Ok(msg) = io_recv.recv_timeout(Duration::from_millis(100)) {
-            if let Ok(cmd) = msg.parse::<GameCmd>() {
-                let result = execute_game_command(&cmd, &mut game);
-                log.log_output(&result);
+            match msg.parse::<GameCmd>() {
+                Ok(cmd) => {
+                    let result = execute_game_command(&cmd, &mut game);
+                    log.log_output(&result);
+                }
+
+                Err(err) => log.log_output(&format_parse_error(&msg, &err)),
             }
         }
 
@@ -110,6 +110,9 @@ pub enum Animation {
     Between(SpriteAnim, Pos, Pos, f32, f32),  // start, end, dist, blocks_per_sec
     Once(SpriteAnim),                         // play sprite once and end
     PlayEffect(Effect),
+    // loop through frames at a rate, picking the frame from the game time so it
+    // is the same whenever the game is at the same time
+    TimedLoop { sprite_key: SpriteKey, frames: Vec<u32>, fps: f32 },
 }
 
 impl Animation {
@@ -119,10 +122,11 @@ impl Animation {
             Animation::Between(sprite_anim, _, _, _, _) => return Some(sprite_anim),
             Animation::Once(sprite_anim) => return Some(sprite_anim),
             Animation::PlayEffect(_) => return None,
+            Animation::TimedLoop { .. } => return None,
         }
     }
 
-    pub fn step(&mut self, pos: Pos, dt: f32, config: &Config) -> AnimationResult {
+    pub fn step(&mut self, pos: Pos, dt: f32, time: f32, config: &Config) -> AnimationResult {
         let mut animation_result = AnimationResult::new();
         animation_result.pos = pos;
 
@@ -155,6 +159,15 @@ impl Animation {
                 animation_result.done = true;
             }
 
+            Animation::TimedLoop { sprite_key, frames, fps } => {
+                if frames.len() > 0 {
+                    let index = timed_frame(frames, *fps, time);
+                    animation_result.sprite = Some(Sprite::new(index, *sprite_key));
+                }
+
+                animation_result.done = false;
+            }
+
             Animation::Once(ref mut sprite_anim) => {
                 if !sprite_anim.looped {
                     let sprite = sprite_anim.sprite();
@@ -169,3 +182,18 @@ impl Animation {
     }
 }
 
+
+/// The frame to show at the given game time for frames played at the given rate.
+pub fn timed_frame(frames: &[u32], fps: f32, time: f32) -> u32 {
+    return frames[(time * fps) as usize % frames.len()];
+}
+
+#[test]
+fn test_timed_frame() {
+    let frames = vec!(4, 5, 6);
+    assert_eq!(4, timed_frame(&frames, 2.0, 0.0));
+    assert_eq!(4, timed_frame(&frames, 2.0, 0.4));
+    assert_eq!(5, timed_frame(&frames, 2.0, 0.5));
+    assert_eq!(6, timed_frame(&frames, 2.0, 1.2));
+    assert_eq!(4, timed_frame(&frames, 2.0, 1.5));
+}
@@ -0,0 +1,140 @@
+#[cfg(feature = "sound")]
+use std::collections::{HashMap, HashSet};
+
+#[cfg(feature = "sound")]
+use log::warn;
+
+#[cfg(feature = "sound")]
+use sdl2::AudioSubsystem;
+#[cfg(feature = "sound")]
+use sdl2::mixer::{self, Chunk, Channel, InitFlag, Sdl2MixerContext};
+
+use roguelike_core::config::Config;
+use roguelike_core::messaging::Msg;
+
+
+#[cfg(feature = "sound")]
+pub const AUDIO_CHUNK_SIZE: i32 = 1024;
+#[cfg(feature = "sound")]
+pub const AUDIO_NUM_CHANNELS: i32 = 16;
+
+/// Plays the sounds for game events. Each sound file is loaded the first time it is played,
+/// and a file that can't be loaded is warned about once and then skipped.
+#[cfg(feature = "sound")]
+pub struct Audio {
+    enabled: bool,
+    sounds: HashMap<String, Chunk>,
+    missing: HashSet<String>,
+    _subsystem: Option<AudioSubsystem>,
+    _mixer: Option<Sdl2MixerContext>,
+}
+
+#[cfg(feature = "sound")]
+impl Audio {
+    /// Audio that never plays, for when there is no mixer.
+    pub fn silent() -> Audio {
+        return Audio {
+            enabled: false,
+            sounds: HashMap::new(),
+            missing: HashSet::new(),
+            _subsystem: None,
+            _mixer: None,
+        };
+    }
+
+    pub fn open(sdl_context: &sdl2::Sdl) -> Audio {
+        let mut audio = Audio::silent();
+
+        let subsystem = match sdl_context.audio() {
+            Ok(subsystem) => subsystem,
+            Err(err) => {
+                warn!("Could not start audio, sounds are disabled: {}", err);
+                return audio;
+            }
+        };
+
+        if let Err(err) = mixer::open_audio(mixer::DEFAULT_FREQUENCY, mixer::DEFAULT_FORMAT, mixer::DEFAULT_CHANNELS, AUDIO_CHUNK_SIZE) {
+            warn!("Could not open audio, sounds are disabled: {}", err);
+            return audio;
+        }
+        mixer::allocate_channels(AUDIO_NUM_CHANNELS);
+
+        // wav files are always supported, ogg files only if the mixer was built with them
+        match mixer::init(InitFlag::OGG) {
+            Ok(mixer_context) => audio._mixer = Some(mixer_context),
+            Err(err) => warn!("Could not load ogg support: {}", err),
+        }
+
+        audio._subsystem = Some(subsystem);
+        audio.enabled = true;
+
+        return audio;
+    }
+
+    pub fn play_msg(&mut self, msg: &Msg, config: &Config) {
+        if !self.enabled || config.mute_audio {
+            return;
+        }
+
+        if let Some(event) = msg_sound_event(msg) {
+            if let Some(file_name) = config.sounds.get(event) {
+                self.play_file(file_name);
+            }
+        }
+    }
+
+    fn play_file(&mut self, file_name: &str) {
+        if self.missing.contains(file_name) {
+            return;
+        }
+
+        if !self.sounds.contains_key(file_name) {
+            match Chunk::from_file(file_name) {
+                Ok(chunk) => {
+                    self.sounds.insert(file_name.to_string(), chunk);
+                }
+
+                Err(err) => {
+                    warn!("Could not load sound {}, skipping it: {}", file_name, err);
+                    self.missing.insert(file_name.to_string());
+                    return;
+                }
+            }
+        }
+
+        // if every channel is busy the sound is dropped
+        let _ = Channel::all().play(&self.sounds[file_name], 0);
+    }
+}
+
+/// Without the sound feature there is no mixer, so no sounds are played.
+#[cfg(not(feature = "sound"))]
+pub struct Audio;
+
+#[cfg(not(feature = "sound"))]
+impl Audio {
+    pub fn silent() -> Audio {
+        return Audio;
+    }
+
+    pub fn open(_sdl_context: &sdl2::Sdl) -> Audio {
+        return Audio;
+    }
+
+    pub fn play_msg(&mut self, _msg: &Msg, _config: &Config) {
+    }
+}
+
+/// The name of the sound for a message, looked up in the config's sounds.
+#[cfg(feature = "sound")]
+pub fn msg_sound_event(msg: &Msg) -> Option<&'static str> {
+    match msg {
+        Msg::Attack(_, _, _) => return Some("attack"),
+        Msg::Killed(_, _, _) => return Some("killed"),
+        Msg::PickedUp(_, _) => return Some("pickup"),
+        Msg::Yell(_) => return Some("yell"),
+        Msg::SoundTrapTriggered(_, _) | Msg::SpikeTrapTriggered(_, _) |
+        Msg::BlinkTrapTriggered(_, _) | Msg::FreezeTrapTriggered(_, _) => return Some("trap"),
+        _ => return None,
+    }
+}
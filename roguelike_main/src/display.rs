@@ -3,7 +3,7 @@ use std::collections::VecDeque;
 use bmp::Image;
 
 use sdl2::render::{Texture, WindowCanvas, TextureCreator, BlendMode};
-use sdl2::video::WindowContext;
+use sdl2::video::{WindowContext, FullscreenType};
 use sdl2::rect::{Rect};
 use sdl2::pixels::{PixelFormatEnum, Color as Sdl2Color};
 
@@ -15,8 +15,9 @@ use roguelike_core::config::*;
 use roguelike_core::messaging::*;
 use roguelike_core::map::*;
 use roguelike_core::utils::aoe_fill;
-use roguelike_core::movement::{Direction};
+use roguelike_core::movement::{Direction, MoveType};
 
+use crate::audio::Audio;
 use crate::animation::{Sprite, Effect, SpriteKey, Animation, SpriteAnim, SpriteIndex};
 
 
@@ -24,6 +25,7 @@ pub struct Display {
     pub state: DisplayState,
     pub targets: DisplayTargets,
     pub mouse_state: MouseState,
+    pub audio: Audio,
 }
 
 impl Display {
@@ -31,9 +33,76 @@ impl Display {
         return Display { state: DisplayState::new(),
                          targets: DisplayTargets::new(canvas),
                          mouse_state: Default::default(),
+                         audio: Audio::silent(),
         };
     }
 
+    /// The map tile under a pixel on the screen, or None if the pixel is outside of the map.
+    pub fn screen_to_map(&self, pixel_x: i32, pixel_y: i32) -> Option<Pos> {
+        let (view_width, view_height) = self.state.view_size;
+        let map_rect = self.targets.canvas_panel.get_rect_from_area(&self.targets.map_area);
+        if !map_rect.contains_point((pixel_x, pixel_y)) {
+            return None;
+        }
+
+        let (camera_x, camera_y) = self.state.camera;
+        let x = camera_x + (pixel_x - map_rect.x) as f32 * view_width as f32 / map_rect.w as f32;
+        let y = camera_y + (pixel_y - map_rect.y) as f32 * view_height as f32 / map_rect.h as f32;
+        let pos = Pos::new(x.floor() as i32, y.floor() as i32);
+
+        let (map_width, map_height) = self.state.map_size;
+        if pos.x < 0 || pos.y < 0 || pos.x >= map_width || pos.y >= map_height {
+            return None;
+        }
+
+        return Some(pos);
+    }
+
+    /// The screen pixel at the upper left corner of a map tile, or None if the tile is not in view.
+    pub fn map_to_screen(&self, pos: Pos) -> Option<Pos> {
+        let (view_width, view_height) = self.state.view_size;
+        let (camera_x, camera_y) = self.state.camera;
+        let x = pos.x as f32 - camera_x;
+        let y = pos.y as f32 - camera_y;
+        if x < 0.0 || y < 0.0 || x >= view_width as f32 || y >= view_height as f32 {
+            return None;
+        }
+
+        let map_rect = self.targets.canvas_panel.get_rect_from_area(&self.targets.map_area);
+        let cell_dims = (map_rect.w as f32 / view_width as f32, map_rect.h as f32 / view_height as f32);
+        return Some(Pos::new(map_rect.x + (x * cell_dims.0) as i32, map_rect.y + (y * cell_dims.1) as i32));
+    }
+
+    /// Move the camera part of the way towards centering the given position, keeping the view on the map.
+    pub fn update_camera(&mut self, focus_pos: Pos, map_size: (i32, i32), lerp: f32, config: &Config) {
+        let view_size = camera_view_size(map_size, config);
+
+        // the view jumps when its size changes, such as when a new level is loaded
+        let target = camera_target(focus_pos, map_size, view_size);
+        if view_size != self.state.view_size || map_size != self.state.map_size {
+            self.state.camera = target;
+        } else {
+            self.state.camera = step_camera(self.state.camera, target, lerp);
+        }
+
+        self.state.view_size = view_size;
+        self.state.map_size = map_size;
+    }
+
+    /// Switch the window between windowed and desktop fullscreen.
+    pub fn toggle_fullscreen(&mut self) -> Result<(), String> {
+        let window = self.targets.canvas_panel.target.window_mut();
+        if window.fullscreen_state() == FullscreenType::Off {
+            window.set_fullscreen(FullscreenType::Desktop)?;
+        } else {
+            window.set_fullscreen(FullscreenType::Off)?;
+        }
+
+        self.targets.resize();
+
+        return Ok(());
+    }
+
     pub fn update_display(&mut self) {
         self.targets.canvas_panel.target.present();
     }
@@ -102,12 +171,39 @@ impl Display {
         return anim;
     }
 
+    /// Create a looping sprite whose frame depends only on the game time
+    pub fn timed_loop_sprite(&mut self, sprite_name: &str, fps: f32) -> Animation {
+        let sprite_key = self.state.lookup_spritekey(sprite_name);
+        let num_sprites = self.state.sprites[&sprite_key].num_sprites;
+        let frames = (0..num_sprites as u32).collect::<Vec<u32>>();
+
+        return Animation::TimedLoop { sprite_key, frames, fps };
+    }
+
     pub fn play_idle_animation(&mut self, entity_id: EntityId, data: &mut GameData, config: &Config) {
         if let Some(anim) = self.get_idle_animation(entity_id, data, config) {
             self.state.play_animation(entity_id, anim);
         }
     }
 
+    pub fn play_attack_animation(&mut self, attacker: EntityId, attacked: EntityId, data: &mut GameData, config: &Config) {
+        if data.entities.typ[&attacker] == EntityType::Player {
+            let attack_sprite =
+                self.new_sprite("player_attack".to_string(), config.player_attack_speed);
+            let attack_anim = Animation::Once(attack_sprite);
+            self.state.play_animation(attacker, attack_anim);
+
+            if let Some(idle_anim) = self.get_idle_animation(attacker, data, config) {
+                self.state.play_animation(attacker, idle_anim);
+            }
+        } else {
+            let attacker_pos = data.entities.pos[&attacker];
+            let attacked_pos = data.entities.pos[&attacked];
+            let beam_effect = Effect::Beam(config.beam_duration, attacker_pos, attacked_pos);
+            self.state.play_effect(beam_effect);
+        }
+    }
+
     pub fn get_idle_animation(&mut self, entity_id: EntityId, data: &mut GameData, config: &Config) -> Option<Animation> {
         let name = data.entities.name[&entity_id];
 
@@ -135,7 +231,7 @@ impl Display {
             } else if data.entities.name[&entity_id] == EntityName::Armil {
                 return Some(self.loop_sprite("armil_idle", config.idle_speed));
             } else if data.entities.name[&entity_id] == EntityName::Lantern {
-                return Some(self.loop_sprite("lantern_idle", config.fire_speed));
+                return Some(self.timed_loop_sprite("lantern_idle", config.fire_speed));
             }
         }
 
@@ -147,9 +243,13 @@ impl Display {
         self.state.prev_turn_fov.clear();
         self.state.current_turn_fov.clear();
         self.state.sound_tiles.clear();
+        self.state.drawn_pos.clear();
+        self.state.move_anims.clear();
     }
 
     pub fn process_message(&mut self, msg: Msg, data: &mut GameData, config: &Config) {
+        self.audio.play_msg(&msg, config);
+
         match msg {
             Msg::StartTurn => {
                 self.state.sound_tiles.clear();
@@ -258,21 +358,14 @@ impl Display {
                 }
             }
 
-            Msg::Attack(attacker, attacked, _damage) => {
-                if data.entities.typ[&attacker] == EntityType::Player {
-                    let attack_sprite =
-                        self.new_sprite("player_attack".to_string(), config.player_attack_speed);
-                    let attack_anim = Animation::Once(attack_sprite);
-                    self.state.play_animation(attacker, attack_anim);
+            Msg::AttackStarted(attacker, attacked) => {
+                self.play_attack_animation(attacker, attacked, data, config);
+            }
 
-                    if let Some(idle_anim) = self.get_idle_animation(attacker, data, config) {
-                        self.state.play_animation(attacker, idle_anim);
-                    }
-                } else {
-                    let attacker_pos = data.entities.pos[&attacker];
-                    let attacked_pos = data.entities.pos[&attacked];
-                    let beam_effect = Effect::Beam(config.beam_duration, attacker_pos, attacked_pos);
-                    self.state.play_effect(beam_effect);
+            Msg::Attack(attacker, attacked, _damage) => {
+                // animated attacks already played when they started
+                if !config.animate_attacks {
+                    self.play_attack_animation(attacker, attacked, data, config);
                 }
             }
 
@@ -331,8 +424,21 @@ impl Display {
                 }
             }
 
+            Msg::Moved(entity_id, move_type, pos) => {
+                // slide from wherever the entity was last drawn
+                if move_type == MoveType::Move || move_type == MoveType::JumpWall {
+                    if let Some(start) = self.state.drawn_pos.get(&entity_id).map(|start| *start) {
+                        if start != pos {
+                            self.state.move_anims.insert(entity_id, (start, None));
+                        }
+                    }
+                }
+            }
+
             Msg::RemovedEntity(entity_id) => {
                 self.state.animations.remove(&entity_id);
+                self.state.drawn_pos.remove(&entity_id);
+                self.state.move_anims.remove(&entity_id);
             }
 
             _ => {
@@ -685,6 +791,14 @@ impl DisplayTargets {
         };
     }
 
+    /// Match the canvas to the window's current size. The panel layout is in cells, so
+    /// only the pixel size of each cell changes.
+    pub fn resize(&mut self) {
+        let (width, height) = self.canvas_panel.target.output_size().unwrap();
+        self.canvas_panel.num_pixels = (width, height);
+        self.canvas_panel.dirty = true;
+    }
+
     pub fn mouse_pos(&self, x: i32, y: i32, map_width: i32, map_height: i32) -> Option<(i32, i32)> {
         let map_rect = self.canvas_panel.get_rect_from_area(&self.map_area);
         return cell_within_rect(map_rect, (map_width, map_height), (x, y));
@@ -715,6 +829,21 @@ pub struct DisplayState {
 
     // tiles that heard a sound
     pub sound_tiles: Vec<Pos>,
+
+    // size of the map last drawn to the screen
+    pub map_size: (i32, i32),
+
+    // upper left tile of the camera's view, and the view's size in tiles
+    pub camera: (f32, f32),
+    pub view_size: (i32, i32),
+
+    // recent message lines shown in the message panel, oldest first
+    pub msg_lines: VecDeque<String>,
+
+    // tile each entity was last drawn on, and the entities sliding from it to their new tile
+    // along with the time the slide started
+    pub drawn_pos: IndexMap<EntityId, Pos>,
+    pub move_anims: IndexMap<EntityId, (Pos, Option<f32>)>,
 }
 
 impl DisplayState {
@@ -731,9 +860,23 @@ impl DisplayState {
             prev_turn_fov: Vec::new(),
             current_turn_fov: Vec::new(),
             sound_tiles: Vec::new(),
+            map_size: (MAP_WIDTH, MAP_HEIGHT),
+            camera: (0.0, 0.0),
+            view_size: (MAP_WIDTH, MAP_HEIGHT),
+            msg_lines: VecDeque::new(),
+            drawn_pos: IndexMap::new(),
+            move_anims: IndexMap::new(),
         };
     }
 
+    /// Add a line to the message panel, dropping the oldest lines beyond the limit.
+    pub fn push_msg_line(&mut self, line: String, limit: usize) {
+        self.msg_lines.push_back(line);
+        while self.msg_lines.len() > limit {
+            self.msg_lines.pop_front();
+        }
+    }
+
     pub fn lookup_spritekey(&self, name: &str) -> SpriteKey {
         for (key, sprite_sheet) in self.sprites.iter() {
             if sprite_sheet.name == *name {
@@ -763,6 +906,43 @@ impl DisplayState {
         sprite_sheet.draw_sprite_at_cell(panel, sprite.index as usize, pos, color, 0.0, sprite.flip_horiz, sprite.flip_vert);
     }
 
+    /// Draw a sprite offset from its cell by a fraction of a tile.
+    pub fn draw_sprite_offset(&mut self,
+                              panel: &mut Panel<&mut WindowCanvas>,
+                              sprite: Sprite,
+                              pos: Pos,
+                              offset: (f32, f32),
+                              color: Color) {
+        let (cell_width, cell_height) = panel.cell_dims();
+        let x = (pos.x as f32 + offset.0) * cell_width as f32;
+        let y = (pos.y as f32 + offset.1) * cell_height as f32;
+
+        let sprite_sheet = &mut self.sprites[&sprite.key];
+        sprite_sheet.draw_sprite_full(panel, sprite.index as usize, Pos::new(x as i32, y as i32), color, 0.0, sprite.flip_horiz, sprite.flip_vert);
+    }
+
+    /// How far, in tiles, an entity is drawn from its tile while it slides there from its
+    /// last tile. The slide is skipped when it would take less than a frame.
+    pub fn move_offset(&mut self, entity_id: EntityId, pos: Pos, time: f32, config: &Config) -> (f32, f32) {
+        let mut offset = (0.0, 0.0);
+
+        if let Some((start, start_time)) = self.move_anims.get(&entity_id).map(|anim| *anim) {
+            let start_time = start_time.unwrap_or(time);
+            self.move_anims[&entity_id].1 = Some(start_time);
+
+            let progress = (time - start_time) / config.move_anim_time;
+            let instant = config.move_anim_time * (config.frame_rate as f32) < 1.0;
+            if !instant && progress < 1.0 {
+                let remaining = 1.0 - progress;
+                offset = ((start.x - pos.x) as f32 * remaining, (start.y - pos.y) as f32 * remaining);
+            } else {
+                self.move_anims.remove(&entity_id);
+            }
+        }
+
+        return offset;
+    }
+
     pub fn play_effect(&mut self, effect: Effect) {
         self.effects.push(effect);
     }
@@ -1063,6 +1243,42 @@ pub fn draw_tile_highlight(panel: &mut Panel<&mut WindowCanvas>,
     panel.target.fill_rect(rect).unwrap();
 }
 
+/// The number of tiles the camera shows, which is the whole map unless the config limits it.
+pub fn camera_view_size(map_size: (i32, i32), config: &Config) -> (i32, i32) {
+    let mut view_size = map_size;
+    if config.camera_view_width > 0 {
+        view_size.0 = std::cmp::min(config.camera_view_width, map_size.0);
+    }
+    if config.camera_view_height > 0 {
+        view_size.1 = std::cmp::min(config.camera_view_height, map_size.1);
+    }
+    return view_size;
+}
+
+/// The camera position that centers the player, stopping at the edges of the map.
+pub fn camera_target(player_pos: Pos, map_size: (i32, i32), view_size: (i32, i32)) -> (f32, f32) {
+    let x = player_pos.x as f32 + 0.5 - view_size.0 as f32 / 2.0;
+    let y = player_pos.y as f32 + 0.5 - view_size.1 as f32 / 2.0;
+    let max_x = (map_size.0 - view_size.0) as f32;
+    let max_y = (map_size.1 - view_size.1) as f32;
+    return (x.max(0.0).min(max_x), y.max(0.0).min(max_y));
+}
+
+/// Ease the camera towards a target. Once close enough, the camera snaps to the target
+/// so the view settles on whole tiles.
+pub fn step_camera(camera: (f32, f32), target: (f32, f32), lerp: f32) -> (f32, f32) {
+    let lerp = lerp.max(0.0).min(1.0);
+    let mut x = camera.0 + (target.0 - camera.0) * lerp;
+    let mut y = camera.1 + (target.1 - camera.1) * lerp;
+    if (target.0 - x).abs() < 0.01 {
+        x = target.0;
+    }
+    if (target.1 - y).abs() < 0.01 {
+        y = target.1;
+    }
+    return (x, y);
+}
+
 pub fn cell_within_rect(rect: Rect, area_cell_dims: (i32, i32), pixel_pos: (i32, i32)) -> Option<(i32, i32)> {
     if pixel_pos.0 >= rect.x && pixel_pos.0 < rect.x + rect.w &&
        pixel_pos.1 >= rect.y && pixel_pos.1 < rect.y + rect.h {
@@ -1110,3 +1326,23 @@ fn needs_flip_horiz(direction: Direction) -> bool {
         Direction::DownLeft => return true,
     }
 }
+
+#[test]
+pub fn test_camera_target() {
+    // centered on the player in the middle of the map
+    assert_eq!((15.0, 15.0), camera_target(Pos::new(20, 20), (40, 40), (11, 11)));
+
+    // stopped at the edges of the map
+    assert_eq!((0.0, 0.0), camera_target(Pos::new(1, 2), (40, 40), (11, 11)));
+    assert_eq!((29.0, 29.0), camera_target(Pos::new(39, 38), (40, 40), (11, 11)));
+
+    // a view of the whole map never moves
+    assert_eq!((0.0, 0.0), camera_target(Pos::new(39, 38), (40, 40), (40, 40)));
+}
+
+#[test]
+pub fn test_step_camera() {
+    assert_eq!((5.0, 2.5), step_camera((0.0, 0.0), (10.0, 5.0), 0.5));
+    assert_eq!((10.0, 5.0), step_camera((0.0, 0.0), (10.0, 5.0), 1.0));
+    assert_eq!((10.0, 5.0), step_camera((9.995, 5.0), (10.0, 5.0), 0.5));
+}
@@ -1,21 +1,25 @@
-use sdl2::event::Event;
+use sdl2::event::{Event, WindowEvent};
 use sdl2::mouse::MouseButton;
 use sdl2::keyboard::Keycode;
 
 use roguelike_core::types::*;
 
-use roguelike_engine::game::*;
 use roguelike_engine::input::*;
 
 use crate::display::*;
 
 
-pub fn translate_event(event: Event, game: &mut Game, display: &mut Display) -> Option<InputEvent> {
+pub fn translate_event(event: Event, display: &mut Display) -> Option<InputEvent> {
     match event {
         Event::Quit {..} => {
             return Some(InputEvent::Quit);
         }
 
+        Event::Window {win_event: WindowEvent::SizeChanged(_, _), ..} => {
+            display.targets.resize();
+            return None;
+        }
+
         Event::KeyDown {keycode, repeat, ..} => {
             let mut dir = KeyDir::Down;
 
@@ -70,7 +74,10 @@ pub fn translate_event(event: Event, game: &mut Game, display: &mut Display) ->
         Event::MouseMotion {x, y, ..} => {
             display.mouse_state.x = x;
             display.mouse_state.y = y;
-            return Some(InputEvent::MousePos(x, y));
+
+            let mouse_pos = Pos::new(x, y);
+            let target_pos = display.screen_to_map(x, y);
+            return Some(InputEvent::MousePos(mouse_pos, target_pos));
         }
 
         Event::MouseButtonDown {mouse_btn, ..} => {
@@ -95,10 +102,7 @@ pub fn translate_event(event: Event, game: &mut Game, display: &mut Display) ->
             };
 
             let mouse_pos = Pos::new(display.mouse_state.x, display.mouse_state.y);
-            let (map_width, map_height) = game.data.map.size();
-            let option_mouse_cell =
-                display.targets.mouse_pos(display.mouse_state.x, display.mouse_state.y, map_width, map_height);
-            let target_pos = option_mouse_cell.map(|pair| Pos::from(pair));
+            let target_pos = display.screen_to_map(display.mouse_state.x, display.mouse_state.y);
             return Some(InputEvent::MouseButton(click, mouse_pos, target_pos, KeyDir::Down));
         }
 
@@ -138,6 +142,7 @@ pub fn keycode_to_char(key: Keycode) -> Option<char> {
         Keycode::Space => Some(' '),
         Keycode::Comma => Some(','),
         Keycode::Minus => Some('-'),
+        Keycode::Equals => Some('='),
         Keycode::Period => Some('.'),
         Keycode::Num0 => Some('0'),
         Keycode::Num1 => Some('1'),
@@ -6,6 +6,7 @@ mod keyboard;
 mod load;
 mod replay;
 mod animation;
+mod audio;
 
 use std::fs;
 use std::io::{BufRead, Write};
@@ -22,20 +23,23 @@ use logging_timer::timer;
 use gumdrop::Options;
 
 use roguelike_core::types::*;
-use roguelike_core::config::Config;
+use roguelike_core::config::{Config, find_config, load_config, user_config_dir};
 use roguelike_core::constants::*;
+use roguelike_core::utils::{daily_seed, utc_date_string};
 use roguelike_core::map::MapLoadConfig;
+use roguelike_core::movement::MoveMode;
 
 use roguelike_engine::game::*;
 use roguelike_engine::generation::*;
 use roguelike_engine::actions::*;
 use roguelike_engine::input::*;
-use roguelike_engine::make_map::{make_map, read_map_xp};
+use roguelike_engine::make_map::{make_map, read_map_xp, spawn_preview};
 use roguelike_engine::log::*;
 
 use roguelike_lib::commands::*;
 
 use crate::throttler::*;
+use crate::audio::Audio;
 use crate::render::*;
 use crate::display::*;
 use crate::load::*;
@@ -43,6 +47,9 @@ use crate::replay::*;
 
 
 pub const CONFIG_NAME: &str = "config.yaml";
+pub const STATS_FILE_NAME: &str = "stats.txt";
+pub const HEADLESS_DEFAULT_TURNS: usize = 100;
+pub const HEADLESS_FRAME_TIME: f32 = 0.1;
 
 
 #[derive(Debug, Clone, Options)]
@@ -59,6 +66,9 @@ pub struct GameOptions {
     #[options(help = "check a previous recorded session against current version", short="c")]
     pub check: Option<String>,
 
+    #[options(help = "compare checked sessions by message contents rather than message text")]
+    pub semantic: bool,
+
     #[options(help = "delay value in milliseconds used when replaying commands", short="y")]
     pub delay: Option<u64>,
 
@@ -71,12 +81,24 @@ pub struct GameOptions {
     #[options(help = "use a given seed for random number generation")]
     pub seed: Option<u64>,
 
+    #[options(help = "play the daily challenge, seeded by the current UTC date (--seed overrides this)")]
+    pub daily: bool,
+
     #[options(help = "take a screenshot and exit", short="t")]
     pub screenshot: bool,
 
     #[options(help = "procgen map config", short="g")]
     pub procgen_map: Option<String>,
 
+    #[options(help = "list the entities the chosen map would spawn with, and exit")]
+    pub spawn_preview: bool,
+
+    #[options(help = "simulate without a window, replaying the --replay log if given, and print the final state")]
+    pub headless: bool,
+
+    #[options(help = "number of turns to pass in headless mode when no replay log is given")]
+    pub turns: Option<usize>,
+
     #[options(help = "display help text")]
     pub help: bool,
 }
@@ -85,9 +107,20 @@ pub struct GameOptions {
 fn main() {
     let opts = GameOptions::parse_args_default_or_exit();
 
+    // a given seed overrides the daily challenge
+    let daily_date =
+        if opts.daily && opts.seed.is_none() {
+            Some(utc_date_string())
+        } else {
+            None
+        };
+
     let seed: u64 =
         if let Some(given_seed) = opts.seed {
             given_seed
+        } else if let Some(date) = &daily_date {
+            eprintln!("Daily challenge for {}", date);
+            daily_seed(date)
         } else {
             1
             // could add string input as a seed generator
@@ -103,10 +136,91 @@ fn main() {
                                       |level_str| LevelFilter::from_str(&level_str).expect("Log level unexpected!"));
     simple_logging::log_to_file("game.log", log_level).unwrap();
 
-    run(seed, opts).unwrap();
+    if opts.spawn_preview {
+        let config = load_config(CONFIG_NAME, user_config_dir());
+        let map_config = choose_map_config(&opts, &config);
+
+        for (name, pos) in spawn_preview(seed, &map_config, &config) {
+            println!("{:?} {} {}", name, pos.x, pos.y);
+        }
+        return;
+    }
+
+    if opts.headless {
+        run_headless(seed, &opts);
+        return;
+    }
+
+    run(seed, daily_date, opts).unwrap();
+}
+
+/// Run a game without SDL or a Display. The replay log's actions are played in order,
+/// or the player passes for the given number of turns while the monsters act, and
+/// the final game state is printed as JSON.
+pub fn run_headless(seed: u64, opts: &GameOptions) {
+    let mut game = create_game(seed);
+    let map_config = choose_map_config(opts, &game.config);
+    start_game(&mut game, &map_config);
+
+    let actions: Vec<InputAction>;
+    if let Some(replay_file) = &opts.replay {
+        actions = read_action_log(replay_file);
+    } else {
+        let num_turns = opts.turns.unwrap_or(HEADLESS_DEFAULT_TURNS);
+        actions = vec![InputAction::Pass(MoveMode::Walk); num_turns];
+    }
+
+    for action in actions {
+        if !game.settings.running || game.settings.state == GameState::Lose {
+            break;
+        }
+
+        game.step_game(action, HEADLESS_FRAME_TIME);
+        game.msg_log.clear();
+    }
+
+    println!("turns {} state {}", game.settings.turn_count, game.settings.state);
+    println!("{}", execute_game_command(&GameCmd::State, &mut game));
+}
+
+/// Create the game from the config, with the mouse made first so entity ids
+/// match between the windowed and headless modes.
+fn create_game(seed: u64) -> Game {
+    let config = load_config(CONFIG_NAME, user_config_dir());
+    let mut game = Game::new(seed, config.clone());
+
+    game.load_vaults(&format!("{}/vaults/", config.resource_dir));
+
+    make_mouse(&mut game.data.entities, &game.config, &mut game.msg_log);
+
+    return game;
+}
+
+fn start_game(game: &mut Game, map_config: &MapLoadConfig) {
+    make_map(map_config, game);
+    game.start_level_banner();
+    game.start_intro();
+}
+
+fn choose_map_config(opts: &GameOptions, config: &Config) -> MapLoadConfig {
+    let mut map_config: MapLoadConfig;
+
+    if let Some(procgen_map) = opts.procgen_map.clone() {
+        map_config = MapLoadConfig::ProcGen(procgen_map);
+    } else {
+        map_config = config.map_load.clone();
+    }
+
+    if let Some(map_config_str) = &opts.map_config {
+        let cli_map_config = map_config_str.parse::<MapLoadConfig>()
+                                           .expect("Could not parse map config option!");
+        map_config = cli_map_config;
+    }
+
+    return map_config;
 }
 
-pub fn run(seed: u64, opts: GameOptions) -> Result<(), String> {
+pub fn run(seed: u64, daily_date: Option<String>, opts: GameOptions) -> Result<(), String> {
     /* Create SDL Context */
     let sdl_context = sdl2::init()?;
     let video = sdl_context.video()?;
@@ -136,27 +250,10 @@ pub fn run(seed: u64, opts: GameOptions) -> Result<(), String> {
     display.add_spritesheet("font".to_string(), font_texture);
 
     /* Create Game Structure */
-    let config = Config::from_file(CONFIG_NAME);
-    let mut game = Game::new(seed, config.clone());
-
-    game.load_vaults("resources/vaults/");
-
-    make_mouse(&mut game.data.entities, &game.config, &mut game.msg_log);
+    let mut game = create_game(seed);
 
     /* Create Map */
-    let mut map_config: MapLoadConfig;
-
-    if let Some(procgen_map) = opts.procgen_map.clone() {
-        map_config = MapLoadConfig::ProcGen(procgen_map);
-    } else {
-        map_config = config.map_load.clone();
-    }
-
-    if let Some(map_config_str) = &opts.map_config {
-        let cli_map_config = map_config_str.parse::<MapLoadConfig>()
-                                           .expect("Could not parse map config option!");
-        map_config = cli_map_config;
-    }
+    let map_config = choose_map_config(&opts, &game.config);
 
     // save map config to a file
     let mut map_config_file = std::fs::File::create(MAP_CONFIG_NAME).unwrap();
@@ -168,15 +265,19 @@ pub fn run(seed: u64, opts: GameOptions) -> Result<(), String> {
         take_screenshot(&mut game, &mut display).unwrap();
         return Ok(());
     } else if let Some(record_name) = opts.check {
+        // checks draw each move immediately rather than waiting on animations
+        game.config.move_anim_time = 0.0;
         let delay = opts.delay.unwrap_or(0);
         let mut event_pump = sdl_context.event_pump().unwrap();
+        let compare = if opts.semantic { LogCompare::Semantic } else { LogCompare::Text };
 
         if record_name == "all" {
-            return check_all_records(&mut game, &mut display, &mut event_pump, delay);
+            return check_all_records(&mut game, &mut display, &mut event_pump, delay, compare);
         } else {
-            return check_single_record(&mut game, &mut display, &mut event_pump, &record_name, delay);
+            return check_single_record(&mut game, &mut display, &mut event_pump, &record_name, delay, compare);
         }
     } else if let Some(record_name) = opts.rerecord {
+        game.config.move_anim_time = 0.0;
         let delay = opts.delay.unwrap_or(0);
         let mut event_pump = sdl_context.event_pump().unwrap();
         if record_name == "all" {
@@ -185,27 +286,39 @@ pub fn run(seed: u64, opts: GameOptions) -> Result<(), String> {
             return rerecord_single(&mut game, &mut display, &mut event_pump, &record_name, delay);
         }
     } else {
-        make_map(&map_config, &mut game);
+        start_game(&mut game, &map_config);
+        display.audio = Audio::open(&sdl_context);
         let event_pump = sdl_context.event_pump().unwrap();
-        return game_loop(game, display, opts, event_pump);
+        return game_loop(game, display, opts, event_pump, seed, daily_date);
     }
 }
 
-pub fn game_loop(mut game: Game, mut display: Display, opts: GameOptions, mut event_pump: sdl2::EventPump) -> Result<(), String> {
+pub fn game_loop(mut game: Game,
+                 mut display: Display,
+                 opts: GameOptions,
+                 mut event_pump: sdl2::EventPump,
+                 seed: u64,
+                 daily_date: Option<String>) -> Result<(), String> {
     // read in the recorded action log, if one is provided
     let mut starting_actions = Vec::new();
     if let Some(replay_file) = &opts.replay {
         starting_actions = read_action_log(&replay_file);
     }
 
-    let mut config_modified_time = fs::metadata(CONFIG_NAME).unwrap().modified().unwrap();
+    let config_path = find_config(CONFIG_NAME, user_config_dir());
+    let mut config_modified_time = fs::metadata(&config_path).unwrap().modified().unwrap();
 
     let mut log = Log::new();
     let mut recording = Recording::new(&game);
 
     /* Setup FPS Throttling */
     let frame_ms = 1000 / game.config.frame_rate as u64;
-    let fps_throttler = Throttler::new(Duration::from_millis(frame_ms));
+    let mut fps_throttler;
+    if game.config.frame_rate_adaptive {
+        fps_throttler = Throttler::adaptive(Duration::from_millis(frame_ms));
+    } else {
+        fps_throttler = Throttler::new(Duration::from_millis(frame_ms));
+    }
 
     /* Set up Input Handling */
     let io_recv = spawn_input_reader();
@@ -225,7 +338,7 @@ pub fn game_loop(mut game: Game, mut display: Display, opts: GameOptions, mut ev
 
             //input_action = process_input_events(frame_time, &mut event_pump, &mut game, &mut display);
             for sdl2_event in event_pump.poll_iter() {
-                if let Some(event) = keyboard::translate_event(sdl2_event, &mut game, &mut display) {
+                if let Some(event) = keyboard::translate_event(sdl2_event, &mut display) {
                     if game.config.recording && matches!(event, InputEvent::Char('[', KeyDir::Up)) {
                         game = recording.backward();
                     } else if game.config.recording && matches!(event, InputEvent::Char(']', KeyDir::Up)) {
@@ -249,8 +362,30 @@ pub fn game_loop(mut game: Game, mut display: Display, opts: GameOptions, mut ev
                 input_action = action;
             }
 
+            // full screen only changes the window, so it is not part of the game's actions
+            if input_action == InputAction::FullScreen {
+                display.toggle_fullscreen()?;
+                input_action = InputAction::None;
+            }
+
+            // saving and loading work on the save file rather than taking a turn, so they
+            // are kept out of the action log that replays are made from. a load starts a
+            // new recording, so undo does not go back past it
+            if input_action == InputAction::SaveGame {
+                game.save_or_report(&game.config.save_file.clone());
+                input_action = InputAction::None;
+            } else if input_action == InputAction::LoadGame {
+                if game.load_or_report(&game.config.save_file.clone()) {
+                    recording = Recording::new(&game);
+                }
+                input_action = InputAction::None;
+            }
+
            /* Record Inputs to Log File */
-           log.log_action(input_action);
+           // undo is not recorded. instead the undone action is removed from the logs
+           if input_action != InputAction::Undo {
+               log.log_action(input_action);
+           }
         }
 
         /* Logic */
@@ -258,19 +393,49 @@ pub fn game_loop(mut game: Game, mut display: Display, opts: GameOptions, mut ev
             let _logic_timer = timer!("LOGIC");
             let dt = Instant::now().duration_since(frame_time).as_secs_f32();
             frame_time = Instant::now();
+
+            // undo restores the game from before the last action, keeping the current key state.
+            // the restored turn's messages were already logged, so they are not logged again.
+            let mut logged_messages = 0;
+            if input_action == InputAction::Undo {
+                if game.settings.god_mode {
+                    if let Some(prev_game) = recording.undo() {
+                        let input = game.input.clone();
+                        game = prev_game;
+                        game.input = input;
+                        logged_messages = game.msg_log.turn_messages.len();
+                        log.undo_action();
+                        display.state.push_msg_line("Undid the last move".to_string(), game.config.msg_panel_lines);
+                    }
+                }
+                input_action = InputAction::None;
+            }
+
+            let was_god_mode = game.settings.god_mode;
             game.step_game(input_action, dt);
-            
-            if game.config.recording && input_action != InputAction::None {
+
+            // god mode records actions so they can be undone. turning it on mid-game starts a
+            // new recording, so undo never goes back past that point
+            let recording_enabled = game.config.recording || game.settings.god_mode;
+            if !game.config.recording && game.settings.god_mode && !was_god_mode {
+                recording = Recording::new(&game);
+            } else if recording_enabled && input_action != InputAction::None {
                 recording.action(&game, input_action);
             }
 
-            for msg_index in 0..game.msg_log.turn_messages.len() {
+            if input_action != InputAction::None {
+                log.log_transcript(input_action, game.msg_log.turn_messages.iter(), &game.data);
+            }
+
+            for msg_index in logged_messages..game.msg_log.turn_messages.len() {
                 let msg = game.msg_log.turn_messages[msg_index];
                 let msg_line = &msg.msg_line(&game.data);
                 if msg_line.len() > 0 {
                     log.log_console(msg_line);
+                    display.state.push_msg_line(msg_line.clone(), game.config.msg_panel_lines);
                 }
                 log.log_msg(&format!("{}", msg));
+                log.log_semantic(&msg);
             }
 
             if game.settings.state == GameState::Win {
@@ -292,7 +457,7 @@ pub fn game_loop(mut game: Game, mut display: Display, opts: GameOptions, mut ev
         /* Configuration */
         {
             let _config_timer = timer!("CONFIG");
-            reload_config(&mut config_modified_time, &mut game);
+            reload_config(&config_path, &mut config_modified_time, &mut game);
         }
 
         /* Wait until the next tick to loop */
@@ -308,9 +473,39 @@ pub fn game_loop(mut game: Game, mut display: Display, opts: GameOptions, mut ev
         save_record(&record_name);
     }
 
+    if let Some(date) = daily_date {
+        save_stats(&game, seed, &date);
+    }
+
     return Ok(());
 }
 
+/// Append the result of a daily challenge run to the stats file.
+fn save_stats(game: &Game, seed: u64, date: &str) {
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let result = if game.data.entities.status[&player_id].alive { "alive" } else { "dead" };
+
+    let stats_line = format!("daily {} seed {} level {} turns {} {}\n",
+                             date, seed, game.settings.level_num, game.settings.turn_count, result);
+
+    // a stats file that can't be written shouldn't take the game down with it
+    let stats_file = std::fs::OpenOptions::new()
+                                  .create(true)
+                                  .append(true)
+                                  .open(STATS_FILE_NAME);
+    match stats_file {
+        Ok(mut stats_file) => {
+            if let Err(err) = stats_file.write_all(stats_line.as_bytes()) {
+                eprintln!("Could not write to stats file {}: {}", STATS_FILE_NAME, err);
+            }
+        }
+
+        Err(err) => {
+            eprintln!("Could not open stats file {}: {}", STATS_FILE_NAME, err);
+        }
+    }
+}
+
 fn save_record(record_name: &str) {
     // create log directory if it doesn't exist
     let path = format!("resources/test_logs/{}", record_name);
@@ -323,11 +518,14 @@ fn save_record(record_name: &str) {
     std::fs::copy(Log::MESSAGE_LOG_NAME, format!("{}/{}", &path, Log::MESSAGE_LOG_NAME))
             .expect("Could not save message log!");
 
+    std::fs::copy(Log::TRANSCRIPT_NAME, format!("{}/{}", &path, Log::TRANSCRIPT_NAME))
+            .expect("Could not save transcript!");
+
     std::fs::copy(MAP_CONFIG_NAME, format!("{}/{}", &path, MAP_CONFIG_NAME))
             .expect("Could not save map config!");
 }
 
-fn reload_config(config_modified_time: &mut SystemTime, game: &mut Game) {
+fn reload_config(config_path: &Path, config_modified_time: &mut SystemTime, game: &mut Game) {
     /* Reload map if configured to do so */
     if game.config.load_map_file_every_frame && Path::new("resources/map.xp").exists() {
         let player = game.data.find_by_name(EntityName::Player).unwrap();
@@ -339,11 +537,11 @@ fn reload_config(config_modified_time: &mut SystemTime, game: &mut Game) {
     }
 
     /* Reload Configuration */
-    if let Ok(current_config_modified_time) = fs::metadata(CONFIG_NAME) {
+    if let Ok(current_config_modified_time) = fs::metadata(config_path) {
         let current_config_modified_time = current_config_modified_time.modified().unwrap();
         if current_config_modified_time != *config_modified_time {
             *config_modified_time = current_config_modified_time;
-            game.config = Config::from_file(CONFIG_NAME);
+            game.config = load_config(CONFIG_NAME, user_config_dir());
         }
     }
 }
@@ -376,7 +574,7 @@ fn update_display(game: &mut Game, display: &mut Display) -> Result<(), String>
 //    let mut input_action: InputAction = InputAction::None;
 //
 //    for sdl2_event in event_pump.poll_iter() {
-//        if let Some(event) = keyboard::translate_event(sdl2_event, game, display) {
+//        if let Some(event) = keyboard::translate_event(sdl2_event, display) {
 //            let action = game.input.handle_event(&mut game.settings, event, frame_time, &game.config);
 //            // NOTE may lose inputs if multiple events create actions!
 //            input_action = action;
@@ -388,13 +586,17 @@ fn update_display(game: &mut Game, display: &mut Display) -> Result<(), String>
 
 fn process_commands(io_recv: &Receiver<String>, game: &mut Game, log: &mut Log) {
     if let Ok(msg) = io_recv.recv_timeout(Duration::from_millis(0)) {
-        if let Ok(cmd) = msg.parse::<GameCmd>() {
-            let result = execute_game_command(&cmd, game);
-            if !result.is_empty() {
-                log.log_output(&result);
+        match msg.parse::<GameCmd>() {
+            Ok(cmd) => {
+                let result = execute_game_command(&cmd, game);
+                if !result.is_empty() {
+                    log.log_output(&result);
+                }
+            }
+
+            Err(err) => {
+                log.log_output(&format_parse_error(&msg, &err));
             }
-        } else {
-            log.log_output(&format!("error '{}' unexpected", msg));
         }
     }
 }
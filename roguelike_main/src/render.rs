@@ -9,15 +9,17 @@ use roguelike_core::map::*;
 use roguelike_core::constants::*;
 use roguelike_core::movement::*;
 use roguelike_core::config::*;
-use roguelike_core::utils::{item_primary_at, lerp_color, sub_pos, reach_by_mode, map_fill_metric};
+use roguelike_core::utils::{item_primary_at, lerp_color, sub_pos, reach_by_mode, map_fill_metric, health_bar_fraction, wrap_text};
 use roguelike_core::perlin::Perlin;
 use roguelike_core::line::line;
 use roguelike_core::ai::*;
 
 use roguelike_engine::game::*;
+use roguelike_engine::actions::InputAction;
+use roguelike_engine::input::action_key;
 
 use crate::display::*;
-use crate::animation::{Sprite, Effect, Animation, AnimationResult};
+use crate::animation::{Sprite, Effect, Animation, AnimationResult, timed_frame};
 
 
 // 10ms to display on last check
@@ -37,8 +39,17 @@ pub fn render_all(display: &mut Display, game: &mut Game)  -> Result<(), String>
     render_panels(display, game, map_rect);
 
     /* Paste Panels on Screen */
-    let map_size = game.data.map.size();
-    render_screen(&mut display.targets, map_size, map_rect);
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = game.data.entities.pos[&player_id];
+    if let Some(intro_pos) = game.intro_camera() {
+        display.update_camera(intro_pos, game.data.map.size(), game.config.intro_camera_lerp, &game.config);
+    } else {
+        display.update_camera(player_pos, game.data.map.size(), game.config.camera_lerp, &game.config);
+    }
+    render_screen(&mut display.targets, display.state.camera, display.state.view_size, map_rect);
+
+    /* Draw Minimap */
+    render_minimap(&mut display.targets.canvas_panel, map_rect, game);
 
     /* Draw Menus */
     render_menus(display, game);
@@ -73,9 +84,13 @@ fn render_panels(display: &mut Display, game: &mut Game, _map_rect: Rect) {
             render_entity_type(EntityType::Column, &mut panel, display_state, game);
             render_entity_type(EntityType::Player, &mut panel, display_state, game);
             render_entity_type(EntityType::Other, &mut panel, display_state, game);
+            render_health_bars(&mut panel, game);
             render_impressions(&mut panel, display_state, game);
             render_effects(&mut panel, display_state, game);
             render_overlays(&mut panel, display_state, game, mouse_map_pos);
+            render_level_transition(&mut panel, game);
+            render_level_banner(&mut panel, display_state, game);
+            render_intro(&mut panel, display_state, game);
         }).unwrap();
     }
 
@@ -114,10 +129,13 @@ fn render_panels(display: &mut Display, game: &mut Game, _map_rect: Rect) {
 }
 
 
-fn render_screen(targets: &mut DisplayTargets, map_size: (i32, i32), map_rect: Rect) {
+fn render_screen(targets: &mut DisplayTargets, camera: (f32, f32), view_size: (i32, i32), map_rect: Rect) {
     // TODO just make the map panel the right size in the first place
     // and re-create it when the map changes.
-    let src = targets.map_panel.get_rect_up_left(map_size.0 as usize, map_size.1 as usize);
+    let (cell_width, cell_height) = targets.map_panel.cell_dims();
+    let mut src = targets.map_panel.get_rect_up_left(view_size.0 as usize, view_size.1 as usize);
+    src.set_x((camera.0 * cell_width as f32) as i32);
+    src.set_y((camera.1 * cell_height as f32) as i32);
     targets.canvas_panel.target.copy(&targets.map_panel.target, src, map_rect).unwrap();
 
     /* Draw Inventory Panel */
@@ -136,6 +154,63 @@ fn render_screen(targets: &mut DisplayTargets, map_size: (i32, i32), map_rect: R
     targets.canvas_panel.target.copy(&targets.player_panel.target, None, dst).unwrap();
 }
 
+/// Draw the whole map in the upper right corner of the map area, scaled so each tile
+/// is a single dot. Only explored tiles are shown unless god mode is on.
+fn render_minimap(canvas_panel: &mut Panel<WindowCanvas>, map_rect: Rect, game: &mut Game) {
+    let size = game.config.minimap_size;
+    if size == 0 {
+        return;
+    }
+
+    let (width, height) = game.data.map.size();
+    let scale = f32::min(size as f32 / width as f32, size as f32 / height as f32);
+    let minimap_width = (width as f32 * scale) as u32;
+    let minimap_height = (height as f32 * scale) as u32;
+
+    let x_offset = map_rect.x() + map_rect.width() as i32 - minimap_width as i32;
+    let y_offset = map_rect.y();
+
+    let canvas = &mut canvas_panel.target;
+    canvas.set_draw_color(Sdl2Color::RGB(0, 0, 0));
+    canvas.fill_rect(Rect::new(x_offset, y_offset, minimap_width, minimap_height)).unwrap();
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let player_pos = game.data.entities.pos[&player_id];
+
+    // dots are at least a pixel, even on maps larger than the minimap
+    let dot_size = std::cmp::max(1, scale.ceil() as u32);
+    let dot_rect = |pos: Pos, dot_size: u32| {
+        Rect::new(x_offset + (pos.x as f32 * scale) as i32,
+                  y_offset + (pos.y as f32 * scale) as i32,
+                  dot_size,
+                  dot_size)
+    };
+
+    for pos in game.data.map.get_all_pos() {
+        let tile = game.data.map[pos];
+        if !tile.explored && !game.settings.god_mode {
+            continue;
+        }
+
+        let visible = game.data.pos_in_fov(player_id, pos, &game.config);
+        let color = match (tile.tile_type, visible) {
+            (TileType::Wall, true) | (TileType::ShortWall, true) => game.config.color_light_brown,
+            (TileType::Wall, false) | (TileType::ShortWall, false) => game.config.color_dark_brown,
+            (TileType::Water, true) => game.config.color_tile_blue_light,
+            (TileType::Water, false) => game.config.color_tile_blue_dark,
+            (TileType::Exit, _) => game.config.color_orange,
+            (TileType::Empty, true) => game.config.color_light_grey,
+            (TileType::Empty, false) => game.config.color_medium_grey,
+        };
+
+        canvas.set_draw_color(sdl2_color(color));
+        canvas.fill_rect(dot_rect(pos, dot_size)).unwrap();
+    }
+
+    canvas.set_draw_color(sdl2_color(game.config.color_red));
+    canvas.fill_rect(dot_rect(player_pos, std::cmp::max(2, dot_size))).unwrap();
+}
+
 fn render_menus(display: &mut Display, game: &mut Game) {
     let canvas_panel = &mut display.targets.canvas_panel;
     let display_state = &mut display.state;
@@ -432,9 +507,34 @@ fn render_info(panel: &mut Panel<&mut WindowCanvas>,
 
             tile_sprite.draw_text_list(panel, &text_list, text_pos, text_color);
         }
+    } else {
+        render_msg_panel(panel, display_state, game);
     }
 }
 
+/// Show the most recent messages, wrapped to the panel width, scrolled back by the
+/// player's current scroll amount.
+fn render_msg_panel(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayState, game: &mut Game) {
+    let width = panel.cells.0 as usize - 2;
+    let height = panel.cells.1 as usize - 2;
+
+    let lines = display_state.msg_lines.iter()
+                                       .flat_map(|line| wrap_text(line, width))
+                                       .collect::<Vec<String>>();
+
+    // scrolling stops at the oldest line
+    let max_scroll = lines.len().saturating_sub(height);
+    game.settings.msg_scroll = std::cmp::min(game.settings.msg_scroll, max_scroll);
+
+    let end = lines.len() - game.settings.msg_scroll;
+    let start = end.saturating_sub(height);
+    let text_list = lines[start..end].to_vec();
+
+    let sprite_key = display_state.lookup_spritekey("tiles");
+    let tile_sprite = &mut display_state.sprites[&sprite_key];
+    tile_sprite.draw_text_list(panel, &text_list, Pos::new(1, 1), game.config.color_soft_green);
+}
+
 fn render_skill_menu(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayState, game: &mut Game) {
     let player_id = game.data.find_by_name(EntityName::Player).unwrap();
 
@@ -507,15 +607,30 @@ fn render_inventory(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut Di
     let sprite_key = display_state.lookup_spritekey("tiles");
     let tile_sprite = &mut display_state.sprites[&sprite_key];
 
-    // Render each object's name in inventory
+    // Render each object's name on the current page, with a header for each category
     let mut y_pos = 2;
     let mut item_index = 0;
 
-    let mut index = 0;
-    while index < game.data.entities.inventory[&player_id].len() {
+    let num_items = game.data.entities.inventory[&player_id].len();
+    let page_size = std::cmp::max(1, game.config.inventory_page_size);
+    let page_start = game.settings.inventory_page * page_size;
+    let page_end = std::cmp::min(num_items, page_start + page_size);
+
+    let mut category = None;
+    let mut index = page_start;
+    while index < page_end {
         let obj_id = game.data.entities.inventory[&player_id][index];
         index += 1;
 
+        let obj_category = game.data.entities.item.get(&obj_id).map(|item| item.category());
+        if obj_category != category {
+            category = obj_category;
+            if let Some(category) = category {
+                tile_sprite.draw_text(panel, &format!("{}", category), Pos::new(1, y_pos), game.config.color_light_grey);
+                y_pos += 1;
+            }
+        }
+
         let color;
         match &game.data.entities.item.get(&obj_id) {
             Some(Item::Stone) => {
@@ -542,6 +657,8 @@ fn render_inventory(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut Di
         let item_marker =
             if index == 0 && item_primary_at(player_id, &mut game.data.entities, 0) {
                 "<"
+            } else if game.settings.selected_item == Some(obj_id) {
+                "*"
             } else {
                 ""
             };
@@ -553,9 +670,15 @@ fn render_inventory(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut Di
         item_index += 1;
     }
 
-    if game.data.entities.inventory[&player_id].len() == 0 {
+    if num_items == 0 {
         let text_pos = Pos::new(1, y_pos);
         tile_sprite.draw_text(panel, &format!("empty"), text_pos, game.config.color_ice_blue);
+    } else if num_items > page_size {
+        let num_pages = (num_items + page_size - 1) / page_size;
+        let prev_key = action_key(InputAction::PrevPage, &game.config).unwrap_or(' ');
+        let next_key = action_key(InputAction::NextPage, &game.config).unwrap_or(' ');
+        let page_text = format!("page {}/{} ({}/{})", game.settings.inventory_page + 1, num_pages, prev_key, next_key);
+        tile_sprite.draw_text(panel, &page_text, Pos::new(1, y_pos + 1), game.config.color_ice_blue);
     }
 }
 
@@ -601,14 +724,14 @@ fn render_background(display: &mut Display, game: &mut Game) {
     }).unwrap();
 }
 
-fn render_surface(panel: &mut Panel<&mut WindowCanvas>, sprite: &mut SpriteSheet, surface: Surface, pos: Pos) {
+fn render_surface(panel: &mut Panel<&mut WindowCanvas>, sprite: &mut SpriteSheet, surface: Surface, pos: Pos, color: Color) {
     match surface {
         Surface::Rubble => {
-            sprite.draw_char(panel, MAP_RUBBLE as char, pos, Color::white());
+            sprite.draw_char(panel, MAP_RUBBLE as char, pos, color);
         }
 
         Surface::Grass => {
-            sprite.draw_char(panel, MAP_GRASS as char, pos, Color::white()); //game.config.color_light_green);
+            sprite.draw_char(panel, MAP_GRASS as char, pos, color); //game.config.color_light_green);
         }
 
         Surface::Floor => {
@@ -718,20 +841,48 @@ fn render_map(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayS
 
             let tile = game.data.map[pos];
 
+            // tiles just past the edge of the player's FOV are hinted at
+            let mut is_in_fov_ext = false;
+            if game.config.fog_of_war && !visible {
+                game.data.entities.status[&player_id].extra_fov += 1;
+                is_in_fov_ext = game.data.pos_in_fov(player_id, pos, &game.config);
+                game.data.entities.status[&player_id].extra_fov -= 1;
+            }
+
+            // tiles that have never been seen are left black
+            if game.config.fog_of_war && !visible && !is_in_fov_ext && !tile.explored {
+                let sprite = &mut display_state.sprites[&sprite_key];
+                sprite.draw_char(panel, MAP_EMPTY_CHAR as char, pos, Color::black());
+                continue;
+            }
+
+            // tiles remembered from earlier turns are drawn greyed out
+            let tile_color =
+                if game.config.fog_of_war && !visible {
+                    game.config.color_light_grey
+                } else {
+                    Color::white()
+                };
+
             let chr = tile.chr;
 
             // if the tile is not empty or water, draw it
             if tile.tile_type == TileType::Water {
                 let sprite = &mut display_state.sprites[&sprite_key];
-                sprite.draw_char(panel, MAP_WATER as char, pos, Color::white());
+                let mut water_chr = MAP_WATER as u32;
+                if game.config.water_frames.len() > 0 {
+                    water_chr = timed_frame(&game.config.water_frames, game.config.water_fps, game.settings.time);
+                }
+                // water frames are checked to be valid characters when the config is loaded
+                sprite.draw_char(panel, std::char::from_u32(water_chr).unwrap(), pos, tile_color);
             } else if chr != MAP_EMPTY_CHAR {
                 let sprite = &mut display_state.sprites[&sprite_key];
-                sprite.draw_char(panel, chr as char, pos, Color::white());
+                sprite.draw_char(panel, chr as char, pos, tile_color);
             }
 
             {
                 let sprite = &mut display_state.sprites[&sprite_key];
-                render_surface(panel, sprite, tile.surface, pos);
+                render_surface(panel, sprite, tile.surface, pos, tile_color);
             }
 
             render_wall_shadow(pos, panel, display_state, game);
@@ -739,16 +890,11 @@ fn render_map(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayS
             /* draw the between-tile walls appropriate to this tile */
             {
                 let sprite = &mut display_state.sprites[&sprite_key];
-                render_itertile_walls(panel, &mut game.data.map, sprite, pos, &game.config);
+                render_itertile_walls(panel, &mut game.data.map, sprite, pos, tile_color);
             }
 
             // apply a FoW darkening to cells
             if game.config.fog_of_war && !visible {
-                game.data.entities.status[&player_id].extra_fov += 1;
-                let is_in_fov_ext = 
-                   game.data.pos_in_fov(player_id, pos, &game.config);
-                game.data.entities.status[&player_id].extra_fov -= 1;
-
                 let mut blackout_color = Color::black();
                 if is_in_fov_ext {
                     blackout_color.a = game.config.fov_edge_alpha
@@ -758,6 +904,15 @@ fn render_map(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayS
                 
                 let sprite = &mut display_state.sprites[&sprite_key];
                 sprite.draw_char(panel, MAP_EMPTY_CHAR as char, pos, blackout_color);
+            } else if game.config.fog_of_war &&
+                      !game.settings.god_mode &&
+                      !game.data.pos_in_direct_fov(player_id, pos, &game.config) {
+                // tiles seen only by another light source are dimmed
+                let mut dim_color = Color::black();
+                dim_color.a = game.config.lit_alpha;
+
+                let sprite = &mut display_state.sprites[&sprite_key];
+                sprite.draw_char(panel, MAP_EMPTY_CHAR as char, pos, dim_color);
             }
         }
     }
@@ -767,10 +922,9 @@ fn render_itertile_walls(panel: &mut Panel<&mut WindowCanvas>,
                          map: &Map,
                          sprite: &mut SpriteSheet,
                          pos: Pos,
-                         _config: &Config) {
+                         wall_color: Color) {
     let (x, y) = pos.to_tuple();
     let tile = map[pos];
-    let wall_color = Color::white();
 
     // Lower walls
     if tile.bottom_wall == Wall::ShortWall {
@@ -927,6 +1081,7 @@ fn render_entity(panel: &mut Panel<&mut WindowCanvas>,
     // and not about to be removed.
     if !game.data.map.is_within_bounds(pos) ||
        game.data.entities.needs_removal[&entity_id] {
+           display_state.drawn_pos.remove(&entity_id);
            return None;
     }
 
@@ -937,7 +1092,7 @@ fn render_entity(panel: &mut Panel<&mut WindowCanvas>,
     if is_in_fov {
         if let Some(mut anims) = display_state.animations.swap_remove(&entity_id) {
             if let Some(mut anim) = anims.pop_front() {
-                animation_result = anim.step(pos, game.settings.dt, &game.config);
+                animation_result = anim.step(pos, game.settings.dt, game.settings.time, &game.config);
 
                 if let Animation::PlayEffect(effect) = anim {
                     display_state.play_effect(effect);
@@ -950,7 +1105,9 @@ fn render_entity(panel: &mut Panel<&mut WindowCanvas>,
                             color = game.config.color_warm_grey;
                         }
 
-                        display_state.draw_sprite(panel, sprite, animation_result.pos, color);
+                        let offset = display_state.move_offset(entity_id, animation_result.pos, game.settings.time, &game.config);
+                        display_state.draw_sprite_offset(panel, sprite, animation_result.pos, offset, color);
+                        display_state.drawn_pos.insert(entity_id, animation_result.pos);
                     }
 
                     // for animations other then effects, keep playing by pushing to front of
@@ -968,10 +1125,14 @@ fn render_entity(panel: &mut Panel<&mut WindowCanvas>,
             let tiles = display_state.lookup_spritekey("tiles");
             let chr = game.data.entities.chr[&entity_id];
             let sprite = Sprite::new(chr as u32, tiles);
-            display_state.draw_sprite(panel, sprite, pos, color);
+            let offset = display_state.move_offset(entity_id, pos, game.settings.time, &game.config);
+            display_state.draw_sprite_offset(panel, sprite, pos, offset, color);
+            display_state.drawn_pos.insert(entity_id, pos);
             animation_result.sprite = Some(sprite);
         }
     } else {
+        display_state.drawn_pos.remove(&entity_id);
+
         // if not in FoV, see if we need to add an impression for a golem
         if game.data.entities.typ[&entity_id] == EntityType::Enemy {
             game.data.entities.status[&player_id].extra_fov += 1;
@@ -992,6 +1153,40 @@ fn render_entity(panel: &mut Panel<&mut WindowCanvas>,
     return animation_result.sprite;
 }
 
+fn render_health_bars(panel: &mut Panel<&mut WindowCanvas>, game: &mut Game) {
+    if !game.config.health_bars {
+        return;
+    }
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let cell_dims = panel.cell_dims();
+
+    panel.target.set_blend_mode(BlendMode::None);
+    panel.target.set_draw_color(sdl2_color(game.config.health_bar_color));
+
+    for entity_id in game.data.entities.ids.clone().iter() {
+        let pos = game.data.entities.pos[entity_id];
+        if !game.data.map.is_within_bounds(pos) || game.data.entities.needs_removal[entity_id] {
+            continue;
+        }
+
+        if !game.settings.god_mode && !game.data.is_in_fov(player_id, *entity_id, &game.config) {
+            continue;
+        }
+
+        if let Some(fighter) = game.data.entities.fighter.get(entity_id) {
+            if let Some(fraction) = health_bar_fraction(fighter, game.config.health_bar_hide_full) {
+                // the bar sits along the top of the entity's tile
+                let bar_rect = Rect::new(pos.x * cell_dims.0 as i32,
+                                         pos.y * cell_dims.1 as i32,
+                                         (cell_dims.0 as f32 * fraction) as u32,
+                                         game.config.health_bar_height);
+                panel.target.fill_rect(bar_rect).unwrap();
+            }
+        }
+    }
+}
+
 fn render_impressions(panel: &mut Panel<&mut WindowCanvas>, display_state: &mut DisplayState, game: &mut Game) {
     // check for entities that have left FOV and make an impression for them
     let mut index = 0;
@@ -1018,6 +1213,63 @@ fn render_entity_type(typ: EntityType, panel: &mut Panel<&mut WindowCanvas>, dis
     }
 }
 
+/// Fade in a new level by covering the map with black that clears over the transition.
+fn render_level_transition(panel: &mut Panel<&mut WindowCanvas>, game: &mut Game) {
+    if let Some(transition_start) = game.settings.level_transition {
+        let duration = game.config.level_transition_duration;
+        if duration <= 0.0 {
+            return;
+        }
+
+        let elapsed = game.settings.time - transition_start;
+        let fade = 1.0 - (elapsed / duration).min(1.0).max(0.0);
+
+        panel.target.set_blend_mode(BlendMode::Blend);
+        panel.target.set_draw_color(Sdl2Color::RGBA(0, 0, 0, (fade * 255.0) as u8));
+        panel.target.fill_rect(None).unwrap();
+    }
+}
+
+fn render_level_banner(panel: &mut Panel<&mut WindowCanvas>,
+                       display_state: &mut DisplayState,
+                       game: &mut Game) {
+    if let Some(banner) = &game.settings.level_banner {
+        let text = format!("Level {} - {}", banner.level_num + 1, banner.theme);
+
+        // center the banner along the top of the map
+        let (map_width, _map_height) = game.data.map.size();
+        let text_start = std::cmp::max(0, (map_width - text.len() as i32) / 2);
+        let text_pos = Pos::new(text_start, 1);
+
+        // draw the banner like a placard header, using the same colors as the UI panels
+        let (cell_width, cell_height) = panel.cell_dims();
+        panel.target.set_draw_color(sdl2_color(game.config.color_mint_green));
+        panel.target.fill_rect(Rect::new(text_pos.x * cell_width as i32 - 3,
+                                         text_pos.y * cell_height as i32,
+                                         (text.len() * cell_width as usize) as u32 + 6,
+                                         cell_height as u32)).unwrap();
+
+        let sprite_key = display_state.lookup_spritekey("tiles");
+        let tile_sprite = &mut display_state.sprites[&sprite_key];
+        tile_sprite.draw_text(panel, &text, text_pos, game.config.color_dark_blue);
+    }
+}
+
+fn render_intro(panel: &mut Panel<&mut WindowCanvas>,
+                display_state: &mut DisplayState,
+                game: &mut Game) {
+    if let Some(text) = game.intro_message() {
+        // center the message in the middle of the map
+        let (map_width, map_height) = game.data.map.size();
+        let text_start = std::cmp::max(0, (map_width - text.len() as i32) / 2);
+        let text_pos = Pos::new(text_start, map_height / 2);
+
+        let sprite_key = display_state.lookup_spritekey("tiles");
+        let tile_sprite = &mut display_state.sprites[&sprite_key];
+        tile_sprite.draw_text(panel, text, text_pos, game.config.color_light_orange);
+    }
+}
+
 fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
                    display_state: &mut DisplayState,
                    game: &mut Game,
@@ -1061,6 +1313,17 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
             color.a = 230;
             tile_sprite.draw_char(panel, ENTITY_CURSOR as char, cursor_pos, color);
 
+            // highlight where the player could move this turn
+            if game.config.overlay_reachable {
+                let mut reachable_color = game.config.color_mint_green;
+                reachable_color.a = game.config.grid_alpha_overlay;
+
+                let reach = reach_by_mode(game.data.entities.move_mode[&player_id]);
+                for pos in reachable_positions(player_id, reach, &game.data, &game.config) {
+                    draw_tile_highlight(panel, pos, reachable_color);
+                }
+            }
+
             // render player ghost
             if cursor_pos != player_pos && game.input.target == None {
                 let alpha = game.data.entities.color[&player_id].a;
@@ -1084,6 +1347,16 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
         }
     }
 
+    // render the travel path to the tile under the mouse
+    if game.config.overlay_path_preview {
+        let mut preview_color = game.config.color_light_orange;
+        preview_color.a = game.config.grid_alpha_overlay;
+
+        for pos in game.settings.path_preview.iter() {
+            draw_tile_highlight(panel, *pos, preview_color);
+        }
+    }
+
     // render FOV if enabled
     if game.config.overlay_player_fov {
         let tile_sprite = &mut display_state.sprites[&sprite_key];
@@ -1188,7 +1461,7 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
     }
 
     // render attack overlay highlighting squares that an entity can attack
-    if game.settings.overlay {
+    if game.settings.overlay_enabled(Overlay::Attack) {
         let keys = game.data.entities.ids.iter().map(|id| *id).collect::<Vec<EntityId>>();
         for entity_id in keys {
             let pos = game.data.entities.pos[&entity_id];
@@ -1234,7 +1507,7 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
     }
 
     // Draw player movement overlay
-    if game.settings.overlay {
+    if game.settings.overlay_enabled(Overlay::Movement) {
         for move_action in Direction::move_actions().iter() {
             // for all movements except staying still
             // calculate the move that would occur
@@ -1242,7 +1515,8 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
                 calculate_move(*move_action,
                                game.data.entities.movement[&player_id],
                                player_id,
-                               &mut game.data) {
+                               &game.data,
+                               &game.config) {
                 // draw a highlight on that square
                 // don't draw overlay on top of character
                 if movement.pos != game.data.entities.pos[&player_id] {
@@ -1257,7 +1531,7 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
     }
 
     // Draw sound tiles overlay
-    if game.settings.overlay {
+    if game.settings.overlay_enabled(Overlay::Sound) {
         // NOTE this currently does not take into account FOV!
         for pos in display_state.sound_tiles.iter() {
             draw_tile_highlight(panel, *pos, highlight_color);
@@ -1265,7 +1539,7 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
     }
 
     // Outline tiles within FOV for clarity
-    if game.settings.overlay {
+    if game.settings.overlay_enabled(Overlay::Fov) {
         let mut highlight_color_fov = game.config.color_light_orange;
         highlight_color_fov.a = game.config.grid_alpha_visible;
 
@@ -1323,8 +1597,8 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
 
             if game.data.is_in_fov(player_id, *entity_id, &game.config) {
                 let mut status_drawn: bool = false;
-                if let Some(status) = game.data.entities.status.get(entity_id) {
-                    if status.frozen > 0 {
+                if let Some(statuses) = game.data.entities.statuses.get(entity_id) {
+                    if statuses.contains_key(&StatusKind::Frozen) {
                         status_drawn = true;
                         tile_sprite.draw_sprite_direction(panel,
                                                           ASTERISK as usize,
@@ -1361,6 +1635,12 @@ fn render_overlays(panel: &mut Panel<&mut WindowCanvas>,
                                                                   alertness_color,
                                                                   0.0);
                             }
+
+                            Behavior::Fleeing(_) => {
+                            }
+
+                            Behavior::Patrolling(_, _) => {
+                            }
                         }
                     }
                 }
@@ -34,6 +34,7 @@ pub struct Recording {
     states: Vec<Game>,
     inputs: Vec<InputAction>,
     cursor: usize,
+    later_states: Vec<Game>, // states after the cursor, with the next state last
 }
 
 impl Recording {
@@ -44,11 +45,16 @@ impl Recording {
             states: states,
             inputs: Vec::new(),
             cursor: 0,
+            later_states: Vec::new(),
         };
     }
 
     pub fn forward(self: &mut Recording) -> Option<Game> {
-        if let Some(action) = self.inputs.get(self.cursor) {
+        if let Some(game) = self.later_states.pop() {
+            self.states.push(game.clone());
+            self.cursor += 1;
+            return Some(game);
+        } else if let Some(action) = self.inputs.get(self.cursor) {
             let mut game = self.states[self.states.len() - 1].clone();
             game.step_game(*action, 0.1);
             let return_game = game.clone();
@@ -63,24 +69,69 @@ impl Recording {
     pub fn backward(self: &mut Recording) -> Game {
         if self.cursor > 0 {
             self.cursor = self.cursor - 1;
-            self.states.pop();
+            let game = self.states.pop().unwrap();
+            self.later_states.push(game);
         }
 
         return self.states[self.states.len() - 1].clone();
     }
 
+    /// Move to the game state after the given number of recorded actions, clamped to the actions
+    /// recorded. The states passed over are kept, so seeking in either direction restores the
+    /// nearest stored state and only simulates actions past the last one stored.
+    pub fn seek(self: &mut Recording, turn: usize) -> Option<Game> {
+        let turn = std::cmp::min(turn, self.inputs.len());
+
+        if turn < self.cursor {
+            for game in self.states.drain(turn + 1..).rev() {
+                self.later_states.push(game);
+            }
+            self.cursor = turn;
+        }
+
+        while self.cursor < turn {
+            self.forward()?;
+        }
+
+        return self.states.last().map(|game| game.clone());
+    }
+
     pub fn action(self: &mut Recording, game: &Game, action: InputAction) {
+        // a new action changes what happens after it
+        self.later_states.clear();
         self.states.push(game.clone());
         self.inputs.insert(self.cursor, action);
         self.cursor += 1;
     }
 
+    /// Drop the last recorded action, returning the game as it was before the action.
+    /// Nothing is undone at the start of the recording, or if the action started a new level.
+    pub fn undo(self: &mut Recording) -> Option<Game> {
+        if self.cursor == 0 {
+            return None;
+        }
+
+        let current_level = self.states[self.states.len() - 1].settings.level_num;
+        let previous_level = self.states[self.states.len() - 2].settings.level_num;
+        if current_level != previous_level {
+            return None;
+        }
+
+        self.states.pop();
+        self.inputs.remove(self.cursor - 1);
+        self.cursor -= 1;
+        self.later_states.clear();
+
+        return self.states.last().map(|game| game.clone());
+    }
+
     pub fn clear(self: &mut Recording) {
         let original = self.states[0].clone();
         self.states.clear();
         self.states.push(original);
         self.inputs.clear();
         self.cursor = 0;
+        self.later_states.clear();
     }
 }
 
@@ -149,10 +200,44 @@ pub fn test_recording() {
     assert_eq!(starting_pos.y + 2, step3_2_pos.y);
 }
 
+#[test]
+pub fn test_recording_seek() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::TestPlayer;
+    let mut game = Game::new(0, config.clone());
 
-pub const MAP_CONFIG_NAME: &str = "map_config.txt";
+    make_map(&config.map_load, &mut game);
 
-pub fn check_all_records(game: &mut Game, display: &mut Display, event_pump: &mut sdl2::EventPump, delay_ms: u64) -> Result<(), String> {
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let starting_pos = game.data.entities.pos[&player_id];
+
+    let mut recording = Recording::new(&game);
+
+    let input_action = InputAction::Move(Direction::Down, MoveMode::Walk);
+    for turn in 0..3 {
+        game.step_game(input_action, 0.1);
+
+        // mark the last stored state, so restoring it can be told apart from simulating it again
+        if turn == 2 {
+            game.settings.god_mode = true;
+        }
+        recording.action(&game, input_action);
+    }
+
+    let game = recording.seek(1).unwrap();
+    assert_eq!(starting_pos.y + 1, game.data.entities.pos[&player_id].y);
+
+    let game = recording.seek(0).unwrap();
+    assert_eq!(starting_pos, game.data.entities.pos[&player_id]);
+
+    // seeking past the end stops at the last action, restored from its stored state
+    let game = recording.seek(100).unwrap();
+    assert_eq!(starting_pos.y + 3, game.data.entities.pos[&player_id].y);
+    assert!(game.settings.god_mode);
+}
+
+
+pub fn check_all_records(game: &mut Game, display: &mut Display, event_pump: &mut sdl2::EventPump, delay_ms: u64, compare: LogCompare) -> Result<(), String> {
     let mut results: Vec<(String, ReplayResult)> = Vec::new();
 
     for entry in fs::read_dir("resources/test_logs/").unwrap() {
@@ -162,7 +247,7 @@ pub fn check_all_records(game: &mut Game, display: &mut Display, event_pump: &mu
         let record_name = record_path.rsplit("/").next().unwrap();
 
         let mut local_game = game.clone();
-        let result = check_record(&mut local_game, display, event_pump, record_name, delay_ms);
+        let result = check_record(&mut local_game, display, event_pump, record_name, delay_ms, compare);
 
         results.push((record_name.to_string(), result));
     }
@@ -187,12 +272,12 @@ pub fn check_all_records(game: &mut Game, display: &mut Display, event_pump: &mu
     return Ok(());
 }
 
-pub fn check_single_record(game: &mut Game, display: &mut Display, event_pump: &mut sdl2::EventPump, record_name: &str, delay_ms: u64) -> Result<(), String> {
-    check_record(game, display, event_pump, record_name, delay_ms);
+pub fn check_single_record(game: &mut Game, display: &mut Display, event_pump: &mut sdl2::EventPump, record_name: &str, delay_ms: u64, compare: LogCompare) -> Result<(), String> {
+    check_record(game, display, event_pump, record_name, delay_ms, compare);
     return Ok(());
 }
 
-fn check_record(game: &mut Game, display: &mut Display, event_pump: &mut sdl2::EventPump, record_name: &str, delay_ms: u64) -> ReplayResult {
+fn check_record(game: &mut Game, display: &mut Display, event_pump: &mut sdl2::EventPump, record_name: &str, delay_ms: u64, compare: LogCompare) -> ReplayResult {
     let path = format!("resources/test_logs/{}", record_name);
 
     let map_config_path = format!("{}/{}", path, MAP_CONFIG_NAME);
@@ -207,33 +292,50 @@ fn check_record(game: &mut Game, display: &mut Display, event_pump: &mut sdl2::E
     let message_path = format!("{}/{}", path, Log::MESSAGE_LOG_NAME);
     let logged_lines = read_message_log(&message_path);
 
-    let prefix = "MSG: ";
+    let prefix = format!("{}: ", compare.log_type());
     let mut old_messages = logged_lines.iter()
-                                       .filter(|line| line.starts_with(prefix))
+                                       .filter(|line| line.starts_with(&prefix))
                                        .map(|line| line[prefix.len()..].to_string())
                                        .collect::<Vec<String>>();
     old_messages.reverse();
     let old_messages = old_messages;
 
+    if compare == LogCompare::Semantic && old_messages.len() == 0 {
+        eprintln!("Record '{}' has no semantic messages- re-record it to check semantically", record_name);
+    }
+
+    let ordered_lines = logged_lines.iter().rev().map(|line| line.clone()).collect::<Vec<String>>();
+    let old_turns = split_turns(&ordered_lines, compare.log_type());
+    if old_turns.is_none() {
+        eprintln!("Record '{}' has no turn markers- re-record it to find the first differing turn", record_name);
+    }
+
     let mut new_messages: Vec<String> = Vec::new();
+    let mut new_turns: Vec<Vec<String>> = Vec::new();
+
+    // messages from creating the map are resolved before the first action, as in the game
+    game.step_game(InputAction::None, delay_ms as f32 / 1000.0);
+    new_turns.push(game.msg_log.turn_messages.iter().map(|msg| compare.msg_line(msg)).collect());
+    new_messages.extend(new_turns[0].iter().map(|line| line.clone()));
+    game.msg_log.clear();
 
     let delay = Duration::from_millis(delay_ms);
     for action in actions {
-        if action == InputAction::Exit {
-            break;
-        }
-
-        game.step_game(action, delay_ms as f32);
+        game.step_game(action, delay_ms as f32 / 1000.0);
 
         for _sdl2_event in event_pump.poll_iter() {
         }
 
         update_display(game, display).unwrap();
 
-        for msg in &game.msg_log.turn_messages {
-            new_messages.push(msg.to_string());
-        }
+        let turn_messages = game.msg_log.turn_messages.iter().map(|msg| compare.msg_line(msg)).collect::<Vec<String>>();
+        new_messages.extend(turn_messages.iter().map(|line| line.clone()));
+        new_turns.push(turn_messages);
         game.msg_log.clear();
+
+        if action == InputAction::Exit {
+            break;
+        }
         std::thread::sleep(delay);
     }
 
@@ -266,6 +368,10 @@ fn check_record(game: &mut Game, display: &mut Display, event_pump: &mut sdl2::E
         msg_index += 1;
     }
 
+    if let Some(old_turns) = old_turns {
+        report_turn_difference(&old_turns, &new_turns);
+    }
+
     let mut log = Log::new();
     eprintln!("\nNew Log:");
     for msg in new_messages.iter() {
@@ -332,6 +438,27 @@ fn check_record(game: &mut Game, display: &mut Display, event_pump: &mut sdl2::E
     return result;
 }
 
+/// Print the first turn whose messages differ, along with the expected and actual messages.
+/// The output only depends on the messages, so it can be diffed between runs.
+fn report_turn_difference(old_turns: &Vec<Vec<String>>, new_turns: &Vec<Vec<String>>) {
+    if let Some(turn) = first_difference(old_turns, new_turns) {
+        let empty = Vec::new();
+
+        eprintln!("");
+        eprintln!("First difference on turn {}", turn);
+
+        eprintln!("Expected messages:");
+        for line in old_turns.get(turn).unwrap_or(&empty) {
+            eprintln!("    {}", line);
+        }
+
+        eprintln!("Actual messages:");
+        for line in new_turns.get(turn).unwrap_or(&empty) {
+            eprintln!("    {}", line);
+        }
+    }
+}
+
 pub fn rerecord_all(game: &mut Game, display: &mut Display, event_pump: &mut sdl2::EventPump, delay_ms: u64) -> Result<(), String> {
     let mut results: Vec<String> = Vec::new();
 
@@ -374,9 +501,17 @@ fn rerecord(game: &mut Game, display: &mut Display, event_pump: &mut sdl2::Event
 
     let mut log = Log::new();
 
+    game.step_game(InputAction::None, delay_ms as f32 / 1000.0);
+    for msg in &game.msg_log.turn_messages {
+        log.log_msg(&format!("{}", msg));
+        log.log_semantic(msg);
+    }
+    game.msg_log.clear();
+
     let delay = Duration::from_millis(delay_ms);
     for action in actions {
-        game.step_game(action, delay_ms as f32);
+        log.log_action(action);
+        game.step_game(action, delay_ms as f32 / 1000.0);
 
         for _sdl2_event in event_pump.poll_iter() { }
 
@@ -384,14 +519,16 @@ fn rerecord(game: &mut Game, display: &mut Display, event_pump: &mut sdl2::Event
 
         for msg in &game.msg_log.turn_messages {
             log.log_msg(&format!("{}", msg));
+            log.log_semantic(msg);
         }
         game.msg_log.clear();
+
+        // the recorded actions end with an exit, so stop there as check_record does
+        if action == InputAction::Exit {
+            break;
+        }
         std::thread::sleep(delay);
     }
-    game.step_game(InputAction::Exit, delay_ms as f32);
-    for msg in &game.msg_log.turn_messages {
-        log.log_msg(&format!("{}", msg));
-    }
 
     std::fs::copy(Log::MESSAGE_LOG_NAME, message_path)
             .expect("Could not save message log!");
@@ -442,3 +579,39 @@ fn update_display(game: &mut Game, display: &mut Display) -> Result<(), String>
     return Ok(());
 }
 
+
+#[test]
+pub fn test_recording_undo() {
+    let mut config = Config::from_file("../config.yaml");
+    config.map_load = MapLoadConfig::TestPlayer;
+    let mut game = Game::new(0, config.clone());
+
+    make_map(&config.map_load, &mut game);
+
+    let player_id = game.data.find_by_name(EntityName::Player).unwrap();
+    let starting_pos = game.data.entities.pos[&player_id];
+
+    let mut recording = Recording::new(&game);
+    assert!(recording.undo().is_none());
+
+    let input_action = InputAction::Move(Direction::Down, MoveMode::Walk);
+    for _ in 0..2 {
+        game.step_game(input_action, 0.1);
+        recording.action(&game, input_action);
+    }
+
+    let game = recording.undo().unwrap();
+    assert_eq!(starting_pos.y + 1, game.data.entities.pos[&player_id].y);
+
+    // an undone action is dropped, so it is not replayed
+    assert!(recording.forward().is_none());
+
+    let mut game = recording.undo().unwrap();
+    assert_eq!(starting_pos, game.data.entities.pos[&player_id]);
+    assert!(recording.undo().is_none());
+
+    // actions that start a new level can't be undone
+    game.settings.level_num += 1;
+    recording.action(&game, input_action);
+    assert!(recording.undo().is_none());
+}
@@ -3,9 +3,28 @@ use std::sync::mpsc::{channel, Receiver};
 use std::time::{Duration, Instant};
 
 
+// the most time an adaptive throttler will try to make up after slow frames, in frames
+const MAX_CATCH_UP_FRAMES: u32 = 2;
+
+// weight of the newest frame in the average frame time
+const FRAME_TIME_SMOOTHING: f32 = 0.1;
+
+enum ThrottleMode {
+    Fixed {
+        tick_receiver: Receiver<usize>,
+        _thread: thread::JoinHandle<()>,
+    },
+    Adaptive {
+        target: Duration,
+        last_frame: Instant,
+        behind: Duration,
+    },
+}
+
 pub struct Throttler {
-    tick_receiver: Receiver<usize>,
-    thread: thread::JoinHandle<()>,
+    mode: ThrottleMode,
+    average_frame_time: Duration,
+    last_wait: Instant,
 }
 
 impl Throttler {
@@ -35,20 +54,85 @@ impl Throttler {
                 tick_error = current_time.duration_since(last_tick)
                                          .checked_sub(tick_length)
                                          .unwrap_or(Duration::from_secs(0));
-                last_tick = current_time; 
+                last_tick = current_time;
 
                 ticks += 1;
             }
         });
 
         return Throttler {
-            tick_receiver,
-            thread,
+            mode: ThrottleMode::Fixed { tick_receiver, _thread: thread },
+            average_frame_time: tick_length,
+            last_wait: Instant::now(),
         };
     }
 
-    pub fn wait(&self) {
-        self.tick_receiver.recv().unwrap();
+    /// A throttler that measures how long each frame took, only sleeping for the rest of the
+    /// target frame time. When frames run long the sleep is skipped, and the following frames
+    /// are shortened to catch up by at most a few frames worth of time.
+    pub fn adaptive(target: Duration) -> Throttler {
+        return Throttler {
+            mode: ThrottleMode::Adaptive { target, last_frame: Instant::now(), behind: Duration::from_secs(0) },
+            average_frame_time: target,
+            last_wait: Instant::now(),
+        };
+    }
+
+    pub fn wait(&mut self) {
+        match &mut self.mode {
+            ThrottleMode::Fixed { tick_receiver, .. } => {
+                tick_receiver.recv().unwrap();
+            }
+
+            ThrottleMode::Adaptive { target, last_frame, behind } => {
+                let frame_time = last_frame.elapsed();
+
+                if frame_time >= *target {
+                    let max_behind = *target * MAX_CATCH_UP_FRAMES;
+                    *behind = std::cmp::min(max_behind, *behind + (frame_time - *target));
+                } else {
+                    let spare_time = *target - frame_time;
+                    let caught_up = std::cmp::min(spare_time, *behind);
+                    *behind -= caught_up;
+                    thread::sleep(spare_time - caught_up);
+                }
+
+                *last_frame = Instant::now();
+            }
+        }
+
+        let frame_time = self.last_wait.elapsed().as_secs_f32();
+        let average = self.average_frame_time.as_secs_f32();
+        self.average_frame_time =
+            Duration::from_secs_f32(average + FRAME_TIME_SMOOTHING * (frame_time - average));
+        self.last_wait = Instant::now();
+    }
+
+    /// The average time between waits, including any time spent sleeping.
+    pub fn average_frame_time(&self) -> Duration {
+        return self.average_frame_time;
+    }
+
+    pub fn fps(&self) -> f32 {
+        return 1.0 / self.average_frame_time.as_secs_f32().max(0.0001);
     }
 }
 
+#[test]
+pub fn test_adaptive_throttler_skips_sleep_when_behind() {
+    let target = Duration::from_millis(50);
+    let mut throttler = Throttler::adaptive(target);
+
+    // a slow frame is not followed by a sleep
+    thread::sleep(Duration::from_millis(120));
+    let wait_start = Instant::now();
+    throttler.wait();
+    assert!(wait_start.elapsed() < Duration::from_millis(40));
+
+    // a fast frame sleeps less than the target while catching up
+    let wait_start = Instant::now();
+    throttler.wait();
+    assert!(wait_start.elapsed() < target);
+
+    assert!(throttler.average_frame_time() > target);
+}